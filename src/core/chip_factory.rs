@@ -9,8 +9,8 @@ use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 use core::{
-    Addressable, Chip, Clock, Cpu, IoPort, IrqLine, Mmu, Pin, Ram, Rom, SoundOutput, SystemModel,
-    VicModel, VideoOutput,
+    Addressable, Chip, Clock, Cpu, IoPort, IrqLine, MemoryWatch, Mmu, Pin, Ram, Rom, SoundOutput,
+    SystemModel, VicModel, VideoOutput,
 };
 
 pub trait ChipFactory {
@@ -48,16 +48,28 @@ pub trait ChipFactory {
         chip_model: VicModel,
         ba_line: Rc<RefCell<Pin>>,
         color_ram: Rc<RefCell<Ram>>,
+        exp_io_line: Rc<RefCell<IoPort>>,
+        expansion_port: Rc<RefCell<dyn Addressable>>,
         frame_buffer: Rc<RefCell<dyn VideoOutput>>,
         irq_line: Rc<RefCell<IrqLine>>,
         ram: Rc<RefCell<Ram>>,
+        raster_position: Rc<Cell<(u16, u16)>>,
         rom_charset: Rc<RefCell<Rom>>,
         vic_base_address: Rc<Cell<u16>>,
+        vic_data_bus: Rc<Cell<u8>>,
+        mem_watch: Rc<MemoryWatch>,
     ) -> Rc<RefCell<dyn Chip>>;
 
     // -- Memory
 
-    fn new_expansion_port(&self, exp_io_line: Rc<RefCell<IoPort>>) -> Rc<RefCell<dyn Addressable>>;
+    fn new_expansion_port(
+        &self,
+        ba_line: Rc<RefCell<Pin>>,
+        exp_io_line: Rc<RefCell<IoPort>>,
+        irq_line: Rc<RefCell<IrqLine>>,
+        nmi_line: Rc<RefCell<IrqLine>>,
+        vic_data_bus: Rc<Cell<u8>>,
+    ) -> Rc<RefCell<dyn Addressable>>;
 
     fn new_memory(
         &self,
@@ -71,6 +83,8 @@ pub trait ChipFactory {
         rom_kernal: Rc<RefCell<Rom>>,
         sid: Rc<RefCell<dyn Chip>>,
         vic: Rc<RefCell<dyn Chip>>,
+        vic_base_address: Rc<Cell<u16>>,
+        mem_watch: Rc<MemoryWatch>,
     ) -> Rc<RefCell<dyn Mmu>>;
 
     fn new_ram(&self, capacity: usize) -> Rc<RefCell<Ram>>;
@@ -15,6 +15,10 @@ impl IrqLine {
         Self { kind, signal: 0 }
     }
 
+    pub fn get_signal(&self) -> u8 {
+        self.signal
+    }
+
     pub fn is_low(&self) -> bool {
         self.signal != 0
     }
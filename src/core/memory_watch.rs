@@ -0,0 +1,122 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::{Cell, RefCell};
+use std::ops::RangeInclusive;
+
+// Design:
+//   MemoryWatch is shared by the memory controllers to report accesses to watched addresses.
+//   It only filters accesses by address and kind and keeps them until the breakpoint manager
+//   examines them after the instruction completes, so conditions and ignore counts live in
+//   one place. While nothing is watched an access costs a single flag test.
+//   When journaling is enabled, writes are also kept together with the value they replaced
+//   so the last instruction can be undone by reverse execution.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MemoryAccess {
+    Read = 1 << 0,
+    Write = 1 << 1,
+    VicRead = 1 << 2,
+}
+
+impl MemoryAccess {
+    pub fn mask(&self) -> u8 {
+        *self as u8
+    }
+}
+
+pub struct MemoryWatch {
+    armed: Cell<u8>,
+    hits: RefCell<Vec<(MemoryAccess, u16, u8)>>,
+    journal: Cell<bool>,
+    ranges: RefCell<Vec<(RangeInclusive<u16>, u8)>>,
+    writes: RefCell<Vec<(u16, u8, u8)>>,
+}
+
+impl MemoryWatch {
+    pub fn new() -> Self {
+        Self {
+            armed: Cell::new(0),
+            hits: RefCell::new(Vec::new()),
+            journal: Cell::new(false),
+            ranges: RefCell::new(Vec::new()),
+            writes: RefCell::new(Vec::new()),
+        }
+    }
+
+    pub fn clear(&self) {
+        if self.armed.get() != 0 {
+            self.hits.borrow_mut().clear();
+        }
+        if self.journal.get() {
+            self.writes.borrow_mut().clear();
+        }
+    }
+
+    pub fn is_journaling(&self) -> bool {
+        self.journal.get()
+    }
+
+    pub fn journal(&self, address: u16, old_value: u8, value: u8) {
+        self.writes.borrow_mut().push((address, old_value, value));
+    }
+
+    pub fn record(&self, access: MemoryAccess, address: u16, value: u8) {
+        if self.armed.get() & access.mask() != 0 {
+            let watched =
+                self.ranges.borrow().iter().any(|&(ref range, mask)| {
+                    mask & access.mask() != 0 && range.contains(&address)
+                });
+            if watched {
+                self.hits.borrow_mut().push((access, address, value));
+            }
+        }
+    }
+
+    pub fn set_ranges(&self, ranges: Vec<(RangeInclusive<u16>, u8)>) {
+        self.armed
+            .set(ranges.iter().fold(0, |armed, &(_, mask)| armed | mask));
+        *self.ranges.borrow_mut() = ranges;
+        self.hits.borrow_mut().clear();
+    }
+
+    pub fn set_journaling(&self, enabled: bool) {
+        self.journal.set(enabled);
+        self.writes.borrow_mut().clear();
+    }
+
+    pub fn take_hits(&self) -> Vec<(MemoryAccess, u16, u8)> {
+        self.hits.replace(Vec::new())
+    }
+
+    pub fn take_writes(&self) -> Vec<(u16, u8, u8)> {
+        self.writes.replace(Vec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn record_filters_by_range_and_access() {
+        let watch = MemoryWatch::new();
+        watch.set_ranges(vec![
+            (0xd020..=0xd021, MemoryAccess::Write.mask()),
+            (0x0400..=0x07ff, MemoryAccess::VicRead.mask()),
+        ]);
+        watch.record(MemoryAccess::Read, 0xd020, 0x01);
+        watch.record(MemoryAccess::Write, 0xd022, 0x01);
+        watch.record(MemoryAccess::Write, 0xd021, 0x02);
+        watch.record(MemoryAccess::VicRead, 0x0400, 0x20);
+        assert_eq!(
+            vec![
+                (MemoryAccess::Write, 0xd021, 0x02),
+                (MemoryAccess::VicRead, 0x0400, 0x20),
+            ],
+            watch.take_hits()
+        );
+        assert_eq!(0, watch.take_hits().len());
+    }
+}
@@ -9,6 +9,7 @@ mod clock;
 mod io_port;
 mod irq_control;
 mod irq_line;
+mod memory_watch;
 mod pin;
 mod ram;
 mod rom;
@@ -19,12 +20,54 @@ pub use self::clock::Clock;
 pub use self::io_port::IoPort;
 pub use self::irq_control::IrqControl;
 pub use self::irq_line::IrqLine;
+pub use self::memory_watch::{MemoryAccess, MemoryWatch};
 pub use self::pin::Pin;
 pub use self::ram::Ram;
 pub use self::rom::Rom;
 pub use self::system_model::{SidModel, SystemModel, VicModel};
 
+// Views of the address space used to inspect memory without side effects. The VIC view
+// covers the 16K bank selected through CIA 2.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MemoryBank {
+    Cart,
+    Cpu,
+    Io,
+    Ram,
+    Rom,
+    Vic,
+}
+
+impl MemoryBank {
+    pub fn from(name: &str) -> Result<MemoryBank, String> {
+        match name {
+            "cart" => Ok(MemoryBank::Cart),
+            "cpu" => Ok(MemoryBank::Cpu),
+            "io" => Ok(MemoryBank::Io),
+            "ram" => Ok(MemoryBank::Ram),
+            "rom" => Ok(MemoryBank::Rom),
+            "vic" => Ok(MemoryBank::Vic),
+            _ => Err(format!("Invalid memory bank {}", name)),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match *self {
+            MemoryBank::Cart => "cart",
+            MemoryBank::Cpu => "cpu",
+            MemoryBank::Io => "io",
+            MemoryBank::Ram => "ram",
+            MemoryBank::Rom => "rom",
+            MemoryBank::Vic => "vic",
+        }
+    }
+}
+
 pub trait Addressable {
+    // Reads without side effects such as clearing status registers
+    fn peek(&self, address: u16) -> u8 {
+        self.read(address)
+    }
     fn read(&self, address: u16) -> u8;
     fn write(&mut self, address: u16, value: u8);
 }
@@ -35,6 +78,10 @@ pub trait Chip {
     fn process_vsync(&mut self);
     fn reset(&mut self);
     // I/O
+    // Chips that cannot read a register without side effects report an open bus
+    fn peek(&self, _reg: u8) -> u8 {
+        0xff
+    }
     fn read(&mut self, reg: u8) -> u8;
     fn write(&mut self, reg: u8, value: u8);
 }
@@ -64,6 +111,7 @@ pub trait Cpu {
 pub trait Mmu {
     fn switch_banks(&mut self, mode: u8);
     // I/O
+    fn peek(&self, bank: MemoryBank, address: u16) -> u8;
     fn read(&self, address: u16) -> u8;
     fn write(&mut self, address: u16, value: u8);
 }
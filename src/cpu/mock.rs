@@ -0,0 +1,51 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use core::{IoPort, IrqLine, MemoryBank, Mmu, Pin, Ram};
+
+use super::Cpu6510;
+
+// Flat 64K memory shared by the cpu and debugger tests.
+pub struct MockMemory {
+    ram: Ram,
+}
+
+impl MockMemory {
+    pub fn new(ram: Ram) -> Self {
+        Self { ram }
+    }
+}
+
+impl Mmu for MockMemory {
+    fn switch_banks(&mut self, _mode: u8) {}
+
+    fn peek(&self, _bank: MemoryBank, address: u16) -> u8 {
+        self.ram.read(address)
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        self.ram.read(address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        self.ram.write(address, value);
+    }
+}
+
+pub fn setup_cpu() -> Cpu6510 {
+    setup_cpu_with_memory().0
+}
+
+pub fn setup_cpu_with_memory() -> (Cpu6510, Rc<RefCell<MockMemory>>) {
+    let ba_line = Rc::new(RefCell::new(Pin::new_high()));
+    let cpu_io_port = Rc::new(RefCell::new(IoPort::new(0x00, 0xff)));
+    let cpu_irq = Rc::new(RefCell::new(IrqLine::new("irq")));
+    let cpu_nmi = Rc::new(RefCell::new(IrqLine::new("nmi")));
+    let mem = Rc::new(RefCell::new(MockMemory::new(Ram::new(0x10000))));
+    let cpu = Cpu6510::new(ba_line, cpu_io_port, cpu_irq, cpu_nmi, mem.clone());
+    (cpu, mem)
+}
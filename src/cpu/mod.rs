@@ -4,6 +4,8 @@
 
 mod cpu6510;
 mod instruction;
+#[cfg(test)]
+pub mod mock;
 mod operand;
 
 pub use self::cpu6510::Cpu6510;
@@ -0,0 +1,108 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// SPEC: http://rr.pokefinder.org/wiki/Action_Replay
+// Design:
+//   Writes to $DE00 control the cartridge. Bit 0 asserts GAME, bit 1 releases EXROM,
+//   bit 2 switches the cartridge off until reset or freeze, bits 3-4 select the 8K bank,
+//   bit 5 maps the 8K RAM at ROML and bit 6 releases the freeze NMI. I/O 2 mirrors the last
+//   page of ROM or RAM. Pressing freeze switches to Ultimax mode with bank 0 and asserts NMI.
+
+const RAM_SIZE: usize = 0x2000;
+
+pub struct ActionReplay {
+    mode: Mode,
+    bank: usize,
+    disabled: bool,
+    frozen: bool,
+    ram_enabled: bool,
+    ram: Vec<u8>,
+}
+
+impl ActionReplay {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Game8k,
+            bank: 0,
+            disabled: false,
+            frozen: false,
+            ram_enabled: false,
+            ram: vec![0; RAM_SIZE],
+        }
+    }
+}
+
+impl Mapper for ActionReplay {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn is_nmi_low(&self) -> bool {
+        self.frozen
+    }
+
+    fn freeze(&mut self) {
+        self.mode = Mode::Ultimax;
+        self.bank = 0;
+        self.disabled = false;
+        self.frozen = true;
+        self.ram_enabled = false;
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0x8000..=0x9fff if self.ram_enabled => self.ram[(address & 0x1fff) as usize],
+            _ => banks.read_roml(self.bank, address),
+        }
+    }
+
+    fn read_io(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0xdf00..=0xdfff if self.disabled => 0,
+            0xdf00..=0xdfff if self.ram_enabled => self.ram[(0x1f00 | (address & 0xff)) as usize],
+            0xdf00..=0xdfff => banks.read_roml(self.bank, 0x1f00 | (address & 0xff)),
+            _ => 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Game8k;
+        self.bank = 0;
+        self.disabled = false;
+        self.frozen = false;
+        self.ram_enabled = false;
+    }
+
+    fn write(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        if let 0x8000..=0x9fff = address {
+            if self.ram_enabled {
+                self.ram[(address & 0x1fff) as usize] = value;
+            }
+        }
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        match address {
+            0xde00..=0xdeff if !self.disabled => {
+                self.bank = ((value >> 3) & 0x03) as usize;
+                self.mode = Mode::from(value & 0x02 != 0, value & 0x01 == 0);
+                self.ram_enabled = value & 0x20 != 0;
+                if value & 0x40 != 0 {
+                    self.frozen = false;
+                }
+                if value & 0x04 != 0 {
+                    self.mode = Mode::Off;
+                    self.disabled = true;
+                    self.ram_enabled = false;
+                }
+            }
+            0xdf00..=0xdfff if self.ram_enabled => {
+                self.ram[(0x1f00 | (address & 0xff)) as usize] = value;
+            }
+            _ => {}
+        }
+    }
+}
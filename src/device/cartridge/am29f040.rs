@@ -0,0 +1,177 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::Banks;
+
+// SPEC: AMD Am29F040B 4 Megabit CMOS 5.0 Volt-only, Uniform Sector Flash Memory
+// Design:
+//   Command state machine of a 512K flash chip mapped as 64 banks of 8K. Commands are
+//   decoded from address lines A0-A10. Program and erase operations complete immediately,
+//   so status polling sees the final data on the first read.
+
+const MANUFACTURER_ID: u8 = 0x01;
+const DEVICE_ID: u8 = 0xa4;
+const BANKS_PER_SECTOR: usize = 8;
+const SECTOR_COUNT: usize = 8;
+
+#[derive(Copy, Clone)]
+pub enum Rom {
+    Low,
+    High,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+    Read,
+    Unlock1,
+    Unlock2,
+    Program,
+    EraseSetup,
+    EraseUnlock1,
+    EraseUnlock2,
+    Autoselect,
+}
+
+pub struct Am29F040 {
+    rom: Rom,
+    state: State,
+}
+
+impl Am29F040 {
+    pub fn new(rom: Rom) -> Self {
+        Self {
+            rom,
+            state: State::Read,
+        }
+    }
+
+    pub fn read(&self, banks: &Banks, bank: usize, address: u16) -> u8 {
+        match self.state {
+            State::Autoselect => match address & 0xff {
+                0x00 => MANUFACTURER_ID,
+                0x01 => DEVICE_ID,
+                _ => 0x00,
+            },
+            _ => match self.rom {
+                Rom::Low => banks.read_roml(bank, address),
+                Rom::High => banks.read_romh(bank, address),
+            },
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.state = State::Read;
+    }
+
+    pub fn write(&mut self, banks: &mut Banks, bank: usize, address: u16, value: u8) {
+        let command_address = address & 0x07ff;
+        self.state = match (self.state, command_address, value) {
+            (_, _, 0xf0) if self.state != State::Program => State::Read,
+            (State::Read, 0x555, 0xaa) | (State::Autoselect, 0x555, 0xaa) => State::Unlock1,
+            (State::Unlock1, 0x2aa, 0x55) => State::Unlock2,
+            (State::Unlock2, 0x555, 0xa0) => State::Program,
+            (State::Unlock2, 0x555, 0x80) => State::EraseSetup,
+            (State::Unlock2, 0x555, 0x90) => State::Autoselect,
+            (State::Program, _, _) => {
+                self.program(banks, bank, address, value);
+                State::Read
+            }
+            (State::EraseSetup, 0x555, 0xaa) => State::EraseUnlock1,
+            (State::EraseUnlock1, 0x2aa, 0x55) => State::EraseUnlock2,
+            (State::EraseUnlock2, 0x555, 0x10) => {
+                self.erase_chip(banks);
+                State::Read
+            }
+            (State::EraseUnlock2, _, 0x30) => {
+                self.erase_sector(banks, bank / BANKS_PER_SECTOR);
+                State::Read
+            }
+            (State::Autoselect, _, _) => State::Autoselect,
+            _ => State::Read,
+        };
+    }
+
+    fn erase_chip(&self, banks: &mut Banks) {
+        for sector in 0..SECTOR_COUNT {
+            self.erase_sector(banks, sector);
+        }
+    }
+
+    fn erase_sector(&self, banks: &mut Banks, sector: usize) {
+        let first = sector * BANKS_PER_SECTOR;
+        for bank in first..first + BANKS_PER_SECTOR {
+            match self.rom {
+                Rom::Low => banks.erase_roml(bank),
+                Rom::High => banks.erase_romh(bank),
+            }
+        }
+    }
+
+    // Programming can only clear bits, erasing is required to set them.
+    fn program(&self, banks: &mut Banks, bank: usize, address: u16, value: u8) {
+        let data = self.read(banks, bank, address) & value;
+        match self.rom {
+            Rom::Low => banks.write_roml(bank, address, data),
+            Rom::High => banks.write_romh(bank, address, data),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(flash: &mut Am29F040, banks: &mut Banks, value: u8) {
+        flash.write(banks, 0, 0x8555, 0xaa);
+        flash.write(banks, 0, 0x82aa, 0x55);
+        flash.write(banks, 0, 0x8555, value);
+    }
+
+    #[test]
+    fn program_byte() {
+        let mut banks = Banks::new();
+        let mut flash = Am29F040::new(Rom::Low);
+        command(&mut flash, &mut banks, 0xa0);
+        flash.write(&mut banks, 3, 0x8010, 0x5a);
+        assert_eq!(0x5a, flash.read(&banks, 3, 0x8010));
+        assert_eq!(true, banks.is_dirty());
+        command(&mut flash, &mut banks, 0xa0);
+        flash.write(&mut banks, 3, 0x8010, 0xf0);
+        assert_eq!(0x50, flash.read(&banks, 3, 0x8010));
+    }
+
+    #[test]
+    fn write_without_command_is_ignored() {
+        let mut banks = Banks::new();
+        let mut flash = Am29F040::new(Rom::High);
+        flash.write(&mut banks, 0, 0xe010, 0x00);
+        assert_eq!(0xff, flash.read(&banks, 0, 0xe010));
+        assert_eq!(false, banks.is_dirty());
+    }
+
+    #[test]
+    fn erase_sector() {
+        let mut banks = Banks::new();
+        let mut flash = Am29F040::new(Rom::High);
+        banks.write_romh(8, 0xe000, 0x00);
+        banks.write_romh(16, 0xe000, 0x00);
+        command(&mut flash, &mut banks, 0x80);
+        flash.write(&mut banks, 0, 0xe555, 0xaa);
+        flash.write(&mut banks, 0, 0xe2aa, 0x55);
+        flash.write(&mut banks, 15, 0xe000, 0x30);
+        assert_eq!(0xff, flash.read(&banks, 8, 0xe000));
+        assert_eq!(0x00, flash.read(&banks, 16, 0xe000));
+    }
+
+    #[test]
+    fn autoselect() {
+        let mut banks = Banks::new();
+        let mut flash = Am29F040::new(Rom::Low);
+        command(&mut flash, &mut banks, 0x90);
+        assert_eq!(MANUFACTURER_ID, flash.read(&banks, 0, 0x8000));
+        assert_eq!(DEVICE_ID, flash.read(&banks, 0, 0x8001));
+        flash.write(&mut banks, 0, 0x8000, 0xf0);
+        assert_eq!(0xff, flash.read(&banks, 0, 0x8000));
+    }
+}
@@ -0,0 +1,139 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::Chip;
+
+// Design:
+//   Chips are split into 8K ROML and ROMH banks when they are added so mappers can select
+//   banks by number without searching the chip list on every read. Chips smaller than 8K
+//   are mirrored to fill the bank. Writes to a missing bank create it erased, and any
+//   write marks the banks as modified so they can be saved back.
+
+const BANK_SIZE: usize = 0x2000;
+
+pub struct Banks {
+    roml: Vec<Vec<u8>>,
+    romh: Vec<Vec<u8>>,
+    romh_offset: u16,
+    dirty: bool,
+}
+
+impl Banks {
+    pub fn new() -> Self {
+        Self {
+            roml: Vec::new(),
+            romh: Vec::new(),
+            romh_offset: 0xa000,
+            dirty: false,
+        }
+    }
+
+    pub fn add(&mut self, chip: &Chip) {
+        let bank = chip.bank_number as usize;
+        match chip.offset {
+            0x8000 if chip.data.len() > BANK_SIZE => {
+                Self::set_bank(&mut self.roml, bank, &chip.data[..BANK_SIZE]);
+                Self::set_bank(&mut self.romh, bank, &chip.data[BANK_SIZE..]);
+            }
+            0x8000 => Self::set_bank(&mut self.roml, bank, &chip.data),
+            0xa000 | 0xe000 => {
+                Self::set_bank(&mut self.romh, bank, &chip.data);
+                self.romh_offset = chip.offset;
+            }
+            _ => warn!(target: "cartridge", "Ignoring chip {} at 0x{:x}", bank, chip.offset),
+        }
+    }
+
+    pub fn get_count(&self) -> usize {
+        self.roml.len().max(self.romh.len())
+    }
+
+    pub fn get_roml(&self, bank: usize) -> Option<&[u8]> {
+        match self.roml.get(bank) {
+            Some(data) if !data.is_empty() => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn get_romh(&self, bank: usize) -> Option<&[u8]> {
+        match self.romh.get(bank) {
+            Some(data) if !data.is_empty() => Some(data),
+            _ => None,
+        }
+    }
+
+    pub fn get_romh_offset(&self) -> u16 {
+        self.romh_offset
+    }
+
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    pub fn set_dirty(&mut self, dirty: bool) {
+        self.dirty = dirty;
+    }
+
+    pub fn erase_roml(&mut self, bank: usize) {
+        Self::set_bank(&mut self.roml, bank, &[]);
+        self.dirty = true;
+    }
+
+    pub fn erase_romh(&mut self, bank: usize) {
+        Self::set_bank(&mut self.romh, bank, &[]);
+        self.dirty = true;
+    }
+
+    pub fn read(&self, bank: usize, address: u16) -> u8 {
+        match address {
+            0x8000..=0x9fff => self.read_roml(bank, address),
+            _ => self.read_romh(bank, address),
+        }
+    }
+
+    pub fn read_roml(&self, bank: usize, address: u16) -> u8 {
+        Self::read_bank(&self.roml, bank, address)
+    }
+
+    pub fn read_romh(&self, bank: usize, address: u16) -> u8 {
+        Self::read_bank(&self.romh, bank, address)
+    }
+
+    pub fn write_roml(&mut self, bank: usize, address: u16, value: u8) {
+        Self::write_bank(&mut self.roml, bank, address, value);
+        self.dirty = true;
+    }
+
+    pub fn write_romh(&mut self, bank: usize, address: u16, value: u8) {
+        Self::write_bank(&mut self.romh, bank, address, value);
+        self.dirty = true;
+    }
+
+    fn read_bank(banks: &[Vec<u8>], bank: usize, address: u16) -> u8 {
+        match banks.get(bank) {
+            Some(data) if !data.is_empty() => data[(address as usize) & (BANK_SIZE - 1)],
+            _ => 0xff,
+        }
+    }
+
+    fn set_bank(banks: &mut Vec<Vec<u8>>, bank: usize, data: &[u8]) {
+        if banks.len() <= bank {
+            banks.resize(bank + 1, Vec::new());
+        }
+        let mut bank_data = vec![0xff; BANK_SIZE];
+        if !data.is_empty() {
+            for (i, value) in bank_data.iter_mut().enumerate() {
+                *value = data[i % data.len()];
+            }
+        }
+        banks[bank] = bank_data;
+    }
+
+    fn write_bank(banks: &mut Vec<Vec<u8>>, bank: usize, address: u16, value: u8) {
+        if banks.get(bank).and_then(|data| data.first()).is_none() {
+            Self::set_bank(banks, bank, &[]);
+        }
+        banks[bank][(address as usize) & (BANK_SIZE - 1)] = value;
+    }
+}
@@ -0,0 +1,52 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   C64 Game System and System 3 cartridges. A write to $DE00+n selects 8K bank n and a read
+//   from I/O 1 selects bank 0.
+
+pub struct C64GameSystem {
+    bank: Cell<usize>,
+}
+
+impl C64GameSystem {
+    pub fn new() -> Self {
+        Self { bank: Cell::new(0) }
+    }
+}
+
+impl Mapper for C64GameSystem {
+    fn get_mode(&self) -> Mode {
+        Mode::Game8k
+    }
+
+    fn peek_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(self.bank.get(), address)
+    }
+
+    fn read_io(&self, _banks: &Banks, address: u16) -> u8 {
+        if let 0xde00..=0xdeff = address {
+            self.bank.set(0);
+        }
+        0
+    }
+
+    fn reset(&mut self) {
+        self.bank.set(0);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, _value: u8) {
+        if let 0xde00..=0xdeff = address {
+            self.bank.set((address & 0x3f) as usize);
+        }
+    }
+}
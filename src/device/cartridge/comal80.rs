@@ -0,0 +1,53 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   Writes to $DE00 select a 16K bank with bits 0-1. Setting bits 5-7 switches the
+//   cartridge off.
+
+pub struct Comal80 {
+    mode: Mode,
+    bank: usize,
+}
+
+impl Comal80 {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Game16k,
+            bank: 0,
+        }
+    }
+}
+
+impl Mapper for Comal80 {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(self.bank, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Game16k;
+        self.bank = 0;
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        if let 0xde00..=0xdeff = address {
+            self.bank = (value & 0x03) as usize;
+            self.mode = if value & 0xe0 == 0xe0 {
+                Mode::Off
+            } else {
+                Mode::Game16k
+            };
+        }
+    }
+}
@@ -0,0 +1,47 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   A read from $DE00+n selects 8K bank n.
+
+pub struct Dinamic {
+    bank: Cell<usize>,
+}
+
+impl Dinamic {
+    pub fn new() -> Self {
+        Self { bank: Cell::new(0) }
+    }
+}
+
+impl Mapper for Dinamic {
+    fn get_mode(&self) -> Mode {
+        Mode::Game8k
+    }
+
+    fn peek_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(self.bank.get(), address)
+    }
+
+    fn read_io(&self, _banks: &Banks, address: u16) -> u8 {
+        if let 0xde00..=0xdeff = address {
+            self.bank.set((address & 0x0f) as usize);
+        }
+        0
+    }
+
+    fn reset(&mut self) {
+        self.bank.set(0);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+}
@@ -0,0 +1,88 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::am29f040::{Am29F040, Rom};
+use super::{Banks, Mapper, Mode};
+
+// SPEC: http://skoe.de/easyflash/files/devdocs/EasyFlash-ProgRef.pdf
+// Design:
+//   $DE00 selects the bank for ROML and ROMH. $DE02 is the control register where bit 0
+//   asserts GAME, bit 1 asserts EXROM and bit 2 selects whether GAME is driven by bit 0 or
+//   by the boot jumper. 256 bytes of RAM are mapped at $DF00. After reset the cartridge
+//   starts in Ultimax mode from bank 0. ROML and ROMH are separate AM29F040 flash chips that
+//   can only be written in Ultimax mode.
+
+const RAM_SIZE: usize = 0x100;
+
+pub struct EasyFlash {
+    bank: usize,
+    control: u8,
+    flash_l: Am29F040,
+    flash_h: Am29F040,
+    ram: Vec<u8>,
+}
+
+impl EasyFlash {
+    pub fn new() -> Self {
+        Self {
+            bank: 0,
+            control: 0,
+            flash_l: Am29F040::new(Rom::Low),
+            flash_h: Am29F040::new(Rom::High),
+            ram: vec![0; RAM_SIZE],
+        }
+    }
+}
+
+impl Mapper for EasyFlash {
+    fn get_mode(&self) -> Mode {
+        let exrom = self.control & 0x02 == 0;
+        let game = if self.control & 0x04 != 0 {
+            self.control & 0x01 == 0
+        } else {
+            false
+        };
+        Mode::from(exrom, game)
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0x8000..=0x9fff => self.flash_l.read(banks, self.bank, address),
+            _ => self.flash_h.read(banks, self.bank, address),
+        }
+    }
+
+    fn read_io(&self, _banks: &Banks, address: u16) -> u8 {
+        match address {
+            0xdf00..=0xdfff => self.ram[(address & 0xff) as usize],
+            _ => 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.bank = 0;
+        self.control = 0;
+        self.flash_l.reset();
+        self.flash_h.reset();
+    }
+
+    fn write(&mut self, banks: &mut Banks, address: u16, value: u8) {
+        if self.get_mode() == Mode::Ultimax {
+            match address {
+                0x8000..=0x9fff => self.flash_l.write(banks, self.bank, address, value),
+                0xe000..=0xffff => self.flash_h.write(banks, self.bank, address, value),
+                _ => {}
+            }
+        }
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        match address {
+            0xde00..=0xdeff if address & 0x02 == 0 => self.bank = (value & 0x3f) as usize,
+            0xde00..=0xdeff => self.control = value & 0x87,
+            0xdf00..=0xdfff => self.ram[(address & 0xff) as usize] = value,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,77 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   The cartridge is switched on while a capacitor is charged. Reads from ROML or I/O 1
+//   discharge it and the ROM is switched off if it is not accessed for about 512 cycles.
+//   I/O 2 always reads the last page of the ROM.
+
+const CAPACITOR_CYCLES: u32 = 512;
+
+pub struct EpyxFastload {
+    mode: Mode,
+    counter: Cell<u32>,
+}
+
+impl EpyxFastload {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Game8k,
+            counter: Cell::new(CAPACITOR_CYCLES),
+        }
+    }
+}
+
+impl Mapper for EpyxFastload {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn clock(&mut self) {
+        let counter = self.counter.get();
+        if counter > 0 {
+            self.counter.set(counter - 1);
+            self.mode = Mode::Game8k;
+        } else {
+            self.mode = Mode::Off;
+        }
+    }
+
+    fn peek(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(0, address)
+    }
+
+    fn peek_io(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0xde00..=0xdeff => 0,
+            _ => banks.read_roml(0, 0x1f00 | (address & 0xff)),
+        }
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        self.counter.set(CAPACITOR_CYCLES);
+        banks.read_roml(0, address)
+    }
+
+    fn read_io(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0xde00..=0xdeff => {
+                self.counter.set(CAPACITOR_CYCLES);
+                0
+            }
+            _ => banks.read_roml(0, 0x1f00 | (address & 0xff)),
+        }
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Game8k;
+        self.counter.set(CAPACITOR_CYCLES);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+}
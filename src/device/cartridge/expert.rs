@@ -0,0 +1,41 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   The Expert cartridge holds 8K of RAM loaded from the image. In program mode the RAM is
+//   mapped at ROML and writes to $8000-$9FFF are stored in cartridge RAM.
+
+pub struct Expert {}
+
+impl Expert {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Mapper for Expert {
+    fn get_mode(&self) -> Mode {
+        Mode::Game8k
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(0, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn reset(&mut self) {}
+
+    fn write(&mut self, banks: &mut Banks, address: u16, value: u8) {
+        if let 0x8000..=0x9fff = address {
+            banks.write_roml(0, address, value);
+        }
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+}
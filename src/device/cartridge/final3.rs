@@ -0,0 +1,71 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// SPEC: http://rr.pokefinder.org/wiki/Final_Cartridge_III
+// Design:
+//   Writes to $DFFF control the cartridge. Bits 0-1 select the 16K bank, bit 4 drives
+//   EXROM, bit 5 drives GAME, bit 6 drives NMI and bit 7 hides the register until reset or
+//   freeze. Both I/O areas mirror the last 512 bytes of the current ROML bank. Pressing
+//   freeze switches to Ultimax mode with bank 0 and asserts NMI.
+
+pub struct Final3 {
+    mode: Mode,
+    bank: usize,
+    hidden: bool,
+    nmi: bool,
+}
+
+impl Final3 {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Game16k,
+            bank: 0,
+            hidden: false,
+            nmi: false,
+        }
+    }
+}
+
+impl Mapper for Final3 {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn is_nmi_low(&self) -> bool {
+        self.nmi
+    }
+
+    fn freeze(&mut self) {
+        self.mode = Mode::Ultimax;
+        self.bank = 0;
+        self.hidden = false;
+        self.nmi = true;
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(self.bank, address)
+    }
+
+    fn read_io(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(self.bank, 0x1e00 | (address & 0x1ff))
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Game16k;
+        self.bank = 0;
+        self.hidden = false;
+        self.nmi = false;
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        if address == 0xdfff && !self.hidden {
+            self.bank = (value & 0x03) as usize;
+            self.mode = Mode::from(value & 0x10 != 0, value & 0x20 != 0);
+            self.nmi = value & 0x40 == 0;
+            self.hidden = value & 0x80 != 0;
+        }
+    }
+}
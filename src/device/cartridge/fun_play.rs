@@ -0,0 +1,53 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   Fun Play and Power Play cartridges. Writes to $DE00 select the 8K bank from bits 3-5
+//   and bit 0 as the high bit. Writing $86 switches the cartridge off.
+
+pub struct FunPlay {
+    mode: Mode,
+    bank: usize,
+}
+
+impl FunPlay {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Game8k,
+            bank: 0,
+        }
+    }
+}
+
+impl Mapper for FunPlay {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(self.bank, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Game8k;
+        self.bank = 0;
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        if let 0xde00..=0xdeff = address {
+            if value == 0x86 {
+                self.mode = Mode::Off;
+            } else {
+                self.bank = (((value >> 3) & 0x07) | ((value & 0x01) << 3)) as usize;
+                self.mode = Mode::Game8k;
+            }
+        }
+    }
+}
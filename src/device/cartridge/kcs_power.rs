@@ -0,0 +1,74 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   Reads from I/O 1 return ROM and switch to 8K mode, or switch the cartridge off when
+//   address bit 1 is set. Writes to I/O 1 switch to 16K mode. $DF00-$DF7F holds 128 bytes
+//   of RAM.
+
+const RAM_SIZE: usize = 0x80;
+
+pub struct KcsPower {
+    mode: Cell<Mode>,
+    ram: Vec<u8>,
+}
+
+impl KcsPower {
+    pub fn new() -> Self {
+        Self {
+            mode: Cell::new(Mode::Game16k),
+            ram: vec![0; RAM_SIZE],
+        }
+    }
+}
+
+impl Mapper for KcsPower {
+    fn get_mode(&self) -> Mode {
+        self.mode.get()
+    }
+
+    fn peek_io(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0xde00..=0xdeff => banks.read_roml(0, 0x1e00 | (address & 0xff)),
+            0xdf00..=0xdf7f => self.ram[(address & 0x7f) as usize],
+            _ => 0,
+        }
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(0, address)
+    }
+
+    fn read_io(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0xde00..=0xdeff => {
+                let mode = if address & 0x02 != 0 {
+                    Mode::Off
+                } else {
+                    Mode::Game8k
+                };
+                self.mode.set(mode);
+                banks.read_roml(0, 0x1e00 | (address & 0xff))
+            }
+            0xdf00..=0xdf7f => self.ram[(address & 0x7f) as usize],
+            _ => 0,
+        }
+    }
+
+    fn reset(&mut self) {
+        self.mode.set(Mode::Game16k);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        match address {
+            0xde00..=0xdeff => self.mode.set(Mode::Game16k),
+            0xdf00..=0xdf7f => self.ram[(address & 0x7f) as usize] = value,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,53 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   Magic Desk, Domark and HES Australia cartridges. Writes to $DE00 select an 8K bank with
+//   bits 0-6 and bit 7 switches the cartridge off.
+
+pub struct MagicDesk {
+    mode: Mode,
+    bank: usize,
+}
+
+impl MagicDesk {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Game8k,
+            bank: 0,
+        }
+    }
+}
+
+impl Mapper for MagicDesk {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(self.bank, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Game8k;
+        self.bank = 0;
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        if let 0xde00..=0xdeff = address {
+            self.bank = (value & 0x7f) as usize;
+            self.mode = if value & 0x80 != 0 {
+                Mode::Off
+            } else {
+                Mode::Game8k
+            };
+        }
+    }
+}
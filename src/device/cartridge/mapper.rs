@@ -0,0 +1,68 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::Banks;
+
+// Design:
+//   A mapper implements the banking logic of a specific cartridge type. The cartridge owns
+//   the ROM banks and forwards ROML/ROMH and I/O accesses to its mapper. Mappers that change
+//   state on reads keep that state in a Cell since reads go through Addressable::read, and
+//   override peek and peek_io so the monitor can read them without switching banks.
+//   The GAME, EXROM, IRQ and NMI lines are sampled by the expansion port on every clock.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    Game8k,
+    Game16k,
+    Ultimax,
+    Off,
+}
+
+impl Mode {
+    pub fn from(exrom: bool, game: bool) -> Mode {
+        match (exrom, game) {
+            (false, true) => Mode::Game8k,
+            (false, false) => Mode::Game16k,
+            (true, false) => Mode::Ultimax,
+            (true, true) => Mode::Off,
+        }
+    }
+
+    pub fn exrom(&self) -> bool {
+        match *self {
+            Mode::Game8k | Mode::Game16k => false,
+            Mode::Ultimax | Mode::Off => true,
+        }
+    }
+
+    pub fn game(&self) -> bool {
+        match *self {
+            Mode::Game16k | Mode::Ultimax => false,
+            Mode::Game8k | Mode::Off => true,
+        }
+    }
+}
+
+pub trait Mapper {
+    fn get_mode(&self) -> Mode;
+    fn is_irq_low(&self) -> bool {
+        false
+    }
+    fn is_nmi_low(&self) -> bool {
+        false
+    }
+    fn clock(&mut self) {}
+    fn freeze(&mut self) {}
+    fn peek(&self, banks: &Banks, address: u16) -> u8 {
+        self.read(banks, address)
+    }
+    fn peek_io(&self, banks: &Banks, address: u16) -> u8 {
+        self.read_io(banks, address)
+    }
+    fn read(&self, banks: &Banks, address: u16) -> u8;
+    fn read_io(&self, banks: &Banks, address: u16) -> u8;
+    fn reset(&mut self);
+    fn write(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+    fn write_io(&mut self, banks: &mut Banks, address: u16, value: u8);
+}
@@ -0,0 +1,665 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+mod action_replay;
+mod am29f040;
+mod banks;
+mod c64_game_system;
+mod comal80;
+mod dinamic;
+mod easy_flash;
+mod epyx_fastload;
+mod expert;
+mod final3;
+mod fun_play;
+mod kcs_power;
+mod mach5;
+mod magic_desk;
+mod mapper;
+mod normal;
+mod ocean;
+mod rex_utility;
+mod ross;
+mod simons_basic;
+mod structured_basic;
+mod super_games;
+mod warp_speed;
+mod westermann;
+mod zaxxon;
+
+use std::io;
+use std::ops::RangeInclusive;
+
+use core::Addressable;
+use device::{DeviceKind, ExpansionDevice};
+
+pub use self::banks::Banks;
+pub use self::mapper::{Mapper, Mode};
+
+use self::action_replay::ActionReplay;
+use self::c64_game_system::C64GameSystem;
+use self::comal80::Comal80;
+use self::dinamic::Dinamic;
+use self::easy_flash::EasyFlash;
+use self::epyx_fastload::EpyxFastload;
+use self::expert::Expert;
+use self::final3::Final3;
+use self::fun_play::FunPlay;
+use self::kcs_power::KcsPower;
+use self::mach5::Mach5;
+use self::magic_desk::MagicDesk;
+use self::normal::Normal;
+use self::ocean::Ocean;
+use self::rex_utility::RexUtility;
+use self::ross::Ross;
+use self::simons_basic::SimonsBasic;
+use self::structured_basic::StructuredBasic;
+use self::super_games::SuperGames;
+use self::warp_speed::WarpSpeed;
+use self::westermann::Westermann;
+use self::zaxxon::Zaxxon;
+
+// SPEC: http://ist.uwaterloo.ca/~schepers/formats/CRT.TXT
+// Design:
+//   Cartridge holds the ROM banks and delegates bank switching to a Mapper selected by
+//   the hardware type. Cartridges with flash memory are saved through a CartridgeStore
+//   when they are flushed after the banks were modified.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ChipType {
+    Rom,
+    Ram,
+    FlashRom,
+}
+
+impl ChipType {
+    pub fn from(chip_type: u16) -> ChipType {
+        match chip_type {
+            0x00 => ChipType::Rom,
+            0x01 => ChipType::Ram,
+            0x02 => ChipType::FlashRom,
+            _ => panic!("invalid chip type {}", chip_type),
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        match *self {
+            ChipType::Rom => 0x00,
+            ChipType::Ram => 0x01,
+            ChipType::FlashRom => 0x02,
+        }
+    }
+}
+
+// I/O areas decoded by the cartridge types
+static IO_NONE: [RangeInclusive<u16>; 0] = [];
+static IO_1: [RangeInclusive<u16>; 1] = [0xde00..=0xdeff];
+static IO_2: [RangeInclusive<u16>; 1] = [0xdf00..=0xdfff];
+static IO_1_2: [RangeInclusive<u16>; 1] = [0xde00..=0xdfff];
+
+pub trait CartridgeStore {
+    fn save(&mut self, cartridge: &Cartridge) -> io::Result<()>;
+}
+
+pub struct Chip {
+    pub chip_type: ChipType,
+    pub bank_number: u8,
+    pub offset: u16,
+    pub size: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum HwType {
+    Normal,
+    ActionReplay,
+    KCSPower,
+    Final3,
+    SimonsBasic,
+    OceanType1,
+    Expert,
+    FunPlay,
+    SuperGames,
+    EpyxFastload,
+    Westermann,
+    RexUtility,
+    C64GameSystem,
+    WarpSpeed,
+    Dinamic,
+    Zaxxon,
+    MagicDesk,
+    Comal80,
+    StructuredBasic,
+    Ross,
+    EasyFlash,
+    Mach5,
+}
+
+impl HwType {
+    pub fn from(value: u16) -> Option<HwType> {
+        match value {
+            0 => Some(HwType::Normal),
+            1 => Some(HwType::ActionReplay),
+            2 => Some(HwType::KCSPower),
+            3 => Some(HwType::Final3),
+            4 => Some(HwType::SimonsBasic),
+            5 => Some(HwType::OceanType1),
+            6 => Some(HwType::Expert),
+            7 => Some(HwType::FunPlay),
+            8 => Some(HwType::SuperGames),
+            10 => Some(HwType::EpyxFastload),
+            11 => Some(HwType::Westermann),
+            12 => Some(HwType::RexUtility),
+            15 => Some(HwType::C64GameSystem),
+            16 => Some(HwType::WarpSpeed),
+            17 => Some(HwType::Dinamic),
+            18 => Some(HwType::Zaxxon),
+            19 => Some(HwType::MagicDesk),
+            21 => Some(HwType::Comal80),
+            22 => Some(HwType::StructuredBasic),
+            23 => Some(HwType::Ross),
+            32 => Some(HwType::EasyFlash),
+            51 => Some(HwType::Mach5),
+            _ => None,
+        }
+    }
+
+    pub fn value(&self) -> u16 {
+        match *self {
+            HwType::Normal => 0,
+            HwType::ActionReplay => 1,
+            HwType::KCSPower => 2,
+            HwType::Final3 => 3,
+            HwType::SimonsBasic => 4,
+            HwType::OceanType1 => 5,
+            HwType::Expert => 6,
+            HwType::FunPlay => 7,
+            HwType::SuperGames => 8,
+            HwType::EpyxFastload => 10,
+            HwType::Westermann => 11,
+            HwType::RexUtility => 12,
+            HwType::C64GameSystem => 15,
+            HwType::WarpSpeed => 16,
+            HwType::Dinamic => 17,
+            HwType::Zaxxon => 18,
+            HwType::MagicDesk => 19,
+            HwType::Comal80 => 21,
+            HwType::StructuredBasic => 22,
+            HwType::Ross => 23,
+            HwType::EasyFlash => 32,
+            HwType::Mach5 => 51,
+        }
+    }
+
+    // Returns the mode a cartridge of this type starts in. Normal and Ocean cartridges take
+    // their mode from the CRT header, so it is derived from the size of the ROM data.
+    pub fn get_start_mode(&self, size: usize) -> Mode {
+        match *self {
+            HwType::Normal if size > 0x2000 => Mode::Game16k,
+            HwType::Normal => Mode::Game8k,
+            HwType::OceanType1 if size > 0x40000 => Mode::Game8k,
+            HwType::OceanType1 => Mode::Game16k,
+            _ => self.new_mapper(Mode::Off).get_mode(),
+        }
+    }
+
+    fn get_io_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        match *self {
+            HwType::Normal | HwType::Expert | HwType::Zaxxon => &IO_NONE,
+            HwType::SimonsBasic
+            | HwType::OceanType1
+            | HwType::FunPlay
+            | HwType::C64GameSystem
+            | HwType::Dinamic
+            | HwType::MagicDesk
+            | HwType::Comal80
+            | HwType::StructuredBasic => &IO_1,
+            HwType::SuperGames | HwType::Westermann | HwType::RexUtility => &IO_2,
+            HwType::ActionReplay
+            | HwType::KCSPower
+            | HwType::Final3
+            | HwType::EpyxFastload
+            | HwType::WarpSpeed
+            | HwType::Ross
+            | HwType::EasyFlash
+            | HwType::Mach5 => &IO_1_2,
+        }
+    }
+
+    fn new_mapper(&self, mode: Mode) -> Box<dyn Mapper> {
+        match *self {
+            HwType::Normal => Box::new(Normal::new(mode)),
+            HwType::ActionReplay => Box::new(ActionReplay::new()),
+            HwType::KCSPower => Box::new(KcsPower::new()),
+            HwType::Final3 => Box::new(Final3::new()),
+            HwType::SimonsBasic => Box::new(SimonsBasic::new()),
+            HwType::OceanType1 => Box::new(Ocean::new(mode)),
+            HwType::Expert => Box::new(Expert::new()),
+            HwType::FunPlay => Box::new(FunPlay::new()),
+            HwType::SuperGames => Box::new(SuperGames::new()),
+            HwType::EpyxFastload => Box::new(EpyxFastload::new()),
+            HwType::Westermann => Box::new(Westermann::new()),
+            HwType::RexUtility => Box::new(RexUtility::new()),
+            HwType::C64GameSystem => Box::new(C64GameSystem::new()),
+            HwType::WarpSpeed => Box::new(WarpSpeed::new()),
+            HwType::Dinamic => Box::new(Dinamic::new()),
+            HwType::Zaxxon => Box::new(Zaxxon::new()),
+            HwType::MagicDesk => Box::new(MagicDesk::new()),
+            HwType::Comal80 => Box::new(Comal80::new()),
+            HwType::StructuredBasic => Box::new(StructuredBasic::new()),
+            HwType::Ross => Box::new(Ross::new()),
+            HwType::EasyFlash => Box::new(EasyFlash::new()),
+            HwType::Mach5 => Box::new(Mach5::new()),
+        }
+    }
+}
+
+pub struct Cartridge {
+    pub version: u16,
+    pub hw_type: HwType,
+    pub exrom: bool,
+    pub game: bool,
+    pub name: String,
+    banks: Banks,
+    mapper: Box<dyn Mapper>,
+    store: Option<Box<dyn CartridgeStore>>,
+}
+
+impl Cartridge {
+    pub fn new(version: u16, hw_type: HwType, exrom: bool, game: bool) -> Self {
+        Self {
+            version,
+            hw_type,
+            exrom,
+            game,
+            name: String::new(),
+            banks: Banks::new(),
+            mapper: hw_type.new_mapper(Mode::from(exrom, game)),
+            store: None,
+        }
+    }
+
+    pub fn get_chips(&self) -> Vec<Chip> {
+        let chip_type = if self.hw_type == HwType::EasyFlash {
+            ChipType::FlashRom
+        } else {
+            ChipType::Rom
+        };
+        let mut chips = Vec::new();
+        for bank in 0..self.banks.get_count() {
+            let roms = [
+                (0x8000, self.banks.get_roml(bank)),
+                (self.banks.get_romh_offset(), self.banks.get_romh(bank)),
+            ];
+            for &(offset, data) in roms.iter() {
+                if let Some(data) = data {
+                    let erased = data.iter().all(|&value| value == 0xff);
+                    if chip_type == ChipType::FlashRom && erased {
+                        continue;
+                    }
+                    chips.push(Chip {
+                        chip_type,
+                        bank_number: bank as u8,
+                        offset,
+                        size: data.len() as u16,
+                        data: data.to_vec(),
+                    });
+                }
+            }
+        }
+        chips
+    }
+
+    pub fn add(&mut self, chip: Chip) {
+        self.banks.add(&chip);
+    }
+
+    pub fn set_store(&mut self, store: Box<dyn CartridgeStore>) {
+        self.store = Some(store);
+    }
+}
+
+impl Addressable for Cartridge {
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xbfff | 0xe000..=0xffff => self.mapper.peek(&self.banks, address),
+            0xde00..=0xdfff => self.mapper.peek_io(&self.banks, address),
+            _ => 0,
+        }
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        match address {
+            0x8000..=0xbfff | 0xe000..=0xffff => self.mapper.read(&self.banks, address),
+            0xde00..=0xdfff => self.mapper.read_io(&self.banks, address),
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0x8000..=0xbfff | 0xe000..=0xffff => self.mapper.write(&mut self.banks, address, value),
+            0xde00..=0xdfff => self.mapper.write_io(&mut self.banks, address, value),
+            _ => {}
+        }
+    }
+}
+
+impl ExpansionDevice for Cartridge {
+    fn get_exrom(&self) -> bool {
+        self.mapper.get_mode().exrom()
+    }
+
+    fn get_game(&self) -> bool {
+        self.mapper.get_mode().game()
+    }
+
+    fn get_io_ranges(&self) -> &[RangeInclusive<u16>] {
+        self.hw_type.get_io_ranges()
+    }
+
+    fn get_kind(&self) -> DeviceKind {
+        DeviceKind::Cartridge
+    }
+
+    fn is_irq_low(&self) -> bool {
+        self.mapper.is_irq_low()
+    }
+
+    fn is_nmi_low(&self) -> bool {
+        self.mapper.is_nmi_low()
+    }
+
+    fn clock(&mut self) {
+        self.mapper.clock();
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.banks.is_dirty() {
+            if let Some(mut store) = self.store.take() {
+                let result = store.save(self);
+                self.store = Some(store);
+                result?;
+                self.banks.set_dirty(false);
+            }
+        }
+        Ok(())
+    }
+
+    fn freeze(&mut self) {
+        self.mapper.freeze();
+    }
+
+    fn reset(&mut self) {
+        self.mapper.reset();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_cartridge(hw_type: HwType, exrom: bool, game: bool, chips: &[(u8, u16)]) -> Cartridge {
+        let mut cartridge = Cartridge::new(0x0100, hw_type, exrom, game);
+        for &(bank_number, offset) in chips.iter() {
+            let value = if offset == 0x8000 {
+                bank_number
+            } else {
+                0x80 | bank_number
+            };
+            cartridge.add(Chip {
+                chip_type: ChipType::Rom,
+                bank_number,
+                offset,
+                size: 0x2000,
+                data: vec![value; 0x2000],
+            });
+        }
+        cartridge.reset();
+        cartridge
+    }
+
+    #[test]
+    fn hw_type_unknown() {
+        assert_eq!(true, HwType::from(0xff).is_none());
+        assert_eq!(Some(HwType::EasyFlash), HwType::from(32));
+    }
+
+    #[test]
+    fn banks_mirror_small_chip() {
+        let mut banks = Banks::new();
+        let mut data = vec![0; 0x1000];
+        data[0] = 0x42;
+        banks.add(&Chip {
+            chip_type: ChipType::Rom,
+            bank_number: 0,
+            offset: 0x8000,
+            size: 0x1000,
+            data,
+        });
+        assert_eq!(0x42, banks.read_roml(0, 0x8000));
+        assert_eq!(0x42, banks.read_roml(0, 0x9000));
+        assert_eq!(0xff, banks.read_romh(0, 0xa000));
+    }
+
+    #[test]
+    fn normal_ultimax() {
+        let cartridge = setup_cartridge(HwType::Normal, true, false, &[(0, 0xe000)]);
+        assert_eq!(Mode::Ultimax, cartridge.mapper.get_mode());
+        assert_eq!(0x80, cartridge.read(0xe000));
+        assert_eq!(0x80, cartridge.read(0xfffc));
+        assert_eq!(0x00, cartridge.read(0x4000));
+    }
+
+    #[test]
+    fn ocean_switches_roml_and_romh() {
+        let mut cartridge = setup_cartridge(
+            HwType::OceanType1,
+            false,
+            false,
+            &[(0, 0x8000), (1, 0x8000), (16, 0xa000), (17, 0xa000)],
+        );
+        cartridge.write(0xde00, 17);
+        assert_eq!(0x91, cartridge.read(0xa000));
+        cartridge.write(0xde00, 1);
+        assert_eq!(0x01, cartridge.read(0x8000));
+        cartridge.reset();
+        assert_eq!(0x00, cartridge.read(0x8000));
+    }
+
+    #[test]
+    fn magic_desk_switches_off() {
+        let mut cartridge =
+            setup_cartridge(HwType::MagicDesk, false, true, &[(0, 0x8000), (3, 0x8000)]);
+        cartridge.write(0xde00, 3);
+        assert_eq!(0x03, cartridge.read(0x8000));
+        assert_eq!(false, cartridge.get_exrom());
+        cartridge.write(0xde00, 0x80);
+        assert_eq!(true, cartridge.get_exrom());
+        assert_eq!(true, cartridge.get_game());
+    }
+
+    #[test]
+    fn c64_game_system_bank_from_address() {
+        let mut cartridge = setup_cartridge(
+            HwType::C64GameSystem,
+            false,
+            true,
+            &[(0, 0x8000), (5, 0x8000)],
+        );
+        cartridge.write(0xde05, 0);
+        assert_eq!(0x05, cartridge.read(0x8000));
+        cartridge.read(0xde00);
+        assert_eq!(0x00, cartridge.read(0x8000));
+    }
+
+    #[test]
+    fn dinamic_bank_from_read() {
+        let cartridge = setup_cartridge(HwType::Dinamic, false, true, &[(0, 0x8000), (2, 0x8000)]);
+        cartridge.read(0xde02);
+        assert_eq!(0x02, cartridge.read(0x8000));
+    }
+
+    #[test]
+    fn fun_play_bank_bits() {
+        let mut cartridge =
+            setup_cartridge(HwType::FunPlay, false, true, &[(0, 0x8000), (9, 0x8000)]);
+        cartridge.write(0xde00, 0x09);
+        assert_eq!(0x09, cartridge.read(0x8000));
+        cartridge.write(0xde00, 0x86);
+        assert_eq!(true, cartridge.get_exrom());
+    }
+
+    #[test]
+    fn super_games_lock() {
+        let mut cartridge = setup_cartridge(
+            HwType::SuperGames,
+            false,
+            false,
+            &[(0, 0x8000), (0, 0xa000), (2, 0x8000), (2, 0xa000)],
+        );
+        cartridge.write(0xdf00, 0x0a);
+        assert_eq!(0x82, cartridge.read(0xa000));
+        cartridge.write(0xdf00, 0x04);
+        assert_eq!(false, cartridge.get_exrom());
+    }
+
+    #[test]
+    fn easy_flash_modes_and_ram() {
+        let mut cartridge =
+            setup_cartridge(HwType::EasyFlash, true, false, &[(0, 0x8000), (0, 0xa000)]);
+        assert_eq!(
+            Mode::Ultimax,
+            Mode::from(cartridge.get_exrom(), cartridge.get_game())
+        );
+        cartridge.write(0xde02, 0x07);
+        assert_eq!(
+            Mode::Game16k,
+            Mode::from(cartridge.get_exrom(), cartridge.get_game())
+        );
+        cartridge.write(0xde02, 0x06);
+        assert_eq!(
+            Mode::Game8k,
+            Mode::from(cartridge.get_exrom(), cartridge.get_game())
+        );
+        cartridge.write(0xde02, 0x04);
+        assert_eq!(
+            Mode::Off,
+            Mode::from(cartridge.get_exrom(), cartridge.get_game())
+        );
+        cartridge.write(0xdf10, 0x55);
+        assert_eq!(0x55, cartridge.read(0xdf10));
+    }
+
+    #[test]
+    fn easy_flash_writes_flash_in_ultimax_mode() {
+        let mut cartridge =
+            setup_cartridge(HwType::EasyFlash, true, false, &[(0, 0x8000), (0, 0xa000)]);
+        cartridge.write(0xde00, 2);
+        for &(address, value) in [
+            (0xe555, 0xaa),
+            (0xe2aa, 0x55),
+            (0xe555, 0xa0),
+            (0xe123, 0x42),
+        ]
+        .iter()
+        {
+            cartridge.write(address, value);
+        }
+        assert_eq!(0x42, cartridge.read(0xe123));
+        cartridge.write(0xde02, 0x07);
+        for &(address, value) in [
+            (0x8555, 0xaa),
+            (0x82aa, 0x55),
+            (0x8555, 0xa0),
+            (0x8123, 0x00),
+        ]
+        .iter()
+        {
+            cartridge.write(address, value);
+        }
+        assert_eq!(0xff, cartridge.read(0x8123));
+        let chips = cartridge.get_chips();
+        assert_eq!(3, chips.len());
+        assert_eq!(2, chips[2].bank_number);
+        assert_eq!(0xa000, chips[2].offset);
+    }
+
+    #[test]
+    fn epyx_fastload_capacitor() {
+        let mut cartridge = setup_cartridge(HwType::EpyxFastload, false, true, &[(0, 0x8000)]);
+        for _ in 0..600 {
+            cartridge.clock();
+        }
+        assert_eq!(true, cartridge.get_exrom());
+        cartridge.read(0xde00);
+        cartridge.clock();
+        assert_eq!(false, cartridge.get_exrom());
+    }
+
+    #[test]
+    fn warp_speed_on_off() {
+        let mut cartridge =
+            setup_cartridge(HwType::WarpSpeed, false, false, &[(0, 0x8000), (0, 0xa000)]);
+        cartridge.write(0xdf00, 0);
+        assert_eq!(true, cartridge.get_exrom());
+        cartridge.write(0xde00, 0);
+        assert_eq!(false, cartridge.get_exrom());
+        assert_eq!(0x00, cartridge.read(0xde00));
+    }
+
+    #[test]
+    fn action_replay_ram_and_freeze() {
+        let mut cartridge = setup_cartridge(
+            HwType::ActionReplay,
+            false,
+            true,
+            &[(0, 0x8000), (1, 0x8000), (2, 0x8000), (3, 0x8000)],
+        );
+        cartridge.write(0xde00, 0x08);
+        assert_eq!(0x01, cartridge.read(0x8000));
+        cartridge.write(0xde00, 0x20);
+        cartridge.write(0x8010, 0x55);
+        cartridge.write(0xdf20, 0xaa);
+        assert_eq!(0x55, cartridge.read(0x8010));
+        assert_eq!(0xaa, cartridge.read(0x9f20));
+        cartridge.write(0xde00, 0x18);
+        cartridge.freeze();
+        assert_eq!(true, cartridge.is_nmi_low());
+        assert_eq!(Mode::Ultimax, Mode::from(cartridge.get_exrom(), cartridge.get_game()));
+        assert_eq!(0x00, cartridge.read(0xe000));
+        cartridge.write(0xde00, 0x40);
+        assert_eq!(false, cartridge.is_nmi_low());
+    }
+
+    #[test]
+    fn final3_nmi_and_hide() {
+        let mut cartridge = setup_cartridge(HwType::Final3, false, false, &[(0, 0x8000), (1, 0x8000)]);
+        cartridge.write(0xdfff, 0x01);
+        assert_eq!(true, cartridge.is_nmi_low());
+        assert_eq!(0x01, cartridge.read(0xde00));
+        cartridge.write(0xdfff, 0xc0 | 0x30);
+        assert_eq!(false, cartridge.is_nmi_low());
+        assert_eq!(true, cartridge.get_exrom());
+        cartridge.write(0xdfff, 0x00);
+        assert_eq!(false, cartridge.is_nmi_low());
+        cartridge.freeze();
+        assert_eq!(true, cartridge.is_nmi_low());
+        assert_eq!(Mode::Ultimax, Mode::from(cartridge.get_exrom(), cartridge.get_game()));
+    }
+
+    #[test]
+    fn simons_basic_game_line() {
+        let mut cartridge = setup_cartridge(
+            HwType::SimonsBasic,
+            false,
+            false,
+            &[(0, 0x8000), (0, 0xa000)],
+        );
+        cartridge.read(0xde00);
+        assert_eq!(true, cartridge.get_game());
+        cartridge.write(0xde00, 0x01);
+        assert_eq!(false, cartridge.get_game());
+    }
+}
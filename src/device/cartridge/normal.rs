@@ -0,0 +1,37 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   Generic 8K, 16K and Ultimax cartridges without bank switching. The mode is taken from
+//   the GAME and EXROM lines in the CRT header.
+
+pub struct Normal {
+    mode: Mode,
+}
+
+impl Normal {
+    pub fn new(mode: Mode) -> Self {
+        Self { mode }
+    }
+}
+
+impl Mapper for Normal {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(0, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn reset(&mut self) {}
+
+    fn write_io(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+}
@@ -0,0 +1,46 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// SPEC: http://ist.uwaterloo.ca/~schepers/formats/CRT.TXT
+// Design:
+//   Writes to $DE00 select the bank with bits 0-5. The same bank number is used for ROML
+//   and ROMH, so 256K images map their upper 128K of chips at $A000 and 512K images run
+//   in 8K mode.
+
+pub struct Ocean {
+    mode: Mode,
+    bank: usize,
+}
+
+impl Ocean {
+    pub fn new(mode: Mode) -> Self {
+        Self { mode, bank: 0 }
+    }
+}
+
+impl Mapper for Ocean {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(self.bank, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn reset(&mut self) {
+        self.bank = 0;
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        if let 0xde00..=0xdeff = address {
+            self.bank = (value & 0x3f) as usize;
+        }
+    }
+}
@@ -0,0 +1,52 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   An 8K cartridge that is switched on by reads from $DFC0-$DFFF and off by reads from
+//   $DF00-$DFBF.
+
+pub struct RexUtility {
+    mode: Cell<Mode>,
+}
+
+impl RexUtility {
+    pub fn new() -> Self {
+        Self {
+            mode: Cell::new(Mode::Game8k),
+        }
+    }
+}
+
+impl Mapper for RexUtility {
+    fn get_mode(&self) -> Mode {
+        self.mode.get()
+    }
+
+    fn peek_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(0, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, address: u16) -> u8 {
+        match address {
+            0xdf00..=0xdfbf => self.mode.set(Mode::Off),
+            0xdfc0..=0xdfff => self.mode.set(Mode::Game8k),
+            _ => {}
+        }
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode.set(Mode::Game8k);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+}
@@ -0,0 +1,55 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   A 16K or 32K cartridge. A read from I/O 1 selects the second 16K bank and a read from
+//   I/O 2 switches the cartridge off.
+
+pub struct Ross {
+    mode: Cell<Mode>,
+    bank: Cell<usize>,
+}
+
+impl Ross {
+    pub fn new() -> Self {
+        Self {
+            mode: Cell::new(Mode::Game16k),
+            bank: Cell::new(0),
+        }
+    }
+}
+
+impl Mapper for Ross {
+    fn get_mode(&self) -> Mode {
+        self.mode.get()
+    }
+
+    fn peek_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(self.bank.get(), address)
+    }
+
+    fn read_io(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0xde00..=0xdeff if banks.get_count() > 1 => self.bank.set(1),
+            0xdf00..=0xdfff => self.mode.set(Mode::Off),
+            _ => {}
+        }
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode.set(Mode::Game16k);
+        self.bank.set(0);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+}
@@ -0,0 +1,54 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   A read from I/O 1 switches to 8K mode exposing the BASIC ROM at $A000 and a write
+//   switches back to 16K mode.
+
+pub struct SimonsBasic {
+    mode: Cell<Mode>,
+}
+
+impl SimonsBasic {
+    pub fn new() -> Self {
+        Self {
+            mode: Cell::new(Mode::Game16k),
+        }
+    }
+}
+
+impl Mapper for SimonsBasic {
+    fn get_mode(&self) -> Mode {
+        self.mode.get()
+    }
+
+    fn peek_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(0, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, address: u16) -> u8 {
+        if let 0xde00..=0xdeff = address {
+            self.mode.set(Mode::Game8k);
+        }
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode.set(Mode::Game16k);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, _value: u8) {
+        if let 0xde00..=0xdeff = address {
+            self.mode.set(Mode::Game16k);
+        }
+    }
+}
@@ -0,0 +1,69 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   Accesses to $DE00 or $DE01 select bank 0, $DE02 selects bank 1 and $DE03 switches
+//   the cartridge off. Both reads and writes are decoded.
+
+pub struct StructuredBasic {
+    mode: Cell<Mode>,
+    bank: Cell<usize>,
+}
+
+impl StructuredBasic {
+    pub fn new() -> Self {
+        Self {
+            mode: Cell::new(Mode::Game8k),
+            bank: Cell::new(0),
+        }
+    }
+
+    fn access(&self, address: u16) {
+        if let 0xde00..=0xdeff = address {
+            match address & 0x03 {
+                0 | 1 => {
+                    self.bank.set(0);
+                    self.mode.set(Mode::Game8k);
+                }
+                2 => {
+                    self.bank.set(1);
+                    self.mode.set(Mode::Game8k);
+                }
+                _ => self.mode.set(Mode::Off),
+            }
+        }
+    }
+}
+
+impl Mapper for StructuredBasic {
+    fn get_mode(&self) -> Mode {
+        self.mode.get()
+    }
+
+    fn peek_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(self.bank.get(), address)
+    }
+
+    fn read_io(&self, _banks: &Banks, address: u16) -> u8 {
+        self.access(address);
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode.set(Mode::Game8k);
+        self.bank.set(0);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, _value: u8) {
+        self.access(address);
+    }
+}
@@ -0,0 +1,59 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   Writes to $DF00 select a 16K bank with bits 0-1. Bit 2 switches the cartridge off and
+//   bit 3 write protects the register until reset.
+
+pub struct SuperGames {
+    mode: Mode,
+    bank: usize,
+    locked: bool,
+}
+
+impl SuperGames {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Game16k,
+            bank: 0,
+            locked: false,
+        }
+    }
+}
+
+impl Mapper for SuperGames {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(self.bank, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Game16k;
+        self.bank = 0;
+        self.locked = false;
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, value: u8) {
+        if let 0xdf00..=0xdfff = address {
+            if !self.locked {
+                self.bank = (value & 0x03) as usize;
+                self.mode = if value & 0x04 != 0 {
+                    Mode::Off
+                } else {
+                    Mode::Game16k
+                };
+                self.locked = value & 0x08 != 0;
+            }
+        }
+    }
+}
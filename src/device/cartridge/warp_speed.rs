@@ -0,0 +1,47 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   A 16K cartridge that is switched on by writes to I/O 1 and off by writes to I/O 2.
+//   Both I/O areas read the last 512 bytes of ROML.
+
+pub struct WarpSpeed {
+    mode: Mode,
+}
+
+impl WarpSpeed {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Game16k,
+        }
+    }
+}
+
+impl Mapper for WarpSpeed {
+    fn get_mode(&self) -> Mode {
+        self.mode
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(0, address)
+    }
+
+    fn read_io(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read_roml(0, 0x1e00 | (address & 0x1ff))
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Game16k;
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, address: u16, _value: u8) {
+        match address {
+            0xde00..=0xdeff => self.mode = Mode::Game16k,
+            0xdf00..=0xdfff => self.mode = Mode::Off,
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,49 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   A 16K cartridge that switches to 8K mode on a read from I/O 2.
+
+pub struct Westermann {
+    mode: Cell<Mode>,
+}
+
+impl Westermann {
+    pub fn new() -> Self {
+        Self {
+            mode: Cell::new(Mode::Game16k),
+        }
+    }
+}
+
+impl Mapper for Westermann {
+    fn get_mode(&self) -> Mode {
+        self.mode.get()
+    }
+
+    fn peek_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        banks.read(0, address)
+    }
+
+    fn read_io(&self, _banks: &Banks, address: u16) -> u8 {
+        if let 0xdf00..=0xdfff = address {
+            self.mode.set(Mode::Game8k);
+        }
+        0
+    }
+
+    fn reset(&mut self) {
+        self.mode.set(Mode::Game16k);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+}
@@ -0,0 +1,54 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+
+use super::{Banks, Mapper, Mode};
+
+// Design:
+//   Zaxxon and Super Zaxxon have a 4K ROML mirrored at $8000-$9FFF and two 8K ROMH banks.
+//   Reading ROML from $8000-$8FFF selects ROMH bank 0 and from $9000-$9FFF bank 1.
+
+pub struct Zaxxon {
+    bank: Cell<usize>,
+}
+
+impl Zaxxon {
+    pub fn new() -> Self {
+        Self { bank: Cell::new(0) }
+    }
+}
+
+impl Mapper for Zaxxon {
+    fn get_mode(&self) -> Mode {
+        Mode::Game16k
+    }
+
+    fn peek(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0x8000..=0x9fff => banks.read_roml(0, address),
+            _ => banks.read_romh(self.bank.get(), address),
+        }
+    }
+
+    fn read(&self, banks: &Banks, address: u16) -> u8 {
+        match address {
+            0x8000..=0x9fff => {
+                self.bank.set(if address < 0x9000 { 0 } else { 1 });
+                banks.read_roml(0, address)
+            }
+            _ => banks.read_romh(self.bank.get(), address),
+        }
+    }
+
+    fn read_io(&self, _banks: &Banks, _address: u16) -> u8 {
+        0
+    }
+
+    fn reset(&mut self) {
+        self.bank.set(0);
+    }
+
+    fn write_io(&mut self, _banks: &mut Banks, _address: u16, _value: u8) {}
+}
@@ -3,15 +3,15 @@
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
 use std::cell::RefCell;
+use std::f64;
 use std::rc::Rc;
 
 use bit_field::BitField;
-use core::{IoPort, Pin};
+use core::{Cpu, IoPort, Pin};
 
+use super::tape_decoder::TapeDecoder;
 use super::Tape;
 
-// DEFERRED device: datassette test cases
-
 /*
   +---------+---+------------+--------------------------------------------+
   |  NAME   |BIT| DIRECTION  |                 DESCRIPTION                |
@@ -27,8 +27,38 @@ use super::Tape;
 
 const DUTY_CYCLE: u32 = 50;
 
+// Tape transport parameters of a C2N with a C60 cassette
+const COUNTER_DIGITS: u32 = 1000;
+const HUB_RADIUS_CM: f64 = 1.1;
+const TAPE_SPEED_CM: f64 = 4.76;
+const TAPE_SIDE_SECONDS: f64 = 1800.0;
+const TAPE_THICKNESS_CM: f64 = 0.0016;
+const WIND_SPOOL_SPEED: f64 = 80.0;
+
+// Kernal tape read
+const ADDR_STATUS: u16 = 0x0090;
+const ADDR_VERIFY: u16 = 0x0093;
+const ADDR_END: u16 = 0x00ae;
+const ADDR_START: u16 = 0x00c1;
+const KERNAL_READ_BLOCK: u16 = 0xf8a1;
+const KERNAL_READ_BLOCK_SIG: [u8; 3] = [0x20, 0xbd, 0xfc];
+const KERNAL_READ_DONE: u16 = 0xfc93;
+const IRQ_VECTOR_READ: u8 = 0x0e;
+const STATUS_CHECKSUM: u8 = 0x20;
+const STATUS_READ_ERROR: u8 = 0x10;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Mode {
+    Stop,
+    Play,
+    Record,
+    FastForward,
+    Rewind,
+}
+
 #[derive(Copy, Clone)]
 enum ControlPort {
+    CassetteWrite = 3,
     CassetteSwitch = 4,
     CassetteMotor = 5,
 }
@@ -68,73 +98,116 @@ impl Pulse {
 }
 
 pub struct Datassette {
+    // Configuration
+    cpu_freq: u32,
     // Dependencies
     cia_flag_pin: Rc<RefCell<Pin>>,
     cpu_io_port: Rc<RefCell<IoPort>>,
     // Runtime State
-    playing: bool,
+    mode: Mode,
     tape: Option<Box<Tape>>,
+    tape_cycles: u64,
+    counter_offset: u32,
     current_pulse: Pulse,
+    wind_cycles: u32,
+    wind_pulse: Option<u32>,
+    write_cycles: u32,
+    write_line: bool,
 }
 
 impl Datassette {
-    pub fn new(cia_flag_pin: Rc<RefCell<Pin>>, cpu_io_port: Rc<RefCell<IoPort>>) -> Self {
+    pub fn new(
+        cpu_freq: u32,
+        cia_flag_pin: Rc<RefCell<Pin>>,
+        cpu_io_port: Rc<RefCell<IoPort>>,
+    ) -> Self {
         Self {
+            cpu_freq,
             cia_flag_pin,
             cpu_io_port,
-            playing: false,
+            mode: Mode::Stop,
             tape: None,
+            tape_cycles: 0,
+            counter_offset: 0,
             current_pulse: Pulse::new(0, DUTY_CYCLE),
+            wind_cycles: 0,
+            wind_pulse: None,
+            write_cycles: 0,
+            write_line: false,
         }
     }
 
     pub fn attach(&mut self, tape: Box<Tape>) {
+        if self.tape.is_some() {
+            self.detach();
+        }
         self.tape = Some(tape);
+        self.tape_cycles = 0;
+        self.counter_offset = 0;
     }
 
     pub fn clock(&mut self) {
-        if self.is_playing() && self.tape.is_some() {
-            if self.current_pulse.is_done() {
-                let pulse_maybe = if let Some(ref mut tape) = self.tape {
-                    tape.read_pulse()
-                } else {
-                    None
-                };
-                if let Some(pulse) = pulse_maybe {
-                    self.current_pulse = Pulse::new(pulse, DUTY_CYCLE);
-                } else {
-                    self.stop();
-                }
-            }
-            if !self.current_pulse.is_done() {
-                self.cia_flag_pin
-                    .borrow_mut()
-                    .set_active(self.current_pulse.advance());
+        if self.tape.is_some() {
+            match self.mode {
+                Mode::Play if self.is_motor_on() => self.clock_read(),
+                Mode::Record if self.is_motor_on() => self.clock_write(),
+                Mode::FastForward | Mode::Rewind => self.clock_wind(),
+                _ => {}
             }
         }
     }
 
     pub fn detach(&mut self) {
         self.stop();
-        self.tape = None;
+        if let Some(mut tape) = self.tape.take() {
+            if let Err(err) = tape.flush() {
+                error!(target: "device", "Failed to save tape: {}", err);
+            }
+        }
+    }
+
+    pub fn fast_forward(&mut self) {
+        info!(target: "device", "Fast forwarding datassette");
+        self.start(Mode::FastForward);
+    }
+
+    // Returns the tape counter which follows the rotations of the take-up reel.
+    pub fn get_counter(&self) -> u32 {
+        let radius = get_reel_radius(self.get_tape_seconds());
+        let rotations = ((radius - HUB_RADIUS_CM) / TAPE_THICKNESS_CM) as u32;
+        (rotations + COUNTER_DIGITS - self.counter_offset) % COUNTER_DIGITS
+    }
+
+    pub fn get_length(&self) -> usize {
+        self.tape.as_ref().map(|tape| tape.get_length()).unwrap_or(0)
+    }
+
+    pub fn get_position(&self) -> usize {
+        self.tape.as_ref().map(|tape| tape.get_position()).unwrap_or(0)
     }
 
     pub fn is_playing(&self) -> bool {
-        // Cassette motor control (0=motor spins)
-        let motor_on = !self.cpu_io_port
-            .borrow()
-            .get_value()
-            .get_bit(ControlPort::CassetteMotor.value());
-        self.playing & motor_on
+        (self.mode == Mode::Play || self.mode == Mode::Record) && self.is_motor_on()
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.mode == Mode::Record && self.is_motor_on()
+    }
+
+    pub fn is_winding(&self) -> bool {
+        self.mode == Mode::FastForward || self.mode == Mode::Rewind
     }
 
     pub fn play(&mut self) {
         info!(target: "device", "Starting datassette");
-        if self.tape.is_some() {
-            self.cpu_io_port
-                .borrow_mut()
-                .set_input_bit(ControlPort::CassetteSwitch.value(), false);
-            self.playing = true;
+        self.start(Mode::Play);
+    }
+
+    pub fn record(&mut self) {
+        info!(target: "device", "Recording datassette");
+        if self.start(Mode::Record) {
+            self.write_cycles = 0;
+            self.write_line = self.get_write_line();
         }
     }
 
@@ -142,18 +215,354 @@ impl Datassette {
         self.cpu_io_port
             .borrow_mut()
             .set_input_bit(ControlPort::CassetteSwitch.value(), true);
-        self.playing = false;
+        self.mode = Mode::Stop;
+        self.tape_cycles = 0;
         self.current_pulse = Pulse::new(0, DUTY_CYCLE);
+        self.wind_cycles = 0;
+        self.wind_pulse = None;
+        self.write_cycles = 0;
+        self.write_line = false;
         if let Some(ref mut tape) = self.tape {
             tape.seek(0);
         }
     }
 
+    pub fn reset_counter(&mut self) {
+        self.counter_offset = 0;
+        self.counter_offset = self.get_counter();
+    }
+
+    pub fn rewind(&mut self) {
+        info!(target: "device", "Rewinding datassette");
+        self.start(Mode::Rewind);
+    }
+
     pub fn stop(&mut self) {
-        info!(target: "device", "Stopping datassette");
+        info!(target: "device", "Stopping datassette at {:03}", self.get_counter());
         self.cpu_io_port
             .borrow_mut()
             .set_input_bit(ControlPort::CassetteSwitch.value(), true);
-        self.playing = false;
+        self.mode = Mode::Stop;
+        self.wind_cycles = 0;
+        self.wind_pulse = None;
+    }
+
+    // Replaces the kernal block read with a block decoded directly from the tape. The kernal
+    // has already waited for PLAY and set up STAL/EAL, so we store the data, finish the read
+    // the way the tape IRQ does and return to the caller. Returns false and leaves the tape
+    // position untouched if no kernal block is found, so turbo loaders play in real time.
+    pub fn trap(&mut self, cpu: &mut Box<dyn Cpu>) -> bool {
+        if cpu.get_pc() != KERNAL_READ_BLOCK || cpu.get_x() != IRQ_VECTOR_READ {
+            return false;
+        }
+        let matches = (0..3)
+            .all(|i| cpu.read(KERNAL_READ_BLOCK + i as u16) == KERNAL_READ_BLOCK_SIG[i]);
+        if !matches {
+            return false;
+        }
+        let (block, cycles) = match self.tape {
+            Some(ref mut tape) => {
+                let pos = tape.get_position();
+                let (block, cycles) = {
+                    let mut decoder = TapeDecoder::new(tape.as_mut());
+                    let block = decoder.read_block();
+                    (block, decoder.get_cycles())
+                };
+                if block.is_none() {
+                    tape.seek(pos);
+                }
+                (block, cycles)
+            }
+            None => return false,
+        };
+        let block = match block {
+            Some(block) => block,
+            None => return false,
+        };
+        self.tape_cycles += cycles;
+        let start = cpu.read(ADDR_START) as u16 | (cpu.read(ADDR_START + 1) as u16) << 8;
+        let end = cpu.read(ADDR_END) as u16 | (cpu.read(ADDR_END + 1) as u16) << 8;
+        let verify = cpu.read(ADDR_VERIFY) != 0;
+        info!(target: "device", "Fast loading block 0x{:04x}-0x{:04x}, size {}", start, end, block.data.len());
+        let mut status = if block.checksum_ok {
+            0
+        } else {
+            STATUS_CHECKSUM
+        };
+        let length = (end.wrapping_sub(start) as usize).min(block.data.len());
+        for (i, value) in block.data.iter().take(length).enumerate() {
+            let address = start.wrapping_add(i as u16);
+            if verify {
+                if cpu.read(address) != *value {
+                    status |= STATUS_READ_ERROR;
+                }
+            } else {
+                cpu.write(address, *value);
+            }
+        }
+        let st = cpu.read(ADDR_STATUS);
+        cpu.write(ADDR_STATUS, st | status);
+        // Clear carry and interrupt disable, which are restored when KERNAL_READ_DONE returns.
+        let p = cpu.get_p();
+        cpu.set_p(p & !0x05);
+        cpu.set_pc(KERNAL_READ_DONE);
+        true
+    }
+
+    // -- Tape Ops
+
+    fn clock_read(&mut self) {
+        if self.current_pulse.is_done() {
+            let pulse_maybe = if let Some(ref mut tape) = self.tape {
+                tape.read_pulse()
+            } else {
+                None
+            };
+            if let Some(pulse) = pulse_maybe {
+                self.current_pulse = Pulse::new(pulse, DUTY_CYCLE);
+                self.tape_cycles += pulse as u64;
+            } else {
+                self.stop();
+            }
+        }
+        if !self.current_pulse.is_done() {
+            self.cia_flag_pin
+                .borrow_mut()
+                .set_active(self.current_pulse.advance());
+        }
+    }
+
+    fn clock_wind(&mut self) {
+        self.wind_cycles += self.get_wind_speed();
+        loop {
+            if self.wind_pulse.is_none() {
+                self.wind_pulse = self.next_wind_pulse();
+            }
+            match self.wind_pulse {
+                Some(pulse) if pulse <= self.wind_cycles => {
+                    self.wind_cycles -= pulse;
+                    self.wind_pulse = None;
+                    self.wind_tape(pulse);
+                }
+                Some(_) => break,
+                None => {
+                    self.stop();
+                    break;
+                }
+            }
+        }
+    }
+
+    fn clock_write(&mut self) {
+        // A pulse is the time between two rising edges of the cassette write line.
+        self.write_cycles = self.write_cycles.saturating_add(1);
+        let write_line = self.get_write_line();
+        if write_line && !self.write_line {
+            if let Some(ref mut tape) = self.tape {
+                tape.write_pulse(self.write_cycles);
+            }
+            self.tape_cycles += self.write_cycles as u64;
+            self.write_cycles = 0;
+        }
+        self.write_line = write_line;
+    }
+
+    fn wind_tape(&mut self, pulse: u32) {
+        if let Some(ref mut tape) = self.tape {
+            let pos = tape.get_position();
+            if self.mode == Mode::FastForward {
+                tape.seek(pos + 1);
+                self.tape_cycles += pulse as u64;
+            } else {
+                tape.seek(pos - 1);
+                self.tape_cycles = self.tape_cycles.saturating_sub(pulse as u64);
+            }
+        }
+    }
+
+    fn get_tape_seconds(&self) -> f64 {
+        self.tape_cycles as f64 / self.cpu_freq as f64
+    }
+
+    // Returns the number of play-time cycles wound per clock. The motor turns the driving reel
+    // (take-up for fast forward, supply for rewind) at a constant angular speed, so the tape
+    // moves faster as tape builds up on that reel. The supply reel assumes a full C60 side.
+    fn get_wind_speed(&self) -> u32 {
+        let seconds = self.get_tape_seconds();
+        let radius = match self.mode {
+            Mode::Rewind => get_reel_radius((TAPE_SIDE_SECONDS - seconds).max(0.0)),
+            _ => get_reel_radius(seconds),
+        };
+        ((WIND_SPOOL_SPEED * radius / TAPE_SPEED_CM) as u32).max(1)
+    }
+
+    fn get_write_line(&self) -> bool {
+        self.cpu_io_port
+            .borrow()
+            .get_value()
+            .get_bit(ControlPort::CassetteWrite.value())
+    }
+
+    fn is_motor_on(&self) -> bool {
+        // Cassette motor control (0=motor spins)
+        !self.cpu_io_port
+            .borrow()
+            .get_value()
+            .get_bit(ControlPort::CassetteMotor.value())
+    }
+
+    // Returns the length of the next pulse in winding direction without moving the tape.
+    fn next_wind_pulse(&mut self) -> Option<u32> {
+        let mode = self.mode;
+        let tape = self.tape.as_mut()?;
+        let pos = tape.get_position();
+        let pulse = match mode {
+            Mode::FastForward => tape.read_pulse(),
+            Mode::Rewind if pos > 0 => {
+                tape.seek(pos - 1);
+                tape.read_pulse()
+            }
+            _ => None,
+        };
+        tape.seek(pos);
+        pulse
+    }
+
+    fn start(&mut self, mode: Mode) -> bool {
+        if self.tape.is_some() {
+            // All transport keys close the cassette sense switch.
+            self.cpu_io_port
+                .borrow_mut()
+                .set_input_bit(ControlPort::CassetteSwitch.value(), false);
+            self.mode = mode;
+            self.current_pulse = Pulse::new(0, DUTY_CYCLE);
+            self.wind_cycles = 0;
+            self.wind_pulse = None;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Returns the radius of a reel holding the given play time of tape.
+fn get_reel_radius(seconds: f64) -> f64 {
+    let wound = TAPE_SPEED_CM * seconds * TAPE_THICKNESS_CM / f64::consts::PI;
+    (HUB_RADIUS_CM * HUB_RADIUS_CM + wound).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tape::tests::PulseTape;
+    use super::*;
+
+    struct Setup {
+        datassette: Datassette,
+        cpu_io_port: Rc<RefCell<IoPort>>,
+        written: Rc<RefCell<Vec<u32>>>,
+    }
+
+    fn setup_datassette(pulses: Vec<u32>) -> Setup {
+        let flag_pin = Rc::new(RefCell::new(Pin::new_low()));
+        let cpu_io_port = Rc::new(RefCell::new(IoPort::new(0x2f, 0xff)));
+        let tape = PulseTape::new(pulses);
+        let written = tape.get_pulses();
+        let mut datassette = Datassette::new(985_248, flag_pin, cpu_io_port.clone());
+        datassette.attach(Box::new(tape));
+        datassette.reset();
+        Setup {
+            datassette,
+            cpu_io_port,
+            written,
+        }
+    }
+
+    #[test]
+    fn record_pulses_from_write_line() {
+        let mut setup = setup_datassette(Vec::new());
+        let cpu_io_port = setup.cpu_io_port.clone();
+        // Motor on, write line low
+        cpu_io_port.borrow_mut().set_value(0x07);
+        setup.datassette.record();
+        assert_eq!(true, setup.datassette.is_recording());
+        assert_eq!(false, cpu_io_port.borrow().get_value().get_bit(4));
+        for pulse in [100, 100, 200, 200].iter() {
+            for i in 0..*pulse {
+                let write_line = i >= pulse / 2;
+                cpu_io_port
+                    .borrow_mut()
+                    .set_value(0x07 | if write_line { 0x08 } else { 0x00 });
+                setup.datassette.clock();
+            }
+        }
+        // First pulse is the gap between pressing record and the first edge
+        assert_eq!(vec![51, 100, 150, 200], *setup.written.borrow());
+    }
+
+    #[test]
+    fn record_stops_with_motor() {
+        let mut setup = setup_datassette(Vec::new());
+        let cpu_io_port = setup.cpu_io_port.clone();
+        // Motor off
+        cpu_io_port.borrow_mut().set_value(0x27);
+        setup.datassette.record();
+        assert_eq!(false, setup.datassette.is_recording());
+        for i in 0..100 {
+            cpu_io_port.borrow_mut().set_value(0x27 | ((i & 0x01) << 3));
+            setup.datassette.clock();
+        }
+        assert_eq!(true, setup.written.borrow().is_empty());
+    }
+
+    fn wind(datassette: &mut Datassette, cycles: u32) {
+        let mut wound = 0;
+        while wound < cycles && datassette.is_winding() {
+            wound += datassette.get_wind_speed();
+            datassette.clock();
+        }
+    }
+
+    #[test]
+    fn fast_forward_and_rewind() {
+        let mut setup = setup_datassette(vec![300; 10]);
+        setup.datassette.fast_forward();
+        assert_eq!(true, setup.datassette.is_winding());
+        wind(&mut setup.datassette, 300 * 4);
+        assert_eq!(4, setup.datassette.get_position());
+        wind(&mut setup.datassette, 300 * 10);
+        assert_eq!(10, setup.datassette.get_position());
+        assert_eq!(false, setup.datassette.is_winding());
+        setup.datassette.rewind();
+        wind(&mut setup.datassette, 300 * 3);
+        assert_eq!(7, setup.datassette.get_position());
+        setup.datassette.stop();
+        assert_eq!(false, setup.datassette.is_winding());
+    }
+
+    #[test]
+    fn counter_follows_tape() {
+        let mut setup = setup_datassette(vec![0x00ff_ffff; 20]);
+        assert_eq!(0, setup.datassette.get_counter());
+        setup.datassette.fast_forward();
+        wind(&mut setup.datassette, 0x00ff_ffff * 21);
+        let counter = setup.datassette.get_counter();
+        assert_eq!(true, counter > 0);
+        setup.datassette.reset_counter();
+        assert_eq!(0, setup.datassette.get_counter());
+        setup.datassette.rewind();
+        wind(&mut setup.datassette, 0x00ff_ffff * 21);
+        assert_eq!(COUNTER_DIGITS - counter, setup.datassette.get_counter());
+    }
+
+    #[test]
+    fn wind_speed_follows_reel_radius() {
+        let mut setup = setup_datassette(vec![0x00ff_ffff; 10]);
+        setup.datassette.rewind();
+        let rewind_speed = setup.datassette.get_wind_speed();
+        setup.datassette.fast_forward();
+        let start_speed = setup.datassette.get_wind_speed();
+        assert_eq!(true, start_speed < rewind_speed);
+        wind(&mut setup.datassette, 0x00ff_ffff * 5);
+        assert_eq!(true, setup.datassette.get_wind_speed() > start_speed);
     }
 }
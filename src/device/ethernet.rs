@@ -0,0 +1,94 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::ops::RangeInclusive;
+
+use core::Addressable;
+use device::{DeviceKind, ExpansionDevice};
+
+// SPEC: Cirrus Logic CS8900A as used by TFE and RR-Net, 8-bit I/O mode
+// Design:
+//   Stand-in for a CS8900A based ethernet interface. It decodes 16 registers from its base
+//   address so drivers can detect the card, but it has no link. Written values are latched
+//   and read back, and the interrupt status queue at offsets 8-9 always reads zero, so no
+//   frames are ever received. Several interfaces can share the port at different bases.
+
+const REG_COUNT: usize = 16;
+const REG_ISQ: usize = 0x08;
+
+pub struct Ethernet {
+    io_ranges: [RangeInclusive<u16>; 1],
+    regs: [u8; REG_COUNT],
+}
+
+impl Ethernet {
+    pub fn new(base: u16) -> Self {
+        if !(0xde00..=0xdff0).contains(&base) || base & 0x0f != 0 {
+            panic!("invalid ethernet base address 0x{:04x}", base);
+        }
+        Ethernet {
+            io_ranges: [base..=base + REG_COUNT as u16 - 1],
+            regs: [0; REG_COUNT],
+        }
+    }
+
+    pub fn get_base(&self) -> u16 {
+        *self.io_ranges[0].start()
+    }
+
+    fn get_reg(&self, address: u16) -> Option<usize> {
+        if self.io_ranges[0].contains(&address) {
+            Some((address - self.get_base()) as usize)
+        } else {
+            None
+        }
+    }
+}
+
+impl ExpansionDevice for Ethernet {
+    fn get_io_ranges(&self) -> &[RangeInclusive<u16>] {
+        &self.io_ranges
+    }
+
+    fn get_kind(&self) -> DeviceKind {
+        DeviceKind::Network
+    }
+
+    fn reset(&mut self) {
+        self.regs = [0; REG_COUNT];
+    }
+}
+
+impl Addressable for Ethernet {
+    fn read(&self, address: u16) -> u8 {
+        match self.get_reg(address) {
+            Some(reg) if reg & !1 == REG_ISQ => 0,
+            Some(reg) => self.regs[reg],
+            None => 0,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if let Some(reg) = self.get_reg(address) {
+            self.regs[reg] = value;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn latch_registers() {
+        let mut ethernet = Ethernet::new(0xde00);
+        ethernet.write(0xde0a, 0x5c);
+        ethernet.write(0xde08, 0xff);
+        assert_eq!(0x5c, ethernet.read(0xde0a));
+        assert_eq!(0x00, ethernet.read(0xde08));
+        ethernet.write(0xdf00, 0x11);
+        ethernet.reset();
+        assert_eq!(0x00, ethernet.read(0xde0a));
+    }
+}
@@ -0,0 +1,82 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::io;
+use std::ops::RangeInclusive;
+
+use core::Addressable;
+
+// Design:
+//   Devices plugged into the expansion port drive the GAME, EXROM, IRQ and NMI lines and
+//   may request DMA. The defaults describe a passive device that leaves all lines high.
+//   Each device claims the parts of $DE00-$DFFF it decodes so the port can detect devices
+//   that would drive the bus at the same time. Devices with volatile contents are flushed
+//   to their store when they are detached. A system has room for one cartridge, RAM
+//   expansion and MIDI interface, while network interfaces can be stacked at different
+//   I/O addresses.
+
+// Kinds of devices in the order of their priority on the GAME and EXROM lines
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd)]
+pub enum DeviceKind {
+    Cartridge,
+    RamExpansion,
+    Midi,
+    Network,
+}
+
+impl DeviceKind {
+    pub fn is_exclusive(&self) -> bool {
+        match *self {
+            DeviceKind::Cartridge | DeviceKind::RamExpansion | DeviceKind::Midi => true,
+            DeviceKind::Network => false,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DmaAccess {
+    Read(u16),
+    Write(u16, u8),
+}
+
+pub trait ExpansionDevice: Addressable {
+    fn get_exrom(&self) -> bool {
+        true
+    }
+    fn get_game(&self) -> bool {
+        true
+    }
+    fn get_io_ranges(&self) -> &[RangeInclusive<u16>] {
+        &[]
+    }
+    fn get_kind(&self) -> DeviceKind;
+    fn is_dma_active(&self) -> bool {
+        false
+    }
+    fn is_io_claimed(&self, address: u16) -> bool {
+        self.get_io_ranges()
+            .iter()
+            .any(|range| range.contains(&address))
+    }
+    fn is_irq_low(&self) -> bool {
+        false
+    }
+    fn is_nmi_low(&self) -> bool {
+        false
+    }
+    fn clock(&mut self) {}
+    fn complete_dma(&mut self, _value: u8) {}
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn freeze(&mut self) {}
+    fn get_dma_access(&self) -> Option<DmaAccess> {
+        None
+    }
+    fn reset(&mut self);
+}
+
+pub trait RamStore {
+    fn save(&mut self, data: &[u8]) -> io::Result<()>;
+}
@@ -2,15 +2,32 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 use bit_field::BitField;
-use core::{Addressable, IoPort};
+use core::{Addressable, IoPort, IrqLine, Pin};
 
-use super::cartridge::Cartridge;
+use super::{DeviceKind, DmaAccess, ExpansionDevice};
 
-// DEFERRED device: expansion port test cases
+// Design:
+//   Cartridges can change GAME and EXROM while the memory controller is busy with the access
+//   that triggered the change, so the lines are sampled on every clock and io_line is only
+//   updated when they differ from the last value. IRQ and NMI are sampled the same way, so a
+//   cartridge triggers NMI when its line goes low.
+//   The port hosts a list of devices ordered by kind priority and then by attach order.
+//   Attaching a device of an exclusive kind replaces the one already there, while other
+//   kinds are added next to it. A device is only attached if the I/O ranges it claims do not
+//   overlap those of the devices it is added to, and reads from I/O
+//   addresses no device claims return the value left on the data bus by the VIC. GAME and
+//   EXROM are driven by the first device that does not leave both lines high, so a RAM
+//   expansion can not combine with a cartridge into an unintended memory configuration.
+//   ROML/ROMH reads go to the same device while writes and $FF00 are seen by all devices.
+//   While a DMA transfer is running BA is held low on every clock because the VIC updates
+//   it independently. Devices are flushed to their store when they are detached.
+
+// Interrupt source of the expansion port, following CIA 1, CIA 2 and VIC
+const IRQ_SOURCE: usize = 3;
 
 #[derive(Copy, Clone)]
 enum IoLine {
@@ -25,65 +42,460 @@ impl IoLine {
 }
 
 pub struct ExpansionPort {
-    cartridge: Option<Cartridge>,
+    devices: Vec<Box<dyn ExpansionDevice>>,
     // I/O
+    ba_line: Rc<RefCell<Pin>>,
+    data_bus: Rc<Cell<u8>>,
     io_line: Rc<RefCell<IoPort>>,
+    io_value: u8,
+    irq_line: Rc<RefCell<IrqLine>>,
+    irq: bool,
+    nmi_line: Rc<RefCell<IrqLine>>,
+    nmi: bool,
 }
 
 impl ExpansionPort {
-    pub fn new(io_line: Rc<RefCell<IoPort>>) -> Self {
+    pub fn new(
+        ba_line: Rc<RefCell<Pin>>,
+        io_line: Rc<RefCell<IoPort>>,
+        irq_line: Rc<RefCell<IrqLine>>,
+        nmi_line: Rc<RefCell<IrqLine>>,
+        data_bus: Rc<Cell<u8>>,
+    ) -> Self {
         Self {
-            cartridge: None,
+            devices: Vec::new(),
+            ba_line,
+            data_bus,
             io_line,
+            io_value: 0,
+            irq_line,
+            irq: false,
+            nmi_line,
+            nmi: false,
         }
     }
 
-    pub fn attach(&mut self, cartridge: Cartridge) {
-        let mut io_value = 0u8;
-        io_value.set_bit(IoLine::Game.value(), cartridge.get_game());
-        io_value.set_bit(IoLine::Exrom.value(), cartridge.get_exrom());
-        self.io_line.borrow_mut().set_value(io_value);
-        self.cartridge = Some(cartridge);
+    // Adds the device unless it conflicts with another one, replacing the device of the same
+    // kind if the kind is exclusive.
+    pub fn attach(&mut self, device: Box<dyn ExpansionDevice>) -> Result<(), String> {
+        let kind = device.get_kind();
+        let replaced =
+            |other: &dyn ExpansionDevice| kind.is_exclusive() && other.get_kind() == kind;
+        for other in self
+            .devices
+            .iter()
+            .filter(|other| !replaced(other.as_ref()))
+        {
+            if let Some(address) = Self::find_conflict(device.as_ref(), other.as_ref()) {
+                return Err(format!(
+                    "{:?} conflicts with {:?} at 0x{:04x}",
+                    kind,
+                    other.get_kind(),
+                    address
+                ));
+            }
+        }
+        if kind.is_exclusive() {
+            self.detach(kind);
+        }
+        let index = self
+            .devices
+            .iter()
+            .position(|other| other.get_kind() > kind)
+            .unwrap_or(self.devices.len());
+        self.devices.insert(index, device);
+        self.update_lines();
+        Ok(())
+    }
+
+    pub fn clock(&mut self) {
+        for device in self.devices.iter_mut() {
+            device.clock();
+        }
+        if self.get_io_value() != self.io_value {
+            self.update_io_line();
+        }
+        self.update_irq_lines();
+        self.update_ba_line();
     }
 
-    pub fn detach(&mut self) {
-        if self.cartridge.is_some() {
-            self.cartridge = None;
-            let mut io_value = 0u8;
-            io_value.set_bit(IoLine::Game.value(), true);
-            io_value.set_bit(IoLine::Exrom.value(), true);
-            self.io_line.borrow_mut().set_value(io_value);
+    pub fn complete_dma(&mut self, value: u8) {
+        if let Some(device) = self.devices.iter_mut().find(|device| device.is_dma_active()) {
+            device.complete_dma(value);
         }
     }
 
+    // Removes the device of this kind that was attached last.
+    pub fn detach(&mut self, kind: DeviceKind) -> Option<Box<dyn ExpansionDevice>> {
+        let index = self
+            .devices
+            .iter()
+            .rposition(|device| device.get_kind() == kind)?;
+        let mut device = self.devices.remove(index);
+        if let Err(err) = device.flush() {
+            error!(target: "device", "Failed to save {:?}: {}", kind, err);
+        }
+        self.update_lines();
+        Some(device)
+    }
+
+    pub fn freeze(&mut self) {
+        for device in self.devices.iter_mut() {
+            if device.get_kind() == DeviceKind::Cartridge {
+                info!(target: "device", "Freezing cartridge");
+                device.freeze();
+            }
+        }
+    }
+
+    pub fn get_dma_access(&self) -> Option<DmaAccess> {
+        self.devices
+            .iter()
+            .find(|device| device.is_dma_active())
+            .and_then(|device| device.get_dma_access())
+    }
+
     pub fn reset(&mut self) {
-        if let Some(ref mut cartridge) = self.cartridge {
-            cartridge.reset();
-            let mut io_value = 0u8;
-            io_value.set_bit(IoLine::Game.value(), cartridge.get_game());
-            io_value.set_bit(IoLine::Exrom.value(), cartridge.get_exrom());
-            self.io_line.borrow_mut().set_value(io_value);
-        } else {
-            let mut io_value = 0u8;
-            io_value.set_bit(IoLine::Game.value(), true);
-            io_value.set_bit(IoLine::Exrom.value(), true);
-            self.io_line.borrow_mut().set_value(io_value);
+        for device in self.devices.iter_mut() {
+            device.reset();
+        }
+        self.update_lines();
+    }
+
+    fn find_conflict(device: &dyn ExpansionDevice, other: &dyn ExpansionDevice) -> Option<u16> {
+        for range in device.get_io_ranges() {
+            for other_range in other.get_io_ranges() {
+                if range.start() <= other_range.end() && other_range.start() <= range.end() {
+                    return Some(*range.start().max(other_range.start()));
+                }
+            }
         }
+        None
+    }
+
+    // The device driving GAME and EXROM is also the one mapped at ROML and ROMH.
+    fn get_memory_device(&self) -> Option<&dyn ExpansionDevice> {
+        self.devices
+            .iter()
+            .find(|device| !device.get_game() || !device.get_exrom())
+            .map(|device| device.as_ref())
+    }
+
+    fn get_io_value(&self) -> u8 {
+        let (game, exrom) = match self.get_memory_device() {
+            Some(device) => (device.get_game(), device.get_exrom()),
+            None => (true, true),
+        };
+        let mut io_value = 0u8;
+        io_value.set_bit(IoLine::Game.value(), game);
+        io_value.set_bit(IoLine::Exrom.value(), exrom);
+        io_value
+    }
+
+    // The VIC drives BA on every cycle before the port is clocked, so the port only pulls
+    // the line low during DMA and otherwise leaves the VIC's state in place.
+    fn update_ba_line(&mut self) {
+        let dma = self.devices.iter().any(|device| device.is_dma_active());
+        if dma {
+            self.ba_line.borrow_mut().set_active(false);
+        }
+    }
+
+    fn update_io_line(&mut self) {
+        self.io_value = self.get_io_value();
+        self.io_line.borrow_mut().set_value(self.io_value);
+    }
+
+    fn update_irq_lines(&mut self) {
+        let irq = self.devices.iter().any(|device| device.is_irq_low());
+        let nmi = self.devices.iter().any(|device| device.is_nmi_low());
+        if irq != self.irq {
+            self.irq = irq;
+            self.irq_line.borrow_mut().set_low(IRQ_SOURCE, irq);
+        }
+        if nmi != self.nmi {
+            self.nmi = nmi;
+            self.nmi_line.borrow_mut().set_low(IRQ_SOURCE, nmi);
+        }
+    }
+
+    fn update_lines(&mut self) {
+        self.update_io_line();
+        self.update_irq_lines();
+        self.update_ba_line();
     }
 }
 
 impl Addressable for ExpansionPort {
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0xde00..=0xdfff => match self
+                .devices
+                .iter()
+                .find(|device| device.is_io_claimed(address))
+            {
+                Some(device) => device.peek(address),
+                None => self.data_bus.get(),
+            },
+            _ => match self.get_memory_device() {
+                Some(device) => device.peek(address),
+                None => self.data_bus.get(),
+            },
+        }
+    }
+
     fn read(&self, address: u16) -> u8 {
-        if let Some(ref cartridge) = self.cartridge {
-            cartridge.read(address)
-        } else {
-            0
+        match address {
+            0xde00..=0xdfff => match self
+                .devices
+                .iter()
+                .find(|device| device.is_io_claimed(address))
+            {
+                Some(device) => device.read(address),
+                None => self.data_bus.get(),
+            },
+            _ => match self.get_memory_device() {
+                Some(device) => device.read(address),
+                None => self.data_bus.get(),
+            },
         }
     }
 
     fn write(&mut self, address: u16, value: u8) {
-        if let Some(ref mut cartridge) = self.cartridge {
-            cartridge.write(address, value)
+        match address {
+            0xde00..=0xdfff => {
+                if let Some(device) = self
+                    .devices
+                    .iter_mut()
+                    .find(|device| device.is_io_claimed(address))
+                {
+                    device.write(address, value);
+                }
+            }
+            _ => {
+                for device in self.devices.iter_mut() {
+                    device.write(address, value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use device::cartridge::{Cartridge, Chip, ChipType, HwType};
+    use device::{Ethernet, GeoRam, GeoRamModel, Reu};
+
+    fn setup_cartridge(hw_type: HwType) -> Box<dyn ExpansionDevice> {
+        let mut cartridge = Cartridge::new(0x0100, hw_type, false, true);
+        cartridge.add(Chip {
+            chip_type: ChipType::Rom,
+            bank_number: 0,
+            offset: 0x8000,
+            size: 0x2000,
+            data: vec![0; 0x2000],
+        });
+        Box::new(cartridge)
+    }
+
+    fn setup_expansion_port() -> (
+        ExpansionPort,
+        Rc<RefCell<IoPort>>,
+        Rc<RefCell<IrqLine>>,
+    ) {
+        let ba_line = Rc::new(RefCell::new(Pin::new_high()));
+        let io_line = Rc::new(RefCell::new(IoPort::new(0xff, 0xff)));
+        let irq_line = Rc::new(RefCell::new(IrqLine::new("irq")));
+        let nmi_line = Rc::new(RefCell::new(IrqLine::new("nmi")));
+        let data_bus = Rc::new(Cell::new(0x55));
+        let expansion_port = ExpansionPort::new(
+            ba_line,
+            io_line.clone(),
+            irq_line,
+            nmi_line.clone(),
+            data_bus,
+        );
+        (expansion_port, io_line, nmi_line)
+    }
+
+    // RAM expansion stand-in that pulls GAME low like a 16K cartridge
+    struct Game16k;
+
+    impl Addressable for Game16k {
+        fn read(&self, _address: u16) -> u8 {
+            0x16
         }
+
+        fn write(&mut self, _address: u16, _value: u8) {}
+    }
+
+    impl ExpansionDevice for Game16k {
+        fn get_exrom(&self) -> bool {
+            false
+        }
+        fn get_game(&self) -> bool {
+            false
+        }
+        fn get_kind(&self) -> DeviceKind {
+            DeviceKind::RamExpansion
+        }
+        fn reset(&mut self) {}
+    }
+
+    #[test]
+    fn attach_detects_io_conflict() {
+        let (mut expansion_port, _, _) = setup_expansion_port();
+        expansion_port.attach(setup_cartridge(HwType::EasyFlash)).unwrap();
+        let result = expansion_port.attach(Box::new(Reu::new(0x20000)));
+        assert_eq!(Err("RamExpansion conflicts with Cartridge at 0xdf00".to_string()), result);
+        assert_eq!(true, expansion_port.detach(DeviceKind::RamExpansion).is_none());
+        expansion_port.attach(setup_cartridge(HwType::OceanType1)).unwrap();
+        assert_eq!(true, expansion_port.attach(Box::new(Reu::new(0x20000))).is_ok());
+    }
+
+    #[test]
+    fn clock_updates_io_line() {
+        let (mut expansion_port, io_line, _) = setup_expansion_port();
+        expansion_port.attach(setup_cartridge(HwType::MagicDesk)).unwrap();
+        assert_eq!(0x08, io_line.borrow().get_value());
+        expansion_port.write(0xde00, 0x80);
+        assert_eq!(0x08, io_line.borrow().get_value());
+        expansion_port.clock();
+        assert_eq!(0x18, io_line.borrow().get_value());
+        expansion_port.detach(DeviceKind::Cartridge);
+        assert_eq!(0x18, io_line.borrow().get_value());
+    }
+
+    #[test]
+    fn ethernet_next_to_cartridge() {
+        let (mut expansion_port, io_line, _) = setup_expansion_port();
+        expansion_port.attach(setup_cartridge(HwType::Normal)).unwrap();
+        expansion_port.attach(Box::new(Ethernet::new(0xde00))).unwrap();
+        expansion_port.attach(Box::new(Ethernet::new(0xde10))).unwrap();
+        assert_eq!(
+            Err("Network conflicts with Network at 0xde10".to_string()),
+            expansion_port.attach(Box::new(Ethernet::new(0xde10)))
+        );
+        assert_eq!(
+            Err("Cartridge conflicts with Network at 0xde00".to_string()),
+            expansion_port.attach(setup_cartridge(HwType::OceanType1))
+        );
+        assert_eq!(0x08, io_line.borrow().get_value());
+        expansion_port.write(0xde0a, 0x01);
+        expansion_port.write(0xde1a, 0x02);
+        assert_eq!(0x01, expansion_port.read(0xde0a));
+        assert_eq!(0x02, expansion_port.read(0xde1a));
+        assert_eq!(0x00, expansion_port.read(0x8000));
+        assert_eq!(true, expansion_port.detach(DeviceKind::Network).is_some());
+        assert_eq!(0x55, expansion_port.read(0xde1a));
+        assert_eq!(0x01, expansion_port.read(0xde0a));
+    }
+
+    #[test]
+    fn freeze_triggers_nmi() {
+        let (mut expansion_port, io_line, nmi_line) = setup_expansion_port();
+        expansion_port.attach(setup_cartridge(HwType::ActionReplay)).unwrap();
+        expansion_port.freeze();
+        expansion_port.clock();
+        assert_eq!(true, nmi_line.borrow().is_low());
+        assert_eq!(0x10, io_line.borrow().get_value());
+        // NMI is acknowledged by the cpu and released by the freezer code
+        nmi_line.borrow_mut().reset();
+        expansion_port.write(0xde00, 0x40);
+        expansion_port.clock();
+        assert_eq!(false, nmi_line.borrow().is_low());
+        assert_eq!(0x08, io_line.borrow().get_value());
+    }
+
+    #[test]
+    fn game_exrom_priority() {
+        let (mut expansion_port, io_line, _) = setup_expansion_port();
+        expansion_port.attach(Box::new(Game16k)).unwrap();
+        assert_eq!(0x00, io_line.borrow().get_value());
+        assert_eq!(0x16, expansion_port.read(0x8000));
+        expansion_port.attach(setup_cartridge(HwType::Normal)).unwrap();
+        assert_eq!(0x08, io_line.borrow().get_value());
+        assert_eq!(0x00, expansion_port.read(0x8000));
+    }
+
+    #[test]
+    fn georam_claims_io() {
+        let (mut expansion_port, _, _) = setup_expansion_port();
+        expansion_port.attach(setup_cartridge(HwType::Normal)).unwrap();
+        expansion_port
+            .attach(Box::new(GeoRam::new(GeoRamModel::GeoRam, 0x10000)))
+            .unwrap();
+        expansion_port.write(0xde00, 0x42);
+        assert_eq!(0x42, expansion_port.read(0xde00));
+        assert_eq!(0x00, expansion_port.read(0x8000));
+        assert_eq!(true, expansion_port.detach(DeviceKind::RamExpansion).is_some());
+        assert_eq!(0x55, expansion_port.read(0xde00));
+    }
+
+    #[test]
+    fn open_bus_read() {
+        let (mut expansion_port, _, _) = setup_expansion_port();
+        assert_eq!(0x55, expansion_port.read(0xde00));
+        expansion_port.attach(setup_cartridge(HwType::SuperGames)).unwrap();
+        assert_eq!(0x55, expansion_port.read(0xde00));
+        assert_eq!(0x00, expansion_port.read(0xdf00));
+    }
+
+    #[test]
+    fn reu_dma_holds_ba_line() {
+        let ba_line = Rc::new(RefCell::new(Pin::new_high()));
+        let io_line = Rc::new(RefCell::new(IoPort::new(0xff, 0xff)));
+        let irq_line = Rc::new(RefCell::new(IrqLine::new("irq")));
+        let nmi_line = Rc::new(RefCell::new(IrqLine::new("nmi")));
+        let data_bus = Rc::new(Cell::new(0));
+        let mut expansion_port = ExpansionPort::new(
+            ba_line.clone(),
+            io_line,
+            irq_line.clone(),
+            nmi_line,
+            data_bus,
+        );
+        expansion_port.attach(Box::new(Reu::new(0x20000))).unwrap();
+        expansion_port.write(0xdf07, 0x01);
+        expansion_port.write(0xdf08, 0x00);
+        expansion_port.write(0xdf09, 0xc0);
+        expansion_port.write(0xdf21, 0x91);
+        assert_eq!(None, expansion_port.get_dma_access());
+        expansion_port.clock();
+        assert_eq!(true, ba_line.borrow().is_low());
+        assert_eq!(Some(DmaAccess::Write(0x0000, 0x00)), expansion_port.get_dma_access());
+        expansion_port.complete_dma(0x00);
+        // VIC releases BA
+        ba_line.borrow_mut().set_active(true);
+        expansion_port.clock();
+        assert_eq!(true, ba_line.borrow().is_high());
+        assert_eq!(true, irq_line.borrow().is_low());
+        assert_eq!(0xc0, expansion_port.read(0xdf00) & 0xc0);
+        expansion_port.clock();
+        assert_eq!(false, irq_line.borrow().is_low());
+    }
+
+    #[test]
+    fn reu_dma_end_keeps_vic_ba_line() {
+        let ba_line = Rc::new(RefCell::new(Pin::new_high()));
+        let io_line = Rc::new(RefCell::new(IoPort::new(0xff, 0xff)));
+        let irq_line = Rc::new(RefCell::new(IrqLine::new("irq")));
+        let nmi_line = Rc::new(RefCell::new(IrqLine::new("nmi")));
+        let data_bus = Rc::new(Cell::new(0));
+        let mut expansion_port =
+            ExpansionPort::new(ba_line.clone(), io_line, irq_line, nmi_line, data_bus);
+        expansion_port.attach(Box::new(Reu::new(0x20000))).unwrap();
+        expansion_port.write(0xdf07, 0x01);
+        expansion_port.write(0xdf08, 0x00);
+        expansion_port.write(0xdf01, 0x90);
+        expansion_port.clock();
+        assert_eq!(true, ba_line.borrow().is_low());
+        expansion_port.complete_dma(0x00);
+        // VIC holds BA for a bad line
+        ba_line.borrow_mut().set_active(false);
+        expansion_port.clock();
+        assert_eq!(None, expansion_port.get_dma_access());
+        assert_eq!(true, ba_line.borrow().is_low());
     }
 }
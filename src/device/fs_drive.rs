@@ -0,0 +1,756 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use loader::P00Loader;
+
+use super::IecDevice;
+
+// SPEC: Inside Commodore DOS, ch. 4 (DOS commands) and ch. 9 (error messages)
+// Design:
+//   FsDrive exposes a host directory as a serial bus disk drive. Files are kept as plain
+//   PRG/SEQ files or as P00/S00 files when the C64 name can not be represented on the host.
+//   Each channel buffers the whole file, reads are served from memory and writes are flushed
+//   to the host file on close. Channel 15 is the command/error channel.
+
+const CHANNEL_LOAD: u8 = 0;
+const CHANNEL_SAVE: u8 = 1;
+const CHANNEL_COMMAND: u8 = 15;
+const BLOCK_SIZE: u64 = 254;
+const BLOCKS_FREE: u16 = 664;
+const DIR_ADDRESS: u16 = 0x0401;
+
+#[derive(Copy, Clone, PartialEq)]
+pub enum FileType {
+    Prg,
+    Seq,
+    Usr,
+}
+
+impl FileType {
+    pub fn from(ext: &str) -> Option<FileType> {
+        match ext.to_lowercase().as_str() {
+            "prg" | "" => Some(FileType::Prg),
+            "seq" => Some(FileType::Seq),
+            "usr" => Some(FileType::Usr),
+            _ => None,
+        }
+    }
+
+    pub fn from_p00(ext: &str) -> Option<FileType> {
+        let ext = ext.to_lowercase();
+        let bytes = ext.as_bytes();
+        if bytes.len() == 3 && bytes[1].is_ascii_digit() && bytes[2].is_ascii_digit() {
+            match bytes[0] {
+                b'p' => Some(FileType::Prg),
+                b's' => Some(FileType::Seq),
+                b'u' => Some(FileType::Usr),
+                _ => None,
+            }
+        } else {
+            None
+        }
+    }
+
+    fn from_code(code: u8) -> Option<FileType> {
+        match code {
+            b'P' => Some(FileType::Prg),
+            b'S' => Some(FileType::Seq),
+            b'U' => Some(FileType::Usr),
+            _ => None,
+        }
+    }
+
+    fn ext(&self) -> &'static str {
+        match *self {
+            FileType::Prg => "prg",
+            FileType::Seq => "seq",
+            FileType::Usr => "usr",
+        }
+    }
+
+    fn name(&self) -> &'static str {
+        match *self {
+            FileType::Prg => "PRG",
+            FileType::Seq => "SEQ",
+            FileType::Usr => "USR",
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DosError {
+    Ok = 0,
+    FilesScratched = 1,
+    SyntaxError = 30,
+    WriteError = 25,
+    FileNotFound = 62,
+    FileExists = 63,
+    FileTypeMismatch = 64,
+    DosVersion = 73,
+}
+
+impl DosError {
+    fn message(&self) -> &'static str {
+        match *self {
+            DosError::Ok => "OK",
+            DosError::FilesScratched => "FILES SCRATCHED",
+            DosError::SyntaxError => "SYNTAX ERROR",
+            DosError::WriteError => "WRITE ERROR",
+            DosError::FileNotFound => "FILE NOT FOUND",
+            DosError::FileExists => "FILE EXISTS",
+            DosError::FileTypeMismatch => "FILE TYPE MISMATCH",
+            DosError::DosVersion => "ZINC64 FS DRIVE",
+        }
+    }
+}
+
+pub struct DirEntry {
+    pub name: Vec<u8>,
+    pub file_type: FileType,
+    pub path: PathBuf,
+    pub size: u64,
+    header_size: usize,
+}
+
+enum Channel {
+    Read { data: Vec<u8>, pos: usize },
+    Write { data: Vec<u8>, entry: DirEntry },
+}
+
+pub struct FsDrive {
+    // Configuration
+    device_number: u8,
+    path: PathBuf,
+    // Runtime State
+    channels: Vec<Option<Channel>>,
+    command: Vec<u8>,
+    status: Vec<u8>,
+    status_pos: usize,
+}
+
+impl FsDrive {
+    pub fn new(device_number: u8, path: &Path) -> Self {
+        info!(target: "device", "Mapping drive {} to {}", device_number, path.display());
+        let mut drive = Self {
+            device_number,
+            path: path.to_path_buf(),
+            channels: (0..CHANNEL_COMMAND).map(|_| None).collect(),
+            command: Vec::new(),
+            status: Vec::new(),
+            status_pos: 0,
+        };
+        drive.set_status(DosError::DosVersion, 0);
+        drive
+    }
+
+    pub fn list(&self) -> io::Result<Vec<DirEntry>> {
+        let p00_loader = P00Loader::new();
+        let mut entries = Vec::new();
+        for dir_entry in fs::read_dir(&self.path)? {
+            let path = dir_entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string();
+            let ext = path
+                .extension()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_string();
+            let size = fs::metadata(&path)?.len();
+            if let Some(file_type) = FileType::from_p00(&ext) {
+                if let Ok(name) = p00_loader.read_filename(&path) {
+                    entries.push(DirEntry {
+                        name,
+                        file_type,
+                        path,
+                        size: size.saturating_sub(P00Loader::HEADER_SIZE as u64),
+                        header_size: P00Loader::HEADER_SIZE,
+                    });
+                }
+            } else if let Some(file_type) = FileType::from(&ext) {
+                entries.push(DirEntry {
+                    name: host_to_petscii(&stem),
+                    file_type,
+                    path,
+                    size,
+                    header_size: 0,
+                });
+            }
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(entries)
+    }
+
+    fn find(&self, pattern: &[u8], file_type: Option<FileType>) -> Option<DirEntry> {
+        self.list().ok().and_then(|entries| {
+            entries.into_iter().find(|entry| {
+                matches_pattern(pattern, &entry.name)
+                    && match file_type {
+                        Some(t) => t == entry.file_type,
+                        None => true,
+                    }
+            })
+        })
+    }
+
+    fn set_status(&mut self, error: DosError, track: u8) {
+        let status = format!(
+            "{:02},{},{:02},{:02}\r",
+            error as u8,
+            error.message(),
+            track,
+            0
+        );
+        self.status = status.into_bytes();
+        self.status_pos = 0;
+    }
+
+    // -- Channel Ops
+
+    fn open_file(&mut self, channel: u8, name: &[u8]) -> Result<Channel, DosError> {
+        let spec = FileSpec::parse(name)?;
+        if spec.name.first() == Some(&b'$') && !spec.write {
+            return self.open_directory(&spec.name[1..]);
+        }
+        let write = spec.write || channel == CHANNEL_SAVE;
+        if write {
+            let existing = self.find(&spec.name, None);
+            if existing.is_some() && !spec.replace {
+                return Err(DosError::FileExists);
+            }
+            let file_type = spec.file_type.unwrap_or(if channel == CHANNEL_SAVE {
+                FileType::Prg
+            } else {
+                FileType::Seq
+            });
+            let entry = match existing {
+                Some(entry) => entry,
+                None => self.new_entry(&spec.name, file_type),
+            };
+            Ok(Channel::Write {
+                data: Vec::new(),
+                entry,
+            })
+        } else {
+            let file_type = if channel == CHANNEL_LOAD {
+                None
+            } else {
+                spec.file_type
+            };
+            let entry = self
+                .find(&spec.name, file_type)
+                .ok_or(DosError::FileNotFound)?;
+            let mut data = Vec::new();
+            File::open(&entry.path)
+                .and_then(|mut file| file.read_to_end(&mut data))
+                .map_err(|_| DosError::FileNotFound)?;
+            Ok(Channel::Read {
+                data,
+                pos: entry.header_size,
+            })
+        }
+    }
+
+    fn open_directory(&mut self, pattern: &[u8]) -> Result<Channel, DosError> {
+        let pattern = match pattern.iter().position(|&c| c == b':') {
+            Some(pos) => &pattern[pos + 1..],
+            None => &pattern[0..0],
+        };
+        let entries = self.list().map_err(|_| DosError::FileNotFound)?;
+        let title = self
+            .path
+            .file_name()
+            .and_then(|s| s.to_str())
+            .unwrap_or("")
+            .to_string();
+        let mut listing = DirListing::new(DIR_ADDRESS);
+        listing.header(&host_to_petscii(&title));
+        for entry in entries.iter() {
+            if pattern.is_empty() || matches_pattern(pattern, &entry.name) {
+                let blocks = (entry.size + BLOCK_SIZE - 1) / BLOCK_SIZE;
+                listing.entry(blocks as u16, &entry.name, entry.file_type);
+            }
+        }
+        listing.footer(BLOCKS_FREE);
+        Ok(Channel::Read {
+            data: listing.build(),
+            pos: 0,
+        })
+    }
+
+    fn new_entry(&self, name: &[u8], file_type: FileType) -> DirEntry {
+        let (path, header_size) = match petscii_to_host(name) {
+            Some(host_name) => (
+                self.path.join(format!("{}.{}", host_name, file_type.ext())),
+                0,
+            ),
+            None => {
+                let mut index = 0;
+                let prefix = file_type.ext()[0..1].to_string();
+                let stem: String = name
+                    .iter()
+                    .filter(|&&c| c.is_ascii_alphanumeric())
+                    .take(8)
+                    .map(|&c| (c as char).to_ascii_lowercase())
+                    .collect();
+                let stem = if stem.is_empty() {
+                    "file".to_string()
+                } else {
+                    stem
+                };
+                let mut path = self.path.join(format!("{}.{}{:02}", stem, prefix, index));
+                while path.exists() && index < 99 {
+                    index += 1;
+                    path = self.path.join(format!("{}.{}{:02}", stem, prefix, index));
+                }
+                (path, P00Loader::HEADER_SIZE)
+            }
+        };
+        DirEntry {
+            name: name.to_vec(),
+            file_type,
+            path,
+            size: 0,
+            header_size,
+        }
+    }
+
+    fn flush(&mut self, data: &[u8], entry: &DirEntry) -> io::Result<()> {
+        info!(target: "device", "Writing {}", entry.path.display());
+        let mut file = File::create(&entry.path)?;
+        if entry.header_size != 0 {
+            P00Loader::new().write_header(&mut file, &entry.name)?;
+        }
+        file.write_all(data)
+    }
+
+    // -- Command Channel
+
+    fn execute_command(&mut self) {
+        let mut command = self.command.clone();
+        self.command.clear();
+        while command.last() == Some(&0x0d) {
+            command.pop();
+        }
+        if command.is_empty() {
+            return;
+        }
+        let result = match command[0] {
+            b'S' => self.cmd_scratch(&command),
+            b'R' => self.cmd_rename(&command),
+            b'I' | b'V' => Ok((DosError::Ok, 0)),
+            b'U' if command.len() > 1 && (command[1] == b'J' || command[1] == b'I') => {
+                self.reset_channels();
+                Ok((DosError::DosVersion, 0))
+            }
+            _ => Err(DosError::SyntaxError),
+        };
+        match result {
+            Ok((error, track)) => self.set_status(error, track),
+            Err(error) => self.set_status(error, 0),
+        }
+    }
+
+    fn cmd_scratch(&mut self, command: &[u8]) -> Result<(DosError, u8), DosError> {
+        let args = command_args(command)?;
+        let mut count = 0u8;
+        for pattern in args.split(|&c| c == b',') {
+            for entry in self.list().map_err(|_| DosError::WriteError)? {
+                if matches_pattern(pattern, &entry.name) {
+                    info!(target: "device", "Scratching {}", entry.path.display());
+                    fs::remove_file(&entry.path).map_err(|_| DosError::WriteError)?;
+                    count = count.saturating_add(1);
+                }
+            }
+        }
+        Ok((DosError::FilesScratched, count))
+    }
+
+    fn cmd_rename(&mut self, command: &[u8]) -> Result<(DosError, u8), DosError> {
+        let args = command_args(command)?;
+        let pos = args
+            .iter()
+            .position(|&c| c == b'=')
+            .ok_or(DosError::SyntaxError)?;
+        let new_name = strip_drive(&args[0..pos]);
+        let old_name = strip_drive(&args[pos + 1..]);
+        if self.find(new_name, None).is_some() {
+            return Err(DosError::FileExists);
+        }
+        let old_entry = self.find(old_name, None).ok_or(DosError::FileNotFound)?;
+        let new_entry = self.new_entry(new_name, old_entry.file_type);
+        if new_entry.header_size == old_entry.header_size && old_entry.header_size == 0 {
+            fs::rename(&old_entry.path, &new_entry.path).map_err(|_| DosError::WriteError)?;
+        } else {
+            let mut data = Vec::new();
+            File::open(&old_entry.path)
+                .and_then(|mut file| file.read_to_end(&mut data))
+                .map_err(|_| DosError::FileNotFound)?;
+            self.flush(&data[old_entry.header_size..], &new_entry)
+                .map_err(|_| DosError::WriteError)?;
+            fs::remove_file(&old_entry.path).map_err(|_| DosError::WriteError)?;
+        }
+        Ok((DosError::Ok, 0))
+    }
+
+    fn reset_channels(&mut self) {
+        for channel in 0..CHANNEL_COMMAND {
+            self.close(channel);
+        }
+        self.command.clear();
+    }
+}
+
+impl IecDevice for FsDrive {
+    fn get_device_number(&self) -> u8 {
+        self.device_number
+    }
+
+    fn open(&mut self, channel: u8, name: &[u8]) {
+        if channel == CHANNEL_COMMAND {
+            self.command = name.to_vec();
+            self.execute_command();
+        } else {
+            self.close(channel);
+            match self.open_file(channel, name) {
+                Ok(file) => {
+                    self.channels[channel as usize] = Some(file);
+                    self.set_status(DosError::Ok, 0);
+                }
+                Err(error) => self.set_status(error, 0),
+            }
+        }
+    }
+
+    fn close(&mut self, channel: u8) {
+        if channel == CHANNEL_COMMAND {
+            self.reset_channels();
+        } else if let Some(Channel::Write { data, entry }) = self.channels[channel as usize].take()
+        {
+            if self.flush(&data, &entry).is_err() {
+                self.set_status(DosError::WriteError, 0);
+            }
+        }
+    }
+
+    fn read(&mut self, channel: u8) -> Option<(u8, bool)> {
+        if channel == CHANNEL_COMMAND {
+            let value = self.status[self.status_pos];
+            self.status_pos += 1;
+            let eoi = self.status_pos == self.status.len();
+            if eoi {
+                self.set_status(DosError::Ok, 0);
+            }
+            Some((value, eoi))
+        } else {
+            match self.channels[channel as usize] {
+                Some(Channel::Read {
+                    ref data,
+                    ref mut pos,
+                }) if *pos < data.len() => {
+                    let value = data[*pos];
+                    *pos += 1;
+                    Some((value, *pos == data.len()))
+                }
+                _ => None,
+            }
+        }
+    }
+
+    fn write(&mut self, channel: u8, value: u8) {
+        if channel == CHANNEL_COMMAND {
+            self.command.push(value);
+        } else if let Some(Channel::Write { ref mut data, .. }) = self.channels[channel as usize] {
+            data.push(value);
+        }
+    }
+
+    fn unlisten(&mut self, channel: u8) {
+        if channel == CHANNEL_COMMAND {
+            self.execute_command();
+        }
+    }
+
+    fn reset(&mut self) {
+        self.reset_channels();
+        self.set_status(DosError::DosVersion, 0);
+    }
+}
+
+struct FileSpec {
+    name: Vec<u8>,
+    file_type: Option<FileType>,
+    replace: bool,
+    write: bool,
+}
+
+impl FileSpec {
+    // Parses "[@][0]:name[,type[,mode]]"
+    pub fn parse(spec: &[u8]) -> Result<FileSpec, DosError> {
+        let mut spec = spec;
+        let replace = spec.first() == Some(&b'@');
+        if replace {
+            spec = &spec[1..];
+        }
+        if spec.first() != Some(&b'$') {
+            spec = strip_drive(spec);
+        }
+        let mut parts = spec.split(|&c| c == b',');
+        let name = parts.next().unwrap_or(&[]).to_vec();
+        if name.is_empty() {
+            return Err(DosError::SyntaxError);
+        }
+        let mut file_type = None;
+        let mut write = false;
+        for part in parts {
+            match part.first() {
+                Some(&b'W') => write = true,
+                Some(&b'R') | Some(&b'A') => {}
+                Some(&code) => {
+                    file_type = Some(FileType::from_code(code).ok_or(DosError::FileTypeMismatch)?)
+                }
+                None => {}
+            }
+        }
+        Ok(FileSpec {
+            name,
+            file_type,
+            replace,
+            write,
+        })
+    }
+}
+
+struct DirListing {
+    data: Vec<u8>,
+    address: u16,
+}
+
+impl DirListing {
+    pub fn new(address: u16) -> Self {
+        let data = vec![(address & 0xff) as u8, (address >> 8) as u8];
+        Self { data, address }
+    }
+
+    pub fn header(&mut self, title: &[u8]) {
+        let mut text = vec![0x12, b'"'];
+        text.extend(title.iter().take(16));
+        while text.len() < 18 {
+            text.push(b' ');
+        }
+        text.extend_from_slice(b"\" 00 2A");
+        self.line(0, &text);
+    }
+
+    pub fn entry(&mut self, blocks: u16, name: &[u8], file_type: FileType) {
+        let mut text = Vec::new();
+        let indent = match blocks {
+            0..=9 => 3,
+            10..=99 => 2,
+            100..=999 => 1,
+            _ => 0,
+        };
+        text.extend(vec![b' '; indent]);
+        text.push(b'"');
+        text.extend(name.iter().take(16));
+        text.push(b'"');
+        text.extend(vec![b' '; 17 - name.len().min(16)]);
+        text.extend_from_slice(file_type.name().as_bytes());
+        self.line(blocks, &text);
+    }
+
+    pub fn footer(&mut self, blocks_free: u16) {
+        self.line(blocks_free, b"BLOCKS FREE.             ");
+    }
+
+    pub fn build(mut self) -> Vec<u8> {
+        self.data.push(0);
+        self.data.push(0);
+        self.data
+    }
+
+    fn line(&mut self, number: u16, text: &[u8]) {
+        let next = self.address + 5 + text.len() as u16;
+        self.data.push((next & 0xff) as u8);
+        self.data.push((next >> 8) as u8);
+        self.data.push((number & 0xff) as u8);
+        self.data.push((number >> 8) as u8);
+        self.data.extend_from_slice(text);
+        self.data.push(0);
+        self.address = next;
+    }
+}
+
+// -- Helpers
+
+fn command_args(command: &[u8]) -> Result<&[u8], DosError> {
+    match command.iter().position(|&c| c == b':') {
+        Some(pos) => Ok(&command[pos + 1..]),
+        None => Err(DosError::SyntaxError),
+    }
+}
+
+fn strip_drive(name: &[u8]) -> &[u8] {
+    match name.iter().position(|&c| c == b':') {
+        Some(pos) if pos <= 1 => &name[pos + 1..],
+        _ => name,
+    }
+}
+
+fn matches_pattern(pattern: &[u8], name: &[u8]) -> bool {
+    let mut i = 0;
+    for &c in pattern {
+        match c {
+            b'*' => return true,
+            b'?' if i < name.len() => {}
+            _ if i < name.len() && name[i] == c => {}
+            _ => return false,
+        }
+        i += 1;
+    }
+    i == name.len()
+}
+
+fn host_to_petscii(name: &str) -> Vec<u8> {
+    name.bytes()
+        .map(|c| match c {
+            b'a'..=b'z' => c - 0x20,
+            0x20..=0x5f => c,
+            _ => b'?',
+        })
+        .collect()
+}
+
+fn petscii_to_host(name: &[u8]) -> Option<String> {
+    let mut host_name = String::new();
+    for &c in name {
+        let ch = match c {
+            0x41..=0x5a => (c + 0x20) as char,
+            0xc1..=0xda => (c - 0x80) as char,
+            b'/' | b'\\' | b':' | b'*' | b'?' | b'"' | b'<' | b'>' | b'|' => return None,
+            0x20..=0x5f => c as char,
+            _ => return None,
+        };
+        host_name.push(ch);
+    }
+    if host_name.is_empty() || host_name.starts_with('.') || host_name.ends_with(' ') {
+        None
+    } else {
+        Some(host_name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn setup_drive(name: &str) -> FsDrive {
+        let path = env::temp_dir().join(format!("zinc64-fs-drive-{}", name));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        FsDrive::new(8, &path)
+    }
+
+    fn read_all(drive: &mut FsDrive, channel: u8) -> Vec<u8> {
+        let mut data = Vec::new();
+        while let Some((value, eoi)) = drive.read(channel) {
+            data.push(value);
+            if eoi {
+                break;
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn match_wildcards() {
+        assert!(matches_pattern(b"GAME", b"GAME"));
+        assert!(matches_pattern(b"GA*", b"GAME"));
+        assert!(matches_pattern(b"G?ME", b"GAME"));
+        assert!(!matches_pattern(b"GAM", b"GAME"));
+        assert!(!matches_pattern(b"GAMES", b"GAME"));
+    }
+
+    #[test]
+    fn save_and_load_file() {
+        let mut drive = setup_drive("save");
+        drive.open(CHANNEL_SAVE, b"0:HELLO");
+        for value in [0x01u8, 0x08, 0xaa].iter() {
+            drive.write(CHANNEL_SAVE, *value);
+        }
+        drive.close(CHANNEL_SAVE);
+        assert!(drive.path.join("hello.prg").exists());
+        drive.open(CHANNEL_LOAD, b"HEL*");
+        assert_eq!(vec![0x01u8, 0x08, 0xaa], read_all(&mut drive, CHANNEL_LOAD));
+    }
+
+    #[test]
+    fn save_with_long_name_as_p00() {
+        let mut drive = setup_drive("p00");
+        drive.open(CHANNEL_SAVE, b"A/B");
+        drive.write(CHANNEL_SAVE, 0x42);
+        drive.close(CHANNEL_SAVE);
+        let entries = drive.list().unwrap();
+        assert_eq!(1, entries.len());
+        assert_eq!(b"A/B".to_vec(), entries[0].name);
+        assert_eq!(1, entries[0].size);
+    }
+
+    #[test]
+    fn load_missing_file() {
+        let mut drive = setup_drive("missing");
+        drive.open(CHANNEL_LOAD, b"NOPE");
+        assert_eq!(None, drive.read(CHANNEL_LOAD));
+        let status = read_all(&mut drive, CHANNEL_COMMAND);
+        assert_eq!(b"62,FILE NOT FOUND,00,00\r".to_vec(), status);
+    }
+
+    #[test]
+    fn scratch_and_rename() {
+        let mut drive = setup_drive("cmd");
+        File::create(drive.path.join("one.prg")).unwrap();
+        File::create(drive.path.join("two.seq")).unwrap();
+        drive.open(CHANNEL_COMMAND, b"R0:THREE=TWO");
+        assert_eq!(
+            b"00,OK,00,00\r".to_vec(),
+            read_all(&mut drive, CHANNEL_COMMAND)
+        );
+        assert!(drive.path.join("three.seq").exists());
+        for c in b"S:ONE".iter() {
+            drive.write(CHANNEL_COMMAND, *c);
+        }
+        drive.unlisten(CHANNEL_COMMAND);
+        let status = read_all(&mut drive, CHANNEL_COMMAND);
+        assert_eq!(b"01,FILES SCRATCHED,01,00\r".to_vec(), status);
+        assert!(!drive.path.join("one.prg").exists());
+    }
+
+    #[test]
+    fn list_directory() {
+        let mut drive = setup_drive("dir");
+        File::create(drive.path.join("game.prg"))
+            .unwrap()
+            .write_all(&[0u8; 300])
+            .unwrap();
+        drive.open(CHANNEL_LOAD, b"$");
+        let data = read_all(&mut drive, CHANNEL_LOAD);
+        assert_eq!(&[0x01u8, 0x04], &data[0..2]);
+        let text = String::from_utf8_lossy(&data);
+        assert!(text.contains("\"GAME\"             PRG"));
+        assert!(text.contains("BLOCKS FREE."));
+        assert_eq!(&[0u8, 0, 0], &data[data.len() - 3..]);
+    }
+}
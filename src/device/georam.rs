@@ -0,0 +1,193 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::io;
+use std::ops::RangeInclusive;
+
+use core::Addressable;
+use device::{DeviceKind, ExpansionDevice, RamStore};
+
+// SPEC: Berkeley Softworks geoRAM, https://www.c64-wiki.com/wiki/GeoRAM
+// Design:
+//   GeoRAM exposes one 256 byte page of its memory at $DE00-$DEFF. The page within a 16K block
+//   is selected by $DFFE and the block by $DFFF. The registers are write-only and decoded by
+//   A0 only, so they are mirrored in $DF80-$DFFF. NeoRAM and BBG-RAM are battery backed clones
+//   with the same registers, so the models only differ in the sizes they were built with.
+//   Contents survive a reset, only the page and block selection are cleared.
+
+const PAGE_SIZE: usize = 0x100;
+const BLOCK_SIZE: usize = 0x4000;
+
+static IO_RANGES: [RangeInclusive<u16>; 2] = [0xde00..=0xdeff, 0xdf80..=0xdfff];
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum GeoRamModel {
+    GeoRam,
+    NeoRam,
+}
+
+impl GeoRamModel {
+    pub fn is_valid_capacity(&self, capacity: usize) -> bool {
+        let (min, max) = match *self {
+            GeoRamModel::GeoRam => (0x10000, 0x40_0000),
+            GeoRamModel::NeoRam => (0x8_0000, 0x20_0000),
+        };
+        capacity.is_power_of_two() && (min..=max).contains(&capacity)
+    }
+}
+
+pub struct GeoRam {
+    model: GeoRamModel,
+    // Registers
+    page: u8,
+    block: u8,
+    // Storage
+    ram: Vec<u8>,
+    dirty: bool,
+    store: Option<Box<dyn RamStore>>,
+}
+
+impl GeoRam {
+    pub fn new(model: GeoRamModel, capacity: usize) -> Self {
+        if !model.is_valid_capacity(capacity) {
+            panic!("invalid {:?} capacity {}", model, capacity);
+        }
+        GeoRam {
+            model,
+            page: 0,
+            block: 0,
+            ram: vec![0; capacity],
+            dirty: false,
+            store: None,
+        }
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.ram.len()
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.ram
+    }
+
+    pub fn get_model(&self) -> GeoRamModel {
+        self.model
+    }
+
+    pub fn load(&mut self, data: &[u8]) {
+        for (dst, src) in self.ram.iter_mut().zip(data.iter()) {
+            *dst = *src;
+        }
+    }
+
+    pub fn set_store(&mut self, store: Box<dyn RamStore>) {
+        self.store = Some(store);
+    }
+
+    fn get_ram_index(&self, address: u16) -> usize {
+        let index = self.block as usize * BLOCK_SIZE
+            + self.page as usize * PAGE_SIZE
+            + (address as usize & (PAGE_SIZE - 1));
+        index & (self.ram.len() - 1)
+    }
+}
+
+impl ExpansionDevice for GeoRam {
+    fn get_io_ranges(&self) -> &[RangeInclusive<u16>] {
+        &IO_RANGES
+    }
+
+    fn get_kind(&self) -> DeviceKind {
+        DeviceKind::RamExpansion
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.dirty {
+            if let Some(mut store) = self.store.take() {
+                let result = store.save(&self.ram);
+                self.store = Some(store);
+                result?;
+                self.dirty = false;
+            }
+        }
+        Ok(())
+    }
+
+    fn reset(&mut self) {
+        self.page = 0;
+        self.block = 0;
+    }
+}
+
+impl Addressable for GeoRam {
+    fn read(&self, address: u16) -> u8 {
+        match address {
+            0xde00..=0xdeff => self.ram[self.get_ram_index(address)],
+            _ => 0xff,
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0xde00..=0xdeff => {
+                let index = self.get_ram_index(address);
+                self.ram[index] = value;
+                self.dirty = true;
+            }
+            0xdf80..=0xdfff if address & 0x01 == 0 => {
+                self.page = value & ((BLOCK_SIZE / PAGE_SIZE) as u8 - 1);
+            }
+            0xdf80..=0xdfff => {
+                self.block = value & (self.ram.len() / BLOCK_SIZE - 1) as u8;
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn capacity() {
+        assert_eq!(true, GeoRamModel::GeoRam.is_valid_capacity(0x10000));
+        assert_eq!(true, GeoRamModel::GeoRam.is_valid_capacity(0x40_0000));
+        assert_eq!(false, GeoRamModel::NeoRam.is_valid_capacity(0x10000));
+        assert_eq!(false, GeoRamModel::GeoRam.is_valid_capacity(0x60000));
+    }
+
+    #[test]
+    fn page_and_block_select() {
+        let mut georam = GeoRam::new(GeoRamModel::GeoRam, 0x8_0000);
+        georam.write(0xdffe, 0x02);
+        georam.write(0xdfff, 0x03);
+        georam.write(0xde10, 0x42);
+        assert_eq!(0x42, georam.get_data()[3 * 0x4000 + 2 * 0x100 + 0x10]);
+        georam.write(0xdffe, 0x00);
+        assert_eq!(0x00, georam.read(0xde10));
+        georam.write(0xdf80, 0x02);
+        assert_eq!(0x42, georam.read(0xde10));
+        assert_eq!(0xff, georam.read(0xdffe));
+    }
+
+    #[test]
+    fn block_wraps_at_capacity() {
+        let mut georam = GeoRam::new(GeoRamModel::GeoRam, 0x10000);
+        georam.write(0xdfff, 0x05);
+        georam.write(0xde00, 0x42);
+        assert_eq!(0x42, georam.get_data()[0x4000]);
+    }
+
+    #[test]
+    fn reset_keeps_contents() {
+        let mut georam = GeoRam::new(GeoRamModel::NeoRam, 0x8_0000);
+        georam.write(0xdfff, 0x01);
+        georam.write(0xde00, 0x42);
+        georam.reset();
+        assert_eq!(0x00, georam.read(0xde00));
+        georam.write(0xdfff, 0x01);
+        assert_eq!(0x42, georam.read(0xde00));
+    }
+}
@@ -0,0 +1,351 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use bit_field::BitField;
+use core::Cpu;
+use log::LogLevel;
+
+// SPEC: Inside Commodore DOS, ch. 3 (serial bus protocol)
+// Design:
+//   Instead of emulating serial bus timing, we trap kernal serial routines (LISTEN, TALK,
+//   SECOND, TKSA, CIOUT, ACPTR, UNLSN, UNTLK) and forward them to the virtual device
+//   addressed by the last LISTEN/TALK. Routines are only trapped when the device is ours,
+//   so all other devices see the unmodified kernal. Each trap verifies the kernal code at the
+//   trap address so that RAM under ROM or a custom kernal are left alone.
+
+const ADDR_STATUS: u16 = 0x0090;
+
+#[derive(Copy, Clone)]
+enum Status {
+    Timeout = 1,
+    Eoi = 6,
+}
+
+impl Status {
+    pub fn value(&self) -> usize {
+        *self as usize
+    }
+}
+
+pub trait IecDevice {
+    fn get_device_number(&self) -> u8;
+    fn open(&mut self, channel: u8, name: &[u8]);
+    fn close(&mut self, channel: u8);
+    // Returns next byte on the channel and whether it is the last one.
+    fn read(&mut self, channel: u8) -> Option<(u8, bool)>;
+    fn write(&mut self, channel: u8, value: u8);
+    fn unlisten(&mut self, channel: u8);
+    fn reset(&mut self);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Routine {
+    Acptr,
+    Ciout,
+    Listen,
+    ListenNoSa,
+    Second,
+    Talk,
+    TalkNoSa,
+    Tksa,
+    Unlisten,
+    Untalk,
+}
+
+struct KernalTrap {
+    address: u16,
+    signature: [u8; 3],
+    routine: Routine,
+}
+
+static TRAPS: [KernalTrap; 10] = [
+    KernalTrap {
+        address: 0xed09,
+        signature: [0x09, 0x40, 0x2c],
+        routine: Routine::Talk,
+    },
+    KernalTrap {
+        address: 0xed0c,
+        signature: [0x09, 0x20, 0x20],
+        routine: Routine::Listen,
+    },
+    KernalTrap {
+        address: 0xedb9,
+        signature: [0x85, 0x95, 0x20],
+        routine: Routine::Second,
+    },
+    KernalTrap {
+        address: 0xedbe,
+        signature: [0xad, 0x00, 0xdd],
+        routine: Routine::ListenNoSa,
+    },
+    KernalTrap {
+        address: 0xedc7,
+        signature: [0x85, 0x95, 0x20],
+        routine: Routine::Tksa,
+    },
+    KernalTrap {
+        address: 0xedcc,
+        signature: [0x78, 0x20, 0xa0],
+        routine: Routine::TalkNoSa,
+    },
+    KernalTrap {
+        address: 0xeddd,
+        signature: [0x24, 0x94, 0x30],
+        routine: Routine::Ciout,
+    },
+    KernalTrap {
+        address: 0xedef,
+        signature: [0x78, 0x20, 0x8e],
+        routine: Routine::Untalk,
+    },
+    KernalTrap {
+        address: 0xedfe,
+        signature: [0xa9, 0x3f, 0x20],
+        routine: Routine::Unlisten,
+    },
+    KernalTrap {
+        address: 0xee13,
+        signature: [0x78, 0xa9, 0x00],
+        routine: Routine::Acptr,
+    },
+];
+
+#[derive(Copy, Clone, PartialEq)]
+enum Mode {
+    Close,
+    Data,
+    Open,
+}
+
+pub struct IecBus {
+    devices: Vec<Box<dyn IecDevice>>,
+    // Runtime State
+    active: Option<usize>,
+    channel: u8,
+    mode: Mode,
+    name: Vec<u8>,
+    talking: bool,
+}
+
+impl IecBus {
+    pub fn new() -> Self {
+        Self {
+            devices: Vec::new(),
+            active: None,
+            channel: 0,
+            mode: Mode::Data,
+            name: Vec::new(),
+            talking: false,
+        }
+    }
+
+    pub fn attach(&mut self, device: Box<dyn IecDevice>) {
+        let number = device.get_device_number();
+        info!(target: "device", "Attaching serial device {}", number);
+        self.detach(number);
+        self.devices.push(device);
+    }
+
+    pub fn detach(&mut self, number: u8) {
+        if let Some(pos) = self.find(number) {
+            info!(target: "device", "Detaching serial device {}", number);
+            self.devices.remove(pos);
+            self.active = None;
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.devices.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        for device in self.devices.iter_mut() {
+            device.reset();
+        }
+        self.active = None;
+        self.channel = 0;
+        self.mode = Mode::Data;
+        self.name.clear();
+        self.talking = false;
+    }
+
+    // Executes kernal serial routine at PC if it targets one of our devices.
+    // Returns true if the routine was handled and the cpu returned to the caller.
+    pub fn trap(&mut self, cpu: &mut Box<dyn Cpu>) -> bool {
+        let pc = cpu.get_pc();
+        let routine = match TRAPS.iter().find(|trap| trap.address == pc) {
+            Some(trap) => {
+                let matches = (0..3).all(|i| cpu.read(pc + i as u16) == trap.signature[i]);
+                if !matches {
+                    return false;
+                }
+                trap.routine
+            }
+            None => return false,
+        };
+        if log_enabled!(LogLevel::Trace) {
+            trace!(target: "device::iec", "Trap {:?} A={:02x}", routine, cpu.get_a());
+        }
+        let handled = match routine {
+            Routine::Listen => self.attention(cpu.get_a(), false),
+            Routine::Talk => self.attention(cpu.get_a(), true),
+            Routine::Second => self.second(cpu.get_a()),
+            Routine::Tksa => self.tksa(cpu.get_a()),
+            Routine::ListenNoSa => self.is_listener(),
+            Routine::TalkNoSa => self.is_talker(),
+            Routine::Ciout => self.ciout(cpu.get_a()),
+            Routine::Acptr => self.acptr(cpu),
+            Routine::Unlisten => self.unlisten(),
+            Routine::Untalk => self.untalk(),
+        };
+        if handled {
+            let p = cpu.get_p();
+            cpu.set_p(p & !0x01);
+            Self::return_from_subroutine(cpu);
+        }
+        handled
+    }
+
+    fn attention(&mut self, device: u8, talk: bool) -> bool {
+        self.active = self.find(device & 0x1f);
+        self.channel = 0;
+        self.mode = Mode::Data;
+        self.talking = talk;
+        self.active.is_some()
+    }
+
+    fn second(&mut self, secondary: u8) -> bool {
+        if self.is_listener() {
+            self.channel = secondary & 0x0f;
+            match secondary & 0xf0 {
+                0xe0 => {
+                    self.mode = Mode::Close;
+                    let channel = self.channel;
+                    self.active_device().close(channel);
+                }
+                0xf0 => {
+                    self.mode = Mode::Open;
+                    self.name.clear();
+                }
+                _ => {
+                    self.mode = Mode::Data;
+                }
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn tksa(&mut self, secondary: u8) -> bool {
+        if self.is_talker() {
+            self.channel = secondary & 0x0f;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ciout(&mut self, value: u8) -> bool {
+        if self.is_listener() {
+            match self.mode {
+                Mode::Open => self.name.push(value),
+                Mode::Data => {
+                    let channel = self.channel;
+                    self.active_device().write(channel, value);
+                }
+                Mode::Close => {}
+            }
+            true
+        } else {
+            false
+        }
+    }
+
+    fn acptr(&mut self, cpu: &mut Box<dyn Cpu>) -> bool {
+        if self.is_talker() {
+            let channel = self.channel;
+            let mut status = cpu.read(ADDR_STATUS);
+            let value = match self.active_device().read(channel) {
+                Some((value, eoi)) => {
+                    status.set_bit(Status::Eoi.value(), eoi);
+                    value
+                }
+                None => {
+                    status.set_bit(Status::Timeout.value(), true);
+                    status.set_bit(Status::Eoi.value(), true);
+                    0
+                }
+            };
+            cpu.write(ADDR_STATUS, status);
+            cpu.set_a(value);
+            let mut p = cpu.get_p() & !0x82;
+            if value == 0 {
+                p |= 0x02;
+            }
+            cpu.set_p(p | (value & 0x80));
+            true
+        } else {
+            false
+        }
+    }
+
+    fn unlisten(&mut self) -> bool {
+        if self.is_listener() {
+            let channel = self.channel;
+            match self.mode {
+                Mode::Open => {
+                    let name = self.name.clone();
+                    self.active_device().open(channel, &name);
+                }
+                Mode::Data => self.active_device().unlisten(channel),
+                Mode::Close => {}
+            }
+            self.active = None;
+            self.mode = Mode::Data;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn untalk(&mut self) -> bool {
+        if self.is_talker() {
+            self.active = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    // -- Helpers
+
+    fn active_device(&mut self) -> &mut Box<dyn IecDevice> {
+        let index = self.active.expect("no active serial device");
+        &mut self.devices[index]
+    }
+
+    fn find(&self, number: u8) -> Option<usize> {
+        self.devices
+            .iter()
+            .position(|device| device.get_device_number() == number)
+    }
+
+    fn is_listener(&self) -> bool {
+        self.active.is_some() && !self.talking
+    }
+
+    fn is_talker(&self) -> bool {
+        self.active.is_some() && self.talking
+    }
+
+    fn return_from_subroutine(cpu: &mut Box<dyn Cpu>) {
+        let sp = cpu.get_sp();
+        let lo = cpu.read(0x0100 + sp.wrapping_add(1) as u16) as u16;
+        let hi = cpu.read(0x0100 + sp.wrapping_add(2) as u16) as u16;
+        cpu.set_sp(sp.wrapping_add(2));
+        cpu.set_pc(((hi << 8) | lo).wrapping_add(1));
+    }
+}
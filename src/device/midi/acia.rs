@@ -0,0 +1,213 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use bit_field::BitField;
+
+use super::MidiBackend;
+
+// SPEC: Motorola MC6850 Asynchronous Communications Interface Adapter data sheet
+// Design:
+//   Only the parts of the ACIA used by MIDI interfaces are emulated. Bytes are moved as a
+//   whole, so the shift registers are replaced by a counter that holds a transmitted byte
+//   for the time it takes to send it at the MIDI baud rate. Received bytes are polled from
+//   the backend at the same rate and are lost with an overrun if the receive register was
+//   not read in time. The word format and counter divide are ignored except for master reset.
+
+// Control register bits
+const CONTROL_TX_IRQ: usize = 5;
+const CONTROL_RX_IRQ: usize = 7;
+
+// Status register bits
+const STATUS_RDRF: usize = 0;
+const STATUS_TDRE: usize = 1;
+const STATUS_OVRN: usize = 5;
+const STATUS_IRQ: usize = 7;
+
+// MIDI sends 10 bits per byte at 31250 baud
+const MIDI_BYTE_RATE: u32 = 3125;
+
+pub struct Acia {
+    byte_cycles: u32,
+    // Registers
+    control: u8,
+    status: u8,
+    rx_data: u8,
+    tx_data: u8,
+    // Runtime State
+    cycles: u64,
+    reset: bool,
+    rx_counter: u32,
+    tx_counter: u32,
+}
+
+impl Acia {
+    pub fn new(clock_freq: u32) -> Self {
+        let mut acia = Self {
+            byte_cycles: clock_freq / MIDI_BYTE_RATE,
+            control: 0,
+            status: 0,
+            rx_data: 0,
+            tx_data: 0,
+            cycles: 0,
+            reset: true,
+            rx_counter: 0,
+            tx_counter: 0,
+        };
+        acia.reset();
+        acia
+    }
+
+    pub fn is_irq_low(&self) -> bool {
+        self.status.get_bit(STATUS_IRQ)
+    }
+
+    pub fn clock(&mut self, backend: &mut dyn MidiBackend) {
+        self.cycles += 1;
+        if self.reset {
+            return;
+        }
+        if self.tx_counter > 0 {
+            self.tx_counter -= 1;
+            if self.tx_counter == 0 {
+                backend.send(self.cycles, self.tx_data);
+                self.status.set_bit(STATUS_TDRE, true);
+                self.update_irq();
+            }
+        }
+        if self.rx_counter > 0 {
+            self.rx_counter -= 1;
+        } else if let Some(value) = backend.receive(self.cycles) {
+            if self.status.get_bit(STATUS_RDRF) {
+                self.status.set_bit(STATUS_OVRN, true);
+            } else {
+                self.rx_data = value;
+                self.status.set_bit(STATUS_RDRF, true);
+            }
+            self.rx_counter = self.byte_cycles;
+            self.update_irq();
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.control = 0;
+        self.status = 1 << STATUS_TDRE;
+        self.rx_data = 0;
+        self.tx_data = 0;
+        self.reset = true;
+        self.rx_counter = 0;
+        self.tx_counter = 0;
+    }
+
+    // -- I/O
+
+    pub fn peek_data(&self) -> u8 {
+        self.rx_data
+    }
+
+    pub fn read_data(&mut self) -> u8 {
+        self.status.set_bit(STATUS_RDRF, false);
+        self.status.set_bit(STATUS_OVRN, false);
+        self.update_irq();
+        self.rx_data
+    }
+
+    pub fn read_status(&self) -> u8 {
+        self.status
+    }
+
+    pub fn write_control(&mut self, value: u8) {
+        if value & 0x03 == 0x03 {
+            self.reset();
+        } else {
+            self.control = value;
+            self.reset = false;
+            self.update_irq();
+        }
+    }
+
+    pub fn write_data(&mut self, value: u8) {
+        if self.reset {
+            return;
+        }
+        self.tx_data = value;
+        self.tx_counter = self.byte_cycles;
+        self.status.set_bit(STATUS_TDRE, false);
+        self.update_irq();
+    }
+
+    fn update_irq(&mut self) {
+        let rx_irq = self.control.get_bit(CONTROL_RX_IRQ)
+            && (self.status.get_bit(STATUS_RDRF) || self.status.get_bit(STATUS_OVRN));
+        let tx_irq = self.control & 0x60 == 1 << CONTROL_TX_IRQ && self.status.get_bit(STATUS_TDRE);
+        self.status.set_bit(STATUS_IRQ, rx_irq || tx_irq);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+
+    struct Loopback {
+        data: VecDeque<u8>,
+    }
+
+    impl MidiBackend for Loopback {
+        fn receive(&mut self, _cycles: u64) -> Option<u8> {
+            self.data.pop_front()
+        }
+
+        fn send(&mut self, _cycles: u64, value: u8) {
+            self.data.push_back(value);
+        }
+    }
+
+    #[test]
+    fn master_reset_blocks_transfers() {
+        let mut acia = Acia::new(MIDI_BYTE_RATE * 10);
+        let mut backend = Loopback {
+            data: VecDeque::new(),
+        };
+        acia.write_data(0x90);
+        for _ in 0..20 {
+            acia.clock(&mut backend);
+        }
+        assert_eq!(0, backend.data.len());
+        assert_eq!(0x02, acia.read_status());
+    }
+
+    #[test]
+    fn transmit_and_receive_irq() {
+        let mut acia = Acia::new(MIDI_BYTE_RATE * 10);
+        let mut backend = Loopback {
+            data: VecDeque::new(),
+        };
+        acia.write_control(0x95);
+        acia.write_data(0x90);
+        assert_eq!(0x00, acia.read_status());
+        for _ in 0..10 {
+            acia.clock(&mut backend);
+        }
+        assert_eq!(0x83, acia.read_status());
+        assert_eq!(true, acia.is_irq_low());
+        assert_eq!(0x90, acia.read_data());
+        assert_eq!(0x02, acia.read_status());
+        assert_eq!(false, acia.is_irq_low());
+    }
+
+    #[test]
+    fn receive_overrun() {
+        let mut acia = Acia::new(MIDI_BYTE_RATE * 10);
+        let mut backend = Loopback {
+            data: vec![0x01, 0x02].into_iter().collect(),
+        };
+        acia.write_control(0x15);
+        for _ in 0..12 {
+            acia.clock(&mut backend);
+        }
+        assert_eq!(0x23, acia.read_status());
+        assert_eq!(0x01, acia.read_data());
+        assert_eq!(0x02, acia.read_status());
+    }
+}
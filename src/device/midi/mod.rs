@@ -0,0 +1,264 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+mod acia;
+mod pipe_backend;
+mod smf_backend;
+
+use std::cell::RefCell;
+use std::io;
+use std::ops::RangeInclusive;
+
+use core::Addressable;
+use device::{DeviceKind, ExpansionDevice};
+
+pub use self::pipe_backend::PipeBackend;
+pub use self::smf_backend::SmfBackend;
+
+use self::acia::Acia;
+
+// Design:
+//   MIDI interfaces are a 6850 ACIA wired to the I/O area. The interfaces only differ in
+//   the register addresses and the interrupt line, so they are described by a table instead
+//   of a mapper per type. The ACIA exchanges bytes with a host backend that is told the
+//   cycle at which each byte is sent or polled, so backends can timestamp or schedule them.
+
+pub trait MidiBackend {
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+    fn receive(&mut self, cycles: u64) -> Option<u8>;
+    fn send(&mut self, cycles: u64, value: u8);
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MidiType {
+    Sequential,
+    Passport,
+    Datel,
+    Namesoft,
+    Maplin,
+}
+
+struct MidiRegs {
+    control: u16,
+    status: u16,
+    tx: u16,
+    rx: u16,
+    nmi: bool,
+}
+
+static IO_SEQUENTIAL: [RangeInclusive<u16>; 1] = [0xde00..=0xde03];
+static IO_PASSPORT: [RangeInclusive<u16>; 1] = [0xde08..=0xde09];
+static IO_DATEL: [RangeInclusive<u16>; 1] = [0xde04..=0xde07];
+static IO_MAPLIN: [RangeInclusive<u16>; 1] = [0xdf00..=0xdf01];
+
+impl MidiType {
+    pub fn from(name: &str) -> Result<MidiType, String> {
+        match name {
+            "sequential" => Ok(MidiType::Sequential),
+            "passport" => Ok(MidiType::Passport),
+            "datel" => Ok(MidiType::Datel),
+            "namesoft" => Ok(MidiType::Namesoft),
+            "maplin" => Ok(MidiType::Maplin),
+            _ => Err(format!("invalid MIDI interface {}", name)),
+        }
+    }
+
+    fn get_io_ranges(&self) -> &'static [RangeInclusive<u16>] {
+        match *self {
+            MidiType::Sequential | MidiType::Namesoft => &IO_SEQUENTIAL,
+            MidiType::Passport => &IO_PASSPORT,
+            MidiType::Datel => &IO_DATEL,
+            MidiType::Maplin => &IO_MAPLIN,
+        }
+    }
+
+    fn get_regs(&self) -> MidiRegs {
+        match *self {
+            MidiType::Sequential => MidiRegs {
+                control: 0xde00,
+                status: 0xde02,
+                tx: 0xde01,
+                rx: 0xde03,
+                nmi: false,
+            },
+            MidiType::Passport => MidiRegs {
+                control: 0xde08,
+                status: 0xde08,
+                tx: 0xde09,
+                rx: 0xde09,
+                nmi: false,
+            },
+            MidiType::Datel => MidiRegs {
+                control: 0xde04,
+                status: 0xde06,
+                tx: 0xde05,
+                rx: 0xde07,
+                nmi: false,
+            },
+            MidiType::Namesoft => MidiRegs {
+                control: 0xde00,
+                status: 0xde02,
+                tx: 0xde01,
+                rx: 0xde03,
+                nmi: true,
+            },
+            MidiType::Maplin => MidiRegs {
+                control: 0xdf00,
+                status: 0xdf00,
+                tx: 0xdf01,
+                rx: 0xdf01,
+                nmi: true,
+            },
+        }
+    }
+}
+
+pub struct Midi {
+    midi_type: MidiType,
+    regs: MidiRegs,
+    acia: RefCell<Acia>,
+    backend: Box<dyn MidiBackend>,
+}
+
+impl Midi {
+    pub fn new(midi_type: MidiType, backend: Box<dyn MidiBackend>, clock_freq: u32) -> Self {
+        Self {
+            midi_type,
+            regs: midi_type.get_regs(),
+            acia: RefCell::new(Acia::new(clock_freq)),
+            backend,
+        }
+    }
+
+    pub fn get_type(&self) -> MidiType {
+        self.midi_type
+    }
+}
+
+impl ExpansionDevice for Midi {
+    fn get_io_ranges(&self) -> &[RangeInclusive<u16>] {
+        self.midi_type.get_io_ranges()
+    }
+
+    fn get_kind(&self) -> DeviceKind {
+        DeviceKind::Midi
+    }
+
+    fn is_irq_low(&self) -> bool {
+        !self.regs.nmi && self.acia.borrow().is_irq_low()
+    }
+
+    fn is_nmi_low(&self) -> bool {
+        self.regs.nmi && self.acia.borrow().is_irq_low()
+    }
+
+    fn clock(&mut self) {
+        self.acia.get_mut().clock(self.backend.as_mut());
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.backend.flush()
+    }
+
+    fn reset(&mut self) {
+        self.acia.get_mut().reset();
+    }
+}
+
+impl Addressable for Midi {
+    fn peek(&self, address: u16) -> u8 {
+        if address == self.regs.status {
+            self.acia.borrow().read_status()
+        } else if address == self.regs.rx {
+            self.acia.borrow().peek_data()
+        } else {
+            0xff
+        }
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        if address == self.regs.status {
+            self.acia.borrow().read_status()
+        } else if address == self.regs.rx {
+            self.acia.borrow_mut().read_data()
+        } else {
+            0xff
+        }
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        if address == self.regs.control {
+            self.acia.get_mut().write_control(value);
+        } else if address == self.regs.tx {
+            self.acia.get_mut().write_data(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::rc::Rc;
+
+    struct SharedBackend {
+        sent: Rc<RefCell<Vec<u8>>>,
+        received: VecDeque<u8>,
+    }
+
+    impl MidiBackend for SharedBackend {
+        fn receive(&mut self, _cycles: u64) -> Option<u8> {
+            self.received.pop_front()
+        }
+
+        fn send(&mut self, _cycles: u64, value: u8) {
+            self.sent.borrow_mut().push(value);
+        }
+    }
+
+    fn setup_midi(midi_type: MidiType, received: &[u8]) -> (Midi, Rc<RefCell<Vec<u8>>>) {
+        let sent = Rc::new(RefCell::new(Vec::new()));
+        let backend = SharedBackend {
+            sent: sent.clone(),
+            received: received.iter().cloned().collect(),
+        };
+        (Midi::new(midi_type, Box::new(backend), 31250), sent)
+    }
+
+    #[test]
+    fn datel_registers() {
+        let (mut midi, sent) = setup_midi(MidiType::Datel, &[]);
+        midi.write(0xde04, 0x16);
+        midi.write(0xde05, 0xf8);
+        assert_eq!(0x00, midi.read(0xde06) & 0x02);
+        for _ in 0..10 {
+            midi.clock();
+        }
+        assert_eq!(0x02, midi.read(0xde06) & 0x02);
+        assert_eq!(vec![0xf8], *sent.borrow());
+    }
+
+    #[test]
+    fn namesoft_receive_nmi() {
+        let (mut midi, _) = setup_midi(MidiType::Namesoft, &[0x90]);
+        midi.write(0xde00, 0x96);
+        midi.clock();
+        assert_eq!(false, midi.is_irq_low());
+        assert_eq!(true, midi.is_nmi_low());
+        assert_eq!(0x90, midi.read(0xde03));
+        assert_eq!(false, midi.is_nmi_low());
+    }
+
+    #[test]
+    fn passport_receive_irq() {
+        let (mut midi, _) = setup_midi(MidiType::Passport, &[0x42]);
+        midi.write(0xde08, 0x96);
+        midi.clock();
+        assert_eq!(true, midi.is_irq_low());
+        assert_eq!(0x81, midi.read(0xde08) & 0x81);
+        assert_eq!(0x42, midi.read(0xde09));
+    }
+}
@@ -0,0 +1,107 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::io::{BufReader, Read, Write};
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+
+use super::MidiBackend;
+
+// Design:
+//   The pipe backend exchanges the raw MIDI byte stream with other programs through named
+//   pipes or character devices. Opening a pipe blocks until the other end is connected, so
+//   each direction is served by its own thread and bytes are handed over through channels.
+
+pub struct PipeBackend {
+    input: Option<mpsc::Receiver<u8>>,
+    output: Option<mpsc::Sender<u8>>,
+}
+
+impl PipeBackend {
+    pub fn new(input_path: Option<&Path>, output_path: Option<&Path>) -> io::Result<PipeBackend> {
+        if let Some(path) = input_path {
+            if !path.exists() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("MIDI input {} not found", path.to_str().unwrap()),
+                ));
+            }
+        }
+        let input = input_path.map(|path| {
+            info!(target: "device", "Reading MIDI from {}", path.to_str().unwrap());
+            let path = path.to_path_buf();
+            let (tx, rx) = mpsc::channel();
+            thread::spawn(move || {
+                let file = match File::open(&path) {
+                    Ok(file) => file,
+                    Err(err) => {
+                        error!(target: "device", "Failed to open MIDI input: {}", err);
+                        return;
+                    }
+                };
+                for byte in BufReader::new(file).bytes() {
+                    match byte {
+                        Ok(value) => {
+                            if tx.send(value).is_err() {
+                                break;
+                            }
+                        }
+                        Err(err) => {
+                            error!(target: "device", "Failed to read MIDI input: {}", err);
+                            break;
+                        }
+                    }
+                }
+            });
+            rx
+        });
+        let output = output_path.map(|path| {
+            info!(target: "device", "Writing MIDI to {}", path.to_str().unwrap());
+            let path = path.to_path_buf();
+            let (tx, rx) = mpsc::channel::<u8>();
+            thread::spawn(move || {
+                let mut file = match OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .open(&path)
+                {
+                    Ok(file) => file,
+                    Err(err) => {
+                        error!(target: "device", "Failed to open MIDI output: {}", err);
+                        return;
+                    }
+                };
+                for value in rx.iter() {
+                    if let Err(err) = file.write_all(&[value]) {
+                        error!(target: "device", "Failed to write MIDI output: {}", err);
+                        break;
+                    }
+                }
+            });
+            tx
+        });
+        Ok(PipeBackend { input, output })
+    }
+}
+
+impl MidiBackend for PipeBackend {
+    fn receive(&mut self, _cycles: u64) -> Option<u8> {
+        match self.input {
+            Some(ref input) => input.try_recv().ok(),
+            None => None,
+        }
+    }
+
+    fn send(&mut self, _cycles: u64, value: u8) {
+        if let Some(ref output) = self.output {
+            if output.send(value).is_err() {
+                self.output = None;
+            }
+        }
+    }
+}
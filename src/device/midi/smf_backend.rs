@@ -0,0 +1,397 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+use super::MidiBackend;
+
+// SPEC: Standard MIDI Files 1.0, The International MIDI Association
+// Design:
+//   Received bytes are read from all tracks of a MIDI file, merged by time and converted to
+//   cycles with the tempo map, so they reach the ACIA at the time they were recorded.
+//   Sent bytes are collected into messages and written as a format 0 file when the device is
+//   flushed. Output uses 1 ms ticks so cycles map to ticks without a tempo map. Real-time and
+//   system common messages have no representation in a MIDI file and are dropped.
+
+const DEFAULT_TEMPO: u64 = 500_000;
+const OUTPUT_DIVISION: u16 = 500;
+
+struct SmfEvent {
+    tick: u64,
+    tempo: Option<u64>,
+    data: Vec<u8>,
+}
+
+pub struct SmfBackend {
+    clock_freq: u32,
+    // Input
+    input: VecDeque<(u64, u8)>,
+    // Output
+    output_path: Option<PathBuf>,
+    events: Vec<(u64, Vec<u8>)>,
+    message: Vec<u8>,
+    running_status: Option<u8>,
+    dirty: bool,
+}
+
+impl SmfBackend {
+    pub fn new(
+        input_path: Option<&Path>,
+        output_path: Option<&Path>,
+        clock_freq: u32,
+    ) -> io::Result<SmfBackend> {
+        let input = match input_path {
+            Some(path) => {
+                info!(target: "device", "Loading MIDI file {}", path.to_str().unwrap());
+                let file = File::open(path)?;
+                let mut rdr = BufReader::new(file);
+                let mut data = Vec::new();
+                rdr.read_to_end(&mut data)?;
+                Self::parse(&data, clock_freq)?
+            }
+            None => VecDeque::new(),
+        };
+        Ok(SmfBackend {
+            clock_freq,
+            input,
+            output_path: output_path.map(|path| path.to_path_buf()),
+            events: Vec::new(),
+            message: Vec::new(),
+            running_status: None,
+            dirty: false,
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut track = Vec::new();
+        let mut last_tick = 0;
+        for &(cycles, ref message) in self.events.iter() {
+            let tick = cycles * 1000 / self.clock_freq as u64;
+            Self::write_varlen(&mut track, (tick - last_tick) as u32);
+            last_tick = tick;
+            if message[0] == 0xf0 {
+                track.push(0xf0);
+                Self::write_varlen(&mut track, (message.len() - 1) as u32);
+                track.extend_from_slice(&message[1..]);
+            } else {
+                track.extend_from_slice(message);
+            }
+        }
+        track.extend_from_slice(&[0x00, 0xff, 0x2f, 0x00]);
+        let mut data = Vec::new();
+        data.extend_from_slice(b"MThd");
+        data.extend_from_slice(&[0x00, 0x00, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01]);
+        data.push((OUTPUT_DIVISION >> 8) as u8);
+        data.push(OUTPUT_DIVISION as u8);
+        data.extend_from_slice(b"MTrk");
+        let len = track.len() as u32;
+        data.extend_from_slice(&[
+            (len >> 24) as u8,
+            (len >> 16) as u8,
+            (len >> 8) as u8,
+            len as u8,
+        ]);
+        data.extend_from_slice(&track);
+        data
+    }
+
+    fn get_data_len(status: u8) -> usize {
+        match status & 0xf0 {
+            0xc0 | 0xd0 => 1,
+            _ => 2,
+        }
+    }
+
+    fn parse(data: &[u8], clock_freq: u32) -> io::Result<VecDeque<(u64, u8)>> {
+        let mut pos = 0;
+        if Self::read_bytes(data, &mut pos, 4)? != b"MThd" {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "invalid MIDI file header",
+            ));
+        }
+        let header_len = Self::read_u32(data, &mut pos)? as usize;
+        let header = Self::read_bytes(data, &mut pos, header_len)?;
+        if header.len() < 6 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "invalid MIDI file header",
+            ));
+        }
+        let division = ((header[4] as u64) << 8) | header[5] as u64;
+        if division & 0x8000 != 0 || division == 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "unsupported MIDI file time division",
+            ));
+        }
+        let mut events = Vec::new();
+        while pos < data.len() {
+            let chunk_type = Self::read_bytes(data, &mut pos, 4)?;
+            let chunk_len = Self::read_u32(data, &mut pos)? as usize;
+            let chunk = Self::read_bytes(data, &mut pos, chunk_len)?;
+            if chunk_type == b"MTrk" {
+                Self::parse_track(chunk, &mut events)?;
+            }
+        }
+        // Stable sort keeps the order of events within a track and of tracks at the same tick
+        events.sort_by_key(|event| event.tick);
+        let mut input = VecDeque::new();
+        let mut tempo = DEFAULT_TEMPO;
+        let mut last_tick = 0;
+        let mut time_us = 0u64;
+        for event in events.iter() {
+            time_us += (event.tick - last_tick) * tempo / division;
+            last_tick = event.tick;
+            if let Some(value) = event.tempo {
+                tempo = value;
+            }
+            let cycles = time_us * clock_freq as u64 / 1_000_000;
+            for &value in event.data.iter() {
+                input.push_back((cycles, value));
+            }
+        }
+        Ok(input)
+    }
+
+    fn parse_track(data: &[u8], events: &mut Vec<SmfEvent>) -> io::Result<()> {
+        let mut pos = 0;
+        let mut tick = 0u64;
+        let mut running_status = 0u8;
+        while pos < data.len() {
+            tick += Self::read_varlen(data, &mut pos)? as u64;
+            let mut status = Self::read_bytes(data, &mut pos, 1)?[0];
+            match status {
+                0xff => {
+                    let meta_type = Self::read_bytes(data, &mut pos, 1)?[0];
+                    let len = Self::read_varlen(data, &mut pos)? as usize;
+                    let meta = Self::read_bytes(data, &mut pos, len)?;
+                    match meta_type {
+                        0x2f => break,
+                        0x51 if len == 3 => events.push(SmfEvent {
+                            tick,
+                            tempo: Some(
+                                ((meta[0] as u64) << 16) | ((meta[1] as u64) << 8) | meta[2] as u64,
+                            ),
+                            data: Vec::new(),
+                        }),
+                        _ => {}
+                    }
+                }
+                0xf0 | 0xf7 => {
+                    let len = Self::read_varlen(data, &mut pos)? as usize;
+                    let mut message = if status == 0xf0 {
+                        vec![0xf0]
+                    } else {
+                        Vec::new()
+                    };
+                    message.extend_from_slice(Self::read_bytes(data, &mut pos, len)?);
+                    events.push(SmfEvent {
+                        tick,
+                        tempo: None,
+                        data: message,
+                    });
+                }
+                _ => {
+                    if status < 0x80 {
+                        if running_status == 0 {
+                            return Err(Error::new(
+                                ErrorKind::InvalidData,
+                                "MIDI data without status",
+                            ));
+                        }
+                        pos -= 1;
+                        status = running_status;
+                    }
+                    running_status = status;
+                    let mut message = vec![status];
+                    let len = Self::get_data_len(status);
+                    message.extend_from_slice(Self::read_bytes(data, &mut pos, len)?);
+                    events.push(SmfEvent {
+                        tick,
+                        tempo: None,
+                        data: message,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_bytes<'a>(data: &'a [u8], pos: &mut usize, len: usize) -> io::Result<&'a [u8]> {
+        if *pos + len > data.len() {
+            return Err(Error::new(ErrorKind::UnexpectedEof, "truncated MIDI file"));
+        }
+        let bytes = &data[*pos..*pos + len];
+        *pos += len;
+        Ok(bytes)
+    }
+
+    fn read_u32(data: &[u8], pos: &mut usize) -> io::Result<u32> {
+        let bytes = Self::read_bytes(data, pos, 4)?;
+        Ok(bytes
+            .iter()
+            .fold(0u32, |value, &byte| (value << 8) | byte as u32))
+    }
+
+    fn read_varlen(data: &[u8], pos: &mut usize) -> io::Result<u32> {
+        let mut value = 0u32;
+        for _ in 0..4 {
+            let byte = Self::read_bytes(data, pos, 1)?[0];
+            value = (value << 7) | (byte & 0x7f) as u32;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            "invalid MIDI variable length",
+        ))
+    }
+
+    fn write_varlen(data: &mut Vec<u8>, value: u32) {
+        let mut buffer = vec![(value & 0x7f) as u8];
+        let mut value = value >> 7;
+        while value > 0 {
+            buffer.push((value & 0x7f) as u8 | 0x80);
+            value >>= 7;
+        }
+        buffer.reverse();
+        data.extend_from_slice(&buffer);
+    }
+}
+
+impl MidiBackend for SmfBackend {
+    fn flush(&mut self) -> io::Result<()> {
+        if self.dirty {
+            if let Some(ref path) = self.output_path {
+                info!(target: "device", "Saving MIDI file {}", path.to_str().unwrap());
+                let file = File::create(path)?;
+                let mut wtr = BufWriter::new(file);
+                wtr.write_all(&self.to_bytes())?;
+                wtr.flush()?;
+            }
+            self.dirty = false;
+        }
+        Ok(())
+    }
+
+    fn receive(&mut self, cycles: u64) -> Option<u8> {
+        match self.input.front() {
+            Some(&(time, _)) if time <= cycles => self.input.pop_front().map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    fn send(&mut self, cycles: u64, value: u8) {
+        match value {
+            0xf8..=0xff => {}
+            0xf0 => {
+                self.message = vec![0xf0];
+                self.running_status = None;
+            }
+            0xf7 if self.message.first() == Some(&0xf0) => {
+                self.message.push(0xf7);
+                self.events.push((cycles, self.message.split_off(0)));
+                self.dirty = true;
+            }
+            0xf1..=0xf7 => {
+                self.message.clear();
+                self.running_status = None;
+            }
+            0x80..=0xef => {
+                self.message = vec![value];
+                self.running_status = Some(value);
+            }
+            _ => {
+                if self.message.first() == Some(&0xf0) {
+                    self.message.push(value);
+                    return;
+                }
+                if self.message.is_empty() {
+                    match self.running_status {
+                        Some(status) => self.message.push(status),
+                        None => return,
+                    }
+                }
+                self.message.push(value);
+                if self.message.len() == 1 + Self::get_data_len(self.message[0]) {
+                    self.events.push((cycles, self.message.split_off(0)));
+                    self.dirty = true;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_messages() {
+        let mut backend = SmfBackend::new(None, None, 1_000_000).unwrap();
+        // Note on with running status, a sysex message and a real-time clock in between
+        for &(cycles, value) in [
+            (0, 0x90),
+            (0, 0x3c),
+            (0, 0xf8),
+            (0, 0x40),
+            (2_000, 0x3c),
+            (2_000, 0x00),
+            (5_000, 0xf0),
+            (5_000, 0x7e),
+            (5_000, 0xf7),
+        ]
+        .iter()
+        {
+            backend.send(cycles, value);
+        }
+        let data = backend.to_bytes();
+        let input = SmfBackend::parse(&data, 1_000_000).unwrap();
+        assert_eq!(
+            vec![
+                (0, 0x90),
+                (0, 0x3c),
+                (0, 0x40),
+                (2_000, 0x90),
+                (2_000, 0x3c),
+                (2_000, 0x00),
+                (5_000, 0xf0),
+                (5_000, 0x7e),
+                (5_000, 0xf7),
+            ],
+            input.into_iter().collect::<Vec<(u64, u8)>>()
+        );
+    }
+
+    #[test]
+    fn read_tempo_change() {
+        let mut data = Vec::new();
+        data.extend_from_slice(b"MThd");
+        data.extend_from_slice(&[0, 0, 0, 6, 0, 0, 0, 1, 0, 100]);
+        let track = [
+            0x00, 0xff, 0x51, 0x03, 0x0f, 0x42, 0x40, // 1 s per quarter
+            0x32, 0x90, 0x3c, 0x40, // half a quarter later
+            0x00, 0xff, 0x2f, 0x00,
+        ];
+        data.extend_from_slice(b"MTrk");
+        data.extend_from_slice(&[0, 0, 0, track.len() as u8]);
+        data.extend_from_slice(&track);
+        let input = SmfBackend::parse(&data, 1_000_000).unwrap();
+        assert_eq!(Some(&(500_000, 0x90)), input.front());
+    }
+
+    #[test]
+    fn receive_waits_for_event_time() {
+        let mut backend = SmfBackend::new(None, None, 1_000_000).unwrap();
+        backend.input.push_back((100, 0xfe));
+        assert_eq!(None, backend.receive(99));
+        assert_eq!(Some(0xfe), backend.receive(100));
+    }
+}
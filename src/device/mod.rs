@@ -4,14 +4,29 @@
 
 pub mod cartridge;
 mod datassette;
+mod ethernet;
+mod expansion_device;
 mod expansion_port;
+mod georam;
+pub mod fs_drive;
+mod iec_bus;
 pub mod joystick;
 pub mod keyboard;
+pub mod midi;
+mod reu;
 mod tape;
+mod tape_decoder;
 
-pub use self::cartridge::{Cartridge, Chip, ChipType, HwType};
+pub use self::cartridge::{Cartridge, CartridgeStore, Chip, ChipType, HwType};
 pub use self::datassette::Datassette;
+pub use self::ethernet::Ethernet;
+pub use self::expansion_device::{DeviceKind, DmaAccess, ExpansionDevice, RamStore};
 pub use self::expansion_port::ExpansionPort;
+pub use self::georam::{GeoRam, GeoRamModel};
+pub use self::fs_drive::FsDrive;
+pub use self::iec_bus::{IecBus, IecDevice};
 pub use self::joystick::Joystick;
 pub use self::keyboard::{Key, KeyEvent, Keyboard};
+pub use self::midi::{Midi, MidiBackend, MidiType};
+pub use self::reu::Reu;
 pub use self::tape::Tape;
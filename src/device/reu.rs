@@ -0,0 +1,574 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::cell::Cell;
+use std::io;
+use std::ops::RangeInclusive;
+
+use bit_field::BitField;
+use core::Addressable;
+use device::{DeviceKind, DmaAccess, ExpansionDevice, RamStore};
+
+// SPEC: Commodore 1700/1764/1750 RAM Expansion Module User's Guide, 8726 REC registers
+// Design:
+//   The REC registers are mirrored every 32 bytes in $DF00-$DFFF. A transfer is armed by
+//   a write to the command register and starts either immediately or on the next write
+//   to $FF00. The REU then holds BA low and moves one byte per cycle. Since the memory
+//   controller may route the access back to the expansion port, each DMA cycle is split
+//   into a bus access requested from the REU and its completion with the value on the bus.
+//   Sizes above 512K use all bits of the bank register like common 16 MB clones.
+
+const MIN_CAPACITY: usize = 0x20000;
+const MAX_CAPACITY: usize = 0x100_0000;
+
+static IO_RANGES: [RangeInclusive<u16>; 1] = [0xdf00..=0xdfff];
+
+#[derive(Copy, Clone)]
+enum Reg {
+    Status = 0x00,
+    Command = 0x01,
+    C64AddrLo = 0x02,
+    C64AddrHi = 0x03,
+    ReuAddrLo = 0x04,
+    ReuAddrHi = 0x05,
+    ReuBank = 0x06,
+    LengthLo = 0x07,
+    LengthHi = 0x08,
+    IrqMask = 0x09,
+    AddrControl = 0x0a,
+}
+
+impl Reg {
+    pub fn from(reg: u8) -> Option<Reg> {
+        match reg {
+            0x00 => Some(Reg::Status),
+            0x01 => Some(Reg::Command),
+            0x02 => Some(Reg::C64AddrLo),
+            0x03 => Some(Reg::C64AddrHi),
+            0x04 => Some(Reg::ReuAddrLo),
+            0x05 => Some(Reg::ReuAddrHi),
+            0x06 => Some(Reg::ReuBank),
+            0x07 => Some(Reg::LengthLo),
+            0x08 => Some(Reg::LengthHi),
+            0x09 => Some(Reg::IrqMask),
+            0x0a => Some(Reg::AddrControl),
+            _ => None,
+        }
+    }
+}
+
+// Status register bits
+const STATUS_IRQ: usize = 7;
+const STATUS_END_OF_BLOCK: usize = 6;
+const STATUS_FAULT: usize = 5;
+const STATUS_SIZE: usize = 4;
+
+// Command register bits
+const COMMAND_EXECUTE: usize = 7;
+const COMMAND_AUTOLOAD: usize = 5;
+const COMMAND_FF00_DISABLED: usize = 4;
+
+// Interrupt mask bits
+const IRQ_ENABLE: usize = 7;
+const IRQ_END_OF_BLOCK: usize = 6;
+const IRQ_FAULT: usize = 5;
+
+// Address control bits
+const FIX_C64_ADDR: usize = 7;
+const FIX_REU_ADDR: usize = 6;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum State {
+    Idle,
+    Armed,
+    Starting,
+    Running,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Transfer {
+    Stash,
+    Fetch,
+    Swap,
+    Verify,
+}
+
+impl Transfer {
+    pub fn from(command: u8) -> Transfer {
+        match command & 0x03 {
+            0x00 => Transfer::Stash,
+            0x01 => Transfer::Fetch,
+            0x02 => Transfer::Swap,
+            _ => Transfer::Verify,
+        }
+    }
+}
+
+pub struct Reu {
+    // Registers
+    status: Cell<u8>,
+    command: u8,
+    c64_address: u16,
+    reu_address: u32,
+    length: u16,
+    irq_mask: u8,
+    address_control: u8,
+    // Autoload
+    c64_base: u16,
+    reu_base: u32,
+    length_base: u16,
+    // Runtime State
+    state: State,
+    swap_value: Option<u8>,
+    // Storage
+    ram: Vec<u8>,
+    dirty: bool,
+    store: Option<Box<dyn RamStore>>,
+}
+
+impl Reu {
+    pub fn new(capacity: usize) -> Self {
+        if !Reu::is_valid_capacity(capacity) {
+            panic!("invalid REU capacity {}", capacity);
+        }
+        Reu {
+            status: Cell::new(0),
+            command: 1 << COMMAND_FF00_DISABLED,
+            c64_address: 0,
+            reu_address: 0,
+            length: 0xffff,
+            irq_mask: 0,
+            address_control: 0,
+            c64_base: 0,
+            reu_base: 0,
+            length_base: 0xffff,
+            state: State::Idle,
+            swap_value: None,
+            ram: vec![0; capacity],
+            dirty: false,
+            store: None,
+        }
+    }
+
+    pub fn is_valid_capacity(capacity: usize) -> bool {
+        capacity.is_power_of_two() && (MIN_CAPACITY..=MAX_CAPACITY).contains(&capacity)
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.ram.len()
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.ram
+    }
+
+    pub fn is_dma_active(&self) -> bool {
+        self.state == State::Running
+    }
+
+    pub fn is_irq_low(&self) -> bool {
+        self.status.get().get_bit(STATUS_IRQ)
+    }
+
+    pub fn load(&mut self, data: &[u8]) {
+        for (dst, src) in self.ram.iter_mut().zip(data.iter()) {
+            *dst = *src;
+        }
+    }
+
+    pub fn set_store(&mut self, store: Box<dyn RamStore>) {
+        self.store = Some(store);
+    }
+
+    // -- Device Ops
+
+    pub fn clock(&mut self) {
+        if self.state == State::Starting {
+            self.state = State::Running;
+        }
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        if self.dirty {
+            if let Some(mut store) = self.store.take() {
+                let result = store.save(&self.ram);
+                self.store = Some(store);
+                result?;
+                self.dirty = false;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn reset(&mut self) {
+        self.status.set(0);
+        self.command = 1 << COMMAND_FF00_DISABLED;
+        self.c64_address = 0;
+        self.reu_address = 0;
+        self.length = 0xffff;
+        self.irq_mask = 0;
+        self.address_control = 0;
+        self.c64_base = 0;
+        self.reu_base = 0;
+        self.length_base = 0xffff;
+        self.state = State::Idle;
+        self.swap_value = None;
+    }
+
+    // Called for every write to $FF00 to start an armed transfer.
+    pub fn trigger(&mut self) {
+        if self.state == State::Armed {
+            self.state = State::Starting;
+        }
+    }
+
+    // -- DMA
+
+    pub fn complete_dma(&mut self, value: u8) {
+        if self.state != State::Running {
+            return;
+        }
+        let index = self.get_ram_index();
+        match Transfer::from(self.command) {
+            Transfer::Stash => {
+                self.ram[index] = value;
+                self.dirty = true;
+            }
+            Transfer::Fetch => {}
+            Transfer::Swap => {
+                if self.swap_value.is_none() {
+                    self.swap_value = Some(value);
+                    return;
+                }
+                self.ram[index] = self.swap_value.take().unwrap();
+                self.dirty = true;
+            }
+            Transfer::Verify => {
+                if self.ram[index] != value {
+                    self.set_status(STATUS_FAULT, true);
+                    let end_of_block = self.advance();
+                    self.finish(end_of_block);
+                    return;
+                }
+            }
+        }
+        if self.advance() {
+            self.finish(true);
+        }
+    }
+
+    pub fn get_dma_access(&self) -> Option<DmaAccess> {
+        if self.state != State::Running {
+            return None;
+        }
+        let value = self.ram[self.get_ram_index()];
+        let access = match Transfer::from(self.command) {
+            Transfer::Stash | Transfer::Verify => DmaAccess::Read(self.c64_address),
+            Transfer::Fetch => DmaAccess::Write(self.c64_address, value),
+            Transfer::Swap if self.swap_value.is_none() => DmaAccess::Read(self.c64_address),
+            Transfer::Swap => DmaAccess::Write(self.c64_address, value),
+        };
+        Some(access)
+    }
+
+    // Returns true when the last byte was transferred.
+    fn advance(&mut self) -> bool {
+        if !self.address_control.get_bit(FIX_C64_ADDR) {
+            self.c64_address = self.c64_address.wrapping_add(1);
+        }
+        if !self.address_control.get_bit(FIX_REU_ADDR) {
+            let mask = ((self.get_bank_mask() as u32) << 16) | 0xffff;
+            self.reu_address = self.reu_address.wrapping_add(1) & mask;
+        }
+        if self.length == 1 {
+            true
+        } else {
+            self.length = self.length.wrapping_sub(1);
+            false
+        }
+    }
+
+    fn finish(&mut self, end_of_block: bool) {
+        self.state = State::Idle;
+        if end_of_block {
+            self.set_status(STATUS_END_OF_BLOCK, true);
+        }
+        self.command.set_bit(COMMAND_EXECUTE, false);
+        self.command.set_bit(COMMAND_FF00_DISABLED, true);
+        if self.command.get_bit(COMMAND_AUTOLOAD) {
+            self.c64_address = self.c64_base;
+            self.reu_address = self.reu_base;
+            self.length = self.length_base;
+        }
+        self.update_irq();
+    }
+
+    fn get_bank_mask(&self) -> u8 {
+        ((self.ram.len() - 1) >> 16) as u8 | 0x07
+    }
+
+    fn get_ram_index(&self) -> usize {
+        (self.reu_address as usize) & (self.ram.len() - 1)
+    }
+
+    fn set_status(&mut self, bit: usize, value: bool) {
+        let mut status = self.status.get();
+        status.set_bit(bit, value);
+        self.status.set(status);
+    }
+
+    fn update_irq(&mut self) {
+        let status = self.status.get();
+        let end_of_block =
+            self.irq_mask.get_bit(IRQ_END_OF_BLOCK) && status.get_bit(STATUS_END_OF_BLOCK);
+        let fault = self.irq_mask.get_bit(IRQ_FAULT) && status.get_bit(STATUS_FAULT);
+        let irq = self.irq_mask.get_bit(IRQ_ENABLE) && (end_of_block || fault);
+        self.set_status(STATUS_IRQ, irq);
+    }
+
+    // -- I/O
+
+    pub fn peek(&self, address: u16) -> u8 {
+        match Reg::from((address & 0x1f) as u8) {
+            Some(Reg::Status) => {
+                let mut value = self.status.get();
+                value.set_bit(STATUS_SIZE, self.ram.len() > MIN_CAPACITY);
+                value
+            }
+            Some(Reg::Command) => self.command,
+            Some(Reg::C64AddrLo) => self.c64_address as u8,
+            Some(Reg::C64AddrHi) => (self.c64_address >> 8) as u8,
+            Some(Reg::ReuAddrLo) => self.reu_address as u8,
+            Some(Reg::ReuAddrHi) => (self.reu_address >> 8) as u8,
+            Some(Reg::ReuBank) => (self.reu_address >> 16) as u8 | !self.get_bank_mask(),
+            Some(Reg::LengthLo) => self.length as u8,
+            Some(Reg::LengthHi) => (self.length >> 8) as u8,
+            Some(Reg::IrqMask) => self.irq_mask | 0x1f,
+            Some(Reg::AddrControl) => self.address_control | 0x3f,
+            None => 0xff,
+        }
+    }
+
+    // Reading the status register clears the interrupt and transfer flags.
+    pub fn read(&self, address: u16) -> u8 {
+        let value = self.peek(address);
+        if let Some(Reg::Status) = Reg::from((address & 0x1f) as u8) {
+            self.status.set(0);
+        }
+        value
+    }
+
+    pub fn write(&mut self, address: u16, value: u8) {
+        match Reg::from((address & 0x1f) as u8) {
+            Some(Reg::Status) => {}
+            Some(Reg::Command) => {
+                self.command = value;
+                if value.get_bit(COMMAND_EXECUTE) {
+                    self.state = if value.get_bit(COMMAND_FF00_DISABLED) {
+                        State::Starting
+                    } else {
+                        State::Armed
+                    };
+                    self.swap_value = None;
+                }
+            }
+            Some(Reg::C64AddrLo) => {
+                self.c64_base = (self.c64_base & 0xff00) | value as u16;
+                self.c64_address = self.c64_base;
+            }
+            Some(Reg::C64AddrHi) => {
+                self.c64_base = (self.c64_base & 0x00ff) | ((value as u16) << 8);
+                self.c64_address = self.c64_base;
+            }
+            Some(Reg::ReuAddrLo) => {
+                self.reu_base = (self.reu_base & 0xff_ff00) | value as u32;
+                self.reu_address = self.reu_base;
+            }
+            Some(Reg::ReuAddrHi) => {
+                self.reu_base = (self.reu_base & 0xff_00ff) | ((value as u32) << 8);
+                self.reu_address = self.reu_base;
+            }
+            Some(Reg::ReuBank) => {
+                let bank = (value & self.get_bank_mask()) as u32;
+                self.reu_base = (self.reu_base & 0x00_ffff) | (bank << 16);
+                self.reu_address = self.reu_base;
+            }
+            Some(Reg::LengthLo) => {
+                self.length_base = (self.length_base & 0xff00) | value as u16;
+                self.length = self.length_base;
+            }
+            Some(Reg::LengthHi) => {
+                self.length_base = (self.length_base & 0x00ff) | ((value as u16) << 8);
+                self.length = self.length_base;
+            }
+            Some(Reg::IrqMask) => {
+                self.irq_mask = value & 0xe0;
+                self.update_irq();
+            }
+            Some(Reg::AddrControl) => self.address_control = value & 0xc0,
+            None => {}
+        }
+    }
+}
+
+impl ExpansionDevice for Reu {
+    fn get_io_ranges(&self) -> &[RangeInclusive<u16>] {
+        &IO_RANGES
+    }
+
+    fn get_kind(&self) -> DeviceKind {
+        DeviceKind::RamExpansion
+    }
+
+    fn is_dma_active(&self) -> bool {
+        Reu::is_dma_active(self)
+    }
+
+    fn is_irq_low(&self) -> bool {
+        Reu::is_irq_low(self)
+    }
+
+    fn clock(&mut self) {
+        Reu::clock(self)
+    }
+
+    fn complete_dma(&mut self, value: u8) {
+        Reu::complete_dma(self, value)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Reu::flush(self)
+    }
+
+    fn get_dma_access(&self) -> Option<DmaAccess> {
+        Reu::get_dma_access(self)
+    }
+
+    fn reset(&mut self) {
+        Reu::reset(self)
+    }
+}
+
+impl Addressable for Reu {
+    fn peek(&self, address: u16) -> u8 {
+        Reu::peek(self, address)
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        Reu::read(self, address)
+    }
+
+    fn write(&mut self, address: u16, value: u8) {
+        match address {
+            0xdf00..=0xdfff => Reu::write(self, address, value),
+            0xff00 => self.trigger(),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_transfer(reu: &mut Reu, mem: &mut [u8]) -> u32 {
+        let mut cycles = 0;
+        reu.clock();
+        while reu.is_dma_active() {
+            let value = match reu.get_dma_access().unwrap() {
+                DmaAccess::Read(address) => mem[address as usize],
+                DmaAccess::Write(address, value) => {
+                    mem[address as usize] = value;
+                    value
+                }
+            };
+            reu.complete_dma(value);
+            reu.clock();
+            cycles += 1;
+        }
+        cycles
+    }
+
+    fn setup_transfer(reu: &mut Reu, c64_address: u16, reu_address: u32, length: u16) {
+        reu.write(0xdf02, c64_address as u8);
+        reu.write(0xdf03, (c64_address >> 8) as u8);
+        reu.write(0xdf04, reu_address as u8);
+        reu.write(0xdf05, (reu_address >> 8) as u8);
+        reu.write(0xdf06, (reu_address >> 16) as u8);
+        reu.write(0xdf07, length as u8);
+        reu.write(0xdf08, (length >> 8) as u8);
+    }
+
+    #[test]
+    fn stash_and_fetch() {
+        let mut reu = Reu::new(0x20000);
+        let mut mem = vec![0u8; 0x10000];
+        mem[0x1000..0x1004].copy_from_slice(&[1, 2, 3, 4]);
+        setup_transfer(&mut reu, 0x1000, 0x10010, 4);
+        reu.write(0xdf01, 0x90);
+        assert_eq!(4, run_transfer(&mut reu, &mut mem));
+        assert_eq!(&[1, 2, 3, 4], &reu.get_data()[0x10010..0x10014]);
+        assert_eq!(0x40, reu.read(0xdf00));
+        setup_transfer(&mut reu, 0x2000, 0x10010, 4);
+        reu.write(0xdf01, 0x91);
+        run_transfer(&mut reu, &mut mem);
+        assert_eq!(&[1, 2, 3, 4], &mem[0x2000..0x2004]);
+    }
+
+    #[test]
+    fn swap() {
+        let mut reu = Reu::new(0x20000);
+        let mut mem = vec![0x11u8; 0x10000];
+        reu.load(&[0x22; 2]);
+        setup_transfer(&mut reu, 0x1000, 0, 2);
+        reu.write(0xdf01, 0x92);
+        assert_eq!(4, run_transfer(&mut reu, &mut mem));
+        assert_eq!(&[0x22, 0x22], &mem[0x1000..0x1002]);
+        assert_eq!(&[0x11, 0x11], &reu.get_data()[0..2]);
+    }
+
+    #[test]
+    fn verify_error() {
+        let mut reu = Reu::new(0x20000);
+        let mut mem = vec![0u8; 0x10000];
+        mem[0x1001] = 0xff;
+        setup_transfer(&mut reu, 0x1000, 0, 4);
+        reu.write(0xdf09, 0xa0);
+        reu.write(0xdf01, 0x93);
+        assert_eq!(2, run_transfer(&mut reu, &mut mem));
+        assert_eq!(true, reu.is_irq_low());
+        assert_eq!(0xa0, reu.read(0xdf00) & 0xe0);
+        assert_eq!(false, reu.is_irq_low());
+    }
+
+    #[test]
+    fn ff00_trigger_and_autoload() {
+        let mut reu = Reu::new(0x80000);
+        let mut mem = vec![0u8; 0x10000];
+        setup_transfer(&mut reu, 0x1000, 0x70000, 0x10);
+        reu.write(0xdf01, 0xa0);
+        reu.clock();
+        assert_eq!(false, reu.is_dma_active());
+        reu.trigger();
+        assert_eq!(0x10, run_transfer(&mut reu, &mut mem));
+        assert_eq!(0x00, reu.read(0xdf02));
+        assert_eq!(0x10, reu.read(0xdf03));
+        assert_eq!(0xff, reu.read(0xdf06));
+        assert_eq!(0x10, reu.read(0xdf07));
+        assert_eq!(0x10, reu.read(0xdf00) & 0x10);
+    }
+
+    #[test]
+    fn fixed_c64_address() {
+        let mut reu = Reu::new(0x20000);
+        let mut mem = vec![0u8; 0x10000];
+        mem[0x1000] = 0x42;
+        setup_transfer(&mut reu, 0x1000, 0, 3);
+        reu.write(0xdf0a, 0x80);
+        reu.write(0xdf01, 0x90);
+        run_transfer(&mut reu, &mut mem);
+        assert_eq!(&[0x42, 0x42, 0x42], &reu.get_data()[0..3]);
+        assert_eq!(0x01, reu.read(0xdf07));
+    }
+}
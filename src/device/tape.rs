@@ -2,7 +2,127 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
+use std::io;
+
+// Design:
+//   Tape position and length are measured in pulses.
+
 pub trait Tape {
+    fn get_length(&self) -> usize;
+    fn get_position(&self) -> usize;
     fn read_pulse(&mut self) -> Option<u32>;
     fn seek(&mut self, pos: usize) -> bool;
+    fn write_pulse(&mut self, pulse: u32);
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+#[cfg(test)]
+pub mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    // In-memory tape for the datassette and decoder tests. It shares its pulses so they can
+    // be inspected after the tape is attached, and appends blocks in the kernal format.
+
+    // Kernal pulse lengths in cycles
+    const SHORT: u32 = 0x30 << 3;
+    const MEDIUM: u32 = 0x42 << 3;
+    const LONG: u32 = 0x56 << 3;
+    const LEADER_LEN: usize = 1000;
+    const SYNC_LEN: u8 = 9;
+
+    pub struct PulseTape {
+        pulses: Rc<RefCell<Vec<u32>>>,
+        pos: usize,
+    }
+
+    impl PulseTape {
+        pub fn new(pulses: Vec<u32>) -> Self {
+            PulseTape {
+                pulses: Rc::new(RefCell::new(pulses)),
+                pos: 0,
+            }
+        }
+
+        pub fn get_pulses(&self) -> Rc<RefCell<Vec<u32>>> {
+            self.pulses.clone()
+        }
+
+        // Appends a leader, the countdown sync, the data, its checksum and the end marker.
+        pub fn write_block(&mut self, data: &[u8], repeat: bool) {
+            for _ in 0..LEADER_LEN {
+                self.pulses.borrow_mut().push(SHORT);
+            }
+            let sync = if repeat { 0x09 } else { 0x89 };
+            for i in 0..SYNC_LEN {
+                self.write_byte(sync - i);
+            }
+            for value in data.iter() {
+                self.write_byte(*value);
+            }
+            let checksum = data.iter().fold(0, |acc, value| acc ^ value);
+            self.write_byte(checksum);
+            let mut pulses = self.pulses.borrow_mut();
+            pulses.push(LONG);
+            pulses.push(SHORT);
+        }
+
+        fn write_byte(&mut self, value: u8) {
+            let mut pulses = self.pulses.borrow_mut();
+            pulses.push(LONG);
+            pulses.push(MEDIUM);
+            let mut parity = 1;
+            for i in 0..9 {
+                let bit = if i < 8 { (value >> i) & 1 } else { parity };
+                parity ^= bit;
+                if bit == 0 {
+                    pulses.push(SHORT);
+                    pulses.push(MEDIUM);
+                } else {
+                    pulses.push(MEDIUM);
+                    pulses.push(SHORT);
+                }
+            }
+        }
+    }
+
+    impl Tape for PulseTape {
+        fn get_length(&self) -> usize {
+            self.pulses.borrow().len()
+        }
+
+        fn get_position(&self) -> usize {
+            self.pos
+        }
+
+        fn read_pulse(&mut self) -> Option<u32> {
+            let pulse = self.pulses.borrow().get(self.pos).cloned();
+            if pulse.is_some() {
+                self.pos += 1;
+            }
+            pulse
+        }
+
+        fn seek(&mut self, pos: usize) -> bool {
+            if pos <= self.pulses.borrow().len() {
+                self.pos = pos;
+                true
+            } else {
+                false
+            }
+        }
+
+        fn write_pulse(&mut self, pulse: u32) {
+            // Recording replaces the rest of the tape.
+            let mut pulses = self.pulses.borrow_mut();
+            pulses.truncate(self.pos);
+            pulses.push(pulse);
+            self.pos += 1;
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,226 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use super::Tape;
+
+// SPEC: https://www.c64-wiki.com/wiki/Datassette_Encoding
+// Design:
+//   Decodes blocks written by the kernal tape routines from the pulse stream. A block is
+//   a countdown sync ($89..$81 for the first copy, $09..$01 for the repeat), data bytes and
+//   a checksum byte. Each byte starts with a long/medium marker followed by 8 data bits and an
+//   odd parity bit, where 0 is short/medium and 1 is medium/short. A long/short marker ends
+//   the block.
+
+// Pulse length thresholds in cycles
+const SHORT_MIN: u32 = 0x24 << 3;
+const SHORT_MAX: u32 = 0x36 << 3;
+const MEDIUM_MAX: u32 = 0x4b << 3;
+const LONG_MAX: u32 = 0x64 << 3;
+const SYNC_LEN: u8 = 9;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Pulse {
+    Short,
+    Medium,
+    Long,
+    Invalid,
+}
+
+impl Pulse {
+    pub fn from(cycles: u32) -> Pulse {
+        if cycles < SHORT_MIN {
+            Pulse::Invalid
+        } else if cycles < SHORT_MAX {
+            Pulse::Short
+        } else if cycles < MEDIUM_MAX {
+            Pulse::Medium
+        } else if cycles < LONG_MAX {
+            Pulse::Long
+        } else {
+            Pulse::Invalid
+        }
+    }
+}
+
+enum Marker {
+    Byte,
+    End,
+}
+
+pub struct Block {
+    pub data: Vec<u8>,
+    pub checksum_ok: bool,
+    pub repeat: bool,
+}
+
+pub struct TapeDecoder<'a> {
+    tape: &'a mut dyn Tape,
+    cycles: u64,
+}
+
+impl<'a> TapeDecoder<'a> {
+    pub fn new(tape: &'a mut dyn Tape) -> Self {
+        TapeDecoder { tape, cycles: 0 }
+    }
+
+    pub fn get_cycles(&self) -> u64 {
+        self.cycles
+    }
+
+    // Reads the next block and skips its repeat. The repeat is used if the first copy
+    // fails the checksum.
+    pub fn read_block(&mut self) -> Option<Block> {
+        let first = self.find_block()?;
+        if first.repeat {
+            return Some(first);
+        }
+        let pos = self.tape.get_position();
+        let cycles = self.cycles;
+        match self.find_block() {
+            Some(ref second) if second.repeat => {
+                if !first.checksum_ok && second.checksum_ok {
+                    return Some(Block {
+                        data: second.data.clone(),
+                        checksum_ok: true,
+                        repeat: false,
+                    });
+                }
+            }
+            _ => {
+                self.tape.seek(pos);
+                self.cycles = cycles;
+            }
+        }
+        Some(first)
+    }
+
+    fn find_block(&mut self) -> Option<Block> {
+        loop {
+            if self.next_pulse()? != Pulse::Long {
+                continue;
+            }
+            if self.next_pulse()? != Pulse::Medium {
+                continue;
+            }
+            if let Some(block) = self.read_block_data() {
+                return Some(block);
+            }
+        }
+    }
+
+    fn read_block_data(&mut self) -> Option<Block> {
+        let first = self.read_byte_data()?;
+        let repeat = match first {
+            0x89 => false,
+            0x09 => true,
+            _ => return None,
+        };
+        for i in 1..SYNC_LEN {
+            match self.read_byte() {
+                Some(Marker::Byte) => {}
+                _ => return None,
+            }
+            let value = self.read_byte_data()?;
+            if value != first - i {
+                return None;
+            }
+        }
+        let mut data = Vec::new();
+        while let Marker::Byte = self.read_byte()? {
+            data.push(self.read_byte_data()?);
+        }
+        let checksum = data.pop()?;
+        let checksum_ok = data.iter().fold(0, |acc, value| acc ^ value) == checksum;
+        Some(Block {
+            data,
+            checksum_ok,
+            repeat,
+        })
+    }
+
+    fn read_byte(&mut self) -> Option<Marker> {
+        if self.next_pulse()? != Pulse::Long {
+            return None;
+        }
+        match self.next_pulse()? {
+            Pulse::Medium => Some(Marker::Byte),
+            Pulse::Short => Some(Marker::End),
+            _ => None,
+        }
+    }
+
+    fn read_byte_data(&mut self) -> Option<u8> {
+        let mut value = 0u8;
+        let mut parity = 1u8;
+        for i in 0..9 {
+            let bit = match (self.next_pulse()?, self.next_pulse()?) {
+                (Pulse::Short, Pulse::Medium) => 0,
+                (Pulse::Medium, Pulse::Short) => 1,
+                _ => return None,
+            };
+            if i < 8 {
+                value |= bit << i;
+                parity ^= bit;
+            } else if bit != parity {
+                return None;
+            }
+        }
+        Some(value)
+    }
+
+    fn next_pulse(&mut self) -> Option<Pulse> {
+        let cycles = self.tape.read_pulse()?;
+        self.cycles += cycles as u64;
+        Some(Pulse::from(cycles))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tape::tests::PulseTape;
+    use super::*;
+
+    // Flips bit 0 of the checksum byte of the last block along with its parity bit.
+    fn corrupt_checksum(tape: &PulseTape) {
+        let pulses = tape.get_pulses();
+        let mut pulses = pulses.borrow_mut();
+        let len = pulses.len();
+        pulses.swap(len - 20, len - 19);
+        pulses.swap(len - 4, len - 3);
+    }
+
+    #[test]
+    fn read_block_and_skip_repeat() {
+        let mut tape = PulseTape::new(Vec::new());
+        tape.write_block(&[0x01, 0x02, 0x03], false);
+        tape.write_block(&[0x01, 0x02, 0x03], true);
+        tape.write_block(&[0x04, 0x05], false);
+        let mut decoder = TapeDecoder::new(&mut tape);
+        let block = decoder.read_block().unwrap();
+        assert_eq!(vec![0x01, 0x02, 0x03], block.data);
+        assert_eq!(true, block.checksum_ok);
+        let block = decoder.read_block().unwrap();
+        assert_eq!(vec![0x04, 0x05], block.data);
+        assert_eq!(true, decoder.read_block().is_none());
+    }
+
+    #[test]
+    fn read_block_uses_repeat_on_checksum_error() {
+        let mut tape = PulseTape::new(Vec::new());
+        tape.write_block(&[0x01, 0x02, 0x03], false);
+        corrupt_checksum(&tape);
+        tape.write_block(&[0x01, 0x02, 0x03], true);
+        let mut decoder = TapeDecoder::new(&mut tape);
+        let block = decoder.read_block().unwrap();
+        assert_eq!(vec![0x01, 0x02, 0x03], block.data);
+        assert_eq!(true, block.checksum_ok);
+    }
+
+    #[test]
+    fn read_block_ignores_turbo_pulses() {
+        let mut tape = PulseTape::new(vec![0x1a << 3, 0x28 << 3, 0x1a << 3, 0x28 << 3]);
+        let mut decoder = TapeDecoder::new(&mut tape);
+        assert_eq!(true, decoder.read_block().is_none());
+    }
+}
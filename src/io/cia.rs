@@ -278,8 +278,8 @@ impl Chip for Cia {
 
     // I/O
 
-    fn read(&mut self, reg: u8) -> u8 {
-        let value = match Reg::from(reg) {
+    fn peek(&self, reg: u8) -> u8 {
+        match Reg::from(reg) {
             Reg::PRA => match self.mode {
                 Mode::Cia1 => self.read_cia1_port_a(),
                 Mode::Cia2 => self.read_cia2_port_a(),
@@ -294,10 +294,7 @@ impl Chip for Cia {
             Reg::TAHI => self.timer_a.get_counter_hi(),
             Reg::TBLO => self.timer_b.get_counter_lo(),
             Reg::TBHI => self.timer_b.get_counter_hi(),
-            Reg::TODTS => {
-                self.tod_clock.set_enabled(true);
-                to_bcd(self.tod_clock.get_tenth())
-            }
+            Reg::TODTS => to_bcd(self.tod_clock.get_tenth()),
             Reg::TODSEC => to_bcd(self.tod_clock.get_seconds()),
             Reg::TODMIN => to_bcd(self.tod_clock.get_minutes()),
             Reg::TODHR => {
@@ -306,6 +303,20 @@ impl Chip for Cia {
                 result
             }
             Reg::SDR => 0,
+            Reg::ICR => self.irq_control.get_data(),
+            Reg::CRA => self.timer_a.get_config(),
+            Reg::CRB => {
+                let mut config = self.timer_b.get_config();
+                config.set_bit(7, self.tod_set_alarm);
+                config
+            }
+        }
+    }
+
+    fn read(&mut self, reg: u8) -> u8 {
+        let value = self.peek(reg);
+        match Reg::from(reg) {
+            Reg::TODTS => self.tod_clock.set_enabled(true),
             Reg::ICR => {
                 /*
                 In a multi-chip system, the IR bit can be polled to detect which chip has generated
@@ -313,21 +324,14 @@ impl Chip for Cia {
                 is cleared and the IRQ line returns high following a
                 read of the DATA register.
                 */
-                let data = self.irq_control.get_data();
                 self.irq_control.clear();
                 self.irq_delay.reset();
                 self.irq_line
                     .borrow_mut()
                     .set_low(self.mode.irq_source(), false);
-                data
-            }
-            Reg::CRA => self.timer_a.get_config(),
-            Reg::CRB => {
-                let mut config = self.timer_b.get_config();
-                config.set_bit(7, self.tod_set_alarm);
-                config
             }
-        };
+            _ => {}
+        }
         if log_enabled!(LogLevel::Trace) {
             trace!(target: "cia::reg", "Read 0x{:02x} = 0x{:02x}", reg, value);
         }
@@ -479,6 +483,15 @@ mod tests {
         cia
     }
 
+    #[test]
+    fn peek_icr_keeps_data() {
+        let mut cia = setup_cia();
+        cia.irq_control.set_event(0);
+        assert_eq!(0x01, cia.peek(Reg::ICR.addr()));
+        assert_eq!(0x01, cia.read(Reg::ICR.addr()));
+        assert_eq!(0x00, cia.peek(Reg::ICR.addr()));
+    }
+
     #[test]
     fn read_regs() {
         let mut cia = setup_cia();
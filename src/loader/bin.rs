@@ -18,11 +18,12 @@ struct BinImage {
 }
 
 impl Image for BinImage {
-    fn mount(&mut self, c64: &mut C64) {
+    fn mount(&mut self, c64: &mut C64) -> Result<(), String> {
         info!(target: "loader", "Mounting BIN image");
         c64.get_cpu_mut().write(0x0001, 0);
         c64.load(&self.data, self.offset);
         c64.get_cpu_mut().set_pc(self.offset);
+        Ok(())
     }
 
     fn unmount(&mut self, _c64: &mut C64) {}
@@ -36,6 +37,14 @@ impl BinLoader {
     pub fn new(offset: u16) -> Self {
         Self { offset }
     }
+
+    pub fn read_data(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok(data)
+    }
 }
 
 impl Loader for BinLoader {
@@ -46,10 +55,7 @@ impl Loader for BinLoader {
 
     fn load(&self, path: &Path) -> Result<Box<Image>, io::Error> {
         info!(target: "loader", "Loading BIN {}", path.to_str().unwrap());
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-        let mut data = Vec::new();
-        reader.read_to_end(&mut data)?;
+        let data = self.read_data(path)?;
         Ok(Box::new(BinImage {
             data,
             offset: self.offset,
@@ -0,0 +1,283 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Error, ErrorKind, Read};
+use std::path::Path;
+use std::result::Result;
+
+use device::cartridge::Mode;
+use device::{Cartridge, Chip, ChipType, HwType};
+use system::{AutostartMethod, Image};
+
+use super::crt::{CrtImage, CrtLoader};
+use super::Loader;
+
+// Design:
+//   Raw cartridge dumps carry no header, so the layout is chosen by the user. Normal 8K and
+//   16K dumps are mapped at $8000, Ultimax dumps up to 8K are mapped at $E000 and 16K dumps
+//   are split between $8000 and $E000. Dumps of bank switched cartridges are split into
+//   consecutive banks, 8K ROML banks for types that start in 8K mode and 16K ROML/ROMH banks
+//   for types that start in 16K mode. Ocean dumps up to 256K run in 16K mode with the first
+//   128K as ROML banks and the rest as ROMH banks, larger dumps run in 8K mode. Types that
+//   start in Ultimax mode or disabled are not supported. The cartridge built from a dump
+//   can be saved as a CRT image.
+
+const BANK_SIZE: usize = 0x2000;
+const MAX_BANKS: usize = 256;
+const OCEAN_BANKS: usize = 64;
+const OCEAN_ROML_BANKS: usize = 16;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CartLayout {
+    Game8k,
+    Game16k,
+    Ultimax,
+    Banked(HwType),
+}
+
+impl CartLayout {
+    pub fn from(layout: &str) -> Result<CartLayout, String> {
+        match layout {
+            "8k" => Ok(CartLayout::Game8k),
+            "16k" => Ok(CartLayout::Game16k),
+            "ultimax" => Ok(CartLayout::Ultimax),
+            _ => layout
+                .parse::<u16>()
+                .ok()
+                .and_then(HwType::from)
+                .map(|hw_type| match hw_type {
+                    HwType::Normal => CartLayout::Game8k,
+                    _ => CartLayout::Banked(hw_type),
+                })
+                .ok_or_else(|| format!("invalid cartridge type {}", layout)),
+        }
+    }
+}
+
+pub struct CartBinLoader {
+    layout: CartLayout,
+}
+
+impl CartBinLoader {
+    pub fn new(layout: CartLayout) -> Self {
+        Self { layout }
+    }
+
+    pub fn build_cartridge(&self, data: &[u8], name: &str) -> io::Result<Cartridge> {
+        let (hw_type, exrom, game, chips) = match self.layout {
+            CartLayout::Game8k => {
+                self.validate_size(data, BANK_SIZE)?;
+                (HwType::Normal, false, true, vec![(0, 0x8000, data)])
+            }
+            CartLayout::Game16k => {
+                self.validate_size(data, 2 * BANK_SIZE)?;
+                (HwType::Normal, false, false, vec![(0, 0x8000, data)])
+            }
+            CartLayout::Ultimax if data.len() > BANK_SIZE => {
+                self.validate_size(data, 2 * BANK_SIZE)?;
+                let (roml, romh) = data.split_at(BANK_SIZE);
+                (
+                    HwType::Normal,
+                    true,
+                    false,
+                    vec![(0, 0x8000, roml), (0, 0xe000, romh)],
+                )
+            }
+            CartLayout::Ultimax => {
+                self.validate_size(data, BANK_SIZE)?;
+                (HwType::Normal, true, false, vec![(0, 0xe000, data)])
+            }
+            CartLayout::Banked(hw_type) => {
+                let mode = hw_type.get_start_mode(data.len());
+                let bank_size = match mode {
+                    Mode::Game8k => BANK_SIZE,
+                    Mode::Game16k => 2 * BANK_SIZE,
+                    Mode::Ultimax | Mode::Off => {
+                        return Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("unsupported cartridge type {:?} for raw dump", hw_type),
+                        ))
+                    }
+                };
+                let chips = if hw_type == HwType::OceanType1 {
+                    self.validate_size(data, OCEAN_BANKS * BANK_SIZE)?;
+                    data.chunks(BANK_SIZE)
+                        .enumerate()
+                        .map(|(bank, chunk)| {
+                            let offset = match mode {
+                                Mode::Game16k if bank >= OCEAN_ROML_BANKS => 0xa000,
+                                _ => 0x8000,
+                            };
+                            (bank as u8, offset, chunk)
+                        })
+                        .collect()
+                } else {
+                    self.validate_size(data, MAX_BANKS * bank_size)?;
+                    data.chunks(bank_size)
+                        .enumerate()
+                        .map(|(bank, chunk)| (bank as u8, 0x8000, chunk))
+                        .collect()
+                };
+                (hw_type, mode.exrom(), mode.game(), chips)
+            }
+        };
+        let mut cartridge = Cartridge::new(0x0100, hw_type, exrom, game);
+        cartridge.name = name.to_string();
+        for (bank_number, offset, chip_data) in chips {
+            cartridge.add(Chip {
+                chip_type: ChipType::Rom,
+                bank_number,
+                offset,
+                size: chip_data.len() as u16,
+                data: chip_data.to_vec(),
+            });
+        }
+        Ok(cartridge)
+    }
+
+    // Wraps a raw dump into a CRT image.
+    pub fn convert(&self, path: &Path, crt_path: &Path) -> io::Result<()> {
+        let cartridge = self.read_cartridge(path)?;
+        CrtLoader::new().save(&cartridge, crt_path)
+    }
+
+    fn read_cartridge(&self, path: &Path) -> io::Result<Cartridge> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let name = path.file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("")
+            .to_uppercase();
+        self.build_cartridge(&data, &name)
+    }
+
+    fn validate_size(&self, data: &[u8], max_size: usize) -> io::Result<()> {
+        if !data.is_empty() && data.len() <= max_size {
+            Ok(())
+        } else {
+            Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("invalid cartridge dump size {}", data.len()),
+            ))
+        }
+    }
+}
+
+impl Loader for CartBinLoader {
+    fn autostart(&self, path: &Path) -> Result<AutostartMethod, io::Error> {
+        let image = self.load(path)?;
+        Ok(AutostartMethod::WithImage(image))
+    }
+
+    fn load(&self, path: &Path) -> Result<Box<dyn Image>, io::Error> {
+        info!(target: "loader", "Loading cartridge BIN {}", path.to_str().unwrap());
+        let cartridge = self.read_cartridge(path)?;
+        Ok(Box::new(CrtImage::new(cartridge)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::Addressable;
+    use device::ExpansionDevice;
+
+    #[test]
+    fn cart_layout_from() {
+        assert_eq!(Ok(CartLayout::Ultimax), CartLayout::from("ultimax"));
+        assert_eq!(Ok(CartLayout::Banked(HwType::OceanType1)), CartLayout::from("5"));
+        assert_eq!(true, CartLayout::from("99").is_err());
+    }
+
+    #[test]
+    fn build_16k_cartridge() {
+        let mut data = vec![0x11; 0x4000];
+        data[0x2000] = 0x22;
+        let loader = CartBinLoader::new(CartLayout::Game16k);
+        let cartridge = loader.build_cartridge(&data, "TEST").unwrap();
+        assert_eq!(false, cartridge.get_exrom());
+        assert_eq!(false, cartridge.get_game());
+        assert_eq!(0x11, cartridge.read(0x8000));
+        assert_eq!(0x22, cartridge.read(0xa000));
+    }
+
+    #[test]
+    fn build_ultimax_cartridge() {
+        let mut data = vec![0x11; 0x1000];
+        data[0x0ffc] = 0x22;
+        let loader = CartBinLoader::new(CartLayout::Ultimax);
+        let cartridge = loader.build_cartridge(&data, "TEST").unwrap();
+        assert_eq!(true, cartridge.get_exrom());
+        assert_eq!(false, cartridge.get_game());
+        assert_eq!(0x22, cartridge.read(0xfffc));
+    }
+
+    #[test]
+    fn build_banked_cartridge() {
+        let mut data = vec![0x11; 0x6000];
+        data[0x4000] = 0x22;
+        let loader = CartBinLoader::new(CartLayout::Banked(HwType::MagicDesk));
+        let mut cartridge = loader.build_cartridge(&data, "TEST").unwrap();
+        assert_eq!(3, cartridge.get_chips().len());
+        cartridge.write(0xde00, 2);
+        assert_eq!(0x22, cartridge.read(0x8000));
+    }
+
+    #[test]
+    fn build_banked_16k_cartridge() {
+        let mut data = vec![0x11; 0x8000];
+        data[0x6000] = 0x22;
+        let loader = CartBinLoader::new(CartLayout::Banked(HwType::Ross));
+        let cartridge = loader.build_cartridge(&data, "TEST").unwrap();
+        assert_eq!(false, cartridge.get_exrom());
+        assert_eq!(false, cartridge.get_game());
+        assert_eq!(4, cartridge.get_chips().len());
+        assert_eq!(0x11, cartridge.read(0xa000));
+        cartridge.read(0xde00);
+        assert_eq!(0x22, cartridge.read(0xa000));
+    }
+
+    #[test]
+    fn build_ocean_cartridge() {
+        let data = (0..32u8)
+            .flat_map(|bank| vec![bank; BANK_SIZE])
+            .collect::<Vec<u8>>();
+        let loader = CartBinLoader::new(CartLayout::Banked(HwType::OceanType1));
+        let mut cartridge = loader.build_cartridge(&data, "TEST").unwrap();
+        assert_eq!(false, cartridge.get_exrom());
+        assert_eq!(false, cartridge.get_game());
+        let layout = cartridge
+            .get_chips()
+            .iter()
+            .map(|chip| (chip.bank_number, chip.offset, chip.data[0]))
+            .collect::<Vec<(u8, u16, u8)>>();
+        assert_eq!(32, layout.len());
+        assert_eq!((15, 0x8000, 15), layout[15]);
+        assert_eq!((16, 0xa000, 16), layout[16]);
+        assert_eq!((31, 0xa000, 31), layout[31]);
+        cartridge.write(0xde00, 17);
+        assert_eq!(17, cartridge.read(0xa000));
+        let data = vec![0x11; 0x80000];
+        let cartridge = loader.build_cartridge(&data, "TEST").unwrap();
+        assert_eq!(false, cartridge.get_exrom());
+        assert_eq!(true, cartridge.get_game());
+        assert_eq!(64, cartridge.get_chips().len());
+    }
+
+    #[test]
+    fn build_banked_ultimax_cartridge() {
+        let loader = CartBinLoader::new(CartLayout::Banked(HwType::EasyFlash));
+        assert_eq!(true, loader.build_cartridge(&[0; 0x4000], "TEST").is_err());
+    }
+
+    #[test]
+    fn build_oversized_cartridge() {
+        let loader = CartBinLoader::new(CartLayout::Game8k);
+        assert_eq!(true, loader.build_cartridge(&[0; 0x4000], "TEST").is_err());
+    }
+}
@@ -4,13 +4,13 @@
 
 use std::fs::File;
 use std::io;
-use std::io::{BufRead, BufReader, Error, ErrorKind, Read};
-use std::path::Path;
+use std::io::{BufRead, BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
 use std::result::Result;
 use std::str;
 
-use byteorder::{BigEndian, ReadBytesExt};
-use device::{Cartridge, Chip, ChipType, HwType};
+use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use device::{Cartridge, CartridgeStore, Chip, ChipType, HwType};
 use system::autostart;
 use system::{C64, Image};
 
@@ -20,6 +20,8 @@ use super::Loader;
 
 static HEADER_SIG: &'static str = "C64 CARTRIDGE   ";
 static CHIP_SIG: &'static str = "CHIP";
+const HEADER_SIZE: u32 = 0x40;
+const CHIP_HEADER_SIZE: u32 = 0x10;
 
 struct Header {
     signature: [u8; 16],
@@ -42,37 +44,77 @@ struct ChipHeader {
     image_size: u16,
 }
 
-struct CrtImage {
+pub struct CrtImage {
     cartridge: Option<Cartridge>,
 }
 
+impl CrtImage {
+    pub fn new(cartridge: Cartridge) -> Self {
+        Self {
+            cartridge: Some(cartridge),
+        }
+    }
+}
+
 impl Image for CrtImage {
-    fn mount(&mut self, c64: &mut C64) {
+    fn mount(&mut self, c64: &mut C64) -> Result<(), String> {
         info!(target: "loader", "Mounting CRT image");
-        c64.attach_cartridge(self.cartridge.take().unwrap());
+        c64.attach_cartridge(self.cartridge.take().unwrap())
     }
     fn unmount(&mut self, c64: &mut C64) {
         c64.detach_cartridge();
     }
 }
 
-pub struct CrtLoader {}
+struct CrtStore {
+    path: PathBuf,
+}
+
+impl CartridgeStore for CrtStore {
+    fn save(&mut self, cartridge: &Cartridge) -> io::Result<()> {
+        CrtLoader::new().save(cartridge, &self.path)
+    }
+}
+
+pub struct CrtLoader {
+    write_back: bool,
+}
 
 impl CrtLoader {
     pub fn new() -> Self {
-        Self {}
-    }
-
-    fn build_cartridge(&self, header: Header) -> Cartridge {
-        Cartridge {
-            version: header.version,
-            hw_type: HwType::from(header.hw_type as u8),
-            exrom: header.exrom_line != 0,
-            game: header.game_line != 0,
-            banks: Vec::new(),
-            bank_lo: 0,
-            bank_hi: 0,
-        }
+        Self { write_back: false }
+    }
+
+    // Modified flash contents are saved back to the image when the cartridge is detached.
+    pub fn with_write_back() -> Self {
+        Self { write_back: true }
+    }
+
+    pub fn save(&self, cartridge: &Cartridge, path: &Path) -> io::Result<()> {
+        info!(target: "loader", "Saving CRT {}", path.to_str().unwrap());
+        let file = File::create(path)?;
+        let mut wtr = BufWriter::new(file);
+        self.write_cartridge(&mut wtr, cartridge)?;
+        wtr.flush()
+    }
+
+    fn build_cartridge(&self, header: Header) -> io::Result<Cartridge> {
+        let hw_type = HwType::from(header.hw_type).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("unsupported cartridge type {}", header.hw_type),
+            )
+        })?;
+        let mut cartridge = Cartridge::new(
+            header.version,
+            hw_type,
+            header.exrom_line != 0,
+            header.game_line != 0,
+        );
+        cartridge.name = String::from_utf8_lossy(&header.name)
+            .trim_end_matches('\0')
+            .to_string();
+        Ok(cartridge)
     }
 
     fn build_chip(&self, header: ChipHeader, data: Vec<u8>) -> Chip {
@@ -85,6 +127,43 @@ impl CrtLoader {
         }
     }
 
+    fn read_cartridge<R: BufRead>(&self, rdr: &mut R) -> io::Result<Cartridge> {
+        let header = self.read_header(rdr)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid cartridge header"))?;
+        info!(target: "loader", "Found cartridge {}, version {}.{}, type {}",
+              str::from_utf8(&header.name).unwrap_or(""),
+              header.version >> 8,
+              header.version & 0xff,
+              header.hw_type);
+        self.validate_header(&header)?;
+        rdr.consume((header.header_length - 0x40) as usize);
+        let mut cartridge = self.build_cartridge(header)?;
+        loop {
+            let chip_header_opt = self.read_chip_header(rdr)
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid cartridge chip header"))?;
+            match chip_header_opt {
+                Some(chip_header) => {
+                    info!(target: "loader", "Found chip {}, offset 0x{:x}, size {}",
+                          chip_header.bank_number, chip_header.load_address, chip_header.length - 0x10);
+                    self.validate_chip_header(&chip_header)?;
+                    let chip_data = self.read_data(rdr, (chip_header.length - 0x10) as usize)
+                        .map_err(|_| {
+                            Error::new(
+                                ErrorKind::InvalidData,
+                                format!("invalid cartridge chip {} data", chip_header.bank_number),
+                            )
+                        })?;
+                    let chip = self.build_chip(chip_header, chip_data);
+                    cartridge.add(chip);
+                }
+                None => {
+                    break;
+                }
+            }
+        }
+        Ok(cartridge)
+    }
+
     fn read_chip_header(&self, rdr: &mut Read) -> io::Result<Option<ChipHeader>> {
         let mut signature = [0u8; 4];
         match rdr.read(&mut signature)? {
@@ -139,6 +218,31 @@ impl CrtLoader {
         Ok(header)
     }
 
+    fn write_cartridge(&self, wtr: &mut dyn Write, cartridge: &Cartridge) -> io::Result<()> {
+        let mut name = [0u8; 32];
+        for (dst, src) in name.iter_mut().zip(cartridge.name.bytes()) {
+            *dst = src;
+        }
+        wtr.write_all(HEADER_SIG.as_bytes())?;
+        wtr.write_u32::<BigEndian>(HEADER_SIZE)?;
+        wtr.write_u16::<BigEndian>(cartridge.version)?;
+        wtr.write_u16::<BigEndian>(cartridge.hw_type.value())?;
+        wtr.write_u8(cartridge.exrom as u8)?;
+        wtr.write_u8(cartridge.game as u8)?;
+        wtr.write_all(&[0u8; 6])?;
+        wtr.write_all(&name)?;
+        for chip in cartridge.get_chips().iter() {
+            wtr.write_all(CHIP_SIG.as_bytes())?;
+            wtr.write_u32::<BigEndian>(CHIP_HEADER_SIZE + chip.data.len() as u32)?;
+            wtr.write_u16::<BigEndian>(chip.chip_type.value())?;
+            wtr.write_u16::<BigEndian>(chip.bank_number as u16)?;
+            wtr.write_u16::<BigEndian>(chip.offset)?;
+            wtr.write_u16::<BigEndian>(chip.data.len() as u16)?;
+            wtr.write_all(&chip.data)?;
+        }
+        Ok(())
+    }
+
     fn validate_chip_header(&self, header: &ChipHeader) -> io::Result<()> {
         let sig = str::from_utf8(&header.signature)
             .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid chip signature"))?;
@@ -173,41 +277,42 @@ impl Loader for CrtLoader {
         info!(target: "loader", "Loading CRT {}", path.to_str().unwrap());
         let file = File::open(path)?;
         let mut rdr = BufReader::new(file);
-        let header = self.read_header(&mut rdr)
-            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid cartridge header"))?;
-        info!(target: "loader", "Found cartridge {}, version {}.{}, type {}",
-              str::from_utf8(&header.name).unwrap_or(""),
-              header.version >> 8,
-              header.version & 0xff,
-              header.hw_type);
-        self.validate_header(&header)?;
-        rdr.consume((header.header_length - 0x40) as usize);
-        let mut cartridge = self.build_cartridge(header);
-        loop {
-            let chip_header_opt = self.read_chip_header(&mut rdr)
-                .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid cartridge chip header"))?;
-            match chip_header_opt {
-                Some(chip_header) => {
-                    info!(target: "loader", "Found chip {}, offset 0x{:x}, size {}",
-                          chip_header.bank_number, chip_header.load_address, chip_header.length - 0x10);
-                    self.validate_chip_header(&chip_header)?;
-                    let chip_data = self.read_data(&mut rdr, (chip_header.length - 0x10) as usize)
-                        .map_err(|_| {
-                            Error::new(
-                                ErrorKind::InvalidData,
-                                format!("invalid cartridge chip {} data", chip_header.bank_number),
-                            )
-                        })?;
-                    let chip = self.build_chip(chip_header, chip_data);
-                    cartridge.add(chip);
-                }
-                None => {
-                    break;
-                }
-            }
+        let mut cartridge = self.read_cartridge(&mut rdr)?;
+        if self.write_back {
+            cartridge.set_store(Box::new(CrtStore {
+                path: path.to_path_buf(),
+            }));
         }
-        Ok(Box::new(CrtImage {
-            cartridge: Some(cartridge),
-        }))
+        Ok(Box::new(CrtImage::new(cartridge)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::Addressable;
+
+    #[test]
+    fn write_and_read_cartridge() {
+        let mut cartridge = Cartridge::new(0x0100, HwType::EasyFlash, true, false);
+        cartridge.name = String::from("TEST");
+        cartridge.add(Chip {
+            chip_type: ChipType::FlashRom,
+            bank_number: 1,
+            offset: 0xa000,
+            size: 0x2000,
+            data: vec![0x42; 0x2000],
+        });
+        let loader = CrtLoader::new();
+        let mut data = Vec::new();
+        loader.write_cartridge(&mut data, &cartridge).unwrap();
+        assert_eq!(0x40 + 0x10 + 0x2000, data.len());
+        let mut restored = loader.read_cartridge(&mut &data[..]).unwrap();
+        assert_eq!(HwType::EasyFlash, restored.hw_type);
+        assert_eq!("TEST", restored.name);
+        assert_eq!(true, restored.exrom);
+        assert_eq!(false, restored.game);
+        restored.write(0xde00, 1);
+        assert_eq!(0x42, restored.read(0xe000));
     }
 }
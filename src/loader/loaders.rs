@@ -7,6 +7,7 @@ use std::path::Path;
 use loader::crt;
 use loader::p00;
 use loader::prg;
+use loader::t64;
 use loader::tap;
 use loader::Loader;
 
@@ -20,6 +21,8 @@ impl Loaders {
             Some("p00") => Box::new(p00::P00Loader::new()),
             Some("P00") => Box::new(p00::P00Loader::new()),
             Some("prg") => Box::new(prg::PrgLoader::new()),
+            Some("t64") => Box::new(t64::T64Loader::new()),
+            Some("T64") => Box::new(t64::T64Loader::new()),
             Some("tap") => Box::new(tap::TapLoader::new()),
             _ => panic!("invalid loader {}", ext.unwrap_or("")),
         }
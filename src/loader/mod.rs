@@ -3,11 +3,15 @@
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
 mod bin;
+mod cart_bin;
 mod crt;
 //mod hex;
 mod loaders;
 mod p00;
 mod prg;
+mod ram_expansion;
+mod reu;
+mod t64;
 mod tap;
 
 use std::io;
@@ -16,7 +20,15 @@ use std::path::Path;
 use system::{AutostartMethod, Image};
 
 pub use self::bin::BinLoader;
+pub use self::cart_bin::{CartBinLoader, CartLayout};
+pub use self::crt::CrtLoader;
 pub use self::loaders::Loaders;
+pub use self::p00::P00Loader;
+pub use self::prg::PrgLoader;
+pub use self::ram_expansion::{RamExpansionLoader, RamExpansionType};
+pub use self::reu::ReuLoader;
+pub use self::t64::{T64Entry, T64Loader};
+pub use self::tap::TapLoader;
 
 pub trait Loader {
     fn autostart(&self, path: &Path) -> Result<AutostartMethod, io::Error>;
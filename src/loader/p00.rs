@@ -4,7 +4,7 @@
 
 use std::fs::File;
 use std::io;
-use std::io::{BufReader, Error, ErrorKind, Read};
+use std::io::{BufReader, Error, ErrorKind, Read, Write};
 use std::path::Path;
 use std::result::Result;
 use std::str;
@@ -21,7 +21,6 @@ struct Header {
     signature: [u8; 7],
     #[allow(dead_code)]
     reserved_1: u8,
-    #[allow(dead_code)]
     filename: [u8; 16],
     #[allow(dead_code)]
     reserved_2: u8,
@@ -35,9 +34,10 @@ struct P00Image {
 }
 
 impl Image for P00Image {
-    fn mount(&mut self, c64: &mut C64) {
+    fn mount(&mut self, c64: &mut C64) -> Result<(), String> {
         info!(target: "loader", "Mounting P00 image");
         c64.load(&self.data, self.offset);
+        Ok(())
     }
 
     fn unmount(&mut self, _c64: &mut C64) {}
@@ -46,10 +46,50 @@ impl Image for P00Image {
 pub struct P00Loader {}
 
 impl P00Loader {
+    pub const HEADER_SIZE: usize = 26;
+
     pub fn new() -> Self {
         Self {}
     }
 
+    pub fn read_filename(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let header = self.read_header(&mut reader)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid P00 header"))?;
+        self.validate_header(&header)?;
+        let filename = header
+            .filename
+            .iter()
+            .take_while(|&&c| c != 0)
+            .cloned()
+            .collect();
+        Ok(filename)
+    }
+
+    pub fn read_program(&self, path: &Path) -> io::Result<(u16, Vec<u8>)> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let header = self.read_header(&mut reader)
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid P00 header"))?;
+        self.validate_header(&header)?;
+        let offset = reader.read_u16::<LittleEndian>()?;
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        Ok((offset, data))
+    }
+
+    pub fn write_header(&self, wtr: &mut dyn Write, filename: &[u8]) -> io::Result<()> {
+        let mut name = [0u8; 16];
+        for (i, c) in filename.iter().take(16).enumerate() {
+            name[i] = *c;
+        }
+        wtr.write_all(HEADER_SIG.as_bytes())?;
+        wtr.write_all(&[0])?;
+        wtr.write_all(&name)?;
+        wtr.write_all(&[0, 0])
+    }
+
     fn read_header(&self, rdr: &mut Read) -> io::Result<Header> {
         let mut signature = [0u8; 7];
         let mut filename = [0u8; 16];
@@ -89,14 +129,7 @@ impl Loader for P00Loader {
 
     fn load(&self, path: &Path) -> Result<Box<Image>, io::Error> {
         info!(target: "loader", "Loading P00 {}", path.to_str().unwrap());
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-        let header = self.read_header(&mut reader)
-            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid P00 header"))?;
-        self.validate_header(&header)?;
-        let offset = reader.read_u16::<LittleEndian>()?;
-        let mut data = Vec::new();
-        reader.read_to_end(&mut data)?;
+        let (offset, data) = self.read_program(path)?;
         info!(target: "loader", "Program offset 0x{:x}, size {}", offset, data.len());
         Ok(Box::new(P00Image { data, offset }))
     }
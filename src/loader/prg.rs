@@ -4,11 +4,11 @@
 
 use std::fs::File;
 use std::io;
-use std::io::{BufReader, Read};
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::path::Path;
 use std::result::Result;
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use system::autostart;
 use system::{Autostart, AutostartMethod, C64, Image};
 
@@ -20,9 +20,10 @@ struct PrgImage {
 }
 
 impl Image for PrgImage {
-    fn mount(&mut self, c64: &mut C64) {
+    fn mount(&mut self, c64: &mut C64) -> Result<(), String> {
         info!(target: "loader", "Mounting PRG image");
         c64.load(&self.data, self.offset);
+        Ok(())
     }
 
     fn unmount(&mut self, _c64: &mut C64) {}
@@ -34,6 +35,32 @@ impl PrgLoader {
     pub fn new() -> Self {
         Self {}
     }
+
+    pub fn read_program(&self, path: &Path) -> io::Result<(u16, Vec<u8>)> {
+        let file = File::open(path)?;
+        let mut rdr = BufReader::new(file);
+        self.read_image(&mut rdr)
+    }
+
+    pub fn save(&self, path: &Path, offset: u16, data: &[u8]) -> io::Result<()> {
+        info!(target: "loader", "Saving PRG {}", path.to_str().unwrap());
+        let file = File::create(path)?;
+        let mut wtr = BufWriter::new(file);
+        self.write_image(&mut wtr, offset, data)?;
+        wtr.flush()
+    }
+
+    fn read_image(&self, rdr: &mut dyn Read) -> io::Result<(u16, Vec<u8>)> {
+        let offset = rdr.read_u16::<LittleEndian>()?;
+        let mut data = Vec::new();
+        rdr.read_to_end(&mut data)?;
+        Ok((offset, data))
+    }
+
+    fn write_image(&self, wtr: &mut dyn Write, offset: u16, data: &[u8]) -> io::Result<()> {
+        wtr.write_u16::<LittleEndian>(offset)?;
+        wtr.write_all(data)
+    }
 }
 
 impl Loader for PrgLoader {
@@ -45,12 +72,26 @@ impl Loader for PrgLoader {
 
     fn load(&self, path: &Path) -> Result<Box<Image>, io::Error> {
         info!(target: "loader", "Loading PRG {}", path.to_str().unwrap());
-        let file = File::open(path)?;
-        let mut reader = BufReader::new(file);
-        let offset = reader.read_u16::<LittleEndian>()?;
-        let mut data = Vec::new();
-        reader.read_to_end(&mut data)?;
+        let (offset, data) = self.read_program(path)?;
         info!(target: "loader", "Program offset 0x{:x}, size {}", offset, data.len());
         Ok(Box::new(PrgImage { data, offset }))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn write_and_read_program() {
+        let loader = PrgLoader::new();
+        let mut data = Vec::new();
+        loader
+            .write_image(&mut data, 0x0801, &[0x0b, 0x08, 0x0a])
+            .unwrap();
+        assert_eq!(vec![0x01, 0x08, 0x0b, 0x08, 0x0a], data);
+        let (offset, program) = loader.read_image(&mut &data[..]).unwrap();
+        assert_eq!(0x0801, offset);
+        assert_eq!(vec![0x0b, 0x08, 0x0a], program);
+    }
+}
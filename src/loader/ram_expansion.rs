@@ -0,0 +1,162 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::result::Result;
+
+use device::{ExpansionDevice, GeoRam, GeoRamModel, RamStore, Reu};
+use system::{C64, Image};
+
+// Design:
+//   RAM expansion images are raw memory dumps. A missing image is created when the expansion
+//   is first saved, and the contents are written back when the expansion is detached.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RamExpansionType {
+    Reu,
+    GeoRam,
+    NeoRam,
+}
+
+impl RamExpansionType {
+    pub fn from(name: &str) -> Result<RamExpansionType, String> {
+        match name {
+            "reu" => Ok(RamExpansionType::Reu),
+            "georam" => Ok(RamExpansionType::GeoRam),
+            "neoram" => Ok(RamExpansionType::NeoRam),
+            _ => Err(format!("invalid RAM expansion {}", name)),
+        }
+    }
+
+    pub fn is_valid_capacity(&self, capacity: usize) -> bool {
+        match *self {
+            RamExpansionType::Reu => Reu::is_valid_capacity(capacity),
+            RamExpansionType::GeoRam => GeoRamModel::GeoRam.is_valid_capacity(capacity),
+            RamExpansionType::NeoRam => GeoRamModel::NeoRam.is_valid_capacity(capacity),
+        }
+    }
+}
+
+struct RamExpansionImage {
+    device: Option<Box<dyn ExpansionDevice>>,
+}
+
+impl Image for RamExpansionImage {
+    fn mount(&mut self, c64: &mut C64) -> Result<(), String> {
+        info!(target: "loader", "Mounting RAM expansion image");
+        c64.attach_ram_expansion(self.device.take().unwrap())
+    }
+
+    fn unmount(&mut self, c64: &mut C64) {
+        c64.detach_ram_expansion();
+    }
+}
+
+struct RamFileStore {
+    path: PathBuf,
+}
+
+impl RamStore for RamFileStore {
+    fn save(&mut self, data: &[u8]) -> io::Result<()> {
+        info!(target: "loader", "Saving RAM expansion {}", self.path.to_str().unwrap());
+        let file = File::create(&self.path)?;
+        let mut wtr = BufWriter::new(file);
+        wtr.write_all(data)?;
+        wtr.flush()
+    }
+}
+
+pub struct RamExpansionLoader {
+    expansion_type: RamExpansionType,
+    capacity: usize,
+}
+
+impl RamExpansionLoader {
+    pub fn new(expansion_type: RamExpansionType, capacity: usize) -> Self {
+        Self {
+            expansion_type,
+            capacity,
+        }
+    }
+
+    pub fn build_device(
+        &self,
+        data: &[u8],
+        store: Option<Box<dyn RamStore>>,
+    ) -> io::Result<Box<dyn ExpansionDevice>> {
+        if !self.expansion_type.is_valid_capacity(self.capacity) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid {:?} size {}", self.expansion_type, self.capacity),
+            ));
+        }
+        if data.len() > self.capacity {
+            warn!(target: "loader", "RAM expansion image is larger than {} bytes", self.capacity);
+        }
+        let device: Box<dyn ExpansionDevice> = match self.expansion_type {
+            RamExpansionType::Reu => {
+                let mut reu = Reu::new(self.capacity);
+                reu.load(data);
+                if let Some(store) = store {
+                    reu.set_store(store);
+                }
+                Box::new(reu)
+            }
+            RamExpansionType::GeoRam | RamExpansionType::NeoRam => {
+                let model = if self.expansion_type == RamExpansionType::GeoRam {
+                    GeoRamModel::GeoRam
+                } else {
+                    GeoRamModel::NeoRam
+                };
+                let mut georam = GeoRam::new(model, self.capacity);
+                georam.load(data);
+                if let Some(store) = store {
+                    georam.set_store(store);
+                }
+                Box::new(georam)
+            }
+        };
+        Ok(device)
+    }
+
+    pub fn load(&self, path: &Path) -> Result<Box<dyn Image>, io::Error> {
+        info!(target: "loader", "Loading RAM expansion {}", path.to_str().unwrap());
+        let mut data = Vec::new();
+        if path.exists() {
+            let file = File::open(path)?;
+            let mut rdr = BufReader::new(file);
+            rdr.read_to_end(&mut data)?;
+        }
+        let store = RamFileStore {
+            path: path.to_path_buf(),
+        };
+        let device = self.build_device(&data, Some(Box::new(store)))?;
+        Ok(Box::new(RamExpansionImage {
+            device: Some(device),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expansion_type_from_name() {
+        assert_eq!(Ok(RamExpansionType::NeoRam), RamExpansionType::from("neoram"));
+        assert_eq!(true, RamExpansionType::from("ramcart").is_err());
+    }
+
+    #[test]
+    fn build_device_loads_data() {
+        let loader = RamExpansionLoader::new(RamExpansionType::GeoRam, 0x10000);
+        let device = loader.build_device(&[0x42], None).unwrap();
+        assert_eq!(0x42, device.read(0xde00));
+        let loader = RamExpansionLoader::new(RamExpansionType::NeoRam, 0x10000);
+        assert_eq!(true, loader.build_device(&[], None).is_err());
+    }
+}
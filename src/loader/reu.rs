@@ -0,0 +1,28 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::io;
+use std::path::Path;
+use std::result::Result;
+
+use system::Image;
+
+use super::ram_expansion::{RamExpansionLoader, RamExpansionType};
+
+// Design:
+//   REU images are raw memory dumps and are handled like the other RAM expansion images.
+
+pub struct ReuLoader {
+    capacity: usize,
+}
+
+impl ReuLoader {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity }
+    }
+
+    pub fn load(&self, path: &Path) -> Result<Box<dyn Image>, io::Error> {
+        RamExpansionLoader::new(RamExpansionType::Reu, self.capacity).load(path)
+    }
+}
@@ -0,0 +1,269 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufReader, Error, ErrorKind, Read};
+use std::path::Path;
+use std::result::Result;
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use system::autostart;
+use system::{Autostart, AutostartMethod, C64, Image};
+
+use super::Loader;
+
+// SPEC: http://ist.uwaterloo.ca/~schepers/formats/T64.TXT
+
+static HEADER_SIG: &'static str = "C64";
+const HEADER_SIZE: usize = 64;
+const ENTRY_SIZE: usize = 32;
+// End address written by a popular but broken conversion tool
+const BROKEN_END_ADDRESS: u16 = 0xc3c6;
+
+struct Header {
+    signature: [u8; 32],
+    version: u16,
+    max_entries: u16,
+    used_entries: u16,
+    #[allow(dead_code)]
+    reserved: u16,
+    name: [u8; 24],
+}
+
+pub struct T64Entry {
+    pub entry_type: u8,
+    pub file_type: u8,
+    pub start_address: u16,
+    pub end_address: u16,
+    pub offset: u32,
+    pub name: Vec<u8>,
+}
+
+impl T64Entry {
+    pub fn size(&self) -> usize {
+        self.end_address.wrapping_sub(self.start_address) as usize
+    }
+}
+
+struct T64Image {
+    data: Vec<u8>,
+    offset: u16,
+}
+
+impl Image for T64Image {
+    fn mount(&mut self, c64: &mut C64) -> Result<(), String> {
+        info!(target: "loader", "Mounting T64 image");
+        c64.load(&self.data, self.offset);
+        Ok(())
+    }
+
+    fn unmount(&mut self, _c64: &mut C64) {}
+}
+
+pub struct T64Loader {
+    entry: usize,
+}
+
+impl T64Loader {
+    pub fn new() -> Self {
+        Self { entry: 0 }
+    }
+
+    pub fn with_entry(entry: usize) -> Self {
+        Self { entry }
+    }
+
+    pub fn list(&self, path: &Path) -> io::Result<Vec<T64Entry>> {
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        self.read_entries(&data)
+    }
+
+    fn read_entries(&self, data: &[u8]) -> io::Result<Vec<T64Entry>> {
+        let header = self
+            .read_header(&mut &data[..])
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "invalid T64 header"))?;
+        self.validate_header(&header)?;
+        let name = String::from_utf8_lossy(&header.name);
+        info!(target: "loader", "Found tape {}, version 0x{:x}, entries {}", name.trim(), header.version, header.used_entries);
+        // Some tools write 0 used entries, so we scan all directory slots.
+        let max_entries = (header.max_entries.max(1) as usize)
+            .min(data.len().saturating_sub(HEADER_SIZE) / ENTRY_SIZE);
+        let mut entries = Vec::new();
+        for i in 0..max_entries {
+            let offset = HEADER_SIZE + i * ENTRY_SIZE;
+            let entry = self.read_entry(&mut &data[offset..offset + ENTRY_SIZE])?;
+            if entry.entry_type != 0 && (entry.offset as usize) < data.len() {
+                entries.push(entry);
+            }
+        }
+        self.fix_end_addresses(&mut entries, data.len());
+        Ok(entries)
+    }
+
+    // Many T64 files have a wrong end address, so we derive the size from the offset of the
+    // next entry in the container.
+    fn fix_end_addresses(&self, entries: &mut [T64Entry], container_size: usize) {
+        let mut offsets = entries
+            .iter()
+            .map(|entry| entry.offset as usize)
+            .collect::<Vec<usize>>();
+        offsets.push(container_size);
+        offsets.sort();
+        for entry in entries.iter_mut() {
+            let offset = entry.offset as usize;
+            let next_offset = offsets
+                .iter()
+                .find(|&&next| next > offset)
+                .cloned()
+                .unwrap_or(container_size);
+            let size = next_offset - offset;
+            let broken = entry.end_address == BROKEN_END_ADDRESS || entry.size() > size;
+            if broken && entry.size() != size {
+                let end_address = entry.start_address.wrapping_add(size as u16);
+                warn!(target: "loader", "Fixing end address 0x{:x}, size {}", entry.end_address, size);
+                entry.end_address = end_address;
+            }
+        }
+    }
+
+    fn read_entry(&self, rdr: &mut dyn Read) -> io::Result<T64Entry> {
+        let entry_type = rdr.read_u8()?;
+        let file_type = rdr.read_u8()?;
+        let start_address = rdr.read_u16::<LittleEndian>()?;
+        let end_address = rdr.read_u16::<LittleEndian>()?;
+        rdr.read_u16::<LittleEndian>()?;
+        let offset = rdr.read_u32::<LittleEndian>()?;
+        rdr.read_u32::<LittleEndian>()?;
+        let mut name = [0u8; 16];
+        rdr.read_exact(&mut name)?;
+        let len = name
+            .iter()
+            .rposition(|&c| c != 0x20 && c != 0)
+            .map(|pos| pos + 1)
+            .unwrap_or(0);
+        Ok(T64Entry {
+            entry_type,
+            file_type,
+            start_address,
+            end_address,
+            offset,
+            name: name[..len].to_vec(),
+        })
+    }
+
+    fn read_header(&self, rdr: &mut dyn Read) -> io::Result<Header> {
+        let mut signature = [0u8; 32];
+        let mut name = [0u8; 24];
+        let header = Header {
+            signature: {
+                rdr.read_exact(&mut signature)?;
+                signature
+            },
+            version: rdr.read_u16::<LittleEndian>()?,
+            max_entries: rdr.read_u16::<LittleEndian>()?,
+            used_entries: rdr.read_u16::<LittleEndian>()?,
+            reserved: rdr.read_u16::<LittleEndian>()?,
+            name: {
+                rdr.read_exact(&mut name)?;
+                name
+            },
+        };
+        Ok(header)
+    }
+
+    fn validate_header(&self, header: &Header) -> io::Result<()> {
+        if header.signature.starts_with(HEADER_SIG.as_bytes()) {
+            Ok(())
+        } else {
+            Err(Error::new(ErrorKind::InvalidData, "invalid T64 signature"))
+        }
+    }
+}
+
+impl Loader for T64Loader {
+    fn autostart(&self, path: &Path) -> Result<AutostartMethod, io::Error> {
+        let image = self.load(path)?;
+        let autostart = Autostart::new(autostart::Mode::Run, image);
+        Ok(AutostartMethod::WithAutostart(Some(autostart)))
+    }
+
+    fn load(&self, path: &Path) -> Result<Box<dyn Image>, io::Error> {
+        info!(target: "loader", "Loading T64 {}", path.to_str().unwrap());
+        let file = File::open(path)?;
+        let mut reader = BufReader::new(file);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let entries = self.read_entries(&data)?;
+        let entry = entries
+            .get(self.entry)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "invalid T64 entry"))?;
+        let start = entry.offset as usize;
+        let end = (start + entry.size()).min(data.len());
+        info!(target: "loader", "Program offset 0x{:x}, size {}", entry.start_address, end - start);
+        Ok(Box::new(T64Image {
+            data: data[start..end].to_vec(),
+            offset: entry.start_address,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn build_t64(entries: &[(u16, u16, &[u8], &[u8])]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut signature = b"C64 tape image file".to_vec();
+        signature.resize(32, 0);
+        data.extend_from_slice(&signature);
+        data.extend_from_slice(&[0x00, 0x01, entries.len() as u8, 0x00, 0x00, 0x00, 0, 0]);
+        data.extend_from_slice(&[0x20; 24]);
+        let mut offset = HEADER_SIZE + entries.len() * ENTRY_SIZE;
+        for &(start, end, name, program) in entries.iter() {
+            data.extend_from_slice(&[0x01, 0x82]);
+            data.extend_from_slice(&[start as u8, (start >> 8) as u8]);
+            data.extend_from_slice(&[end as u8, (end >> 8) as u8, 0, 0]);
+            data.extend_from_slice(&[offset as u8, (offset >> 8) as u8, 0, 0, 0, 0, 0, 0]);
+            let mut padded = name.to_vec();
+            padded.resize(16, 0x20);
+            data.extend_from_slice(&padded);
+            offset += program.len();
+        }
+        for &(_, _, _, program) in entries.iter() {
+            data.extend_from_slice(program);
+        }
+        data
+    }
+
+    #[test]
+    fn read_directory_entries() {
+        let data = build_t64(&[
+            (0x0801, 0x0804, b"FIRST", &[1, 2, 3]),
+            (0xc000, 0xc002, b"SECOND", &[4, 5]),
+        ]);
+        let entries = T64Loader::new().read_entries(&data).unwrap();
+        assert_eq!(2, entries.len());
+        assert_eq!(b"FIRST".to_vec(), entries[0].name);
+        assert_eq!(0x0801, entries[0].start_address);
+        assert_eq!(3, entries[0].size());
+        assert_eq!(b"SECOND".to_vec(), entries[1].name);
+        assert_eq!(0xc002, entries[1].end_address);
+    }
+
+    #[test]
+    fn fix_broken_end_address() {
+        let data = build_t64(&[
+            (0x0801, 0xc3c6, b"FIRST", &[1, 2, 3]),
+            (0xc000, 0xc001, b"SECOND", &[4, 5]),
+        ]);
+        let entries = T64Loader::new().read_entries(&data).unwrap();
+        assert_eq!(0x0804, entries[0].end_address);
+        // Short end address is kept
+        assert_eq!(0xc001, entries[1].end_address);
+    }
+}
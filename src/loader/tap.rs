@@ -4,12 +4,12 @@
 
 use std::fs::File;
 use std::io;
-use std::io::{BufReader, Error, ErrorKind, Read};
-use std::path::Path;
+use std::io::{BufReader, BufWriter, Error, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
 use std::result::Result;
 use std::str;
 
-use byteorder::{LittleEndian, ReadBytesExt};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use device::Tape;
 use system::autostart;
 use system::{Autostart, AutostartMethod, C64, Image};
@@ -19,6 +19,7 @@ use super::Loader;
 // SPEC: http://ist.uwaterloo.ca/~schepers/formats/TAP.TXT
 
 static HEADER_SIG: &'static str = "C64-TAPE-RAW";
+const TAP_VERSION: u8 = 1;
 
 struct Header {
     signature: [u8; 12],
@@ -33,9 +34,10 @@ struct TapImage {
 }
 
 impl Image for TapImage {
-    fn mount(&mut self, c64: &mut C64) {
+    fn mount(&mut self, c64: &mut C64) -> Result<(), String> {
         info!(target: "loader", "Mounting TAP image");
         c64.attach_tape(self.tape.take().unwrap());
+        Ok(())
     }
 
     fn unmount(&mut self, c64: &mut C64) {
@@ -50,6 +52,17 @@ impl TapLoader {
         Self {}
     }
 
+    // Creates an empty TAP v1 image that is saved to path when the tape is detached.
+    pub fn create(&self, path: &Path) -> Result<Box<dyn Image>, io::Error> {
+        info!(target: "loader", "Creating TAP {}", path.to_str().unwrap());
+        let mut tape = TapTape::new(path, TAP_VERSION, Vec::new());
+        tape.modified = true;
+        tape.flush()?;
+        Ok(Box::new(TapImage {
+            tape: Some(Box::new(tape)),
+        }))
+    }
+
     fn read_header(&self, rdr: &mut Read) -> io::Result<Header> {
         let mut signature = [0u8; 12];
         let mut reserved = [0u8; 3];
@@ -99,27 +112,87 @@ impl Loader for TapLoader {
         self.validate_header(&header)?;
         let mut data = vec![0; header.size as usize];
         rdr.read_exact(&mut data)?;
-        let tape = TapTape {
-            version: header.version,
-            data,
-            pos: 0,
-        };
+        if header.version > 1 {
+            return Err(Error::new(ErrorKind::InvalidData, "invalid tape version"));
+        }
+        let tape = TapTape::new(path, header.version, data);
         Ok(Box::new(TapImage {
             tape: Some(Box::new(tape)),
         }))
     }
 }
 
+// Design:
+//   Pulses are indexed by their data offset, so that the tape can be positioned and
+//   wound in both directions in terms of pulses.
+
 struct TapTape {
+    path: PathBuf,
     version: u8,
     data: Vec<u8>,
+    offsets: Vec<usize>,
     pos: usize,
+    modified: bool,
+}
+
+impl TapTape {
+    pub fn new(path: &Path, version: u8, data: Vec<u8>) -> Self {
+        let mut tape = Self {
+            path: path.to_path_buf(),
+            version,
+            data,
+            offsets: Vec::new(),
+            pos: 0,
+            modified: false,
+        };
+        tape.build_offsets();
+        tape
+    }
+
+    fn build_offsets(&mut self) {
+        self.offsets.clear();
+        let mut offset = 0;
+        while offset < self.data.len() {
+            let size = self.pulse_size(offset);
+            if offset + size > self.data.len() {
+                warn!(target: "loader", "Truncated pulse at offset {}", offset);
+                break;
+            }
+            self.offsets.push(offset);
+            offset += size;
+        }
+    }
+
+    fn pulse_size(&self, offset: usize) -> usize {
+        if self.data[offset] == 0 && self.version != 0 {
+            4
+        } else {
+            1
+        }
+    }
+
+    fn write_header(&self, wtr: &mut dyn Write) -> io::Result<()> {
+        wtr.write_all(HEADER_SIG.as_bytes())?;
+        wtr.write_u8(self.version)?;
+        wtr.write_all(&[0u8; 3])?;
+        wtr.write_u32::<LittleEndian>(self.data.len() as u32)?;
+        Ok(())
+    }
 }
 
 impl Tape for TapTape {
+    fn get_length(&self) -> usize {
+        self.offsets.len()
+    }
+
+    fn get_position(&self) -> usize {
+        self.pos
+    }
+
     fn read_pulse(&mut self) -> Option<u32> {
-        if self.pos < self.data.len() {
-            let value = self.data[self.pos] as u32;
+        if self.pos < self.offsets.len() {
+            let offset = self.offsets[self.pos];
+            let value = self.data[offset] as u32;
             self.pos += 1;
             if value != 0 {
                 Some(value << 3)
@@ -127,10 +200,9 @@ impl Tape for TapTape {
                 let pulse = match self.version {
                     0 => 256 << 3,
                     1 => {
-                        let byte1 = self.data[self.pos] as u32;
-                        let byte2 = self.data[self.pos + 1] as u32;
-                        let byte3 = self.data[self.pos + 2] as u32;
-                        self.pos += 3;
+                        let byte1 = self.data[offset + 1] as u32;
+                        let byte2 = self.data[offset + 2] as u32;
+                        let byte3 = self.data[offset + 3] as u32;
                         (byte3 << 16) | (byte2 << 8) | byte1
                     }
                     _ => panic!("invalid version {}", self.version),
@@ -143,11 +215,115 @@ impl Tape for TapTape {
     }
 
     fn seek(&mut self, pos: usize) -> bool {
-        if pos < self.data.len() {
+        if pos <= self.offsets.len() {
             self.pos = pos;
             true
         } else {
             false
         }
     }
+
+    fn write_pulse(&mut self, pulse: u32) {
+        // Recording replaces the rest of the tape.
+        let offset = if self.pos < self.offsets.len() {
+            self.offsets[self.pos]
+        } else {
+            self.data.len()
+        };
+        self.data.truncate(offset);
+        self.offsets.truncate(self.pos);
+        self.offsets.push(offset);
+        let value = pulse >> 3;
+        if value > 0 && value < 256 {
+            self.data.push(value as u8);
+        } else {
+            self.data.push(0);
+            if self.version != 0 {
+                let pulse = pulse.min(0x00ff_ffff);
+                self.data.push((pulse & 0xff) as u8);
+                self.data.push(((pulse >> 8) & 0xff) as u8);
+                self.data.push(((pulse >> 16) & 0xff) as u8);
+            }
+        }
+        self.pos = self.offsets.len();
+        self.modified = true;
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.modified {
+            info!(target: "loader", "Saving TAP {}", self.path.to_str().unwrap());
+            let file = File::create(&self.path)?;
+            let mut wtr = BufWriter::new(file);
+            self.write_header(&mut wtr)?;
+            wtr.write_all(&self.data)?;
+            wtr.flush()?;
+            self.modified = false;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+
+    fn new_tape(name: &str, data: Vec<u8>) -> TapTape {
+        let path = env::temp_dir().join(format!("zinc64-tap-{}.tap", name));
+        TapTape::new(&path, TAP_VERSION, data)
+    }
+
+    #[test]
+    fn write_pulse_short_and_long() {
+        let mut tape = new_tape("write", Vec::new());
+        tape.write_pulse(0x30 << 3);
+        tape.write_pulse(0x012345);
+        assert_eq!(vec![0x30, 0x00, 0x45, 0x23, 0x01], tape.data);
+        tape.seek(0);
+        assert_eq!(Some(0x30 << 3), tape.read_pulse());
+        assert_eq!(Some(0x012345), tape.read_pulse());
+        assert_eq!(None, tape.read_pulse());
+    }
+
+    #[test]
+    fn write_pulse_truncates_tape() {
+        let mut tape = new_tape("truncate", vec![0x20, 0x00, 0x00, 0x10, 0x00, 0x40]);
+        tape.seek(1);
+        tape.write_pulse(0x50 << 3);
+        assert_eq!(vec![0x20, 0x50], tape.data);
+        assert_eq!(2, tape.get_length());
+    }
+
+    #[test]
+    fn seek_and_read_pulses() {
+        let mut tape = new_tape(
+            "seek",
+            vec![0x20, 0x00, 0x00, 0x10, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00],
+        );
+        assert_eq!(4, tape.get_length());
+        assert_eq!(true, tape.seek(3));
+        assert_eq!(Some(0), tape.read_pulse());
+        assert_eq!(4, tape.get_position());
+        assert_eq!(None, tape.read_pulse());
+        assert_eq!(true, tape.seek(1));
+        assert_eq!(Some(0x1000), tape.read_pulse());
+        assert_eq!(Some(0x40 << 3), tape.read_pulse());
+        assert_eq!(false, tape.seek(5));
+    }
+
+    #[test]
+    fn flush_writes_tap_image() {
+        let mut tape = new_tape("flush", Vec::new());
+        tape.write_pulse(0x30 << 3);
+        tape.write_pulse(0x2f << 3);
+        tape.flush().unwrap();
+        let loader = TapLoader::new();
+        let data = fs::read(&tape.path).unwrap();
+        let header = loader.read_header(&mut &data[..]).unwrap();
+        assert_eq!(true, loader.validate_header(&header).is_ok());
+        assert_eq!(1, header.version);
+        assert_eq!(2, header.size);
+        assert_eq!(&[0x30, 0x2f], &data[20..]);
+    }
 }
@@ -2,10 +2,10 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
-use core::{Addressable, Mmu, Ram, Rom};
+use core::{Addressable, MemoryAccess, MemoryBank, MemoryWatch, Mmu, Ram, Rom};
 use log::LogLevel;
 
 use super::{Bank, Configuration, MemoryMap};
@@ -16,11 +16,18 @@ use super::{Bank, Configuration, MemoryMap};
 //   We define Addressable trait to represent a bank of memory and use memory configuration
 //   based on zones that can be mapped to different banks. CPU uses IoPort @ 0x0001 to reconfigure
 //   memory layout.
+//   CPU accesses are reported to the memory watch so watchpoints can be checked once the
+//   instruction completes. Expansion port DMA goes through the same path and is reported too.
+//   Journaled writes carry the RAM value they replace since only RAM is restored when
+//   execution is reversed.
+//   Peeks serve the debugger and neither report to the memory watch nor touch chip state.
+//   The VIC view follows the bank selected through CIA 2 and mirrors VicMemory.
 
 pub struct Memory {
     // Configuration
     map: MemoryMap,
     configuration: Configuration,
+    ultimax: bool,
     // Addressable
     basic: Rc<RefCell<Rom>>,
     charset: Rc<RefCell<Rom>>,
@@ -28,6 +35,9 @@ pub struct Memory {
     io: Box<dyn Addressable>,
     kernal: Rc<RefCell<Rom>>,
     ram: Rc<RefCell<Ram>>,
+    vic_base_address: Rc<Cell<u16>>,
+    // Debugging
+    watch: Rc<MemoryWatch>,
 }
 
 #[allow(dead_code)]
@@ -52,33 +62,27 @@ impl Memory {
         rom_basic: Rc<RefCell<Rom>>,
         rom_charset: Rc<RefCell<Rom>>,
         rom_kernal: Rc<RefCell<Rom>>,
+        vic_base_address: Rc<Cell<u16>>,
+        watch: Rc<MemoryWatch>,
     ) -> Self {
         let map = MemoryMap::new();
         let configuration = map.get(1);
         Memory {
             map,
             configuration,
+            ultimax: false,
             basic: rom_basic,
             charset: rom_charset,
             expansion_port,
             io,
             kernal: rom_kernal,
             ram,
+            vic_base_address,
+            watch,
         }
     }
-}
-
-impl Mmu for Memory {
-    fn switch_banks(&mut self, mode: u8) {
-        if log_enabled!(LogLevel::Trace) {
-            trace!(target: "mem::banks", "Switching to {}", mode);
-        }
-        self.configuration = self.map.get(mode);
-    }
-
-    // I/O
 
-    fn read(&self, address: u16) -> u8 {
+    fn fetch(&self, address: u16, peek: bool) -> u8 {
         let zone = address >> 12;
         match self.configuration.get(zone as u8) {
             Bank::Ram => self.ram.borrow().read(address),
@@ -87,25 +91,98 @@ impl Mmu for Memory {
                 .borrow()
                 .read(address - BaseAddr::Charset.addr()),
             Bank::Kernal => self.kernal.borrow().read(address),
+            Bank::RomL | Bank::RomH if peek => self.expansion_port.borrow().peek(address),
             Bank::RomL => self.expansion_port.borrow().read(address),
             Bank::RomH => self.expansion_port.borrow().read(address),
+            Bank::Io if peek => self.io.peek(address),
             Bank::Io => self.io.read(address),
             Bank::Disabled => 0,
         }
     }
 
+    fn fetch_vic(&self, address: u16) -> u8 {
+        let full_address = self.vic_base_address.get() | (address & 0x3fff);
+        if self.ultimax {
+            match full_address & 0x3000 {
+                0x3000 => self.expansion_port
+                    .borrow()
+                    .peek(0xf000 | (full_address & 0x0fff)),
+                _ => self.ram.borrow().read(full_address),
+            }
+        } else {
+            match full_address >> 12 {
+                0x01 => self.charset.borrow().read(full_address - 0x1000),
+                0x09 => self.charset.borrow().read(full_address - 0x9000),
+                _ => self.ram.borrow().read(full_address),
+            }
+        }
+    }
+}
+
+impl Mmu for Memory {
+    fn switch_banks(&mut self, mode: u8) {
+        if log_enabled!(LogLevel::Trace) {
+            trace!(target: "mem::banks", "Switching to {}", mode);
+        }
+        self.configuration = self.map.get(mode);
+        self.ultimax = mode & 0x18 == 0x10;
+    }
+
+    // I/O
+
+    fn peek(&self, bank: MemoryBank, address: u16) -> u8 {
+        match bank {
+            MemoryBank::Cart => self.expansion_port.borrow().peek(address),
+            MemoryBank::Cpu => self.fetch(address, true),
+            MemoryBank::Io => match address {
+                0xd000..=0xdfff => self.io.peek(address),
+                _ => self.ram.borrow().read(address),
+            },
+            MemoryBank::Ram => self.ram.borrow().read(address),
+            MemoryBank::Rom => match address {
+                0xa000..=0xbfff => self.basic.borrow().read(address),
+                0xd000..=0xdfff => self.charset
+                    .borrow()
+                    .read(address - BaseAddr::Charset.addr()),
+                0xe000..=0xffff => self.kernal.borrow().read(address),
+                _ => self.ram.borrow().read(address),
+            },
+            MemoryBank::Vic => self.fetch_vic(address),
+        }
+    }
+
+    fn read(&self, address: u16) -> u8 {
+        let value = self.fetch(address, false);
+        self.watch.record(MemoryAccess::Read, address, value);
+        value
+    }
+
     fn write(&mut self, address: u16, value: u8) {
+        self.watch.record(MemoryAccess::Write, address, value);
+        if self.watch.is_journaling() {
+            self.watch
+                .journal(address, self.ram.borrow().read(address), value);
+        }
         let zone = address >> 12;
         match self.configuration.get(zone as u8) {
             Bank::Ram => self.ram.borrow_mut().write(address, value),
             Bank::Basic => self.ram.borrow_mut().write(address, value),
             Bank::Charset => self.ram.borrow_mut().write(address, value),
             Bank::Kernal => self.ram.borrow_mut().write(address, value),
-            Bank::RomL => self.ram.borrow_mut().write(address, value),
-            Bank::RomH => self.ram.borrow_mut().write(address, value),
+            Bank::RomL | Bank::RomH => {
+                // In Ultimax mode RAM is not selected for the cartridge areas
+                if !self.ultimax {
+                    self.ram.borrow_mut().write(address, value);
+                }
+                self.expansion_port.borrow_mut().write(address, value);
+            }
             Bank::Io => self.io.write(address, value),
             Bank::Disabled => {}
         }
+        // Expansion port devices can snoop writes to $FF00 outside of Ultimax mode
+        if address == 0xff00 && !self.ultimax {
+            self.expansion_port.borrow_mut().write(address, value);
+        }
     }
 }
 
@@ -134,11 +211,22 @@ mod tests {
         )));
         let mut mmio = Box::new(Ram::new(0x10000));
         mmio.fill(0x22);
-        let expansion_port = Rc::new(RefCell::new(Ram::new(0x1000)));
+        let expansion_port = Rc::new(RefCell::new(Ram::new(0x10000)));
         expansion_port.borrow_mut().fill(0x33);
         let ram = Rc::new(RefCell::new(Ram::new(0x10000)));
         ram.borrow_mut().fill(0x44);
-        Memory::new(expansion_port, mmio, ram, basic, charset, kernal)
+        let vic_base_address = Rc::new(Cell::new(0x0000));
+        let watch = Rc::new(MemoryWatch::new());
+        Memory::new(
+            expansion_port,
+            mmio,
+            ram,
+            basic,
+            charset,
+            kernal,
+            vic_base_address,
+            watch,
+        )
     }
 
     #[test]
@@ -169,6 +257,69 @@ mod tests {
         assert_eq!(0x12, mem.read(BaseAddr::Kernal.addr()));
     }
 
+    #[test]
+    fn peek_banks() {
+        let mut mem = setup_memory();
+        mem.switch_banks(31);
+        mem.watch.set_ranges(vec![(0xa000..=0xe000, MemoryAccess::Read.mask())]);
+        assert_eq!(0x10, mem.peek(MemoryBank::Cpu, 0xa000));
+        assert_eq!(0x44, mem.peek(MemoryBank::Ram, 0xa000));
+        assert_eq!(0x11, mem.peek(MemoryBank::Rom, 0xd000));
+        assert_eq!(0x22, mem.peek(MemoryBank::Io, 0xd000));
+        assert_eq!(0x44, mem.peek(MemoryBank::Io, 0xe000));
+        assert_eq!(0x33, mem.peek(MemoryBank::Cart, 0x8000));
+        assert_eq!(0x11, mem.peek(MemoryBank::Vic, 0x1000));
+        assert_eq!(0x44, mem.peek(MemoryBank::Vic, 0x2000));
+        assert_eq!(0, mem.watch.take_hits().len());
+    }
+
+    #[test]
+    fn read_ultimax() {
+        let mut mem = setup_memory();
+        mem.switch_banks(0x10 | 0x07);
+        assert_eq!(0x44, mem.read(0x0800));
+        assert_eq!(0x00, mem.read(0x4000));
+        assert_eq!(0x33, mem.read(0x8000));
+        assert_eq!(0x00, mem.read(0xa000));
+        assert_eq!(0x22, mem.read(0xd000));
+        assert_eq!(0x33, mem.read(0xfffc));
+    }
+
+    #[test]
+    fn watch_read_write() {
+        let mut mem = setup_memory();
+        mem.switch_banks(31);
+        mem.watch.set_ranges(vec![(
+            0xa000..=0xa000,
+            MemoryAccess::Read.mask() | MemoryAccess::Write.mask(),
+        )]);
+        mem.read(0xa000);
+        mem.write(0xa000, 0xff);
+        mem.read(0xa001);
+        assert_eq!(
+            vec![
+                (MemoryAccess::Read, 0xa000, 0x10),
+                (MemoryAccess::Write, 0xa000, 0xff),
+            ],
+            mem.watch.take_hits()
+        );
+    }
+
+    #[test]
+    fn watch_journal_writes() {
+        let mut mem = setup_memory();
+        mem.switch_banks(31);
+        mem.write(0x0800, 0x01);
+        assert_eq!(0, mem.watch.take_writes().len());
+        mem.watch.set_journaling(true);
+        mem.write(0x0800, 0x02);
+        mem.write(0xa000, 0x03);
+        assert_eq!(
+            vec![(0x0800, 0x01, 0x02), (0xa000, 0x44, 0x03)],
+            mem.watch.take_writes()
+        );
+    }
+
     #[test]
     fn write_page_0() {
         let mut mem = setup_memory();
@@ -182,4 +333,24 @@ mod tests {
         mem.write(0x0100, 0xff);
         assert_eq!(0xff, mem.ram.borrow().read(0x0100));
     }
+
+    #[test]
+    fn write_rom_l() {
+        let mut mem = setup_memory();
+        mem.switch_banks(0x07);
+        mem.write(0x8000, 0xff);
+        assert_eq!(0xff, mem.ram.borrow().read(0x8000));
+        assert_eq!(0xff, mem.expansion_port.borrow().read(0x8000));
+    }
+
+    #[test]
+    fn write_ultimax() {
+        let mut mem = setup_memory();
+        mem.switch_banks(0x10 | 0x07);
+        mem.write(0x4000, 0xff);
+        mem.write(0x8000, 0xff);
+        assert_eq!(0x44, mem.ram.borrow().read(0x4000));
+        assert_eq!(0x44, mem.ram.borrow().read(0x8000));
+        assert_eq!(0xff, mem.expansion_port.borrow().read(0x8000));
+    }
 }
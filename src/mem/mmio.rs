@@ -37,6 +37,18 @@ impl Mmio {
 }
 
 impl Addressable for Mmio {
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0xd000..=0xd3ff => self.vic.borrow().peek((address & 0x003f) as u8),
+            0xd400..=0xd7ff => self.sid.borrow().peek((address & 0x001f) as u8),
+            0xd800..=0xdbff => self.color_ram.borrow().read(address - 0xd800),
+            0xdc00..=0xdcff => self.cia_1.borrow().peek((address & 0x000f) as u8),
+            0xdd00..=0xddff => self.cia_2.borrow().peek((address & 0x000f) as u8),
+            0xde00..=0xdfff => self.expansion_port.borrow().peek(address),
+            _ => panic!("invalid address 0x{:x}", address),
+        }
+    }
+
     fn read(&self, address: u16) -> u8 {
         match address {
             0xd000...0xd3ff => self.vic.borrow_mut().read((address & 0x003f) as u8),
@@ -115,6 +115,10 @@ impl Chip for Sid {
 
     // I/O
 
+    fn peek(&self, reg: u8) -> u8 {
+        self.resid.read(reg)
+    }
+
     fn read(&mut self, reg: u8) -> u8 {
         self.sync();
         self.resid.read(reg)
@@ -5,7 +5,7 @@
 use super::C64;
 
 pub trait Image {
-    fn mount(&mut self, c64: &mut C64);
+    fn mount(&mut self, c64: &mut C64) -> Result<(), String>;
     fn unmount(&mut self, c64: &mut C64);
 }
 
@@ -23,11 +23,12 @@ impl Autostart {
         Autostart { mode, image }
     }
 
-    pub fn execute(&mut self, c64: &mut C64) {
-        self.image.mount(c64);
+    pub fn execute(&mut self, c64: &mut C64) -> Result<(), String> {
+        self.image.mount(c64)?;
         let keyboard = c64.get_keyboard();
         let command = self.get_command().to_string() + "\n";
         keyboard.borrow_mut().enqueue(&command);
+        Ok(())
     }
 
     fn get_command(&self) -> &str {
@@ -44,19 +45,20 @@ pub enum AutostartMethod {
 }
 
 impl AutostartMethod {
-    pub fn execute(&mut self, c64: &mut C64) {
+    pub fn execute(&mut self, c64: &mut C64) -> Result<(), String> {
         match *self {
             AutostartMethod::WithImage(ref mut image) => {
-                image.mount(c64);
+                image.mount(c64)?;
                 c64.reset(false);
             }
             AutostartMethod::WithBinImage(ref mut image) => {
-                image.mount(c64);
+                image.mount(c64)?;
             }
             AutostartMethod::WithAutostart(ref mut autostart) => {
                 c64.set_autostart(autostart.take());
                 c64.reset(false);
             }
         }
+        Ok(())
     }
 }
@@ -2,11 +2,105 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
+use std::fmt;
+use std::rc::Rc;
 use std::slice::Iter;
 
-use core::Cpu;
+use core::{MemoryAccess, MemoryWatch};
 
-use super::Condition;
+use super::{Condition, EvalContext, SymbolTable};
+
+// Design:
+//   Breakpoints and watchpoints share one index space so the monitor can enable, disable,
+//   ignore or delete either of them by number. Watchpoints hook into the memory controllers
+//   through MemoryWatch which collects accesses to watched ranges while an instruction runs.
+//   They are evaluated together with breakpoints after the instruction, so the machine stops
+//   on the instruction following the access. Conditions of watchpoints can test the accessed
+//   value through VALUE.
+//   Catchpoints trigger on machine events instead of addresses. Since the machine is only
+//   checked between instructions, a raster catchpoint fires once the beam passed its position
+//   during the last instruction and an interrupt catchpoint fires once its source raised the
+//   line. The state before the instruction is provided by the caller as StepEvents.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Interrupt {
+    Irq,
+    Nmi,
+}
+
+impl fmt::Display for Interrupt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Interrupt::Irq => write!(f, "irq"),
+            Interrupt::Nmi => write!(f, "nmi"),
+        }
+    }
+}
+
+// The values match the source bits used by the chips on the interrupt lines.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InterruptSource {
+    Cia1 = 0,
+    Cia2 = 1,
+    Vic = 2,
+    Expansion = 3,
+}
+
+impl InterruptSource {
+    pub fn from(name: &str) -> Result<InterruptSource, String> {
+        match name.to_lowercase().as_str() {
+            "cia1" => Ok(InterruptSource::Cia1),
+            "cia2" => Ok(InterruptSource::Cia2),
+            "vic" => Ok(InterruptSource::Vic),
+            "exp" | "expansion" => Ok(InterruptSource::Expansion),
+            _ => Err(format!("Invalid interrupt source {}", name)),
+        }
+    }
+
+    pub fn mask(&self) -> u8 {
+        1 << (*self as u8)
+    }
+}
+
+impl fmt::Display for InterruptSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            InterruptSource::Cia1 => write!(f, "cia1"),
+            InterruptSource::Cia2 => write!(f, "cia2"),
+            InterruptSource::Vic => write!(f, "vic"),
+            InterruptSource::Expansion => write!(f, "exp"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Trigger {
+    Interrupt(Interrupt, Option<InterruptSource>),
+    Raster(u16, Option<u16>),
+}
+
+impl fmt::Display for Trigger {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Trigger::Interrupt(kind, Some(source)) => write!(f, "{} {}", kind, source),
+            Trigger::Interrupt(kind, None) => write!(f, "{}", kind),
+            Trigger::Raster(line, Some(cycle)) => {
+                write!(f, "raster ${:03x} cycle ${:02x}", line, cycle)
+            }
+            Trigger::Raster(line, None) => write!(f, "raster ${:03x}", line),
+        }
+    }
+}
+
+// Machine state the caller sampled before the last instruction.
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct StepEvents {
+    pub raster_position: (u16, u16),
+    pub irq_raised: u8,
+    pub nmi_raised: u8,
+}
 
 pub struct Breakpoint {
     pub index: u16,
@@ -17,28 +111,134 @@ pub struct Breakpoint {
     autodelete: bool,
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WatchMode {
+    Read,
+    Write,
+    Access,
+}
+
+impl fmt::Display for WatchMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            &WatchMode::Read => write!(f, "read"),
+            &WatchMode::Write => write!(f, "write"),
+            &WatchMode::Access => write!(f, "access"),
+        }
+    }
+}
+
+pub struct Watchpoint {
+    pub index: u16,
+    pub start: u16,
+    pub end: u16,
+    pub mode: WatchMode,
+    pub vic: bool,
+    pub enabled: bool,
+    pub condition: Option<Condition>,
+    ignore: u16,
+}
+
+impl Watchpoint {
+    fn get_access_mask(&self) -> u8 {
+        let mut mask = match self.mode {
+            WatchMode::Read => MemoryAccess::Read.mask(),
+            WatchMode::Write => MemoryAccess::Write.mask(),
+            WatchMode::Access => MemoryAccess::Read.mask() | MemoryAccess::Write.mask(),
+        };
+        if self.vic && self.mode != WatchMode::Write {
+            mask |= MemoryAccess::VicRead.mask();
+        }
+        mask
+    }
+
+    fn matches(&self, access: MemoryAccess, address: u16) -> bool {
+        self.get_access_mask() & access.mask() != 0 && address >= self.start && address <= self.end
+    }
+}
+
+pub struct Catchpoint {
+    pub index: u16,
+    pub trigger: Trigger,
+    pub enabled: bool,
+    pub condition: Option<Condition>,
+    ignore: u16,
+}
+
+impl Catchpoint {
+    fn matches(&self, ctx: &EvalContext, events: &StepEvents) -> bool {
+        match self.trigger {
+            Trigger::Interrupt(kind, source) => {
+                let raised = match kind {
+                    Interrupt::Irq => events.irq_raised,
+                    Interrupt::Nmi => events.nmi_raised,
+                };
+                raised & source.map_or(0xff, |source| source.mask()) != 0
+            }
+            Trigger::Raster(line, cycle) => {
+                let target = (line, cycle.unwrap_or(1));
+                let start = events.raster_position;
+                let end = (ctx.raster_line, ctx.raster_cycle);
+                if start <= end {
+                    start < target && target <= end
+                } else {
+                    start < target || target <= end
+                }
+            }
+        }
+    }
+}
+
 pub struct BreakpointManager {
     breakpoints: Vec<Breakpoint>,
+    catchpoints: Vec<Catchpoint>,
+    watchpoints: Vec<Watchpoint>,
     bp_index: u16,
+    symbols: SymbolTable,
+    watch: Rc<MemoryWatch>,
 }
 
 impl BreakpointManager {
-    pub fn new() -> Self {
+    pub fn new(watch: Rc<MemoryWatch>) -> Self {
         Self {
             breakpoints: Vec::new(),
+            catchpoints: Vec::new(),
+            watchpoints: Vec::new(),
             bp_index: 1,
+            symbols: SymbolTable::new(),
+            watch,
         }
     }
 
-    pub fn check(&mut self, cpu: &Box<dyn Cpu>) -> Option<usize> {
+    pub fn catch(&mut self, trigger: Trigger) -> u16 {
+        let index = self.bp_index;
+        let cp = Catchpoint {
+            index,
+            trigger,
+            enabled: true,
+            condition: None,
+            ignore: 0,
+        };
+        self.catchpoints.push(cp);
+        self.bp_index += 1;
+        index
+    }
+
+    pub fn check(&mut self, ctx: &EvalContext, events: &StepEvents) -> Option<u16> {
+        self.check_watchpoints(ctx)
+            .or_else(|| self.check_catchpoints(ctx, events))
+            .or_else(|| self.check_breakpoints(ctx))
+    }
+
+    fn check_breakpoints(&mut self, ctx: &EvalContext) -> Option<u16> {
         if self.breakpoints.is_empty() {
             None
         } else {
-            let pc = cpu.get_pc();
+            let pc = ctx.cpu.get_pc();
             let bp_pos = self.breakpoints.iter_mut().position(|bp| {
                 if bp.address == pc && bp.enabled {
                     if bp.ignore == 0 {
-                        bp.condition.as_ref().map_or(true, |cond| cond.eval(cpu))
+                        bp.condition.as_ref().map_or(true, |cond| cond.eval(ctx))
                     } else {
                         bp.ignore -= 1;
                         false
@@ -47,23 +247,72 @@ impl BreakpointManager {
                     false
                 }
             });
-            if let Some(pos) = bp_pos {
+            bp_pos.map(|pos| {
+                let index = self.breakpoints[pos].index;
                 if self.breakpoints[pos].autodelete {
                     self.breakpoints.remove(pos);
                 }
+                index
+            })
+        }
+    }
+
+    fn check_catchpoints(&mut self, ctx: &EvalContext, events: &StepEvents) -> Option<u16> {
+        for cp in self.catchpoints.iter_mut() {
+            if cp.enabled
+                && cp.matches(ctx, events)
+                && cp.condition.as_ref().map_or(true, |cond| cond.eval(ctx))
+            {
+                if cp.ignore == 0 {
+                    return Some(cp.index);
+                }
+                cp.ignore -= 1;
+            }
+        }
+        None
+    }
+
+    fn check_watchpoints(&mut self, ctx: &EvalContext) -> Option<u16> {
+        if self.watchpoints.is_empty() {
+            return None;
+        }
+        for (access, address, value) in self.watch.take_hits() {
+            let access_ctx = EvalContext { value, ..*ctx };
+            for wp in self.watchpoints.iter_mut() {
+                if wp.enabled
+                    && wp.matches(access, address)
+                    && wp.condition
+                        .as_ref()
+                        .map_or(true, |cond| cond.eval(&access_ctx))
+                {
+                    if wp.ignore == 0 {
+                        return Some(wp.index);
+                    }
+                    wp.ignore -= 1;
+                }
             }
-            bp_pos
         }
+        None
     }
 
     pub fn clear(&mut self) {
         self.breakpoints.clear();
+        self.catchpoints.clear();
+        self.watchpoints.clear();
+        self.update_watch();
     }
 
     pub fn enable_all(&mut self, enabled: bool) {
         for bp in self.breakpoints.iter_mut() {
             bp.enabled = enabled;
         }
+        for cp in self.catchpoints.iter_mut() {
+            cp.enabled = enabled;
+        }
+        for wp in self.watchpoints.iter_mut() {
+            wp.enabled = enabled;
+        }
+        self.update_watch();
     }
 
     pub fn get(&mut self, index: u16) -> Result<&Breakpoint, String> {
@@ -73,14 +322,46 @@ impl BreakpointManager {
         }
     }
 
+    pub fn get_catchpoint(&self, index: u16) -> Result<&Catchpoint, String> {
+        match self.catchpoints.iter().position(|cp| cp.index == index) {
+            Some(pos) => Ok(&self.catchpoints[pos]),
+            None => Err(format!("Invalid index {}", index)),
+        }
+    }
+
+    pub fn get_symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
+    pub fn get_symbols_mut(&mut self) -> &mut SymbolTable {
+        &mut self.symbols
+    }
+
+    pub fn get_watchpoint(&self, index: u16) -> Result<&Watchpoint, String> {
+        match self.watchpoints.iter().position(|wp| wp.index == index) {
+            Some(pos) => Ok(&self.watchpoints[pos]),
+            None => Err(format!("Invalid index {}", index)),
+        }
+    }
+
     pub fn is_bp_present(&self) -> bool {
-        self.breakpoints.iter().position(|bp| bp.enabled).is_some()
+        self.breakpoints.iter().any(|bp| bp.enabled)
+            || self.catchpoints.iter().any(|cp| cp.enabled)
+            || self.watchpoints.iter().any(|wp| wp.enabled)
     }
 
     pub fn ignore(&mut self, index: u16, count: u16) -> Result<(), String> {
-        match self.find_mut(index) {
-            Some(bp) => {
-                bp.ignore = count;
+        if let Some(bp) = self.find_mut(index) {
+            bp.ignore = count;
+            return Ok(());
+        }
+        if let Some(cp) = self.find_catchpoint_mut(index) {
+            cp.ignore = count;
+            return Ok(());
+        }
+        match self.find_watchpoint_mut(index) {
+            Some(wp) => {
+                wp.ignore = count;
                 Ok(())
             }
             None => Err(format!("Invalid index {}", index)),
@@ -91,10 +372,27 @@ impl BreakpointManager {
         self.breakpoints.iter()
     }
 
+    pub fn list_catchpoints(&self) -> Iter<'_, Catchpoint> {
+        self.catchpoints.iter()
+    }
+
+    pub fn list_watchpoints(&self) -> Iter<'_, Watchpoint> {
+        self.watchpoints.iter()
+    }
+
     pub fn remove(&mut self, index: u16) -> Result<(), String> {
-        match self.breakpoints.iter().position(|bp| bp.index == index) {
+        if let Some(pos) = self.breakpoints.iter().position(|bp| bp.index == index) {
+            self.breakpoints.remove(pos);
+            return Ok(());
+        }
+        if let Some(pos) = self.catchpoints.iter().position(|cp| cp.index == index) {
+            self.catchpoints.remove(pos);
+            return Ok(());
+        }
+        match self.watchpoints.iter().position(|wp| wp.index == index) {
             Some(pos) => {
-                self.breakpoints.remove(pos);
+                self.watchpoints.remove(pos);
+                self.update_watch();
                 Ok(())
             }
             None => Err(format!("Invalid index {}", index)),
@@ -122,10 +420,18 @@ impl BreakpointManager {
         expr: &String,
         radix: Option<u32>,
     ) -> Result<(), String> {
-        match self.find_mut(index) {
-            Some(bp) => {
-                let condition = Condition::parse(expr.as_str(), radix)?;
-                bp.condition = Some(condition);
+        let condition = Condition::parse(expr.as_str(), radix, &self.symbols)?;
+        if let Some(bp) = self.find_mut(index) {
+            bp.condition = Some(condition);
+            return Ok(());
+        }
+        if let Some(cp) = self.find_catchpoint_mut(index) {
+            cp.condition = Some(condition);
+            return Ok(());
+        }
+        match self.find_watchpoint_mut(index) {
+            Some(wp) => {
+                wp.condition = Some(condition);
                 Ok(())
             }
             None => Err(format!("Invalid index {}", index)),
@@ -133,13 +439,40 @@ impl BreakpointManager {
     }
 
     pub fn set_enabled(&mut self, index: u16, enabled: bool) -> Result<(), String> {
-        match self.find_mut(index) {
-            Some(bp) => {
-                bp.enabled = enabled;
-                Ok(())
+        if let Some(bp) = self.find_mut(index) {
+            bp.enabled = enabled;
+            return Ok(());
+        }
+        if let Some(cp) = self.find_catchpoint_mut(index) {
+            cp.enabled = enabled;
+            return Ok(());
+        }
+        match self.find_watchpoint_mut(index) {
+            Some(wp) => {
+                wp.enabled = enabled;
             }
-            None => Err(format!("Invalid index {}", index)),
+            None => return Err(format!("Invalid index {}", index)),
         }
+        self.update_watch();
+        Ok(())
+    }
+
+    pub fn watch(&mut self, start: u16, end: u16, mode: WatchMode, vic: bool) -> u16 {
+        let index = self.bp_index;
+        let wp = Watchpoint {
+            index,
+            start,
+            end,
+            mode,
+            vic,
+            enabled: true,
+            condition: None,
+            ignore: 0,
+        };
+        self.watchpoints.push(wp);
+        self.bp_index += 1;
+        self.update_watch();
+        index
     }
 
     fn find_mut(&mut self, index: u16) -> Option<&mut Breakpoint> {
@@ -148,4 +481,143 @@ impl BreakpointManager {
             None => None,
         }
     }
+
+    fn find_catchpoint_mut(&mut self, index: u16) -> Option<&mut Catchpoint> {
+        match self.catchpoints.iter().position(|cp| cp.index == index) {
+            Some(pos) => Some(&mut self.catchpoints[pos]),
+            None => None,
+        }
+    }
+
+    fn find_watchpoint_mut(&mut self, index: u16) -> Option<&mut Watchpoint> {
+        match self.watchpoints.iter().position(|wp| wp.index == index) {
+            Some(pos) => Some(&mut self.watchpoints[pos]),
+            None => None,
+        }
+    }
+
+    fn update_watch(&mut self) {
+        let ranges = self.watchpoints
+            .iter()
+            .filter(|wp| wp.enabled)
+            .map(|wp| (wp.start..=wp.end, wp.get_access_mask()))
+            .collect();
+        self.watch.set_ranges(ranges);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{Cpu, Mmu, Ram};
+    use cpu::mock::{setup_cpu, MockMemory};
+
+    fn setup_context<'a>(cpu: &'a dyn Cpu, mem: &'a dyn Mmu) -> EvalContext<'a> {
+        EvalContext {
+            cpu,
+            mem,
+            raster_line: 0,
+            raster_cycle: 1,
+            vic_bank: 0,
+            value: 0,
+        }
+    }
+
+    #[test]
+    fn breakpoint_condition() {
+        let mut cpu = setup_cpu();
+        let mem = MockMemory::new(Ram::new(0x10000));
+        cpu.set_pc(0xc000);
+        let mut bpm = BreakpointManager::new(Rc::new(MemoryWatch::new()));
+        let index = bpm.set(0xc000, false);
+        bpm.set_condition(index, &"x == 2".to_string(), None).unwrap();
+        assert_eq!(None, bpm.check(&setup_context(&cpu, &mem), &StepEvents::default()));
+        cpu.set_x(0x02);
+        assert_eq!(Some(index), bpm.check(&setup_context(&cpu, &mem), &StepEvents::default()));
+    }
+
+    #[test]
+    fn catchpoint_interrupt() {
+        let cpu = setup_cpu();
+        let mem = MockMemory::new(Ram::new(0x10000));
+        let ctx = setup_context(&cpu, &mem);
+        let mut bpm = BreakpointManager::new(Rc::new(MemoryWatch::new()));
+        let vic_irq = bpm.catch(Trigger::Interrupt(Interrupt::Irq, Some(InterruptSource::Vic)));
+        let nmi = bpm.catch(Trigger::Interrupt(Interrupt::Nmi, None));
+        let mut events = StepEvents {
+            irq_raised: InterruptSource::Cia1.mask(),
+            ..StepEvents::default()
+        };
+        assert_eq!(None, bpm.check(&ctx, &events));
+        events.irq_raised = InterruptSource::Vic.mask();
+        assert_eq!(Some(vic_irq), bpm.check(&ctx, &events));
+        events.irq_raised = 0;
+        events.nmi_raised = InterruptSource::Cia2.mask();
+        assert_eq!(Some(nmi), bpm.check(&ctx, &events));
+    }
+
+    #[test]
+    fn catchpoint_raster() {
+        let cpu = setup_cpu();
+        let mem = MockMemory::new(Ram::new(0x10000));
+        let mut ctx = setup_context(&cpu, &mem);
+        let mut bpm = BreakpointManager::new(Rc::new(MemoryWatch::new()));
+        let index = bpm.catch(Trigger::Raster(0x33, Some(12)));
+        let mut events = StepEvents {
+            raster_position: (0x33, 8),
+            ..StepEvents::default()
+        };
+        ctx.raster_line = 0x33;
+        ctx.raster_cycle = 11;
+        assert_eq!(None, bpm.check(&ctx, &events));
+        events.raster_position = (0x33, 11);
+        ctx.raster_cycle = 14;
+        assert_eq!(Some(index), bpm.check(&ctx, &events));
+        events.raster_position = (0x137, 60);
+        ctx.raster_line = 0x00;
+        ctx.raster_cycle = 3;
+        assert_eq!(None, bpm.check(&ctx, &events));
+        bpm.remove(index).unwrap();
+        let index = bpm.catch(Trigger::Raster(0x00, None));
+        assert_eq!(Some(index), bpm.check(&ctx, &events));
+    }
+
+    #[test]
+    fn watchpoint_value_condition_and_ignore() {
+        let cpu = setup_cpu();
+        let mem = MockMemory::new(Ram::new(0x10000));
+        let ctx = setup_context(&cpu, &mem);
+        let watch = Rc::new(MemoryWatch::new());
+        let mut bpm = BreakpointManager::new(watch.clone());
+        let index = bpm.watch(0xd020, 0xd020, WatchMode::Write, false);
+        bpm.set_condition(index, &"value==01".to_string(), None)
+            .unwrap();
+        bpm.ignore(index, 1).unwrap();
+        watch.record(MemoryAccess::Read, 0xd020, 0x01);
+        watch.record(MemoryAccess::Write, 0xd020, 0x00);
+        assert_eq!(None, bpm.check(&ctx, &StepEvents::default()));
+        watch.record(MemoryAccess::Write, 0xd020, 0x01);
+        assert_eq!(None, bpm.check(&ctx, &StepEvents::default()));
+        watch.record(MemoryAccess::Write, 0xd020, 0x01);
+        assert_eq!(Some(index), bpm.check(&ctx, &StepEvents::default()));
+    }
+
+    #[test]
+    fn watchpoint_vic_reads() {
+        let cpu = setup_cpu();
+        let mem = MockMemory::new(Ram::new(0x10000));
+        let ctx = setup_context(&cpu, &mem);
+        let watch = Rc::new(MemoryWatch::new());
+        let mut bpm = BreakpointManager::new(watch.clone());
+        let index = bpm.watch(0x0400, 0x07ff, WatchMode::Read, false);
+        watch.record(MemoryAccess::VicRead, 0x0400, 0x20);
+        assert_eq!(None, bpm.check(&ctx, &StepEvents::default()));
+        bpm.remove(index).unwrap();
+        let index = bpm.watch(0x0400, 0x07ff, WatchMode::Read, true);
+        watch.record(MemoryAccess::VicRead, 0x0400, 0x20);
+        assert_eq!(Some(index), bpm.check(&ctx, &StepEvents::default()));
+        bpm.set_enabled(index, false).unwrap();
+        watch.record(MemoryAccess::VicRead, 0x0400, 0x20);
+        assert_eq!(None, bpm.check(&ctx, &StepEvents::default()));
+    }
 }
@@ -9,12 +9,21 @@ use std::rc::Rc;
 use std::result::Result;
 use std::sync::{Arc, Mutex};
 
-use core::{Chip, ChipFactory, Clock, Cpu, IoPort, IrqLine, Pin, Ram, TickFn};
+use core::{
+    Chip, ChipFactory, Clock, Cpu, IoPort, IrqLine, MemoryAccess, MemoryBank, MemoryWatch, Mmu,
+    Pin, Ram, TickFn,
+};
 use device::joystick;
-use device::{Cartridge, Datassette, ExpansionPort, Joystick, Keyboard, Tape};
+use device::{
+    Cartridge, Datassette, DeviceKind, DmaAccess, ExpansionDevice, ExpansionPort, IecBus, IecDevice,
+    Joystick, Keyboard, Midi, Tape,
+};
 
-use super::breakpoint::BreakpointManager;
-use super::{Autostart, CircularBuffer, Config, FrameBuffer, Palette};
+use super::breakpoint::{BreakpointManager, StepEvents};
+use super::{
+    Autostart, CircularBuffer, Config, EvalContext, FrameBuffer, Journal, JournalEntry, Palette,
+    Trace, TraceEntry,
+};
 
 // Design:
 //   C64 represents the machine itself and all of its components. Connections between different
@@ -46,10 +55,18 @@ pub struct C64 {
     vic: Rc<RefCell<dyn Chip>>,
     // Memory
     color_ram: Rc<RefCell<Ram>>,
+    mem: Rc<RefCell<dyn Mmu>>,
+    mem_watch: Rc<MemoryWatch>,
     ram: Rc<RefCell<Ram>>,
+    // I/O Lines
+    irq_line: Rc<RefCell<IrqLine>>,
+    nmi_line: Rc<RefCell<IrqLine>>,
+    raster_position: Rc<Cell<(u16, u16)>>,
+    vic_base_address: Rc<Cell<u16>>,
     // Peripherals
     datassette: Rc<RefCell<Datassette>>,
     expansion_port: Rc<RefCell<ExpansionPort>>,
+    iec_bus: IecBus,
     joystick_1: Option<Rc<RefCell<Joystick>>>,
     joystick_2: Option<Rc<RefCell<Joystick>>>,
     keyboard: Rc<RefCell<Keyboard>>,
@@ -59,10 +76,15 @@ pub struct C64 {
     // Configuration
     autostart: Option<Autostart>,
     breakpoints: BreakpointManager,
+    journal: Option<Journal>,
+    trace: Option<Trace>,
     // Runtime State
     clock: Rc<Clock>,
     frame_count: u32,
+    last_irq_signal: u8,
+    last_nmi_signal: u8,
     last_pc: u16,
+    last_raster_position: (u16, u16),
     tick_fn: TickFn,
 }
 
@@ -95,14 +117,24 @@ impl C64 {
         let exp_io_line = Rc::new(RefCell::new(IoPort::new(0xff, 0xff)));
         let irq_line = Rc::new(RefCell::new(IrqLine::new("irq")));
         let nmi_line = Rc::new(RefCell::new(IrqLine::new("nmi")));
+        let raster_position = Rc::new(Cell::new((0u16, 0u16)));
         let vic_base_address = Rc::new(Cell::new(0u16));
+        let vic_data_bus = Rc::new(Cell::new(0u8));
 
         // Memory
         let color_ram = factory.new_ram(config.model.color_ram);
+        let mem_watch = Rc::new(MemoryWatch::new());
         let ram = factory.new_ram(config.model.memory_size);
         let rom_basic = factory.new_rom(Path::new("res/rom/basic.rom"), BaseAddr::Basic.addr())?;
         let rom_charset = factory.new_rom(Path::new("res/rom/characters.rom"), 0)?;
         let rom_kernal = factory.new_rom(Path::new("res/rom/kernal.rom"), BaseAddr::Kernal.addr())?;
+        let expansion_port = Rc::new(RefCell::new(ExpansionPort::new(
+            ba_line.clone(),
+            exp_io_line.clone(),
+            irq_line.clone(),
+            nmi_line.clone(),
+            vic_data_bus.clone(),
+        )));
 
         // Chipset
         let cia_1 = factory.new_cia_1(
@@ -126,15 +158,19 @@ impl C64 {
             config.model.vic_model,
             ba_line.clone(),
             color_ram.clone(),
+            exp_io_line.clone(),
+            expansion_port.clone(),
             frame_buffer.clone(),
             irq_line.clone(),
             ram.clone(),
+            raster_position.clone(),
             rom_charset.clone(),
             vic_base_address.clone(),
+            vic_data_bus.clone(),
+            mem_watch.clone(),
         );
 
         // Memory Controller and Processor
-        let expansion_port = Rc::new(RefCell::new(ExpansionPort::new(exp_io_line.clone())));
         let mem = factory.new_memory(
             cia_1.clone(),
             cia_2.clone(),
@@ -146,6 +182,8 @@ impl C64 {
             rom_kernal.clone(),
             sid.clone(),
             vic.clone(),
+            vic_base_address.clone(),
+            mem_watch.clone(),
         );
         let cpu = factory.new_cpu(
             ba_line.clone(),
@@ -157,6 +195,7 @@ impl C64 {
 
         // Peripherals
         let datassette = Rc::new(RefCell::new(Datassette::new(
+            config.model.cpu_freq,
             cia_1_flag_pin.clone(),
             cpu_io_port.clone(),
         )));
@@ -212,12 +251,27 @@ impl C64 {
             let cia_2_clone = cia_2.clone();
             let clock_clone = clock.clone();
             let datassette_clone = datassette.clone();
+            let expansion_port_clone = expansion_port.clone();
+            let mem_clone = mem.clone();
             let vic_clone = vic.clone();
             Rc::new(move || {
                 vic_clone.borrow_mut().clock();
                 cia_1_clone.borrow_mut().clock();
                 cia_2_clone.borrow_mut().clock();
                 datassette_clone.borrow_mut().clock();
+                // DMA accesses are performed here so the memory controller can reach the port
+                let dma_access = expansion_port_clone.borrow().get_dma_access();
+                if let Some(access) = dma_access {
+                    let value = match access {
+                        DmaAccess::Read(address) => mem_clone.borrow().read(address),
+                        DmaAccess::Write(address, value) => {
+                            mem_clone.borrow_mut().write(address, value);
+                            value
+                        }
+                    };
+                    expansion_port_clone.borrow_mut().complete_dma(value);
+                }
+                expansion_port_clone.borrow_mut().clock();
                 clock_clone.tick();
             })
         };
@@ -230,18 +284,30 @@ impl C64 {
             cia_2: cia_2.clone(),
             color_ram: color_ram.clone(),
             expansion_port: expansion_port.clone(),
+            mem: mem.clone(),
+            mem_watch: mem_watch.clone(),
             ram: ram.clone(),
+            irq_line: irq_line.clone(),
+            nmi_line: nmi_line.clone(),
+            raster_position,
+            vic_base_address,
             datassette,
+            iec_bus: IecBus::new(),
             joystick_1: joystick1,
             joystick_2: joystick2,
             keyboard: keyboard.clone(),
             frame_buffer: frame_buffer.clone(),
             sound_buffer: sound_buffer.clone(),
             autostart: None,
-            breakpoints: BreakpointManager::new(),
+            breakpoints: BreakpointManager::new(mem_watch),
+            journal: None,
+            trace: None,
             clock,
             frame_count: 0,
+            last_irq_signal: 0,
+            last_nmi_signal: 0,
             last_pc: 0,
+            last_raster_position: (0, 0),
             tick_fn,
         })
     }
@@ -294,6 +360,14 @@ impl C64 {
         self.frame_count
     }
 
+    pub fn get_journal(&self) -> Option<&Journal> {
+        self.journal.as_ref()
+    }
+
+    pub fn get_journal_mut(&mut self) -> Option<&mut Journal> {
+        self.journal.as_mut()
+    }
+
     pub fn get_joystick(&self, index: u8) -> Option<Rc<RefCell<Joystick>>> {
         if let Some(ref joystick) = self.joystick_1 {
             if joystick.borrow().get_index() == index {
@@ -320,6 +394,10 @@ impl C64 {
         self.keyboard.clone()
     }
 
+    pub fn get_raster_position(&self) -> (u16, u16) {
+        self.raster_position.get()
+    }
+
     pub fn get_sid(&self) -> Rc<RefCell<dyn Chip>> {
         self.sid.clone()
     }
@@ -328,6 +406,14 @@ impl C64 {
         self.sound_buffer.clone()
     }
 
+    pub fn get_trace(&self) -> Option<&Trace> {
+        self.trace.as_ref()
+    }
+
+    pub fn get_trace_mut(&mut self) -> Option<&mut Trace> {
+        self.trace.as_mut()
+    }
+
     pub fn get_vic(&self) -> Rc<RefCell<dyn Chip>> {
         self.vic.clone()
     }
@@ -340,8 +426,32 @@ impl C64 {
         self.autostart = autostart;
     }
 
+    pub fn set_journal(&mut self, journal: Option<Journal>) {
+        self.mem_watch.set_journaling(journal.is_some());
+        self.journal = journal;
+    }
+
+    pub fn set_trace(&mut self, trace: Option<Trace>) {
+        self.trace = trace;
+    }
+
     pub fn check_breakpoints(&mut self) -> bool {
-        self.breakpoints.check(&self.cpu).is_some()
+        let (raster_line, raster_cycle) = self.raster_position.get();
+        let mem = self.mem.borrow();
+        let ctx = EvalContext {
+            cpu: self.cpu.as_ref(),
+            mem: &*mem,
+            raster_line,
+            raster_cycle,
+            vic_bank: (self.vic_base_address.get() >> 14) as u8,
+            value: 0,
+        };
+        let events = StepEvents {
+            raster_position: self.last_raster_position,
+            irq_raised: self.irq_line.borrow().get_signal() & !self.last_irq_signal,
+            nmi_raised: self.nmi_line.borrow().get_signal() & !self.last_nmi_signal,
+        };
+        self.breakpoints.check(&ctx, &events).is_some()
     }
 
     pub fn load(&mut self, data: &Vec<u8>, offset: u16) {
@@ -353,6 +463,15 @@ impl C64 {
         }
     }
 
+    // Reads memory as seen through the bank without side effects on the chips. The CPU
+    // view includes the processor port at $00-$01.
+    pub fn peek(&self, bank: MemoryBank, address: u16) -> u8 {
+        match (bank, address) {
+            (MemoryBank::Cpu, 0x0000) | (MemoryBank::Cpu, 0x0001) => self.cpu.read(address),
+            _ => self.mem.borrow().peek(bank, address),
+        }
+    }
+
     pub fn reset(&mut self, hard: bool) {
         info!(target: "c64", "Resetting system");
         // Memory
@@ -374,6 +493,7 @@ impl C64 {
         self.expansion_port.borrow_mut().reset();
         // Peripherals
         self.datassette.borrow_mut().reset();
+        self.iec_bus.reset();
         if let Some(ref joystick) = self.joystick_1 {
             joystick.borrow_mut().reset();
         }
@@ -383,6 +503,10 @@ impl C64 {
         self.keyboard.borrow_mut().reset();
         self.frame_buffer.borrow_mut().reset();
         self.sound_buffer.lock().unwrap().reset();
+        // Debugging
+        if let Some(ref mut journal) = self.journal {
+            journal.clear();
+        }
         // Runtime State
         // self.clock.reset();
         self.frame_count = 0;
@@ -420,22 +544,131 @@ impl C64 {
         }
     }
 
+    pub fn step_back(&mut self) -> bool {
+        let entry = match self.journal.as_mut().and_then(|journal| journal.pop()) {
+            Some(entry) => entry,
+            None => return false,
+        };
+        for &(address, old_value, _) in entry.writes.iter().rev() {
+            if address <= 0x0001 {
+                self.cpu.write(address, old_value);
+            } else {
+                self.ram.borrow_mut().write(address, old_value);
+            }
+        }
+        // Writes undone are reported again so watchpoints stop at the instruction that made them
+        self.mem_watch.clear();
+        for &(address, _, value) in entry.writes.iter() {
+            self.mem_watch.record(MemoryAccess::Write, address, value);
+        }
+        self.cpu.set_a(entry.a);
+        self.cpu.set_x(entry.x);
+        self.cpu.set_y(entry.y);
+        self.cpu.set_p(entry.p);
+        self.cpu.set_sp(entry.sp);
+        self.cpu.set_pc(entry.pc);
+        self.last_irq_signal = self.irq_line.borrow().get_signal();
+        self.last_nmi_signal = self.nmi_line.borrow().get_signal();
+        self.last_raster_position = self.raster_position.get();
+        true
+    }
+
     pub fn step_internal(&mut self, tick_fn: &TickFn) {
         self.last_pc = self.cpu.get_pc();
+        self.last_irq_signal = self.irq_line.borrow().get_signal();
+        self.last_nmi_signal = self.nmi_line.borrow().get_signal();
+        self.last_raster_position = self.raster_position.get();
+        if self.trace.is_some() {
+            self.record_trace();
+        }
+        // Accesses made while halted, like monitor reads, must not trigger watchpoints
+        self.mem_watch.clear();
+        if self.journal.is_some() {
+            let mut entry = JournalEntry {
+                pc: self.cpu.get_pc(),
+                a: self.cpu.get_a(),
+                x: self.cpu.get_x(),
+                y: self.cpu.get_y(),
+                p: self.cpu.get_p(),
+                sp: self.cpu.get_sp(),
+                writes: Vec::new(),
+            };
+            self.execute(tick_fn);
+            entry.writes = self.mem_watch.take_writes();
+            if let Some(ref mut journal) = self.journal {
+                journal.push(entry);
+            }
+        } else {
+            self.execute(tick_fn);
+        }
+    }
+
+    fn execute(&mut self, tick_fn: &TickFn) {
+        if self.iec_bus.is_enabled() && self.iec_bus.trap(&mut self.cpu) {
+            return;
+        }
+        if self.config.datassette.fast_load && self.datassette.borrow_mut().trap(&mut self.cpu) {
+            return;
+        }
         self.cpu.step(&tick_fn);
         if self.autostart.is_some() {
             if self.cpu.get_pc() == BaseAddr::BootComplete.addr() {
                 if let Some(mut autostart) = self.autostart.take() {
-                    autostart.execute(self);
+                    if let Err(err) = autostart.execute(self) {
+                        error!(target: "c64", "Failed to autostart image: {}", err);
+                    }
                 }
             }
         }
     }
 
+    fn record_trace(&mut self) {
+        let pc = self.cpu.get_pc();
+        let (raster_line, raster_cycle) = self.raster_position.get();
+        let entry = TraceEntry {
+            pc,
+            opcode: [
+                self.peek(MemoryBank::Cpu, pc),
+                self.peek(MemoryBank::Cpu, pc.wrapping_add(1)),
+                self.peek(MemoryBank::Cpu, pc.wrapping_add(2)),
+            ],
+            a: self.cpu.get_a(),
+            x: self.cpu.get_x(),
+            y: self.cpu.get_y(),
+            p: self.cpu.get_p(),
+            sp: self.cpu.get_sp(),
+            cycles: self.clock.get(),
+            raster_line,
+            raster_cycle,
+        };
+        if let Some(ref mut trace) = self.trace {
+            trace.push(entry);
+        }
+    }
+
     // -- Peripherals Ops
 
-    pub fn attach_cartridge(&mut self, cartridge: Cartridge) {
-        self.expansion_port.borrow_mut().attach(cartridge);
+    pub fn attach_cartridge(&mut self, cartridge: Cartridge) -> Result<(), String> {
+        self.expansion_port.borrow_mut().attach(Box::new(cartridge))
+    }
+
+    pub fn attach_drive(&mut self, drive: Box<dyn IecDevice>) {
+        self.iec_bus.attach(drive);
+    }
+
+    pub fn attach_midi(&mut self, midi: Midi) -> Result<(), String> {
+        self.expansion_port.borrow_mut().attach(Box::new(midi))
+    }
+
+    pub fn attach_network(&mut self, network: Box<dyn ExpansionDevice>) -> Result<(), String> {
+        self.expansion_port.borrow_mut().attach(network)
+    }
+
+    pub fn attach_ram_expansion(
+        &mut self,
+        ram_expansion: Box<dyn ExpansionDevice>,
+    ) -> Result<(), String> {
+        self.expansion_port.borrow_mut().attach(ram_expansion)
     }
 
     pub fn attach_tape(&mut self, tape: Box<Tape>) {
@@ -443,13 +676,37 @@ impl C64 {
     }
 
     pub fn detach_cartridge(&mut self) {
-        self.expansion_port.borrow_mut().detach();
+        self.expansion_port
+            .borrow_mut()
+            .detach(DeviceKind::Cartridge);
         self.reset(false);
     }
 
+    pub fn detach_drive(&mut self, device_number: u8) {
+        self.iec_bus.detach(device_number);
+    }
+
+    pub fn detach_midi(&mut self) {
+        self.expansion_port.borrow_mut().detach(DeviceKind::Midi);
+    }
+
+    pub fn detach_network(&mut self) -> Option<Box<dyn ExpansionDevice>> {
+        self.expansion_port.borrow_mut().detach(DeviceKind::Network)
+    }
+
+    pub fn detach_ram_expansion(&mut self) -> Option<Box<dyn ExpansionDevice>> {
+        self.expansion_port
+            .borrow_mut()
+            .detach(DeviceKind::RamExpansion)
+    }
+
     pub fn detach_tape(&mut self) {
         self.datassette.borrow_mut().detach();
     }
+
+    pub fn freeze_cartridge(&mut self) {
+        self.expansion_port.borrow_mut().freeze();
+    }
 }
 
 #[cfg(test)]
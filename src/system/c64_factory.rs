@@ -9,8 +9,8 @@ use std::rc::Rc;
 use std::sync::{Arc, Mutex};
 
 use core::{
-    Addressable, Chip, ChipFactory, Clock, Cpu, IoPort, IrqLine, Mmu, Pin, Ram, Rom, SoundOutput,
-    SystemModel, VicModel, VideoOutput,
+    Addressable, Chip, ChipFactory, Clock, Cpu, IoPort, IrqLine, MemoryWatch, Mmu, Pin, Ram, Rom,
+    SoundOutput, SystemModel, VicModel, VideoOutput,
 };
 use cpu::Cpu6510;
 use device::ExpansionPort;
@@ -99,13 +99,26 @@ impl ChipFactory for C64Factory {
         chip_model: VicModel,
         ba_line: Rc<RefCell<Pin>>,
         color_ram: Rc<RefCell<Ram>>,
+        exp_io_line: Rc<RefCell<IoPort>>,
+        expansion_port: Rc<RefCell<dyn Addressable>>,
         frame_buffer: Rc<RefCell<dyn VideoOutput>>,
         irq_line: Rc<RefCell<IrqLine>>,
         ram: Rc<RefCell<Ram>>,
+        raster_position: Rc<Cell<(u16, u16)>>,
         rom_charset: Rc<RefCell<Rom>>,
         vic_base_address: Rc<Cell<u16>>,
+        vic_data_bus: Rc<Cell<u8>>,
+        mem_watch: Rc<MemoryWatch>,
     ) -> Rc<RefCell<dyn Chip>> {
-        let vic_mem = VicMemory::new(vic_base_address, rom_charset, ram);
+        let vic_mem = VicMemory::new(
+            vic_base_address,
+            rom_charset,
+            vic_data_bus,
+            exp_io_line,
+            expansion_port,
+            ram,
+            mem_watch,
+        );
         Rc::new(RefCell::new(Vic::new(
             chip_model,
             ba_line,
@@ -113,13 +126,27 @@ impl ChipFactory for C64Factory {
             irq_line,
             frame_buffer,
             vic_mem,
+            raster_position,
         )))
     }
 
     // -- Memory
 
-    fn new_expansion_port(&self, exp_io_line: Rc<RefCell<IoPort>>) -> Rc<RefCell<dyn Addressable>> {
-        Rc::new(RefCell::new(ExpansionPort::new(exp_io_line)))
+    fn new_expansion_port(
+        &self,
+        ba_line: Rc<RefCell<Pin>>,
+        exp_io_line: Rc<RefCell<IoPort>>,
+        irq_line: Rc<RefCell<IrqLine>>,
+        nmi_line: Rc<RefCell<IrqLine>>,
+        vic_data_bus: Rc<Cell<u8>>,
+    ) -> Rc<RefCell<dyn Addressable>> {
+        Rc::new(RefCell::new(ExpansionPort::new(
+            ba_line,
+            exp_io_line,
+            irq_line,
+            nmi_line,
+            vic_data_bus,
+        )))
     }
 
     fn new_memory(
@@ -134,6 +161,8 @@ impl ChipFactory for C64Factory {
         rom_kernal: Rc<RefCell<Rom>>,
         sid: Rc<RefCell<dyn Chip>>,
         vic: Rc<RefCell<dyn Chip>>,
+        vic_base_address: Rc<Cell<u16>>,
+        mem_watch: Rc<MemoryWatch>,
     ) -> Rc<RefCell<dyn Mmu>> {
         let io = Box::new(Mmio::new(
             cia_1,
@@ -150,6 +179,8 @@ impl ChipFactory for C64Factory {
             rom_basic,
             rom_charset,
             rom_kernal,
+            vic_base_address,
+            mem_watch,
         )))
     }
 
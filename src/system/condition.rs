@@ -6,184 +6,391 @@ use std::fmt;
 use std::iter::Peekable;
 use std::str::Chars;
 
-use core::Cpu;
+use core::{Cpu, MemoryBank, Mmu};
 
-enum Operator {
-    Equal,
-    NotEqual,
-    Greater,
-    GreaterEqual,
-    Less,
-    LessEqual,
+use super::SymbolTable;
+
+// Design:
+//   Conditions are C-like expressions over registers, flags, memory (@addr), raster position,
+//   banking state, symbols and constants. They are compiled once into postfix code and
+//   evaluated on a fixed size stack, so checking them on every instruction does not allocate.
+//   All values are unsigned 32-bit and logical operators yield 0 or 1.
+//   Names of registers, flags and state take precedence over numbers in the current radix,
+//   so hex constants that spell one of them need a '$' or leading zero, e.g. $c or 0c.
+//   Symbols can be written as .name or as a bare name that is not a number. Only the
+//   dotted form can refer to scoped labels such as .music.init.
+
+const MAX_DEPTH: usize = 16;
+
+#[derive(Clone, Copy)]
+pub struct EvalContext<'a> {
+    pub cpu: &'a dyn Cpu,
+    pub mem: &'a dyn Mmu,
+    pub raster_line: u16,
+    pub raster_cycle: u16,
+    pub vic_bank: u8,
+    pub value: u8,
 }
 
-impl fmt::Display for Operator {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &Operator::Equal => write!(f, "=="),
-            &Operator::NotEqual => write!(f, "!="),
-            &Operator::Greater => write!(f, ">"),
-            &Operator::GreaterEqual => write!(f, ">="),
-            &Operator::Less => write!(f, "<"),
-            &Operator::LessEqual => write!(f, "<="),
+impl<'a> EvalContext<'a> {
+    // Reads go through peek so that evaluating a condition never acks an interrupt or
+    // clocks a register. The processor port lives in the cpu.
+    fn peek(&self, address: u16) -> u8 {
+        match address {
+            0x0000 | 0x0001 => self.cpu.read(address),
+            _ => self.mem.peek(MemoryBank::Cpu, address),
         }
     }
 }
 
-enum Reg {
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Operand {
     A,
     X,
     Y,
     P,
     SP,
     PC,
+    Flag(u8),
+    RasterLine,
+    RasterCycle,
+    Port,
+    Bank,
+    Value,
 }
 
-impl fmt::Display for Reg {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &Reg::A => write!(f, "A"),
-            &Reg::X => write!(f, "X"),
-            &Reg::Y => write!(f, "Y"),
-            &Reg::P => write!(f, "P"),
-            &Reg::SP => write!(f, "SP"),
-            &Reg::PC => write!(f, "PC"),
+impl Operand {
+    fn from(name: &str) -> Option<Operand> {
+        match name.to_lowercase().as_str() {
+            "a" => Some(Operand::A),
+            "x" => Some(Operand::X),
+            "y" => Some(Operand::Y),
+            "p" => Some(Operand::P),
+            "sp" => Some(Operand::SP),
+            "pc" => Some(Operand::PC),
+            "c" => Some(Operand::Flag(1 << 0)),
+            "z" => Some(Operand::Flag(1 << 1)),
+            "i" => Some(Operand::Flag(1 << 2)),
+            "d" => Some(Operand::Flag(1 << 3)),
+            "b" => Some(Operand::Flag(1 << 4)),
+            "v" => Some(Operand::Flag(1 << 6)),
+            "n" => Some(Operand::Flag(1 << 7)),
+            "rl" => Some(Operand::RasterLine),
+            "cy" => Some(Operand::RasterCycle),
+            "port" => Some(Operand::Port),
+            "bank" => Some(Operand::Bank),
+            "value" => Some(Operand::Value),
+            _ => None,
+        }
+    }
+
+    fn eval(&self, ctx: &EvalContext) -> u32 {
+        match *self {
+            Operand::A => ctx.cpu.get_a() as u32,
+            Operand::X => ctx.cpu.get_x() as u32,
+            Operand::Y => ctx.cpu.get_y() as u32,
+            Operand::P => ctx.cpu.get_p() as u32,
+            Operand::SP => ctx.cpu.get_sp() as u32,
+            Operand::PC => ctx.cpu.get_pc() as u32,
+            Operand::Flag(mask) => (ctx.cpu.get_p() & mask != 0) as u32,
+            Operand::RasterLine => ctx.raster_line as u32,
+            Operand::RasterCycle => ctx.raster_cycle as u32,
+            Operand::Port => ctx.peek(0x0001) as u32,
+            Operand::Bank => ctx.vic_bank as u32,
+            Operand::Value => ctx.value as u32,
         }
     }
 }
 
-enum Value {
-    Constant(u16),
-    Register(Reg),
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum UnaryOp {
+    Not,
+    Negate,
+    Complement,
+    Peek,
 }
 
-impl fmt::Display for Value {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match self {
-            &Value::Constant(val) if val <= 0xff => write!(f, "{:02x}", val),
-            &Value::Constant(val) => write!(f, "{:04x}", val),
-            &Value::Register(ref reg) => write!(f, "{}", reg),
+impl UnaryOp {
+    fn eval(&self, value: u32, ctx: &EvalContext) -> u32 {
+        match *self {
+            UnaryOp::Not => (value == 0) as u32,
+            UnaryOp::Negate => value.wrapping_neg(),
+            UnaryOp::Complement => !value,
+            UnaryOp::Peek => ctx.peek(value as u16) as u32,
         }
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum BinaryOp {
+    Or,
+    And,
+    BitOr,
+    BitXor,
+    BitAnd,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    ShiftLeft,
+    ShiftRight,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+}
+
+impl BinaryOp {
+    fn from(op: &str) -> Option<(BinaryOp, u8)> {
+        match op {
+            "||" => Some((BinaryOp::Or, 1)),
+            "&&" => Some((BinaryOp::And, 2)),
+            "|" => Some((BinaryOp::BitOr, 3)),
+            "^" => Some((BinaryOp::BitXor, 4)),
+            "&" => Some((BinaryOp::BitAnd, 5)),
+            "==" => Some((BinaryOp::Equal, 6)),
+            "!=" => Some((BinaryOp::NotEqual, 6)),
+            "<" => Some((BinaryOp::Less, 7)),
+            "<=" => Some((BinaryOp::LessEqual, 7)),
+            ">" => Some((BinaryOp::Greater, 7)),
+            ">=" => Some((BinaryOp::GreaterEqual, 7)),
+            "<<" => Some((BinaryOp::ShiftLeft, 8)),
+            ">>" => Some((BinaryOp::ShiftRight, 8)),
+            "+" => Some((BinaryOp::Add, 9)),
+            "-" => Some((BinaryOp::Sub, 9)),
+            "*" => Some((BinaryOp::Mul, 10)),
+            "/" => Some((BinaryOp::Div, 10)),
+            "%" => Some((BinaryOp::Rem, 10)),
+            _ => None,
+        }
+    }
+
+    fn eval(&self, left: u32, right: u32) -> u32 {
+        match *self {
+            BinaryOp::Or => (left != 0 || right != 0) as u32,
+            BinaryOp::And => (left != 0 && right != 0) as u32,
+            BinaryOp::BitOr => left | right,
+            BinaryOp::BitXor => left ^ right,
+            BinaryOp::BitAnd => left & right,
+            BinaryOp::Equal => (left == right) as u32,
+            BinaryOp::NotEqual => (left != right) as u32,
+            BinaryOp::Less => (left < right) as u32,
+            BinaryOp::LessEqual => (left <= right) as u32,
+            BinaryOp::Greater => (left > right) as u32,
+            BinaryOp::GreaterEqual => (left >= right) as u32,
+            BinaryOp::ShiftLeft => left.checked_shl(right).unwrap_or(0),
+            BinaryOp::ShiftRight => left.checked_shr(right).unwrap_or(0),
+            BinaryOp::Add => left.wrapping_add(right),
+            BinaryOp::Sub => left.wrapping_sub(right),
+            BinaryOp::Mul => left.wrapping_mul(right),
+            BinaryOp::Div => left.checked_div(right).unwrap_or(0),
+            BinaryOp::Rem => left.checked_rem(right).unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Op {
+    Constant(u32),
+    Load(Operand),
+    Unary(UnaryOp),
+    Binary(BinaryOp),
+}
+
 pub struct Condition {
-    op: Operator,
-    reg: Reg,
-    val: Value,
+    code: Vec<Op>,
+    expr: String,
 }
 
 impl fmt::Display for Condition {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{} {} {}", self.reg, self.op, self.val)
+        write!(f, "{}", self.expr)
     }
 }
 
 impl Condition {
-    pub fn parse(expr: &str, radix: Option<u32>) -> Result<Condition, String> {
-        let parser = Parser::new(radix.unwrap_or(16));
+    pub fn parse(
+        expr: &str,
+        radix: Option<u32>,
+        symbols: &SymbolTable,
+    ) -> Result<Condition, String> {
+        let parser = Parser::new(radix.unwrap_or(16), symbols);
         parser.parse(expr)
     }
 
-    pub fn eval(&self, cpu: &Box<dyn Cpu>) -> bool {
-        match self.op {
-            Operator::Equal => self.eval_reg(&self.reg, cpu) == self.eval_val(&self.val, cpu),
-            Operator::NotEqual => self.eval_reg(&self.reg, cpu) != self.eval_val(&self.val, cpu),
-            Operator::Greater => self.eval_reg(&self.reg, cpu) > self.eval_val(&self.val, cpu),
-            Operator::GreaterEqual => {
-                self.eval_reg(&self.reg, cpu) >= self.eval_val(&self.val, cpu)
+    pub fn eval(&self, ctx: &EvalContext) -> bool {
+        let mut stack = [0u32; MAX_DEPTH];
+        let mut sp = 0;
+        for op in self.code.iter() {
+            match *op {
+                Op::Constant(value) => {
+                    stack[sp] = value;
+                    sp += 1;
+                }
+                Op::Load(operand) => {
+                    stack[sp] = operand.eval(ctx);
+                    sp += 1;
+                }
+                Op::Unary(op) => {
+                    stack[sp - 1] = op.eval(stack[sp - 1], ctx);
+                }
+                Op::Binary(op) => {
+                    sp -= 1;
+                    stack[sp - 1] = op.eval(stack[sp - 1], stack[sp]);
+                }
             }
-            Operator::Less => self.eval_reg(&self.reg, cpu) < self.eval_val(&self.val, cpu),
-            Operator::LessEqual => self.eval_reg(&self.reg, cpu) <= self.eval_val(&self.val, cpu),
         }
+        stack[0] != 0
     }
+}
 
-    fn eval_reg(&self, reg: &Reg, cpu: &Box<dyn Cpu>) -> u16 {
-        match reg {
-            &Reg::A => cpu.get_a() as u16,
-            &Reg::X => cpu.get_x() as u16,
-            &Reg::Y => cpu.get_y() as u16,
-            &Reg::P => cpu.get_p() as u16,
-            &Reg::SP => cpu.get_sp() as u16,
-            &Reg::PC => cpu.get_pc(),
+struct Parser<'a> {
+    radix: u32,
+    symbols: &'a SymbolTable,
+    // Runtime State
+    code: Vec<Op>,
+    depth: usize,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(radix: u32, symbols: &'a SymbolTable) -> Self {
+        Parser {
+            radix,
+            symbols,
+            code: Vec::new(),
+            depth: 0,
+            tokens: Vec::new(),
+            pos: 0,
         }
     }
 
-    fn eval_val(&self, val: &Value, cpu: &Box<dyn Cpu>) -> u16 {
-        match val {
-            &Value::Constant(value) => value,
-            &Value::Register(ref reg) => self.eval_reg(reg, cpu),
+    pub fn parse(mut self, expr: &str) -> Result<Condition, String> {
+        let tokenizer = Tokenizer::new(expr.chars());
+        self.tokens = tokenizer.collect::<Result<Vec<Token>, String>>()?;
+        if self.tokens.is_empty() {
+            return Err(format!("Invalid expression {}", expr));
+        }
+        self.parse_binary(1)?;
+        if let Some(token) = self.tokens.get(self.pos) {
+            return Err(format!("Unexpected token {}", token));
         }
+        Ok(Condition {
+            code: self.code,
+            expr: expr.trim().to_string(),
+        })
     }
-}
 
-struct Parser {
-    radix: u32,
-}
-
-impl Parser {
-    pub fn new(radix: u32) -> Self {
-        Parser { radix }
+    fn emit(&mut self, op: Op) -> Result<(), String> {
+        match op {
+            Op::Constant(_) | Op::Load(_) => self.depth += 1,
+            Op::Binary(_) => self.depth -= 1,
+            Op::Unary(_) => {}
+        }
+        if self.depth > MAX_DEPTH {
+            return Err("Expression is too complex".to_string());
+        }
+        self.code.push(op);
+        Ok(())
     }
 
-    pub fn parse(&self, expr: &str) -> Result<Condition, String> {
-        let mut tokenizer = Tokenizer::new(expr.chars());
-        let reg = match tokenizer.next() {
-            Some(Token::Atom(token)) => self.parse_reg(token.as_str()),
-            _ => Err(format!("Invalid expression {}", expr)),
-        }?;
-        let op = match tokenizer.next() {
-            Some(Token::Op(token)) => self.parse_op(token.as_str()),
-            _ => Err(format!("Invalid expression {}", expr)),
-        }?;
-        let val = match tokenizer.next() {
-            Some(Token::Atom(token)) => self.parse_val(token.as_str()),
-            _ => Err(format!("Invalid expression {}", expr)),
-        }?;
-        let condition = Condition { op, reg, val };
-        Ok(condition)
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
     }
 
-    fn parse_num(&self, num: &str) -> Result<u16, String> {
-        u16::from_str_radix(num, self.radix).map_err(|_| format!("Invalid number {}", num))
+    fn parse_binary(&mut self, min_prec: u8) -> Result<(), String> {
+        self.parse_unary()?;
+        while let Some(Token::Op(op)) = self.tokens.get(self.pos) {
+            let (op, prec) = match BinaryOp::from(op.as_str()) {
+                Some((op, prec)) if prec >= min_prec => (op, prec),
+                _ => break,
+            };
+            self.pos += 1;
+            self.parse_binary(prec + 1)?;
+            self.emit(Op::Binary(op))?;
+        }
+        Ok(())
     }
 
-    fn parse_op(&self, op: &str) -> Result<Operator, String> {
-        match op {
-            "==" => Ok(Operator::Equal),
-            "!=" => Ok(Operator::NotEqual),
-            ">" => Ok(Operator::Greater),
-            ">=" => Ok(Operator::GreaterEqual),
-            "<" => Ok(Operator::Less),
-            "<=" => Ok(Operator::LessEqual),
-            _ => Err(format!("Invalid op {}", op)),
+    fn parse_name(&self, name: &str) -> Result<Op, String> {
+        if let Some(operand) = Operand::from(name) {
+            Ok(Op::Load(operand))
+        } else if let Ok(value) = u32::from_str_radix(name, self.radix) {
+            Ok(Op::Constant(value))
+        } else {
+            self.parse_symbol(name)
         }
     }
 
-    fn parse_reg(&self, reg: &str) -> Result<Reg, String> {
-        match reg {
-            "a" | "A" => Ok(Reg::A),
-            "x" | "X" => Ok(Reg::X),
-            "y" | "Y" => Ok(Reg::Y),
-            "p" | "P" => Ok(Reg::P),
-            "sp" | "SP" => Ok(Reg::SP),
-            "pc" | "PC" => Ok(Reg::PC),
-            _ => Err(format!("Invalid register {}", reg)),
+    fn parse_symbol(&self, name: &str) -> Result<Op, String> {
+        match self.symbols.get(name) {
+            Some(address) => Ok(Op::Constant(address as u32)),
+            None => Err(format!("Unknown symbol {}", name)),
         }
     }
 
-    fn parse_val(&self, val: &str) -> Result<Value, String> {
-        match self.parse_reg(val) {
-            Ok(reg) => Ok(Value::Register(reg)),
-            Err(_) => self.parse_num(val).map(|v| Value::Constant(v)),
+    fn parse_unary(&mut self) -> Result<(), String> {
+        match self.next() {
+            Some(Token::Op(op)) => {
+                let op = match op.as_str() {
+                    "!" => UnaryOp::Not,
+                    "-" => UnaryOp::Negate,
+                    "~" => UnaryOp::Complement,
+                    "@" => UnaryOp::Peek,
+                    _ => return Err(format!("Unexpected token {}", op)),
+                };
+                self.parse_unary()?;
+                self.emit(Op::Unary(op))
+            }
+            Some(Token::LParen) => {
+                self.parse_binary(1)?;
+                match self.next() {
+                    Some(Token::RParen) => Ok(()),
+                    _ => Err("Missing )".to_string()),
+                }
+            }
+            Some(Token::Number(value)) => self.emit(Op::Constant(value)),
+            Some(Token::Name(name)) => {
+                let op = self.parse_name(name.as_str())?;
+                self.emit(op)
+            }
+            Some(Token::Symbol(name)) => {
+                let op = self.parse_symbol(name.as_str())?;
+                self.emit(op)
+            }
+            Some(token) => Err(format!("Unexpected token {}", token)),
+            None => Err("Unexpected end of expression".to_string()),
         }
     }
 }
 
-#[derive(Eq, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub enum Token {
-    Atom(String),
+    LParen,
+    Name(String),
+    Number(u32),
     Op(String),
+    RParen,
+    Symbol(String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Token::LParen => write!(f, "("),
+            Token::Name(ref name) => write!(f, "{}", name),
+            Token::Number(value) => write!(f, "${:x}", value),
+            Token::Op(ref op) => write!(f, "{}", op),
+            Token::RParen => write!(f, ")"),
+            Token::Symbol(ref name) => write!(f, ".{}", name),
+        }
+    }
 }
 
 pub struct Tokenizer<'a> {
@@ -199,17 +406,54 @@ impl<'a> Tokenizer<'a> {
 }
 
 impl<'a> Iterator for Tokenizer<'a> {
-    type Item = Token;
+    type Item = Result<Token, String>;
 
-    fn next(&mut self) -> Option<Token> {
-        match *self.iter.peek().unwrap_or(&'⊥') {
-            c if c.is_alphanumeric() => Some(Token::Atom(consume_while(&mut self.iter, |c| {
-                c.is_alphanumeric()
-            }))),
-            c if is_symbol(c) => Some(Token::Op(consume_while(&mut self.iter, |c| is_symbol(c)))),
-            c if c.is_whitespace() => self.next(),
-            '⊥' => None,
-            _ => self.next(),
+    fn next(&mut self) -> Option<Result<Token, String>> {
+        let c = *self.iter.peek()?;
+        match c {
+            c if c.is_whitespace() => {
+                self.iter.next();
+                self.next()
+            }
+            c if is_name(c) => Some(Ok(Token::Name(consume_while(&mut self.iter, is_name)))),
+            '$' => {
+                self.iter.next();
+                let digits = consume_while(&mut self.iter, |c| c.is_ascii_hexdigit());
+                Some(
+                    u32::from_str_radix(digits.as_str(), 16)
+                        .map(Token::Number)
+                        .map_err(|_| format!("Invalid number ${}", digits)),
+                )
+            }
+            '.' => {
+                self.iter.next();
+                let name = consume_while(&mut self.iter, |c| is_name(c) || c == '.');
+                Some(Ok(Token::Symbol(name)))
+            }
+            '(' => {
+                self.iter.next();
+                Some(Ok(Token::LParen))
+            }
+            ')' => {
+                self.iter.next();
+                Some(Ok(Token::RParen))
+            }
+            _ => {
+                self.iter.next();
+                let mut op = c.to_string();
+                if let Some(&next) = self.iter.peek() {
+                    let pair = format!("{}{}", c, next);
+                    if is_pair_op(pair.as_str()) {
+                        self.iter.next();
+                        op = pair;
+                    }
+                }
+                if is_pair_op(op.as_str()) || "!~-@+*/%&|^<>".contains(c) {
+                    Some(Ok(Token::Op(op)))
+                } else {
+                    Some(Err(format!("Invalid character {}", c)))
+                }
+            }
         }
     }
 }
@@ -229,10 +473,121 @@ where
     s
 }
 
-fn is_symbol(c: char) -> bool {
-    let result = match c {
-        '<' | '=' | '>' | '!' => true,
-        _ => false,
-    };
-    result
+fn is_name(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+fn is_pair_op(op: &str) -> bool {
+    ["||", "&&", "==", "!=", "<=", ">=", "<<", ">>"].contains(&op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cpu::mock::{setup_cpu_with_memory, MockMemory};
+    use std::cell::RefCell;
+
+    fn eval(
+        expr: &str,
+        cpu: &dyn Cpu,
+        mem: &RefCell<MockMemory>,
+        symbols: &SymbolTable,
+    ) -> Result<bool, String> {
+        let condition = Condition::parse(expr, None, symbols)?;
+        let mem = mem.borrow();
+        let ctx = EvalContext {
+            cpu,
+            mem: &*mem,
+            raster_line: 0x33,
+            raster_cycle: 12,
+            vic_bank: 1,
+            value: 0x42,
+        };
+        Ok(condition.eval(&ctx))
+    }
+
+    #[test]
+    fn eval_registers_and_constants() {
+        let (mut cpu, mem) = setup_cpu_with_memory();
+        cpu.set_a(0x10);
+        cpu.set_pc(0xc000);
+        let symbols = SymbolTable::new();
+        assert_eq!(Ok(true), eval("a==10", &cpu, &mem, &symbols));
+        assert_eq!(
+            Ok(true),
+            eval("PC == c000 && a < $11", &cpu, &mem, &symbols)
+        );
+        assert_eq!(Ok(false), eval("a == $c", &cpu, &mem, &symbols));
+        assert_eq!(Ok(true), eval("(a + 2) * 2 == 24", &cpu, &mem, &symbols));
+        assert_eq!(Ok(true), eval("!(a & 0f) && ~a & 1", &cpu, &mem, &symbols));
+    }
+
+    #[test]
+    fn eval_flags_memory_and_state() {
+        let (mut cpu, mem) = setup_cpu_with_memory();
+        cpu.set_p(0x81);
+        cpu.write(0xd012, 0x33);
+        let symbols = SymbolTable::new();
+        assert_eq!(Ok(true), eval("n && c && !z", &cpu, &mem, &symbols));
+        assert_eq!(
+            Ok(true),
+            eval("@$d012 == rl && cy == 0c", &cpu, &mem, &symbols)
+        );
+        assert_eq!(
+            Ok(true),
+            eval("bank == 1 || value == 0", &cpu, &mem, &symbols)
+        );
+    }
+
+    #[test]
+    fn eval_memory_without_side_effects() {
+        struct PeekOnlyMemory;
+
+        impl Mmu for PeekOnlyMemory {
+            fn switch_banks(&mut self, _mode: u8) {}
+
+            fn peek(&self, _bank: MemoryBank, address: u16) -> u8 {
+                (address >> 8) as u8
+            }
+
+            fn read(&self, _address: u16) -> u8 {
+                panic!("read during condition eval");
+            }
+
+            fn write(&mut self, _address: u16, _value: u8) {}
+        }
+
+        let (cpu, _) = setup_cpu_with_memory();
+        let condition = Condition::parse("@$dc0d == $dc && port == $ff", None, &SymbolTable::new());
+        let ctx = EvalContext {
+            cpu: &cpu,
+            mem: &PeekOnlyMemory,
+            raster_line: 0,
+            raster_cycle: 0,
+            vic_bank: 0,
+            value: 0,
+        };
+        assert_eq!(true, condition.unwrap().eval(&ctx));
+    }
+
+    #[test]
+    fn parse_symbols_and_errors() {
+        let (mut cpu, mem) = setup_cpu_with_memory();
+        cpu.set_pc(0x0810);
+        let mut symbols = SymbolTable::new();
+        symbols.insert("loop", 0x0810);
+        assert_eq!(Ok(true), eval("pc == .loop", &cpu, &mem, &symbols));
+        assert_eq!(Ok(true), eval("pc == loop", &cpu, &mem, &symbols));
+        assert_eq!(
+            Ok(true),
+            eval("@RASTER == 0 && .CHROUT == $ffd2", &cpu, &mem, &symbols)
+        );
+        assert_eq!(
+            Err("Unknown symbol start".to_string()),
+            eval("pc == start", &cpu, &mem, &symbols)
+        );
+        assert_eq!(true, eval("(a == 1", &cpu, &mem, &symbols).is_err());
+        assert_eq!(true, eval("a == 1 2", &cpu, &mem, &symbols).is_err());
+        assert_eq!(true, eval("a = 1", &cpu, &mem, &symbols).is_err());
+    }
 }
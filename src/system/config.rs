@@ -7,6 +7,7 @@ use device::joystick;
 
 pub struct Config {
     pub model: SystemModel,
+    pub datassette: DatassetteConfig,
     pub joystick: JoystickConfig,
     pub sound: SoundConfig,
 }
@@ -15,12 +16,23 @@ impl Config {
     pub fn new(model: SystemModel) -> Config {
         Config {
             model,
+            datassette: DatassetteConfig::default(),
             joystick: JoystickConfig::default(),
             sound: SoundConfig::default(),
         }
     }
 }
 
+pub struct DatassetteConfig {
+    pub fast_load: bool,
+}
+
+impl DatassetteConfig {
+    pub fn default() -> DatassetteConfig {
+        DatassetteConfig { fast_load: false }
+    }
+}
+
 pub struct JoystickConfig {
     pub axis_motion_threshold: i16,
     pub joystick_1: joystick::Mode,
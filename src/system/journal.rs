@@ -0,0 +1,179 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::collections::VecDeque;
+use std::mem;
+
+// Design:
+//   The journal enables reverse execution. For every instruction it keeps the CPU registers
+//   before the instruction and the RAM writes it made together with the replaced values, so
+//   stepping back restores the registers and undoes the writes in reverse order. Chip state
+//   and the cycle counter are not rewound. The size of the journal is the allocated capacity
+//   of the entry queue and of the write lists. The queue grows in steps that stay within the
+//   memory budget and the oldest entries are dropped once the journal would exceed it.
+
+const ENTRY_SIZE: usize = mem::size_of::<JournalEntry>();
+const MIN_CAPACITY: usize = 64;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct JournalEntry {
+    pub pc: u16,
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub sp: u8,
+    pub writes: Vec<(u16, u8, u8)>,
+}
+
+impl JournalEntry {
+    fn writes_size(&self) -> usize {
+        self.writes.capacity() * mem::size_of::<(u16, u8, u8)>()
+    }
+}
+
+pub struct Journal {
+    budget: usize,
+    entries: VecDeque<JournalEntry>,
+    writes_size: usize,
+}
+
+impl Journal {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            entries: VecDeque::new(),
+            writes_size: 0,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.writes_size = 0;
+    }
+
+    pub fn get_budget(&self) -> usize {
+        self.budget
+    }
+
+    pub fn get_size(&self) -> usize {
+        self.entries.capacity() * ENTRY_SIZE + self.writes_size
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn pop(&mut self) -> Option<JournalEntry> {
+        let entry = self.entries.pop_back();
+        if let Some(ref entry) = entry {
+            self.writes_size -= entry.writes_size();
+        }
+        entry
+    }
+
+    pub fn push(&mut self, entry: JournalEntry) {
+        if self.entries.len() == self.entries.capacity() {
+            self.grow();
+        }
+        self.writes_size += entry.writes_size();
+        self.entries.push_back(entry);
+        self.trim();
+    }
+
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.trim();
+    }
+
+    // Makes room for one more entry. The queue only grows while its capacity and the write
+    // lists fit the budget, otherwise the slot of the oldest entry is reused.
+    fn grow(&mut self) {
+        let capacity = self.entries.capacity();
+        let max_capacity = self.budget.saturating_sub(self.writes_size) / ENTRY_SIZE;
+        let new_capacity = (capacity * 2).max(MIN_CAPACITY).min(max_capacity);
+        if new_capacity > capacity {
+            let additional = new_capacity - self.entries.len();
+            self.entries.reserve_exact(additional);
+        } else {
+            self.pop_front();
+        }
+    }
+
+    fn pop_front(&mut self) -> Option<JournalEntry> {
+        let entry = self.entries.pop_front();
+        if let Some(ref entry) = entry {
+            self.writes_size -= entry.writes_size();
+        }
+        entry
+    }
+
+    fn trim(&mut self) {
+        if self.entries.capacity() * ENTRY_SIZE > self.budget {
+            while self.entries.len() * ENTRY_SIZE + self.writes_size > self.budget
+                && self.pop_front().is_some()
+            {}
+            self.entries.shrink_to_fit();
+        }
+        while self.get_size() > self.budget && self.pop_front().is_some() {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_entry(pc: u16, writes: Vec<(u16, u8, u8)>) -> JournalEntry {
+        JournalEntry {
+            pc,
+            a: 0,
+            x: 0,
+            y: 0,
+            p: 0x20,
+            sp: 0xff,
+            writes,
+        }
+    }
+
+    #[test]
+    fn push_pop_within_budget() {
+        let write_size = mem::size_of::<(u16, u8, u8)>();
+        let mut journal = Journal::new(ENTRY_SIZE * 3 + write_size * 3);
+        for pc in 0xc000..0xc004 {
+            journal.push(new_entry(pc, vec![(0x0400, 0x20, 0x01)]));
+        }
+        assert_eq!(3, journal.len());
+        assert_eq!(journal.get_budget(), journal.get_size());
+        journal.push(new_entry(
+            0xc004,
+            vec![(0x0400, 0x20, 0x01), (0x0401, 0x20, 0x02)],
+        ));
+        assert_eq!(2, journal.len());
+        assert_eq!(true, journal.get_size() <= journal.get_budget());
+        let entry = journal.pop().unwrap();
+        assert_eq!(0xc004, entry.pc);
+        assert_eq!(2, entry.writes.len());
+        assert_eq!(0xc003, journal.pop().unwrap().pc);
+        assert_eq!(None, journal.pop());
+        assert_eq!(ENTRY_SIZE * 3, journal.get_size());
+    }
+
+    #[test]
+    fn queue_capacity_stays_within_budget() {
+        let budget = ENTRY_SIZE * 100 + 0x100;
+        let mut journal = Journal::new(budget);
+        for pc in 0..1000 {
+            journal.push(new_entry(pc, vec![(pc, 0x00, 0x01)]));
+            assert_eq!(true, journal.get_size() <= budget);
+        }
+        assert_eq!(999, journal.pop().unwrap().pc);
+        journal.set_budget(ENTRY_SIZE * 10);
+        assert_eq!(true, journal.get_size() <= ENTRY_SIZE * 10);
+        assert_eq!(998, journal.pop().unwrap().pc);
+    }
+}
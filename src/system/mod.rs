@@ -10,14 +10,22 @@ mod circular_buffer;
 mod condition;
 pub mod config;
 mod frame_buffer;
+mod journal;
 mod palette;
+mod symbol_table;
+mod trace;
 
 pub use self::autostart::{Autostart, AutostartMethod, Image};
-pub use self::breakpoint::Breakpoint;
+pub use self::breakpoint::{
+    Breakpoint, Catchpoint, Interrupt, InterruptSource, StepEvents, Trigger, WatchMode, Watchpoint,
+};
 pub use self::c64::C64;
 pub use self::c64_factory::C64Factory;
 pub use self::circular_buffer::CircularBuffer;
-pub use self::condition::Condition;
+pub use self::condition::{Condition, EvalContext};
 pub use self::config::Config;
 pub use self::frame_buffer::FrameBuffer;
+pub use self::journal::{Journal, JournalEntry};
 pub use self::palette::Palette;
+pub use self::symbol_table::SymbolTable;
+pub use self::trace::{Trace, TraceEntry, TraceOutput};
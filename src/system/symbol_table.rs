@@ -0,0 +1,472 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::Read;
+use std::path::Path;
+
+// SPEC: VICE monitor labels (al C:0810 .main), ca65 ld65 -Ln (al 000810 .main),
+//   ACME --symbollist (main = $0810), KickAssembler -symbolfile (.label main=$0810),
+//   KickAssembler -debugdump (<Labels> section of C64debugger xml) and ld65 --dbgfile
+//   (sym id=0,name="main",...,val=0x810,...).
+// Design:
+//   Labels map names to addresses in both directions. Built-in names for Kernal, BASIC
+//   and I/O registers are consulted after the loaded labels, so user labels take precedence
+//   for lookups and display. Label files are detected line by line, which lets one parser
+//   handle all supported formats.
+
+static BUILTINS: &[(u16, &str)] = &[
+    // Zero Page
+    (0x0073, "CHRGET"),
+    (0x0079, "CHRGOT"),
+    // Vectors
+    (0x0314, "CINV"),
+    (0x0316, "CBINV"),
+    (0x0318, "NMINV"),
+    // BASIC
+    (0xa000, "BCOLD"),
+    (0xa002, "BWARM"),
+    (0xa474, "READY"),
+    (0xa7ae, "NEWSTT"),
+    (0xab1e, "STROUT"),
+    (0xbdcd, "LINPRT"),
+    // VIC
+    (0xd000, "SP0X"),
+    (0xd001, "SP0Y"),
+    (0xd002, "SP1X"),
+    (0xd003, "SP1Y"),
+    (0xd004, "SP2X"),
+    (0xd005, "SP2Y"),
+    (0xd006, "SP3X"),
+    (0xd007, "SP3Y"),
+    (0xd008, "SP4X"),
+    (0xd009, "SP4Y"),
+    (0xd00a, "SP5X"),
+    (0xd00b, "SP5Y"),
+    (0xd00c, "SP6X"),
+    (0xd00d, "SP6Y"),
+    (0xd00e, "SP7X"),
+    (0xd00f, "SP7Y"),
+    (0xd010, "MSIGX"),
+    (0xd011, "SCROLY"),
+    (0xd012, "RASTER"),
+    (0xd013, "LPENX"),
+    (0xd014, "LPENY"),
+    (0xd015, "SPENA"),
+    (0xd016, "SCROLX"),
+    (0xd017, "YXPAND"),
+    (0xd018, "VMCSB"),
+    (0xd019, "VICIRQ"),
+    (0xd01a, "IRQMSK"),
+    (0xd01b, "SPBGPR"),
+    (0xd01c, "SPMC"),
+    (0xd01d, "XXPAND"),
+    (0xd01e, "SPSPCL"),
+    (0xd01f, "SPBGCL"),
+    (0xd020, "EXTCOL"),
+    (0xd021, "BGCOL0"),
+    (0xd022, "BGCOL1"),
+    (0xd023, "BGCOL2"),
+    (0xd024, "BGCOL3"),
+    (0xd025, "SPMC0"),
+    (0xd026, "SPMC1"),
+    (0xd027, "SP0COL"),
+    (0xd028, "SP1COL"),
+    (0xd029, "SP2COL"),
+    (0xd02a, "SP3COL"),
+    (0xd02b, "SP4COL"),
+    (0xd02c, "SP5COL"),
+    (0xd02d, "SP6COL"),
+    (0xd02e, "SP7COL"),
+    // SID
+    (0xd400, "FRELO1"),
+    (0xd401, "FREHI1"),
+    (0xd402, "PWLO1"),
+    (0xd403, "PWHI1"),
+    (0xd404, "VCREG1"),
+    (0xd405, "ATDCY1"),
+    (0xd406, "SUREL1"),
+    (0xd407, "FRELO2"),
+    (0xd408, "FREHI2"),
+    (0xd409, "PWLO2"),
+    (0xd40a, "PWHI2"),
+    (0xd40b, "VCREG2"),
+    (0xd40c, "ATDCY2"),
+    (0xd40d, "SUREL2"),
+    (0xd40e, "FRELO3"),
+    (0xd40f, "FREHI3"),
+    (0xd410, "PWLO3"),
+    (0xd411, "PWHI3"),
+    (0xd412, "VCREG3"),
+    (0xd413, "ATDCY3"),
+    (0xd414, "SUREL3"),
+    (0xd415, "CUTLO"),
+    (0xd416, "CUTHI"),
+    (0xd417, "RESON"),
+    (0xd418, "SIGVOL"),
+    (0xd419, "POTX"),
+    (0xd41a, "POTY"),
+    (0xd41b, "RANDOM"),
+    (0xd41c, "ENV3"),
+    // CIA 1
+    (0xdc00, "CIAPRA"),
+    (0xdc01, "CIAPRB"),
+    (0xdc02, "CIDDRA"),
+    (0xdc03, "CIDDRB"),
+    (0xdc04, "TIMALO"),
+    (0xdc05, "TIMAHI"),
+    (0xdc06, "TIMBLO"),
+    (0xdc07, "TIMBHI"),
+    (0xdc08, "TODTEN"),
+    (0xdc09, "TODSEC"),
+    (0xdc0a, "TODMIN"),
+    (0xdc0b, "TODHRS"),
+    (0xdc0c, "CIASDR"),
+    (0xdc0d, "CIAICR"),
+    (0xdc0e, "CIACRA"),
+    (0xdc0f, "CIACRB"),
+    // CIA 2
+    (0xdd00, "CI2PRA"),
+    (0xdd01, "CI2PRB"),
+    (0xdd02, "C2DDRA"),
+    (0xdd03, "C2DDRB"),
+    (0xdd04, "TI2ALO"),
+    (0xdd05, "TI2AHI"),
+    (0xdd06, "TI2BLO"),
+    (0xdd07, "TI2BHI"),
+    (0xdd08, "TO2TEN"),
+    (0xdd09, "TO2SEC"),
+    (0xdd0a, "TO2MIN"),
+    (0xdd0b, "TO2HRS"),
+    (0xdd0c, "CI2SDR"),
+    (0xdd0d, "CI2ICR"),
+    (0xdd0e, "CI2CRA"),
+    (0xdd0f, "CI2CRB"),
+    // Kernal
+    (0xea31, "IRQ"),
+    (0xff81, "CINT"),
+    (0xff84, "IOINIT"),
+    (0xff87, "RAMTAS"),
+    (0xff8a, "RESTOR"),
+    (0xff8d, "VECTOR"),
+    (0xff90, "SETMSG"),
+    (0xff93, "SECOND"),
+    (0xff96, "TKSA"),
+    (0xff99, "MEMTOP"),
+    (0xff9c, "MEMBOT"),
+    (0xff9f, "SCNKEY"),
+    (0xffa2, "SETTMO"),
+    (0xffa5, "ACPTR"),
+    (0xffa8, "CIOUT"),
+    (0xffab, "UNTLK"),
+    (0xffae, "UNLSN"),
+    (0xffb1, "LISTEN"),
+    (0xffb4, "TALK"),
+    (0xffb7, "READST"),
+    (0xffba, "SETLFS"),
+    (0xffbd, "SETNAM"),
+    (0xffc0, "OPEN"),
+    (0xffc3, "CLOSE"),
+    (0xffc6, "CHKIN"),
+    (0xffc9, "CHKOUT"),
+    (0xffcc, "CLRCHN"),
+    (0xffcf, "CHRIN"),
+    (0xffd2, "CHROUT"),
+    (0xffd5, "LOAD"),
+    (0xffd8, "SAVE"),
+    (0xffdb, "SETTIM"),
+    (0xffde, "RDTIM"),
+    (0xffe1, "STOP"),
+    (0xffe4, "GETIN"),
+    (0xffe7, "CLALL"),
+    (0xffea, "UDTIM"),
+    (0xffed, "SCREEN"),
+    (0xfff0, "PLOT"),
+    (0xfff3, "IOBASE"),
+];
+
+#[derive(Clone)]
+pub struct SymbolTable {
+    labels: HashMap<u16, String>,
+    symbols: HashMap<String, u16>,
+}
+
+impl SymbolTable {
+    pub fn new() -> Self {
+        Self {
+            labels: HashMap::new(),
+            symbols: HashMap::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.labels.clear();
+        self.symbols.clear();
+    }
+
+    pub fn get(&self, name: &str) -> Option<u16> {
+        self.symbols.get(name).cloned().or_else(|| {
+            BUILTINS
+                .iter()
+                .find(|&&(_, builtin)| builtin == name)
+                .map(|&(address, _)| address)
+        })
+    }
+
+    pub fn get_label(&self, address: u16) -> Option<&str> {
+        match self.labels.get(&address) {
+            Some(name) => Some(name.as_str()),
+            None => BUILTINS
+                .iter()
+                .find(|&&(builtin, _)| builtin == address)
+                .map(|&(_, name)| name),
+        }
+    }
+
+    pub fn insert(&mut self, name: &str, address: u16) {
+        if let Some(previous) = self.symbols.insert(name.to_string(), address) {
+            self.remove_label(previous, name);
+        }
+        self.labels.insert(address, name.to_string());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    pub fn list(&self) -> Vec<(u16, &str)> {
+        let mut symbols = self
+            .symbols
+            .iter()
+            .map(|(name, &address)| (address, name.as_str()))
+            .collect::<Vec<(u16, &str)>>();
+        symbols.sort();
+        symbols
+    }
+
+    pub fn load(&mut self, path: &Path) -> io::Result<usize> {
+        info!(target: "debugger", "Loading labels from {}", path.to_str().unwrap());
+        let mut file = File::open(path)?;
+        let mut text = String::new();
+        file.read_to_string(&mut text)?;
+        Ok(self.parse(&text))
+    }
+
+    pub fn parse(&mut self, text: &str) -> usize {
+        let mut count = 0;
+        let mut scopes: Vec<String> = Vec::new();
+        let mut in_labels = false;
+        for line in text.lines() {
+            let line = line.trim();
+            let symbol = if line.starts_with("<Labels") {
+                in_labels = true;
+                None
+            } else if line.starts_with("</Labels") {
+                in_labels = false;
+                None
+            } else if in_labels {
+                parse_dbg_xml_label(line)
+            } else if line.starts_with(".label ") || line.starts_with(".namespace ") {
+                let symbol = parse_kickass_label(line);
+                if line.ends_with('{') {
+                    let scope = match symbol {
+                        Some((ref name, _)) => name.clone(),
+                        None => line[".namespace ".len()..line.len() - 1].trim().to_string(),
+                    };
+                    scopes.push(scope);
+                }
+                symbol.map(|(name, address)| {
+                    let mut path = scopes.clone();
+                    if line.ends_with('{') {
+                        path.pop();
+                    }
+                    path.push(name);
+                    (path.join("."), address)
+                })
+            } else if line == "}" {
+                scopes.pop();
+                None
+            } else {
+                parse_vice_label(line)
+                    .or_else(|| parse_dbg_sym(line))
+                    .or_else(|| parse_acme_label(line))
+            };
+            if let Some((name, address)) = symbol {
+                self.insert(name.as_str(), address);
+                count += 1;
+            }
+        }
+        count
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<u16> {
+        let address = self.symbols.remove(name);
+        if let Some(address) = address {
+            self.remove_label(address, name);
+        }
+        address
+    }
+
+    fn remove_label(&mut self, address: u16, name: &str) {
+        if self
+            .labels
+            .get(&address)
+            .map_or(false, |label| label == name)
+        {
+            self.labels.remove(&address);
+            if let Some((other, _)) = self.symbols.iter().find(|&(_, &a)| a == address) {
+                self.labels.insert(address, other.clone());
+            }
+        }
+    }
+}
+
+fn is_label(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '.' || c == '@')
+}
+
+fn parse_address(value: &str) -> Option<u16> {
+    let value = value.trim();
+    let result = if let Some(digits) = value.strip_prefix('$') {
+        u32::from_str_radix(digits, 16)
+    } else if let Some(digits) = value.strip_prefix("0x") {
+        u32::from_str_radix(digits, 16)
+    } else {
+        value.parse::<u32>()
+    };
+    result.ok().map(|address| address as u16)
+}
+
+// main = $0810 ; ?
+fn parse_acme_label(line: &str) -> Option<(String, u16)> {
+    let line = line.split(';').next().unwrap();
+    let mut parts = line.splitn(2, '=');
+    let name = parts.next()?.trim();
+    let address = parse_address(parts.next()?)?;
+    if is_label(name) {
+        Some((name.to_string(), address))
+    } else {
+        None
+    }
+}
+
+// Default,$0810,main,...
+fn parse_dbg_xml_label(line: &str) -> Option<(String, u16)> {
+    let fields = line.split(',').collect::<Vec<&str>>();
+    if fields.len() < 3 || !is_label(fields[2]) {
+        return None;
+    }
+    let address = parse_address(fields[1])?;
+    Some((fields[2].to_string(), address))
+}
+
+// sym id=0,name="main",addrsize=absolute,scope=0,def=1,ref=4,val=0x810,seg=0,type=lab
+fn parse_dbg_sym(line: &str) -> Option<(String, u16)> {
+    let mut name = None;
+    let mut address = None;
+    let mut is_label = false;
+    let fields = line.strip_prefix("sym")?;
+    if !fields.starts_with(char::is_whitespace) {
+        return None;
+    }
+    for field in fields.trim().split(',') {
+        let mut parts = field.splitn(2, '=');
+        match (parts.next(), parts.next()) {
+            (Some("name"), Some(value)) => name = Some(value.trim_matches('"').to_string()),
+            (Some("val"), Some(value)) => address = parse_address(value),
+            (Some("type"), Some(value)) => is_label = value == "lab",
+            _ => {}
+        }
+    }
+    match (name, address) {
+        (Some(name), Some(address)) if is_label => Some((name, address)),
+        _ => None,
+    }
+}
+
+// .label main=$0810
+fn parse_kickass_label(line: &str) -> Option<(String, u16)> {
+    let line = line.strip_prefix(".label ")?.trim_end_matches('{');
+    let mut parts = line.splitn(2, '=');
+    let name = parts.next()?.trim();
+    let address = parse_address(parts.next()?)?;
+    if is_label(name) {
+        Some((name.to_string(), address))
+    } else {
+        None
+    }
+}
+
+// al C:0810 .main
+fn parse_vice_label(line: &str) -> Option<(String, u16)> {
+    let mut tokens = line.split_whitespace();
+    if tokens.next() != Some("al") {
+        return None;
+    }
+    let address = tokens.next()?;
+    let address = address.strip_prefix("C:").unwrap_or(address);
+    let address = u32::from_str_radix(address, 16).ok()? as u16;
+    let name = tokens.next()?;
+    let name = name.strip_prefix('.').unwrap_or(name);
+    if is_label(name) {
+        Some((name.to_string(), address))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtins_and_labels() {
+        let mut symbols = SymbolTable::new();
+        assert_eq!(Some(0xffd2), symbols.get("CHROUT"));
+        assert_eq!(Some("EXTCOL"), symbols.get_label(0xd020));
+        symbols.insert("border", 0xd020);
+        assert_eq!(Some("border"), symbols.get_label(0xd020));
+        symbols.insert("frame", 0xd020);
+        symbols.remove("frame");
+        assert_eq!(Some("border"), symbols.get_label(0xd020));
+        symbols.remove("border");
+        assert_eq!(Some("EXTCOL"), symbols.get_label(0xd020));
+        assert_eq!(true, symbols.is_empty());
+    }
+
+    #[test]
+    fn parse_label_files() {
+        let mut symbols = SymbolTable::new();
+        assert_eq!(
+            2,
+            symbols.parse("al C:0810 .main\nal 000815 .loop\n\nfoo bar")
+        );
+        assert_eq!(Some(0x0810), symbols.get("main"));
+        assert_eq!(Some("loop"), symbols.get_label(0x0815));
+        let acme = "; ACME symbol list\n\tirq\t= $c000\n\tcount\t= 4 ; ?\n";
+        assert_eq!(2, symbols.parse(acme));
+        assert_eq!(Some(0xc000), symbols.get("irq"));
+        assert_eq!(Some(4), symbols.get("count"));
+        let kickass = ".label start=$0810\n.namespace music {\n.label init=$1000\n}\n";
+        assert_eq!(2, symbols.parse(kickass));
+        assert_eq!(Some(0x1000), symbols.get("music.init"));
+        let dbg = "<Labels values=\"SEGMENT,ADDRESS,NAME,START,END,FILE_IDX\">\n\
+                   Default,$0820,upstart,1,1,1,8,0\n</Labels>\n";
+        assert_eq!(1, symbols.parse(dbg));
+        assert_eq!(Some(0x0820), symbols.get("upstart"));
+        let ld65 = "sym\tid=0,name=\"reset\",addrsize=absolute,val=0x830,seg=0,type=lab\n\
+                    sym\tid=1,name=\"SIZE\",addrsize=zeropage,val=0x10,type=equ\n";
+        assert_eq!(1, symbols.parse(ld65));
+        assert_eq!(Some(0x0830), symbols.get("reset"));
+    }
+}
@@ -0,0 +1,141 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::collections::VecDeque;
+
+// Design:
+//   The trace keeps the machine state before each executed instruction in a bounded history.
+//   Entries only hold raw bytes so recording stays cheap and the disassembly is done by
+//   whoever displays them. An optional output receives every entry as it is recorded, which
+//   is used to stream the trace to a file.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct TraceEntry {
+    pub pc: u16,
+    pub opcode: [u8; 3],
+    pub a: u8,
+    pub x: u8,
+    pub y: u8,
+    pub p: u8,
+    pub sp: u8,
+    pub cycles: u64,
+    pub raster_line: u16,
+    pub raster_cycle: u16,
+}
+
+pub trait TraceOutput {
+    fn write(&mut self, entry: &TraceEntry);
+}
+
+pub struct Trace {
+    capacity: usize,
+    entries: VecDeque<TraceEntry>,
+    output: Option<Box<dyn TraceOutput>>,
+}
+
+impl Trace {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: VecDeque::with_capacity(capacity),
+            output: None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn last(&self, count: usize) -> Vec<TraceEntry> {
+        let skip = self.entries.len().saturating_sub(count);
+        self.entries.iter().skip(skip).cloned().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn push(&mut self, entry: TraceEntry) {
+        if let Some(ref mut output) = self.output {
+            output.write(&entry);
+        }
+        if self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        while self.entries.len() > capacity {
+            self.entries.pop_front();
+        }
+        self.capacity = capacity;
+    }
+
+    pub fn set_output(&mut self, output: Option<Box<dyn TraceOutput>>) {
+        self.output = output;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    struct MockOutput {
+        pcs: Rc<RefCell<Vec<u16>>>,
+    }
+
+    impl TraceOutput for MockOutput {
+        fn write(&mut self, entry: &TraceEntry) {
+            self.pcs.borrow_mut().push(entry.pc);
+        }
+    }
+
+    fn new_entry(pc: u16) -> TraceEntry {
+        TraceEntry {
+            pc,
+            opcode: [0xea, 0x00, 0x00],
+            a: 0,
+            x: 0,
+            y: 0,
+            p: 0x20,
+            sp: 0xff,
+            cycles: 0,
+            raster_line: 0,
+            raster_cycle: 1,
+        }
+    }
+
+    #[test]
+    fn push_keeps_last_entries() {
+        let pcs = Rc::new(RefCell::new(Vec::new()));
+        let mut trace = Trace::new(3);
+        trace.set_output(Some(Box::new(MockOutput { pcs: pcs.clone() })));
+        for pc in 0xc000..0xc005 {
+            trace.push(new_entry(pc));
+        }
+        assert_eq!(3, trace.len());
+        let history = trace.last(2);
+        assert_eq!(
+            vec![0xc003, 0xc004],
+            history.iter().map(|e| e.pc).collect::<Vec<u16>>()
+        );
+        assert_eq!(5, pcs.borrow().len());
+        trace.set_capacity(1);
+        assert_eq!(0xc004, trace.last(10)[0].pc);
+    }
+}
@@ -2,7 +2,7 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
 use bit_field::BitField;
@@ -153,6 +153,7 @@ pub struct Vic {
     // I/O
     ba_line: Rc<RefCell<Pin>>,
     irq_line: Rc<RefCell<IrqLine>>,
+    raster_position: Rc<Cell<(u16, u16)>>,
 }
 
 impl Vic {
@@ -163,6 +164,7 @@ impl Vic {
         irq_line: Rc<RefCell<IrqLine>>,
         frame_buffer: Rc<RefCell<dyn VideoOutput>>,
         mem: VicMemory,
+        raster_position: Rc<Cell<(u16, u16)>>,
     ) -> Vic {
         info!(target: "video", "Initializing VIC");
         let spec = Spec::new(chip_model);
@@ -204,6 +206,7 @@ impl Vic {
             // I/O
             ba_line,
             irq_line,
+            raster_position,
         };
         vic
     }
@@ -443,7 +446,7 @@ impl Vic {
         */
         for i in 0..8 {
             if self.sprite_units[i].expansion_flop {
-                self.raster_unit.mc_base[i] += 1;
+                self.raster_unit.mc_base[i] = self.raster_unit.mc_base[i].wrapping_add(1);
                 if self.raster_unit.mc_base[i] == 63 {
                     self.raster_unit.sprite_dma[i] = false;
                     self.sprite_units[i].display = false;
@@ -689,7 +692,7 @@ impl Chip for Vic {
                 */
                 for i in 0..8 {
                     if self.sprite_units[i].expansion_flop {
-                        self.raster_unit.mc_base[i] += 2;
+                        self.raster_unit.mc_base[i] = self.raster_unit.mc_base[i].wrapping_add(2);
                     }
                 }
                 let is_bad_line = self.raster_unit.is_bad_line;
@@ -847,6 +850,7 @@ impl Chip for Vic {
                 rt.set_sync(true);
             }
         }
+        self.raster_position.set((self.y, self.cycle));
     }
 
     fn clock_delta(&mut self, delta: u32) {
@@ -878,12 +882,13 @@ impl Chip for Vic {
         // Runtime State
         self.cycle = 1;
         self.y = 0x0100;
+        self.raster_position.set((self.y, self.cycle));
     }
 
     // I/O
 
-    fn read(&mut self, reg: u8) -> u8 {
-        let value = match reg {
+    fn peek(&self, reg: u8) -> u8 {
+        match reg {
             // Reg::M0X - Reg::M7X
             0x00 | 0x02 | 0x04 | 0x06 | 0x08 | 0x0a | 0x0c | 0x0e => {
                 (self.sprite_units[(reg >> 1) as usize].config.x & 0x00ff) as u8
@@ -980,17 +985,9 @@ impl Chip for Vic {
                 result
             }
             // Reg::MM
-            0x1e => {
-                let result = self.mux_unit.mm_collision;
-                self.mux_unit.mm_collision = 0;
-                result
-            }
+            0x1e => self.mux_unit.mm_collision,
             // Reg::MD
-            0x1f => {
-                let result = self.mux_unit.mb_collision;
-                self.mux_unit.mb_collision = 0;
-                result
-            }
+            0x1f => self.mux_unit.mb_collision,
             // Reg::EC
             0x20 => self.border_unit.config.border_color | 0xf0,
             // Reg::B0C - Reg::B3C
@@ -1000,7 +997,17 @@ impl Chip for Vic {
             // Reg::M0C - Reg::M7C
             0x27...0x2e => self.sprite_units[(reg - 0x27) as usize].config.color | 0xf0,
             _ => 0xff,
-        };
+        }
+    }
+
+    fn read(&mut self, reg: u8) -> u8 {
+        let value = self.peek(reg);
+        // Collision registers are cleared when read
+        match reg {
+            0x1e => self.mux_unit.mm_collision = 0,
+            0x1f => self.mux_unit.mb_collision = 0,
+            _ => {}
+        }
         if log_enabled!(LogLevel::Trace) {
             trace!(target: "vic::reg", "Read 0x{:02x} = 0x{:02x}", reg, value);
         }
@@ -1134,3 +1141,56 @@ impl Chip for Vic {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use core::{Addressable, IoPort, MemoryWatch, Rom};
+
+    struct NullOutput {}
+
+    impl VideoOutput for NullOutput {
+        fn get_dimension(&self) -> (usize, usize) {
+            (504, 312)
+        }
+        fn set_sync(&mut self, _value: bool) {}
+        fn write(&mut self, _index: usize, _color: u8) {}
+        fn write_at(&mut self, _x: u16, _y: u16, _color: u8) {}
+    }
+
+    fn setup_vic() -> Vic {
+        let expansion_port: Rc<RefCell<dyn Addressable>> = Rc::new(RefCell::new(Ram::new(0x10000)));
+        let mem = VicMemory::new(
+            Rc::new(Cell::new(0)),
+            Rc::new(RefCell::new(Rom::new(0x1000, 0x0000, 0x00))),
+            Rc::new(Cell::new(0)),
+            Rc::new(RefCell::new(IoPort::new(0xff, 0xff))),
+            expansion_port,
+            Rc::new(RefCell::new(Ram::new(0x10000))),
+            Rc::new(MemoryWatch::new()),
+        );
+        let mut vic = Vic::new(
+            VicModel::Mos6569,
+            Rc::new(RefCell::new(Pin::new_high())),
+            Rc::new(RefCell::new(Ram::new(0x0400))),
+            Rc::new(RefCell::new(IrqLine::new("irq"))),
+            Rc::new(RefCell::new(NullOutput {})),
+            mem,
+            Rc::new(Cell::new((0, 0))),
+        );
+        vic.reset();
+        vic
+    }
+
+    #[test]
+    fn idle_sprite_mc_base_wraps() {
+        // MCBASE keeps counting while the expansion flip flop is set, even without DMA,
+        // so it passes 255 within a frame
+        let mut vic = setup_vic();
+        for _ in 0..312 * 63 {
+            vic.clock();
+        }
+        assert_eq!(false, vic.raster_unit.sprite_dma[0]);
+        assert_eq!(true, vic.sprite_units[0].expansion_flop);
+    }
+}
@@ -5,34 +5,121 @@
 use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
-use core::{Ram, Rom};
+use core::{Addressable, IoPort, MemoryAccess, MemoryWatch, Ram, Rom};
+
+// Design:
+//   In Ultimax mode the VIC does not see the character ROM and fetches from ROMH instead of
+//   RAM in the upper 4K of each 16K bank, so it reads $F000-$FFFF of the cartridge at
+//   $3000, $7000, $B000 and $F000.
+//   The last fetched value stays on the data bus and is what the CPU reads from I/O areas
+//   that no device drives.
+//   Fetches are reported to the memory watch so watchpoints can include VIC DMA.
 
 pub struct VicMemory {
     base_address: Rc<Cell<u16>>,
     charset: Rc<RefCell<Rom>>,
+    data_bus: Rc<Cell<u8>>,
+    exp_io_line: Rc<RefCell<IoPort>>,
+    expansion_port: Rc<RefCell<dyn Addressable>>,
     ram: Rc<RefCell<Ram>>,
+    watch: Rc<MemoryWatch>,
 }
 
 impl VicMemory {
     pub fn new(
         base_address: Rc<Cell<u16>>,
         charset: Rc<RefCell<Rom>>,
+        data_bus: Rc<Cell<u8>>,
+        exp_io_line: Rc<RefCell<IoPort>>,
+        expansion_port: Rc<RefCell<dyn Addressable>>,
         ram: Rc<RefCell<Ram>>,
+        watch: Rc<MemoryWatch>,
     ) -> VicMemory {
         VicMemory {
             base_address,
             charset,
+            data_bus,
+            exp_io_line,
+            expansion_port,
             ram,
+            watch,
         }
     }
 
     pub fn read(&self, address: u16) -> u8 {
         let full_address = self.base_address.get() | address;
-        let zone = full_address >> 12;
-        match zone {
-            0x01 => self.charset.borrow().read(full_address - 0x1000),
-            0x09 => self.charset.borrow().read(full_address - 0x9000),
-            _ => self.ram.borrow().read(full_address),
+        let value = self.fetch(full_address);
+        self.data_bus.set(value);
+        self.watch.record(MemoryAccess::VicRead, full_address, value);
+        value
+    }
+
+    fn fetch(&self, full_address: u16) -> u8 {
+        if self.is_ultimax() {
+            match full_address & 0x3000 {
+                0x3000 => self.expansion_port
+                    .borrow()
+                    .read(0xf000 | (full_address & 0x0fff)),
+                _ => self.ram.borrow().read(full_address),
+            }
+        } else {
+            let zone = full_address >> 12;
+            match zone {
+                0x01 => self.charset.borrow().read(full_address - 0x1000),
+                0x09 => self.charset.borrow().read(full_address - 0x9000),
+                _ => self.ram.borrow().read(full_address),
+            }
         }
     }
+
+    fn is_ultimax(&self) -> bool {
+        self.exp_io_line.borrow().get_value() & 0x18 == 0x10
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn setup_vic_memory(exp_io_value: u8) -> (VicMemory, Rc<Cell<u8>>) {
+        let base_address = Rc::new(Cell::new(0x4000));
+        let charset = Rc::new(RefCell::new(Rom::new(0x1000, 0x0000, 0x11)));
+        let data_bus = Rc::new(Cell::new(0));
+        let exp_io_line = Rc::new(RefCell::new(IoPort::new(0xff, 0xff)));
+        exp_io_line.borrow_mut().set_value(exp_io_value);
+        let expansion_port = Rc::new(RefCell::new(Ram::new(0x10000)));
+        expansion_port.borrow_mut().fill(0x33);
+        let ram = Rc::new(RefCell::new(Ram::new(0x10000)));
+        ram.borrow_mut().fill(0x44);
+        let mem = VicMemory::new(
+            base_address,
+            charset,
+            data_bus.clone(),
+            exp_io_line,
+            expansion_port,
+            ram,
+            Rc::new(MemoryWatch::new()),
+        );
+        (mem, data_bus)
+    }
+
+    #[test]
+    fn read_ram() {
+        let (mem, _) = setup_vic_memory(0x18);
+        assert_eq!(0x44, mem.read(0x3000));
+    }
+
+    #[test]
+    fn read_sets_data_bus() {
+        let (mem, data_bus) = setup_vic_memory(0x18);
+        mem.read(0x1000);
+        assert_eq!(0x44, data_bus.get());
+    }
+
+    #[test]
+    fn read_ultimax_romh() {
+        let (mem, _) = setup_vic_memory(0x10);
+        assert_eq!(0x44, mem.read(0x2fff));
+        assert_eq!(0x33, mem.read(0x3000));
+    }
 }
@@ -4,12 +4,18 @@
 
 extern crate zinc64;
 
-use std::cell::Cell;
+use std::cell::{Cell, RefCell};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
 use std::rc::Rc;
 
-use zinc64::core::{SystemModel, TickFn};
+use zinc64::core::{MemoryBank, SystemModel, TickFn};
+use zinc64::device::{Cartridge, Chip, ChipType, FsDrive, HwType, RamStore, Reu};
 use zinc64::io::cia;
-use zinc64::system::{C64, C64Factory, Config};
+use zinc64::loader::{CartBinLoader, CartLayout, Loader, TapLoader};
+use zinc64::system::{C64, C64Factory, Config, Journal, Trace, Trigger, WatchMode};
 
 /*
 Program CIA1TAB - TA, TB, PB67 and ICR in cascaded mode
@@ -116,6 +122,375 @@ fn exec_keyboard_read() {
     }
 }
 
+#[test]
+fn exec_load_from_fs_drive() {
+    /*
+    .c000  a9 01      lda #$01
+    .c002  a2 08      ldx #$08
+    .c004  a0 01      ldy #$01
+    .c006  20 ba ff   jsr $ffba
+    .c009  a9 04      lda #$04
+    .c00b  a2 20      ldx #$20
+    .c00d  a0 c0      ldy #$c0
+    .c00f  20 bd ff   jsr $ffbd
+    .c012  a9 00      lda #$00
+    .c014  20 d5 ff   jsr $ffd5
+    .c017  4c 17 c0   jmp $c017
+    .c020  "DATA"
+    */
+    let code = [
+        0xa9u8, 0x01, 0xa2, 0x08, 0xa0, 0x01, 0x20, 0xba, 0xff, 0xa9, 0x04, 0xa2, 0x20, 0xa0,
+        0xc0, 0x20, 0xbd, 0xff, 0xa9, 0x00, 0x20, 0xd5, 0xff, 0x4c, 0x17, 0xc0,
+    ];
+    let path = env::temp_dir().join("zinc64-c64-tests-fs-drive");
+    let _ = fs::remove_dir_all(&path);
+    fs::create_dir_all(&path).unwrap();
+    fs::write(path.join("data.prg"), [0x00u8, 0x30, 0x11, 0x22, 0x33]).unwrap();
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    c64.attach_drive(Box::new(FsDrive::new(8, &path)));
+    c64.reset(false);
+    while c64.get_cpu().get_pc() != 0xa65c {
+        c64.step();
+    }
+    c64.load(&code.to_vec(), 0xc000);
+    c64.load(&b"DATA".to_vec(), 0xc020);
+    c64.get_cpu_mut().set_pc(0xc000);
+    let mut steps = 0;
+    while c64.get_cpu().get_pc() != 0xc017 {
+        c64.step();
+        steps += 1;
+        assert!(steps < 1_000_000, "load did not complete");
+    }
+    assert_eq!(0x11, c64.get_cpu().read(0x3000));
+    assert_eq!(0x22, c64.get_cpu().read(0x3001));
+    assert_eq!(0x33, c64.get_cpu().read(0x3002));
+    assert_eq!(0x40, c64.get_cpu().read(0x0090));
+}
+
+// Appends a kernal block to TAP v1 data: leader, countdown sync, data, checksum, end marker.
+fn write_tap_block(tap: &mut Vec<u8>, data: &[u8], repeat: bool) {
+    fn write_byte(tap: &mut Vec<u8>, value: u8) {
+        tap.extend_from_slice(&[0x56, 0x42]);
+        let mut parity = 1;
+        for i in 0..9 {
+            let bit = if i < 8 { (value >> i) & 1 } else { parity };
+            parity ^= bit;
+            let pair = if bit == 0 { [0x30, 0x42] } else { [0x42, 0x30] };
+            tap.extend_from_slice(&pair);
+        }
+    }
+    tap.extend_from_slice(&[0x30; 1000]);
+    let sync = if repeat { 0x09 } else { 0x89 };
+    for i in 0..9 {
+        write_byte(tap, sync - i);
+    }
+    for value in data.iter() {
+        write_byte(tap, *value);
+    }
+    write_byte(tap, data.iter().fold(0, |acc, value| acc ^ value));
+    tap.extend_from_slice(&[0x56, 0x30]);
+}
+
+fn write_tap_file(name: &str, data: &[u8]) -> PathBuf {
+    let mut tap = b"C64-TAPE-RAW\x01\x00\x00\x00".to_vec();
+    tap.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    tap.extend_from_slice(data);
+    let path = env::temp_dir().join(name);
+    fs::write(&path, tap).unwrap();
+    path
+}
+
+#[test]
+fn exec_load_from_tape_with_fast_load() {
+    /*
+    .c000  a9 01      lda #$01
+    .c002  a2 01      ldx #$01
+    .c004  a0 01      ldy #$01
+    .c006  20 ba ff   jsr $ffba
+    .c009  a9 04      lda #$04
+    .c00b  a2 20      ldx #$20
+    .c00d  a0 c0      ldy #$c0
+    .c00f  20 bd ff   jsr $ffbd
+    .c012  a9 00      lda #$00
+    .c014  20 d5 ff   jsr $ffd5
+    .c017  4c 17 c0   jmp $c017
+    .c020  "DATA"
+    */
+    let code = [
+        0xa9u8, 0x01, 0xa2, 0x01, 0xa0, 0x01, 0x20, 0xba, 0xff, 0xa9, 0x04, 0xa2, 0x20, 0xa0,
+        0xc0, 0x20, 0xbd, 0xff, 0xa9, 0x00, 0x20, 0xd5, 0xff, 0x4c, 0x17, 0xc0,
+    ];
+    let mut header = vec![0x20u8; 192];
+    header[0..5].copy_from_slice(&[0x03, 0x00, 0x30, 0x03, 0x30]);
+    header[5..9].copy_from_slice(b"DATA");
+    let mut data = Vec::new();
+    write_tap_block(&mut data, &header, false);
+    write_tap_block(&mut data, &header, true);
+    write_tap_block(&mut data, &[0x11, 0x22, 0x33], false);
+    write_tap_block(&mut data, &[0x11, 0x22, 0x33], true);
+    let path = write_tap_file("zinc64-c64-tests-load.tap", &data);
+    let mut config = Config::new(SystemModel::from("pal"));
+    config.datassette.fast_load = true;
+    let config = Rc::new(config);
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    let mut image = TapLoader::new().load(&path).unwrap();
+    image.mount(&mut c64).unwrap();
+    c64.reset(false);
+    while c64.get_cpu().get_pc() != 0xa65c {
+        c64.step();
+    }
+    c64.get_datasette().borrow_mut().play();
+    // Program mode, no kernal messages
+    c64.get_cpu_mut().write(0x009d, 0x00);
+    c64.load(&code.to_vec(), 0xc000);
+    c64.load(&b"DATA".to_vec(), 0xc020);
+    c64.get_cpu_mut().set_pc(0xc000);
+    let mut steps = 0;
+    while c64.get_cpu().get_pc() != 0xc017 {
+        c64.step();
+        steps += 1;
+        assert!(steps < 1_000_000, "load did not complete");
+    }
+    assert_eq!(0x11, c64.get_cpu().read(0x3000));
+    assert_eq!(0x22, c64.get_cpu().read(0x3001));
+    assert_eq!(0x33, c64.get_cpu().read(0x3002));
+    assert_eq!(0x00, c64.get_cpu().read(0x0090));
+    assert_eq!(false, c64.get_cpu().get_p() & 0x01 != 0);
+}
+
+struct SharedStore(Rc<RefCell<Vec<u8>>>);
+
+impl RamStore for SharedStore {
+    fn save(&mut self, data: &[u8]) -> io::Result<()> {
+        *self.0.borrow_mut() = data.to_vec();
+        Ok(())
+    }
+}
+
+#[test]
+fn exec_reu_stash() {
+    /*
+    .c000  a9 90      lda #$90
+    .c002  8d 01 df   sta $df01
+    .c005  4c 05 c0   jmp $c005
+    */
+    let code = [0xa9u8, 0x90, 0x8d, 0x01, 0xdf, 0x4c, 0x05, 0xc0];
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    let data = Rc::new(RefCell::new(Vec::new()));
+    let mut reu = Reu::new(0x20000);
+    reu.set_store(Box::new(SharedStore(data.clone())));
+    c64.attach_ram_expansion(Box::new(reu)).unwrap();
+    c64.reset(false);
+    c64.load(&code.to_vec(), 0xc000);
+    c64.load(&(0..0x40u8).collect::<Vec<u8>>(), 0xc100);
+    for &(reg, value) in [(0x02, 0x00), (0x03, 0xc1), (0x05, 0x10), (0x07, 0x40), (0x08, 0x00)]
+        .iter()
+    {
+        c64.get_cpu_mut().write(0xdf00 + reg, value);
+    }
+    c64.get_cpu_mut().set_pc(0xc000);
+    c64.step();
+    c64.step();
+    let cycles = c64.get_cycles();
+    c64.step();
+    assert_eq!(0xc005, c64.get_cpu().get_pc());
+    assert!(c64.get_cycles() - cycles >= 0x40 + 3);
+    c64.detach_ram_expansion().unwrap();
+    assert_eq!(&(0..0x40u8).collect::<Vec<u8>>()[..], &data.borrow()[0x1000..0x1040]);
+}
+
+#[test]
+fn autostart_reports_conflicting_cartridge() {
+    let path = env::temp_dir().join("zinc64-c64-tests-ross.bin");
+    fs::write(&path, vec![0u8; 0x4000]).unwrap();
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    c64.attach_ram_expansion(Box::new(Reu::new(0x20000))).unwrap();
+    let loader = CartBinLoader::new(CartLayout::Banked(HwType::Ross));
+    let mut autostart = loader.autostart(&path).unwrap();
+    assert_eq!(
+        Err("Cartridge conflicts with RamExpansion at 0xdf00".to_string()),
+        autostart.execute(&mut c64)
+    );
+}
+
+#[test]
+fn monitor_peek_keeps_cartridge_bank() {
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    let mut cartridge = Cartridge::new(0x0100, HwType::Dinamic, false, true);
+    for &(bank_number, value) in [(0u8, 0x11u8), (1, 0x22)].iter() {
+        cartridge.add(Chip {
+            chip_type: ChipType::Rom,
+            bank_number,
+            offset: 0x8000,
+            size: 0x2000,
+            data: vec![value; 0x2000],
+        });
+    }
+    c64.attach_cartridge(cartridge).unwrap();
+    c64.reset(false);
+    // Reading IO1 selects bank 1, peeking it from the monitor must not
+    for &bank in [MemoryBank::Cpu, MemoryBank::Io, MemoryBank::Cart].iter() {
+        c64.peek(bank, 0xde01);
+    }
+    assert_eq!(0x11, c64.peek(MemoryBank::Cpu, 0x8000));
+    c64.get_cpu().read(0xde01);
+    assert_eq!(0x22, c64.peek(MemoryBank::Cpu, 0x8000));
+}
+
+#[test]
+fn exec_simons_basic_bank_switching() {
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    let mut cartridge = Cartridge::new(0x0100, HwType::SimonsBasic, false, true);
+    for &(offset, value) in [(0x8000u16, 0x11u8), (0xa000, 0x22)].iter() {
+        cartridge.add(Chip {
+            chip_type: ChipType::Rom,
+            bank_number: 0,
+            offset,
+            size: 0x2000,
+            data: vec![value; 0x2000],
+        });
+    }
+    c64.attach_cartridge(cartridge).unwrap();
+    c64.reset(false);
+    assert_eq!(0x11, c64.get_cpu().read(0x8000));
+    assert_eq!(0x22, c64.get_cpu().read(0xa000));
+    // Reading IO1 releases GAME and maps BASIC back in
+    c64.get_cpu().read(0xde00);
+    c64.step();
+    assert_eq!(0x11, c64.get_cpu().read(0x8000));
+    assert_eq!(0x94, c64.get_cpu().read(0xa000));
+    // Writing IO1 asserts GAME and maps ROMH at $A000
+    c64.get_cpu_mut().write(0xde00, 0x01);
+    c64.step();
+    assert_eq!(0x22, c64.get_cpu().read(0xa000));
+}
+
+#[test]
+fn exec_watchpoint_on_store() {
+    /*
+    .c000  a2 00      ldx #$00
+    .c002  e8         inx
+    .c003  8e 20 d0   stx $d020
+    .c006  4c 02 c0   jmp $c002
+    */
+    let code = [0xa2u8, 0x00, 0xe8, 0x8e, 0x20, 0xd0, 0x4c, 0x02, 0xc0];
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    c64.load(&code.to_vec(), 0xc000);
+    c64.get_cpu_mut().write(0x0001, 0x06);
+    c64.get_cpu_mut().set_pc(0xc000);
+    let index = c64
+        .get_bpm_mut()
+        .watch(0xd020, 0xd020, WatchMode::Write, false);
+    c64.get_bpm_mut()
+        .set_condition(index, &"value == 03".to_string(), None)
+        .unwrap();
+    assert_eq!(false, c64.run_frame());
+    assert_eq!(0xc006, c64.get_cpu().get_pc());
+    assert_eq!(0x03, c64.get_cpu().get_x());
+}
+
+#[test]
+fn exec_raster_catchpoint() {
+    /*
+    .c000  4c 00 c0   jmp $c000
+    */
+    let code = [0x4cu8, 0x00, 0xc0];
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    c64.load(&code.to_vec(), 0xc000);
+    c64.get_cpu_mut().write(0x0001, 0x06);
+    c64.get_cpu_mut().set_pc(0xc000);
+    c64.get_bpm_mut().catch(Trigger::Raster(0x33, Some(12)));
+    assert_eq!(false, c64.run_frame());
+    let (line, cycle) = c64.get_raster_position();
+    assert_eq!(0x33, line);
+    assert!((12..12 + 3).contains(&cycle));
+}
+
+#[test]
+fn exec_trace_history() {
+    /*
+    .c000  a2 05      ldx #$05
+    .c002  ca         dex
+    .c003  d0 fd      bne $c002
+    */
+    let code = [0xa2u8, 0x05, 0xca, 0xd0, 0xfd];
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    c64.load(&code.to_vec(), 0xc000);
+    c64.get_cpu_mut().write(0x0001, 0x06);
+    c64.get_cpu_mut().set_pc(0xc000);
+    c64.set_trace(Some(Trace::new(4)));
+    for _ in 0..5 {
+        c64.step();
+    }
+    let history = c64.get_trace().unwrap().last(4);
+    assert_eq!(4, history.len());
+    assert_eq!(0xc002, history[2].pc);
+    assert_eq!([0xca, 0xd0, 0xfd], history[2].opcode);
+    assert_eq!(0x04, history[2].x);
+    assert_eq!(0xc003, history[3].pc);
+}
+
+#[test]
+fn exec_step_back() {
+    /*
+    .c000  a2 00      ldx #$00
+    .c002  e8         inx
+    .c003  8e 20 d0   stx $d020
+    .c006  8e 00 04   stx $0400
+    .c009  4c 02 c0   jmp $c002
+    */
+    let code = [
+        0xa2u8, 0x00, 0xe8, 0x8e, 0x20, 0xd0, 0x8e, 0x00, 0x04, 0x4c, 0x02, 0xc0,
+    ];
+    let config = Rc::new(Config::new(SystemModel::from("pal")));
+    let factory = Box::new(C64Factory::new(config.clone()));
+    let mut c64 = C64::new(config.clone(), factory).unwrap();
+    c64.load(&code.to_vec(), 0xc000);
+    c64.get_cpu_mut().write(0x0001, 0x06);
+    c64.get_cpu_mut().write(0x0400, 0x20);
+    c64.get_cpu_mut().set_pc(0xc000);
+    c64.set_journal(Some(Journal::new(0x10000)));
+    for _ in 0..9 {
+        c64.step();
+    }
+    assert_eq!(0x02, c64.get_cpu().read(0x0400));
+    assert_eq!(0xc002, c64.get_cpu().get_pc());
+    c64.get_bpm_mut()
+        .watch(0x0400, 0x0400, WatchMode::Write, false);
+    let mut steps = 0;
+    loop {
+        assert_eq!(true, c64.step_back());
+        steps += 1;
+        if c64.check_breakpoints() {
+            break;
+        }
+    }
+    assert_eq!(2, steps);
+    assert_eq!(0xc006, c64.get_cpu().get_pc());
+    assert_eq!(0x02, c64.get_cpu().get_x());
+    assert_eq!(0x01, c64.get_cpu().read(0x0400));
+    while c64.step_back() {}
+    assert_eq!(0xc000, c64.get_cpu().get_pc());
+    assert_eq!(0x20, c64.get_cpu().read(0x0400));
+}
+
 /*
 #[test]
 fn read_keyboard_s() {
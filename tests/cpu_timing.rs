@@ -7,7 +7,7 @@ extern crate zinc64;
 use std::cell::{Cell, RefCell};
 use std::rc::Rc;
 
-use zinc64::core::{Cpu, IoPort, IrqLine, Mmu, Pin, Ram, TickFn};
+use zinc64::core::{Cpu, IoPort, IrqLine, MemoryBank, Mmu, Pin, Ram, TickFn};
 use zinc64::cpu::Cpu6510;
 
 struct MockMemory {
@@ -23,6 +23,10 @@ impl MockMemory {
 impl Mmu for MockMemory {
     fn switch_banks(&mut self, _mode: u8) {}
 
+    fn peek(&self, _bank: MemoryBank, address: u16) -> u8 {
+        self.ram.read(address)
+    }
+
     fn read(&self, address: u16) -> u8 {
         self.ram.read(address)
     }
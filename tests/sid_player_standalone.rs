@@ -14,6 +14,7 @@ use zinc64::core::{
     Cpu,
     IoPort,
     IrqLine,
+    MemoryBank,
     Mmu,
     Pin,
     Ram,
@@ -48,6 +49,13 @@ impl Mmu for SimpleMemory {
         self.mode = mode;
     }
 
+    fn peek(&self, _bank: MemoryBank, address: u16) -> u8 {
+        match address {
+            0xd400..=0xd7ff => self.sid.borrow().peek((address & 0x001f) as u8),
+            _ => self.ram.borrow().read(address),
+        }
+    }
+
     fn read(&self, address: u16) -> u8 {
         match address {
             0xd400 ... 0xd7ff => self.sid.borrow_mut().read((address & 0x001f) as u8),
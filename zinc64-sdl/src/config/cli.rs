@@ -9,8 +9,13 @@ use std::result::Result;
 use getopts;
 use zinc64::core::SystemModel;
 use zinc64::device;
-use zinc64::loader::{BinLoader, Loader, Loaders};
-use zinc64::system::{C64, Config};
+use zinc64::loader::{
+    BinLoader, CartBinLoader, CartLayout, CrtLoader, Loader, Loaders, RamExpansionLoader,
+    RamExpansionType, T64Loader, TapLoader,
+};
+use zinc64::system::{C64, Config, Interrupt, InterruptSource, Journal, Trace, Trigger};
+
+use debug::TraceFile;
 
 use super::{JamAction, Options};
 
@@ -50,6 +55,11 @@ impl Cli {
                 let addr: SocketAddr = s.parse().unwrap();
                 addr
             }),
+            debug_info: matches
+                .opt_strs("labels")
+                .into_iter()
+                .filter(|path| path.to_lowercase().ends_with(".dbg"))
+                .collect(),
             jam_action: matches
                 .opt_str("jamaction")
                 .map(|s| JamAction::from(&s))
@@ -84,6 +94,10 @@ impl Cli {
 
     pub fn set_c64_options(c64: &mut C64, matches: &getopts::Matches) -> Result<(), String> {
         Cli::set_debug_options(c64, matches)?;
+        Cli::set_drive_options(c64, matches)?;
+        Cli::set_midi_options(c64, matches)?;
+        Cli::set_ram_expansion_options(c64, matches)?;
+        Cli::set_tape_options(c64, matches)?;
         Cli::set_autostart_options(c64, matches)?;
         Ok(())
     }
@@ -95,6 +109,12 @@ impl Cli {
             .optopt("", "autostart", "attach and autostart image", "path")
             .optopt("", "binary", "load binary into memory", "path")
             .optopt("", "offset", "offset at which to load binary", "address")
+            .optopt("", "t64entry", "select entry of T64 image to autostart", "index")
+            .optopt("", "carttype", "autostart image is a raw cartridge dump of this type", "[8k|16k|ultimax|type]")
+            .optopt("", "crtcreate", "save raw cartridge dump given by --autostart and --carttype as CRT image", "path")
+            .optflag("", "crtwrite", "save modified flash contents back to the CRT image")
+            .optflag("", "fasttape", "enable fast loading of standard kernal tapes")
+            .optopt("", "tapecreate", "create and attach empty tape image for recording", "path")
             // App
             .optflag("", "console", "start in console mode")
             .optflag("f", "fullscreen", "enable fullscreen")
@@ -105,17 +125,32 @@ impl Cli {
             // Device
             .optopt("", "joydev1", "set device for joystick 1", "none")
             .optopt("", "joydev2", "set device for joystick 2", "numpad")
+            .optopt("", "fsdir", "attach host directory as virtual drive", "path")
+            .optopt("", "fsdevice", "set device number of virtual drive", "8")
+            .optopt("", "reu", "attach REU with the specified size in KB", "512")
+            .optopt("", "georam", "attach GeoRAM with the specified size in KB", "512")
+            .optopt("", "neoram", "attach NeoRAM with the specified size in KB", "512")
+            .optopt("", "ramimage", "load RAM expansion contents from file and save them on exit", "path")
+            .optopt("", "reuimage", "same as --ramimage", "path")
+            .optopt("", "midi", "attach MIDI interface", "[sequential|passport|datel|namesoft|maplin]")
+            .optopt("", "midiin", "play MIDI file into the interface", "path")
+            .optopt("", "midiout", "record MIDI sent by the interface to file", "path")
+            .optflag("", "midipipe", "exchange raw MIDI bytes with pipes instead of MIDI files")
             // Sound
             .optflag("", "nosound", "disable sound playback")
             .optflag("", "nosidfilters", "disable SID filters")
             .optopt("", "soundbufsize", "set sound buffer size in samples", "4096")
             .optopt("", "soundrate", "set sound sample rate in Hz", "44100")
             // Debug
-            .optmulti("", "bp", "set breakpoint at this address", "address")
+            .optmulti("", "bp", "set breakpoint at this address, label, raster position or interrupt", "[address|label|raster:line[:cycle]|irq[:source]|nmi[:source]]")
             .optflag("d", "debug", "start debugger")
             .optopt("", "debugaddress", "start debugger bound to the specified address", "127.0.0.1:9999")
             .optopt("", "jamaction", "set cpu jam handling", "[continue|quit|reset]")
+            .optmulti("", "labels", "load labels from VICE, ACME, KickAssembler or ca65 file", "path")
             .optopt("", "rap", "start rap server bound to the specified address", "127.0.0.1:9999")
+            .optopt("", "reverse", "record execution for stepping back within this size in KB", "16384")
+            .optopt("", "trace", "keep a history of the last executed instructions", "1024")
+            .optopt("", "tracefile", "write a trace of all executed instructions to file", "path")
             // Logging
             .optopt("", "loglevel", "set log level", "[error|warn|info|debug|trace]")
             .optmulti("", "log", "set log level for a target", "target=level")
@@ -126,6 +161,7 @@ impl Cli {
     }
 
     fn parse_device_config(config: &mut Config, matches: &getopts::Matches) -> Result<(), String> {
+        config.datassette.fast_load = matches.opt_present("fasttape");
         if let Some(joydev) = matches.opt_str("joydev1") {
             config.joystick.joystick_1 = device::joystick::Mode::from(&joydev);
         }
@@ -152,12 +188,40 @@ impl Cli {
     }
 
     fn set_autostart_options(c64: &mut C64, matches: &getopts::Matches) -> Result<(), String> {
+        if matches.opt_present("crtcreate")
+            && !(matches.opt_present("autostart") && matches.opt_present("carttype"))
+        {
+            return Err("--crtcreate requires --autostart and --carttype".to_string());
+        }
         match matches.opt_str("autostart") {
             Some(image_path) => {
                 let path = Path::new(&image_path);
-                let loader = Loaders::from_path(path);
+                let loader: Box<dyn Loader> = match matches.opt_str("t64entry") {
+                    Some(entry) => {
+                        let entry = entry
+                            .parse::<usize>()
+                            .map_err(|_| format!("invalid T64 entry {}", entry))?;
+                        Box::new(T64Loader::with_entry(entry))
+                    }
+                    None if matches.opt_present("carttype") => {
+                        let layout = CartLayout::from(&matches.opt_str("carttype").unwrap())?;
+                        let loader = CartBinLoader::new(layout);
+                        if let Some(crt_path) = matches.opt_str("crtcreate") {
+                            loader
+                                .convert(path, Path::new(&crt_path))
+                                .map_err(|err| format!("{}", err))?;
+                        }
+                        Box::new(loader)
+                    }
+                    None if matches.opt_present("crtwrite")
+                        && path.extension().and_then(|ext| ext.to_str()) == Some("crt") =>
+                    {
+                        Box::new(CrtLoader::with_write_back())
+                    }
+                    None => Loaders::from_path(path),
+                };
                 let mut autostart = loader.autostart(path).map_err(|err| format!("{}", err))?;
-                autostart.execute(c64);
+                autostart.execute(c64)?;
             }
             None => match matches.opt_str("binary") {
                 Some(binary_path) => {
@@ -168,7 +232,7 @@ impl Cli {
                     let path = Path::new(&binary_path);
                     let loader = BinLoader::new(offset);
                     let mut image = loader.load(path).map_err(|err| format!("{}", err))?;
-                    image.mount(c64);
+                    image.mount(c64)?;
                 }
                 None => {}
             },
@@ -176,12 +240,172 @@ impl Cli {
         Ok(())
     }
 
+    fn set_drive_options(c64: &mut C64, matches: &getopts::Matches) -> Result<(), String> {
+        if let Some(dir) = matches.opt_str("fsdir") {
+            let device_number = match matches.opt_str("fsdevice") {
+                Some(s) => s
+                    .parse::<u8>()
+                    .map_err(|_| format!("invalid drive device number {}", s))?,
+                None => 8,
+            };
+            if !(8..=30).contains(&device_number) {
+                return Err(format!("invalid drive device number {}", device_number));
+            }
+            let path = Path::new(&dir);
+            if !path.is_dir() {
+                return Err(format!("invalid drive directory {}", dir));
+            }
+            c64.attach_drive(Box::new(device::FsDrive::new(device_number, path)));
+        }
+        Ok(())
+    }
+
+    fn set_midi_options(c64: &mut C64, matches: &getopts::Matches) -> Result<(), String> {
+        if let Some(name) = matches.opt_str("midi") {
+            let midi_type = device::MidiType::from(&name)?;
+            let clock_freq = c64.get_config().model.cpu_freq;
+            let input = matches.opt_str("midiin");
+            let output = matches.opt_str("midiout");
+            let input_path = input.as_ref().map(Path::new);
+            let output_path = output.as_ref().map(Path::new);
+            let backend: Box<dyn device::MidiBackend> = if matches.opt_present("midipipe") {
+                Box::new(
+                    device::midi::PipeBackend::new(input_path, output_path)
+                        .map_err(|err| format!("{}", err))?,
+                )
+            } else {
+                Box::new(
+                    device::midi::SmfBackend::new(input_path, output_path, clock_freq)
+                        .map_err(|err| format!("{}", err))?,
+                )
+            };
+            c64.attach_midi(device::Midi::new(midi_type, backend, clock_freq))?;
+        }
+        Ok(())
+    }
+
+    fn set_ram_expansion_options(c64: &mut C64, matches: &getopts::Matches) -> Result<(), String> {
+        let selected = ["reu", "georam", "neoram"]
+            .iter()
+            .filter(|name| matches.opt_present(name))
+            .cloned()
+            .collect::<Vec<&str>>();
+        if selected.len() > 1 {
+            return Err("only one RAM expansion can be attached".to_string());
+        }
+        let image_path = matches
+            .opt_str("ramimage")
+            .or_else(|| matches.opt_str("reuimage"));
+        if selected.is_empty() && image_path.is_none() {
+            return Ok(());
+        }
+        let name = selected.first().cloned().unwrap_or("reu");
+        let expansion_type = RamExpansionType::from(name)?;
+        let size = match matches.opt_str(name) {
+            Some(size) => size
+                .parse::<usize>()
+                .map_err(|_| format!("invalid {} size {}", name, size))?,
+            None => 512,
+        };
+        let loader = RamExpansionLoader::new(expansion_type, size * 1024);
+        match image_path {
+            Some(image_path) => {
+                let mut image = loader
+                    .load(Path::new(&image_path))
+                    .map_err(|err| format!("{}", err))?;
+                image.mount(c64)?;
+            }
+            None => {
+                let device = loader
+                    .build_device(&[], None)
+                    .map_err(|err| format!("{}", err))?;
+                c64.attach_ram_expansion(device)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn set_tape_options(c64: &mut C64, matches: &getopts::Matches) -> Result<(), String> {
+        if let Some(tape_path) = matches.opt_str("tapecreate") {
+            let path = Path::new(&tape_path);
+            let loader = TapLoader::new();
+            let mut image = loader.create(path).map_err(|err| format!("{}", err))?;
+            image.mount(c64)?;
+        }
+        Ok(())
+    }
+
     fn set_debug_options(c64: &mut C64, matches: &getopts::Matches) -> Result<(), String> {
-        let bps_strs = matches.opt_strs("bp");
-        let bps = bps_strs.iter().map(|s| s.parse::<u16>().unwrap());
-        for bp in bps {
-            c64.get_bpm_mut().set(bp, false);
+        for path in matches.opt_strs("labels") {
+            c64.get_bpm_mut()
+                .get_symbols_mut()
+                .load(Path::new(&path))
+                .map_err(|err| format!("failed to load labels from {}, error - {}", path, err))?;
+        }
+        for bp in matches.opt_strs("bp") {
+            let parts = bp.split(':').collect::<Vec<&str>>();
+            let bpm = c64.get_bpm_mut();
+            match (parts[0], parts.len()) {
+                ("raster", 2) => {
+                    bpm.catch(Trigger::Raster(Cli::parse_number(parts[1])?, None));
+                }
+                ("raster", 3) => {
+                    let line = Cli::parse_number(parts[1])?;
+                    let cycle = Cli::parse_number(parts[2])?;
+                    bpm.catch(Trigger::Raster(line, Some(cycle)));
+                }
+                ("irq", _) | ("nmi", _) if parts.len() <= 2 => {
+                    let kind = if parts[0] == "irq" {
+                        Interrupt::Irq
+                    } else {
+                        Interrupt::Nmi
+                    };
+                    let source = match parts.get(1) {
+                        Some(name) => Some(InterruptSource::from(name)?),
+                        None => None,
+                    };
+                    bpm.catch(Trigger::Interrupt(kind, source));
+                }
+                (address, 1) => {
+                    let address = match Cli::parse_number(address) {
+                        Ok(address) => address,
+                        Err(error) => bpm.get_symbols().get(address).ok_or(error)?,
+                    };
+                    bpm.set(address, false);
+                }
+                _ => return Err(format!("invalid breakpoint {}", bp)),
+            }
+        }
+        if let Some(size) = matches.opt_str("reverse") {
+            let size = size
+                .parse::<usize>()
+                .map_err(|_| format!("invalid reverse size {}", size))?;
+            c64.set_journal(Some(Journal::new(size * 1024)));
+        }
+        if matches.opt_present("trace") || matches.opt_present("tracefile") {
+            let size = match matches.opt_str("trace") {
+                Some(size) => size
+                    .parse::<usize>()
+                    .map_err(|_| format!("invalid trace size {}", size))?,
+                None => 1024,
+            };
+            let mut trace = Trace::new(size);
+            if let Some(path) = matches.opt_str("tracefile") {
+                let file = TraceFile::new(Path::new(&path)).map_err(|err| format!("{}", err))?;
+                trace.set_output(Some(Box::new(file)));
+            }
+            c64.set_trace(Some(trace));
         }
         Ok(())
     }
+
+    fn parse_number(value: &str) -> Result<u16, String> {
+        let hex = value.strip_prefix('$').or_else(|| value.strip_prefix("0x"));
+        let result = if let Some(digits) = hex {
+            u16::from_str_radix(digits, 16)
+        } else {
+            value.parse::<u16>()
+        };
+        result.map_err(|_| format!("invalid number {}", value))
+    }
 }
@@ -33,6 +33,7 @@ pub struct Options {
     // Debug
     pub debug: bool,
     pub dbg_address: Option<SocketAddr>,
+    pub debug_info: Vec<String>,
     pub jam_action: JamAction,
     pub rap_address: Option<SocketAddr>,
 }
@@ -0,0 +1,264 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use zinc64::system::SymbolTable;
+
+use super::disassembler::{Mode, OPCODES};
+
+// Design:
+//   Lines are assembled one instruction at a time using the opcode table of the
+//   disassembler. The operand syntax determines a list of candidate addressing modes and
+//   the shortest one that exists for the mnemonic is picked, so zero page forms are used
+//   whenever the value fits. Values are numbers in the monitor radix, $hex, labels or their
+//   low (<) and high (>) bytes. Common alternative names of undocumented opcodes are
+//   accepted as well.
+
+const OPCODE_NOP: u8 = 0xea;
+
+pub fn assemble(
+    line: &str,
+    address: u16,
+    radix: u32,
+    symbols: &SymbolTable,
+) -> Result<Vec<u8>, String> {
+    let mut parts = line.trim().splitn(2, char::is_whitespace);
+    let mnemonic = parts.next().unwrap_or("").to_lowercase();
+    let mnemonic = canonical_name(mnemonic.as_str());
+    if !OPCODES.iter().any(|&(name, _)| name == mnemonic) {
+        return Err(format!("Unknown mnemonic {}", mnemonic));
+    }
+    let operand = parts
+        .next()
+        .unwrap_or("")
+        .split_whitespace()
+        .collect::<String>()
+        .replace(",X", ",x")
+        .replace(",Y", ",y");
+    let (modes, value) = parse_operand(operand.as_str(), radix, symbols)?;
+    for mode in modes {
+        let fits = match mode {
+            Mode::ZeroPage | Mode::ZeroPageX | Mode::ZeroPageY => value <= 0xff,
+            _ => true,
+        };
+        if !fits {
+            continue;
+        }
+        if let Some(opcode) = find_opcode(mnemonic, mode) {
+            return encode(opcode, mode, value, address);
+        }
+    }
+    Err(format!("Invalid addressing mode for {}", mnemonic))
+}
+
+fn canonical_name(mnemonic: &str) -> &str {
+    match mnemonic {
+        "aso" => "slo",
+        "rln" => "rla",
+        "lse" => "sre",
+        "sxa" | "xas" => "shx",
+        "sya" | "say" => "shy",
+        "dcm" => "dcp",
+        "isb" | "ins" => "isc",
+        "asr" => "alr",
+        "ane" => "xaa",
+        "lxa" => "lax",
+        "sbx" => "axs",
+        "sha" => "ahx",
+        "shs" => "tas",
+        "lar" | "lae" => "las",
+        "jam" | "hlt" => "kil",
+        "dop" | "top" => "nop",
+        _ => mnemonic,
+    }
+}
+
+fn encode(opcode: u8, mode: Mode, value: u16, address: u16) -> Result<Vec<u8>, String> {
+    let bytes = match mode {
+        Mode::Implied | Mode::Accumulator => vec![opcode],
+        Mode::Relative => {
+            let offset = value.wrapping_sub(address.wrapping_add(2)) as i16;
+            if !(-128..=127).contains(&offset) {
+                return Err(format!("Branch target ${:04x} out of range", value));
+            }
+            vec![opcode, offset as u8]
+        }
+        _ if mode.len() == 3 => vec![opcode, value as u8, (value >> 8) as u8],
+        _ => vec![opcode, value as u8],
+    };
+    Ok(bytes)
+}
+
+fn find_opcode(mnemonic: &str, mode: Mode) -> Option<u8> {
+    // Prefer the documented nop over its undocumented duplicates
+    if mnemonic == "nop" && mode == Mode::Implied {
+        return Some(OPCODE_NOP);
+    }
+    OPCODES
+        .iter()
+        .position(|&(name, opcode_mode)| name == mnemonic && opcode_mode == mode)
+        .map(|opcode| opcode as u8)
+}
+
+fn parse_operand(
+    operand: &str,
+    radix: u32,
+    symbols: &SymbolTable,
+) -> Result<(Vec<Mode>, u16), String> {
+    let operand = if operand.starts_with("$(") {
+        &operand[1..]
+    } else {
+        operand
+    };
+    if operand.is_empty() {
+        return Ok((vec![Mode::Implied, Mode::Accumulator], 0));
+    }
+    if operand.to_lowercase() == "a" || operand.to_lowercase() == "acc" {
+        return Ok((vec![Mode::Accumulator], 0));
+    }
+    if let Some(value) = operand.strip_prefix('#') {
+        let value = parse_value(value, radix, symbols)?;
+        if value > 0xff {
+            return Err(format!("Invalid immediate value {}", operand));
+        }
+        return Ok((vec![Mode::Immediate], value));
+    }
+    if let Some(inner) = operand.strip_prefix('(') {
+        let (mode, value) = if let Some(value) = inner.strip_suffix(",x)") {
+            (Mode::IndirectX, value)
+        } else if let Some(value) = inner.strip_suffix("),y") {
+            (Mode::IndirectY, value)
+        } else if let Some(value) = inner.strip_suffix(')') {
+            (Mode::Indirect, value)
+        } else {
+            return Err(format!("Invalid operand {}", operand));
+        };
+        let value = parse_value(value, radix, symbols)?;
+        if mode != Mode::Indirect && value > 0xff {
+            return Err(format!("Invalid zero page address {}", operand));
+        }
+        return Ok((vec![mode], value));
+    }
+    let (modes, value) = if let Some(value) = operand.strip_suffix(",x") {
+        (vec![Mode::ZeroPageX, Mode::AbsoluteX], value)
+    } else if let Some(value) = operand.strip_suffix(",y") {
+        (vec![Mode::ZeroPageY, Mode::AbsoluteY], value)
+    } else {
+        (
+            vec![Mode::Relative, Mode::ZeroPage, Mode::Absolute],
+            operand,
+        )
+    };
+    Ok((modes, parse_value(value, radix, symbols)?))
+}
+
+fn parse_value(value: &str, radix: u32, symbols: &SymbolTable) -> Result<u16, String> {
+    if let Some(value) = value.strip_prefix('<') {
+        return parse_value(value, radix, symbols).map(|value| value & 0xff);
+    }
+    if let Some(value) = value.strip_prefix('>') {
+        return parse_value(value, radix, symbols).map(|value| value >> 8);
+    }
+    let result = if let Some(digits) = value.strip_prefix('$') {
+        u16::from_str_radix(digits, 16).ok()
+    } else if let Some(name) = value.strip_prefix('.') {
+        symbols.get(name)
+    } else {
+        u16::from_str_radix(value, radix)
+            .ok()
+            .or_else(|| symbols.get(value))
+    };
+    result.ok_or_else(|| format!("Invalid value {}", value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::disassembler::Disassembler;
+    use super::*;
+
+    fn asm(line: &str) -> Result<Vec<u8>, String> {
+        let mut symbols = SymbolTable::new();
+        symbols.insert("loop", 0xc010);
+        assemble(line, 0xc000, 16, &symbols)
+    }
+
+    #[test]
+    fn assemble_addressing_modes() {
+        assert_eq!(Ok(vec![0xa9, 0x01]), asm("lda #$01"));
+        assert_eq!(Ok(vec![0xa5, 0x12]), asm("LDA 12"));
+        assert_eq!(Ok(vec![0xad, 0x34, 0x12]), asm("lda $1234"));
+        assert_eq!(Ok(vec![0xb5, 0x12]), asm("lda $12, X"));
+        assert_eq!(Ok(vec![0xb9, 0x12, 0x00]), asm("lda $12,y"));
+        assert_eq!(Ok(vec![0xa1, 0x12]), asm("lda ($12,x)"));
+        assert_eq!(Ok(vec![0xb1, 0x12]), asm("lda ($12),y"));
+        assert_eq!(Ok(vec![0x6c, 0x34, 0x12]), asm("jmp ($1234)"));
+        assert_eq!(Ok(vec![0x0a]), asm("asl"));
+        assert_eq!(Ok(vec![0x0a]), asm("asl a"));
+        assert_eq!(Ok(vec![0xea]), asm("nop"));
+    }
+
+    #[test]
+    fn assemble_branches_and_labels() {
+        assert_eq!(Ok(vec![0xd0, 0x0e]), asm("bne loop"));
+        assert_eq!(Ok(vec![0xd0, 0xfe]), asm("bne $c000"));
+        assert_eq!(Ok(vec![0x8d, 0x10, 0xc0]), asm("sta .loop"));
+        assert_eq!(Ok(vec![0xa9, 0x10]), asm("lda #<loop"));
+        assert_eq!(Ok(vec![0xa9, 0xc0]), asm("lda #>loop"));
+        assert_eq!(
+            Err("Branch target $c100 out of range".to_string()),
+            asm("bne $c100")
+        );
+    }
+
+    #[test]
+    fn assemble_undocumented_aliases() {
+        assert_eq!(Ok(vec![0x9e, 0x34, 0x12]), asm("sxa $1234,y"));
+        assert_eq!(Ok(vec![0x9e, 0x34, 0x12]), asm("xas $1234,y"));
+        assert_eq!(Ok(vec![0x9c, 0x34, 0x12]), asm("sya $1234,x"));
+        assert_eq!(Ok(vec![0x9c, 0x34, 0x12]), asm("say $1234,x"));
+        assert_eq!(Ok(vec![0x87, 0x12]), asm("sax $12"));
+        assert_eq!(Ok(vec![0xcb, 0x12]), asm("sbx #$12"));
+        assert_eq!(Ok(vec![0xe7, 0x12]), asm("isb $12"));
+        assert_eq!(Err("Unknown mnemonic axr".to_string()), asm("axr $12"));
+        assert_eq!(Err("Unknown mnemonic rrd".to_string()), asm("rrd $12"));
+    }
+
+    #[test]
+    fn assemble_errors() {
+        assert_eq!(
+            Err("Invalid addressing mode for sta".to_string()),
+            asm("sta #$01")
+        );
+        assert_eq!(
+            Err("Invalid immediate value #$100".to_string()),
+            asm("lda #$100")
+        );
+        assert_eq!(Err("Invalid value start".to_string()), asm("jmp start"));
+    }
+
+    #[test]
+    fn assemble_disassembled_opcodes() {
+        for opcode in 0..=255u8 {
+            let mode = OPCODES[opcode as usize].1;
+            let data = match mode.len() {
+                3 => vec![opcode, 0x34, 0x12],
+                2 => vec![opcode, 0x12],
+                _ => vec![opcode],
+            };
+            let (instr, _) = Disassembler::new(data.clone(), 0xc000).disassemble(0xc000);
+            let line = match mode {
+                Mode::Relative => {
+                    format!("{} ${:04x}", instr.get_mnemonic(), instr.get_target(0xc000))
+                }
+                _ => instr.to_string(),
+            };
+            let bytes = asm(line.as_str()).unwrap();
+            assert_eq!(
+                OPCODES[opcode as usize], OPCODES[bytes[0] as usize],
+                "{}",
+                line
+            );
+            assert_eq!(&data[1..], &bytes[1..], "{}", line);
+        }
+    }
+}
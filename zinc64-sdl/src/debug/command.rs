@@ -4,6 +4,9 @@
 
 use std::sync::mpsc::Sender;
 
+use zinc64::core::MemoryBank;
+use zinc64::system::{SymbolTable, TraceEntry, Trigger, WatchMode};
+
 pub enum Command {
     Attach(Sender<CommandResult>),
     Detach,
@@ -18,13 +21,30 @@ pub enum Command {
     BpList,
     BpRemove(u16),
     BpSet(u16, bool),
+    // Catchpoint
+    CpList,
+    CpSet(Trigger, Option<String>, u32),
+    // Symbol
+    SymAdd(String, u16),
+    SymClear,
+    SymLoad(String),
+    SymRead,
+    SymRemove(String),
+    // Watchpoint
+    WpList,
+    WpSet(u16, u16, WatchMode, bool, Option<String>, u32),
     // Debugger
     Continue,
     RegRead,
     RegWrite(Vec<RegOp>),
+    ReverseContinue,
+    ReverseSet(Option<usize>),
     Step,
+    StepBack,
+    TraceRead(usize),
+    TraceSet(Option<usize>),
     // Memory
-    MemRead(u16, u16),
+    MemRead(MemoryBank, u16, u16),
     MemWrite(u16, Vec<u8>),
     // System
     SysQuit,
@@ -39,7 +59,9 @@ pub enum CommandResult {
     Error(String),
     Number(u16),
     Registers(RegData),
+    Symbols(SymbolTable),
     Text(String),
+    Trace(Vec<TraceEntry>),
     Unit,
 }
 
@@ -52,6 +74,8 @@ pub struct RegData {
     pub pc: u16,
     pub port_00: u8,
     pub port_01: u8,
+    pub raster_line: u16,
+    pub raster_cycle: u16,
 }
 
 pub enum RegOp {
@@ -2,26 +2,36 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
+use std::fs;
 use std::io;
 use std::io::{BufRead, BufReader, BufWriter, Cursor, Error, ErrorKind, Write};
 use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::Path;
 use std::sync::mpsc;
 use std::u16;
 use std::u8;
 
 use bit_field::BitField;
 use byteorder::{BigEndian, ReadBytesExt};
-use zinc64::cpu::Instruction;
+use zinc64::core::MemoryBank;
+use zinc64::loader::{BinLoader, P00Loader, PrgLoader};
+use zinc64::system::{Interrupt, InterruptSource, SymbolTable, Trigger, WatchMode};
 
+use super::assembler;
 use super::charset;
 use super::command::{Command, CommandResult, RegData, RegOp};
-use super::disassembler::Disassembler;
+use super::disassembler;
+use super::disassembler::{Disassembler, Instruction};
+use super::source_map::SourceMap;
+use super::trace;
 
 // SPEC: Vice -> Alt-H -> help -> [Enter]
 
 const OPCODE_JSR: u8 = 0x20;
 const OPCODE_RTI: u8 = 0x40;
 const OPCODE_RTS: u8 = 0x60;
+const REVERSE_BUDGET: usize = 16384;
+const TRACE_SIZE: usize = 1024;
 
 // TODO debugger: print triggered breakpoint
 
@@ -35,22 +45,46 @@ pub enum Cmd {
     BpList,
     BpSet(u16),
     BpUntil(u16),
+    // Catchpoint
+    CpList,
+    CpSet(Trigger, Option<String>),
+    // Label
+    LabelAdd(u16, String),
+    LabelClear,
+    LabelDelete(String),
+    LabelList,
+    LabelLoad(String),
+    // Watchpoint
+    WpList,
+    WpSet(u16, u16, WatchMode, bool, Option<String>),
     // Debugger
+    Back(u16),
     Goto(Option<u16>),
+    History(u16),
+    List(Option<(String, usize)>),
     Next(u16),
     RegRead,
     RegWrite(Vec<RegOp>),
     Return,
+    Reverse(Option<usize>),
+    ReverseContinue,
     Step(u16),
+    Trace(Option<usize>),
     // Memory
+    Assemble(u16, Option<String>),
+    Bank(Option<MemoryBank>),
     Compare(u16, u16, u16),
     Disassemble(Option<u16>, Option<u16>),
     Fill(u16, u16, Vec<u8>),
     Hunt(u16, u16, Vec<u8>),
+    Load(String, Option<u16>),
+    LoadBin(String, u16),
     Memory(Option<u16>, Option<u16>),
     MemChar(Option<u16>),
     Move(u16, u16, u16),
     Petscii(u16, Option<u16>),
+    Save(String, u16, u16),
+    SaveBin(String, u16, u16),
     // System
     Reset(bool),
     Screen,
@@ -64,11 +98,15 @@ pub enum Cmd {
 
 pub struct Debugger {
     command_tx: mpsc::Sender<Command>,
+    debug_info: Vec<String>,
 }
 
 impl Debugger {
-    pub fn new(command_tx: mpsc::Sender<Command>) -> Self {
-        Self { command_tx }
+    pub fn new(command_tx: mpsc::Sender<Command>, debug_info: Vec<String>) -> Self {
+        Self {
+            command_tx,
+            debug_info,
+        }
     }
 
     pub fn start(&self, addr: SocketAddr) -> io::Result<()> {
@@ -77,6 +115,7 @@ impl Debugger {
             match stream {
                 Ok(stream) => {
                     let mut conn = Connection::new(self.command_tx.clone(), stream).unwrap();
+                    conn.load_debug_info(&self.debug_info);
                     match conn.handle() {
                         Ok(_) => info!(target: "debugger", "Connection closed"),
                         Err(error) => {
@@ -101,6 +140,7 @@ struct Connection {
     response_rx: mpsc::Receiver<CommandResult>,
     response_tx: mpsc::Sender<CommandResult>,
     // Runtime State
+    bank: MemoryBank,
     regs: Option<RegData>,
     running: bool,
 }
@@ -117,6 +157,7 @@ impl Connection {
             command_tx,
             response_rx,
             response_tx,
+            bank: MemoryBank::Cpu,
             regs: None,
             running: true,
         };
@@ -126,6 +167,7 @@ impl Connection {
     pub fn handle(&mut self) -> io::Result<()> {
         let tx = self.response_tx.clone();
         self.execute_unit_cmd(Command::Attach(tx))?;
+        self.read_symbols()?;
         while self.running {
             self.regs = Some(self.read_regs()?);
             self.write_prompt()?;
@@ -149,22 +191,48 @@ impl Connection {
             Cmd::BpList => self.cmd_bp_list(),
             Cmd::BpSet(address) => self.cmd_bp_set(address),
             Cmd::BpUntil(address) => self.cmd_bp_until(address),
+            // Catchpoint
+            Cmd::CpList => self.cmd_cp_list(),
+            Cmd::CpSet(trigger, condition) => self.cmd_cp_set(trigger, condition),
+            // Label
+            Cmd::LabelAdd(address, name) => self.cmd_label_add(address, name),
+            Cmd::LabelClear => self.cmd_label_clear(),
+            Cmd::LabelDelete(name) => self.cmd_label_delete(name),
+            Cmd::LabelList => self.cmd_label_list(),
+            Cmd::LabelLoad(path) => self.cmd_label_load(path),
+            // Watchpoint
+            Cmd::WpList => self.cmd_wp_list(),
+            Cmd::WpSet(start, end, mode, vic, condition) => {
+                self.cmd_wp_set(start, end, mode, vic, condition)
+            }
             // Debugger
+            Cmd::Back(count) => self.cmd_back(count),
             Cmd::Goto(address) => self.cmd_goto(address),
+            Cmd::History(count) => self.cmd_history(count),
+            Cmd::List(location) => self.cmd_list(location),
             Cmd::Next(count) => self.cmd_next(count),
             Cmd::RegRead => self.cmd_reg_read(),
             Cmd::RegWrite(ops) => self.cmd_reg_write(ops),
             Cmd::Return => self.cmd_return(),
+            Cmd::Reverse(budget) => self.cmd_reverse(budget),
+            Cmd::ReverseContinue => self.cmd_reverse_continue(),
             Cmd::Step(count) => self.cmd_step(count),
+            Cmd::Trace(capacity) => self.cmd_trace(capacity),
             // Memory
+            Cmd::Assemble(address, source) => self.cmd_assemble(address, source),
+            Cmd::Bank(bank) => self.cmd_bank(bank),
             Cmd::Compare(start, end, target) => self.cmd_compare(start, end, target),
             Cmd::Disassemble(start, end) => self.cmd_disassemble(start, end),
             Cmd::Fill(start, end, data) => self.cmd_fill(start, end, data),
             Cmd::Hunt(start, end, data) => self.cmd_hunt(start, end, data),
+            Cmd::Load(path, address) => self.cmd_load(path, address),
+            Cmd::LoadBin(path, address) => self.cmd_load_bin(path, address),
             Cmd::Memory(start, end) => self.cmd_memory(start, end),
             Cmd::MemChar(address) => self.cmd_memchar(address),
             Cmd::Move(start, end, target) => self.cmd_move(start, end, target),
             Cmd::Petscii(start, end) => self.cmd_petscii(start, end),
+            Cmd::Save(path, start, end) => self.cmd_save(path, start, end),
+            Cmd::SaveBin(path, start, end) => self.cmd_save_bin(path, start, end),
             // System
             Cmd::Reset(hard) => self.cmd_reset(hard),
             Cmd::Screen => self.cmd_screen(),
@@ -186,14 +254,28 @@ impl Connection {
         }
     }
 
-    fn read_mem(&mut self, start: u16, end: u16) -> io::Result<Vec<u8>> {
-        match self.execute_emu(Command::MemRead(start, end))? {
+    fn load_debug_info(&mut self, paths: &[String]) {
+        let source_map = self.command_parser.get_source_map_mut();
+        for path in paths {
+            if let Err(err) = source_map.load(Path::new(path)) {
+                error!(target: "debugger", "Failed to load debug info from {}, error - {}", path, err);
+            }
+        }
+    }
+
+    fn read_bank(&mut self, bank: MemoryBank, start: u16, end: u16) -> io::Result<Vec<u8>> {
+        match self.execute_emu(Command::MemRead(bank, start, end))? {
             CommandResult::Buffer(data) => Ok(data),
             CommandResult::Error(error) => Err(Error::new(ErrorKind::Other, error)),
             _ => Err(Error::new(ErrorKind::Other, "Invalid debugger result")),
         }
     }
 
+    fn read_mem(&mut self, start: u16, end: u16) -> io::Result<Vec<u8>> {
+        let bank = self.bank;
+        self.read_bank(bank, start, end)
+    }
+
     fn read_regs(&mut self) -> io::Result<RegData> {
         match self.execute_emu(Command::RegRead)? {
             CommandResult::Registers(regs) => Ok(regs),
@@ -202,6 +284,29 @@ impl Connection {
         }
     }
 
+    fn read_symbols(&mut self) -> io::Result<()> {
+        match self.execute_emu(Command::SymRead)? {
+            CommandResult::Symbols(symbols) => {
+                self.command_parser.set_symbols(symbols);
+                Ok(())
+            }
+            CommandResult::Error(error) => Err(Error::new(ErrorKind::Other, error)),
+            _ => Err(Error::new(ErrorKind::Other, "Invalid debugger result")),
+        }
+    }
+
+    fn write_file_data(&mut self, path: &str, address: u16, data: Vec<u8>) -> io::Result<String> {
+        let end = address as usize + data.len();
+        if end > 0x10000 {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("{} does not fit in memory at ${:04x}", path, address),
+            ));
+        }
+        self.execute_unit_cmd(Command::MemWrite(address, data))?;
+        Ok(format!("Loaded {} to ${:04x}-${:04x}\n", path, address, end))
+    }
+
     fn write_prompt(&mut self) -> io::Result<()> {
         let pc = self.regs.as_ref().map_or(0, |r| r.pc);
         write!(self.writer, "${:04x}> ", pc)?;
@@ -264,49 +369,167 @@ impl Connection {
         self.execute_text_cmd(Command::BpSet(address, true))?;
         self.execute_unit_cmd(Command::Continue)?;
         let regs = self.read_regs()?;
-        let mem = self.read_mem(regs.pc, regs.pc.wrapping_add(10))?;
+        let mem = self.read_bank(MemoryBank::Cpu, regs.pc, regs.pc.wrapping_add(10))?;
         let dis = Disassembler::new(mem.clone(), regs.pc);
         let (instr, instr_len) = dis.disassemble(regs.pc);
         Ok(self.format_instr(&regs, &instr, &mem[0..instr_len]))
     }
 
+    // -- Catchpoint
+
+    fn cmd_cp_list(&mut self) -> io::Result<String> {
+        self.execute_text_cmd(Command::CpList)
+    }
+
+    fn cmd_cp_set(&mut self, trigger: Trigger, condition: Option<String>) -> io::Result<String> {
+        let radix = self.command_parser.get_radix();
+        self.execute_text_cmd(Command::CpSet(trigger, condition, radix))
+    }
+
+    // -- Label
+
+    fn cmd_label_add(&mut self, address: u16, name: String) -> io::Result<String> {
+        self.execute_unit_cmd(Command::SymAdd(name, address))?;
+        self.read_symbols()?;
+        Ok(String::new())
+    }
+
+    fn cmd_label_clear(&mut self) -> io::Result<String> {
+        self.execute_unit_cmd(Command::SymClear)?;
+        self.read_symbols()?;
+        self.command_parser.get_source_map_mut().clear();
+        Ok("Cleared all labels\n".to_string())
+    }
+
+    fn cmd_label_delete(&mut self, name: String) -> io::Result<String> {
+        self.execute_unit_cmd(Command::SymRemove(name))?;
+        self.read_symbols()?;
+        Ok(String::new())
+    }
+
+    fn cmd_label_list(&mut self) -> io::Result<String> {
+        let mut buffer = String::new();
+        for (address, name) in self.command_parser.get_symbols().list() {
+            buffer.push_str(format!("${:04x} .{}\n", address, name).as_str());
+        }
+        if buffer.is_empty() {
+            buffer.push_str("No labels are defined\n");
+        }
+        Ok(buffer)
+    }
+
+    fn cmd_label_load(&mut self, path: String) -> io::Result<String> {
+        let mut result = self.execute_text_cmd(Command::SymLoad(path.clone()))?;
+        self.read_symbols()?;
+        if path.to_lowercase().ends_with(".dbg") {
+            let count = self
+                .command_parser
+                .get_source_map_mut()
+                .load(Path::new(&path))?;
+            result.push_str(format!("Loaded {} source lines from {}\n", count, path).as_str());
+        }
+        Ok(result)
+    }
+
+    // -- Watchpoint
+
+    fn cmd_wp_list(&mut self) -> io::Result<String> {
+        self.execute_text_cmd(Command::WpList)
+    }
+
+    fn cmd_wp_set(
+        &mut self,
+        start: u16,
+        end: u16,
+        mode: WatchMode,
+        vic: bool,
+        condition: Option<String>,
+    ) -> io::Result<String> {
+        let radix = self.command_parser.get_radix();
+        self.execute_text_cmd(Command::WpSet(start, end, mode, vic, condition, radix))
+    }
+
     // -- Debugger
 
+    fn cmd_back(&mut self, count: u16) -> io::Result<String> {
+        let mut bp_hit = 0;
+        for _i in 0..count {
+            bp_hit = self.execute_num_cmd(Command::StepBack)?;
+            if bp_hit > 0 {
+                break;
+            }
+        }
+        let mut buffer = String::new();
+        if bp_hit > 0 {
+            buffer.push_str("Stopped on breakpoint\n");
+        }
+        buffer.push_str(self.format_current_instr()?.as_str());
+        Ok(buffer)
+    }
+
     fn cmd_goto(&mut self, address: Option<u16>) -> io::Result<String> {
         if let Some(address) = address {
             self.execute_unit_cmd(Command::RegWrite(vec![RegOp::SetPC(address)]))?;
         }
         self.execute_unit_cmd(Command::Continue)?;
         let regs = self.read_regs()?;
-        let mem = self.read_mem(regs.pc, regs.pc.wrapping_add(10))?;
+        let mem = self.read_bank(MemoryBank::Cpu, regs.pc, regs.pc.wrapping_add(10))?;
         let dis = Disassembler::new(mem.clone(), regs.pc);
         let (instr, instr_len) = dis.disassemble(regs.pc);
         Ok(self.format_instr(&regs, &instr, &mem[0..instr_len]))
     }
 
+    fn cmd_history(&mut self, count: u16) -> io::Result<String> {
+        let entries = match self.execute_emu(Command::TraceRead(count as usize))? {
+            CommandResult::Trace(entries) => entries,
+            CommandResult::Error(error) => return Err(Error::new(ErrorKind::Other, error)),
+            _ => return Err(Error::new(ErrorKind::Other, "Invalid debugger result")),
+        };
+        let mut buffer = String::new();
+        for entry in entries.iter() {
+            buffer.push_str(trace::format_entry(entry).as_str());
+        }
+        Ok(buffer)
+    }
+
+    fn cmd_list(&mut self, location: Option<(String, usize)>) -> io::Result<String> {
+        let location = match location {
+            Some(location) => Some(location),
+            None => {
+                let pc = self.regs.as_ref().map_or(0, |r| r.pc);
+                self.command_parser
+                    .get_source_map()
+                    .find_line(pc)
+                    .map(|(file, line, _)| (file.to_string(), line))
+            }
+        };
+        let (file, line) = match location {
+            Some(location) => location,
+            None => return Err(Error::new(ErrorKind::Other, "No source line")),
+        };
+        let source_map = self.command_parser.get_source_map();
+        let mut buffer = String::new();
+        for i in line.saturating_sub(5).max(1)..line + 5 {
+            if let Some(text) = source_map.get_text(&file, i) {
+                let marker = if i == line { '>' } else { ' ' };
+                buffer.push_str(format!("{}{:5} {}\n", marker, i, text).as_str());
+            }
+        }
+        if buffer.is_empty() {
+            return Err(Error::new(
+                ErrorKind::Other,
+                format!("No source for {}:{}", file, line),
+            ));
+        }
+        Ok(buffer)
+    }
+
     fn cmd_next(&mut self, count: u16) -> io::Result<String> {
         let mut bp_hit = 0;
         for _i in 0..count {
-            let regs = self.read_regs()?;
-            let mem = self.read_mem(regs.pc, regs.pc.wrapping_add(1))?;
-            let opcode = mem[0];
-            if opcode == OPCODE_JSR {
-                let target = regs.pc.wrapping_add(3);
-                loop {
-                    let regs = self.read_regs()?;
-                    if regs.pc == target {
-                        break;
-                    }
-                    bp_hit = self.execute_num_cmd(Command::Step)?;
-                    if bp_hit > 0 {
-                        break;
-                    }
-                }
-            } else {
-                bp_hit = self.execute_num_cmd(Command::Step)?;
-                if bp_hit > 0 {
-                    break;
-                }
+            bp_hit = self.step_line(true)?;
+            if bp_hit > 0 {
+                break;
             }
         }
         let mut buffer = String::new();
@@ -314,7 +537,7 @@ impl Connection {
             buffer.push_str(format!("Stopped on breakpoint\n").as_str());
         }
         let regs = self.read_regs()?;
-        let mem = self.read_mem(regs.pc, regs.pc.wrapping_add(10))?;
+        let mem = self.read_bank(MemoryBank::Cpu, regs.pc, regs.pc.wrapping_add(10))?;
         let dis = Disassembler::new(mem.clone(), regs.pc);
         let (instr, instr_len) = dis.disassemble(regs.pc);
         buffer.push_str(
@@ -339,7 +562,7 @@ impl Connection {
         let mut bp_hit = 0;
         loop {
             let regs = self.read_regs()?;
-            let mem = self.read_mem(regs.pc, regs.pc.wrapping_add(1))?;
+            let mem = self.read_bank(MemoryBank::Cpu, regs.pc, regs.pc.wrapping_add(1))?;
             let opcode = mem[0];
             if opcode == OPCODE_RTS || opcode == OPCODE_RTI {
                 break;
@@ -354,7 +577,7 @@ impl Connection {
             buffer.push_str(format!("Stopped on breakpoint\n").as_str());
         }
         let regs = self.read_regs()?;
-        let mem = self.read_mem(regs.pc, regs.pc.wrapping_add(10))?;
+        let mem = self.read_bank(MemoryBank::Cpu, regs.pc, regs.pc.wrapping_add(10))?;
         let dis = Disassembler::new(mem.clone(), regs.pc);
         let (instr, instr_len) = dis.disassemble(regs.pc);
         buffer.push_str(
@@ -364,10 +587,24 @@ impl Connection {
         Ok(buffer)
     }
 
+    fn cmd_reverse(&mut self, budget: Option<usize>) -> io::Result<String> {
+        self.execute_unit_cmd(Command::ReverseSet(budget.map(|budget| budget * 1024)))?;
+        match budget {
+            Some(budget) => Ok(format!("Recording up to {} KB for reverse execution\n", budget)),
+            None => Ok("Reverse execution disabled\n".to_string()),
+        }
+    }
+
+    fn cmd_reverse_continue(&mut self) -> io::Result<String> {
+        let mut buffer = self.execute_text_cmd(Command::ReverseContinue)?;
+        buffer.push_str(self.format_current_instr()?.as_str());
+        Ok(buffer)
+    }
+
     fn cmd_step(&mut self, count: u16) -> io::Result<String> {
         let mut bp_hit = 0;
         for _i in 0..count {
-            bp_hit = self.execute_num_cmd(Command::Step)?;
+            bp_hit = self.step_line(false)?;
             if bp_hit > 0 {
                 break;
             }
@@ -377,7 +614,7 @@ impl Connection {
             buffer.push_str(format!("Stopped on breakpoint\n").as_str());
         }
         let regs = self.read_regs()?;
-        let mem = self.read_mem(regs.pc, regs.pc.wrapping_add(10))?;
+        let mem = self.read_bank(MemoryBank::Cpu, regs.pc, regs.pc.wrapping_add(10))?;
         let dis = Disassembler::new(mem.clone(), regs.pc);
         let (instr, instr_len) = dis.disassemble(regs.pc);
         buffer.push_str(
@@ -387,8 +624,58 @@ impl Connection {
         Ok(buffer)
     }
 
+    fn cmd_trace(&mut self, capacity: Option<usize>) -> io::Result<String> {
+        self.execute_unit_cmd(Command::TraceSet(capacity))?;
+        match capacity {
+            Some(capacity) => Ok(format!("Tracing the last {} instructions\n", capacity)),
+            None => Ok("Tracing disabled\n".to_string()),
+        }
+    }
+
     // -- Memory
 
+    fn cmd_assemble(&mut self, address: u16, source: Option<String>) -> io::Result<String> {
+        let mut address = address;
+        let mut source = source;
+        loop {
+            let line = match source.take() {
+                Some(line) => line,
+                None => {
+                    write!(self.writer, ".{:04x}  ", address)?;
+                    self.writer.flush()?;
+                    let mut input = String::new();
+                    self.reader.read_line(&mut input)?;
+                    input
+                }
+            };
+            if line.trim().is_empty() {
+                break;
+            }
+            let result = assembler::assemble(
+                line.as_str(),
+                address,
+                self.command_parser.get_radix(),
+                self.command_parser.get_symbols(),
+            );
+            match result {
+                Ok(data) => {
+                    let len = data.len() as u16;
+                    self.execute_unit_cmd(Command::MemWrite(address, data))?;
+                    address = address.wrapping_add(len);
+                }
+                Err(error) => self.writer.write_all(format!("Error: {}\n", error).as_bytes())?,
+            }
+        }
+        Ok(String::new())
+    }
+
+    fn cmd_bank(&mut self, bank: Option<MemoryBank>) -> io::Result<String> {
+        if let Some(bank) = bank {
+            self.bank = bank;
+        }
+        Ok(format!("Set bank to {}\n", self.bank.name()))
+    }
+
     fn cmd_compare(&mut self, start: u16, end: u16, target: u16) -> io::Result<String> {
         let source_data = self.read_mem(start, end)?;
         let target_end = target.wrapping_add(target + source_data.len() as u16);
@@ -418,6 +705,7 @@ impl Connection {
         let dis = Disassembler::new(data, start);
         let mut buffer = String::new();
         let mut address = start;
+        let symbols = self.command_parser.get_symbols();
         while address < end {
             let (instr, instr_len) = dis.disassemble(address);
             let mut instr_bytes = String::new();
@@ -425,7 +713,10 @@ impl Connection {
                 let byte = dis.read_byte(address + i);
                 instr_bytes.push_str(format!("{:02x} ", byte).as_str());
             }
-            let instr_text = format!("{}", instr);
+            if let Some(label) = symbols.get_label(address) {
+                buffer.push_str(format!("{}:\n", label).as_str());
+            }
+            let instr_text = disassembler::format_with_labels(&instr, address, symbols);
             buffer.push_str(
                 format!("${:04x}  {:12} {}\n", address, instr_bytes, instr_text).as_str(),
             );
@@ -462,6 +753,20 @@ impl Connection {
         Ok(buffer)
     }
 
+    fn cmd_load(&mut self, path: String, address: Option<u16>) -> io::Result<String> {
+        let (offset, data) = if path.to_lowercase().ends_with(".p00") {
+            P00Loader::new().read_program(Path::new(&path))?
+        } else {
+            PrgLoader::new().read_program(Path::new(&path))?
+        };
+        self.write_file_data(&path, address.unwrap_or(offset), data)
+    }
+
+    fn cmd_load_bin(&mut self, path: String, address: u16) -> io::Result<String> {
+        let data = BinLoader::new(address).read_data(Path::new(&path))?;
+        self.write_file_data(&path, address, data)
+    }
+
     fn cmd_memory(&mut self, start: Option<u16>, end: Option<u16>) -> io::Result<String> {
         let start = start.unwrap_or(self.regs.as_ref().map(|r| r.pc).unwrap_or(0));
         let data = self.read_mem(start, end.unwrap_or(start + 96))?;
@@ -530,6 +835,18 @@ impl Connection {
         Ok(buffer)
     }
 
+    fn cmd_save(&mut self, path: String, start: u16, end: u16) -> io::Result<String> {
+        let data = self.read_mem(start, end)?;
+        PrgLoader::new().save(Path::new(&path), start, &data)?;
+        Ok(format!("Saved ${:04x}-${:04x} to {}\n", start, end, path))
+    }
+
+    fn cmd_save_bin(&mut self, path: String, start: u16, end: u16) -> io::Result<String> {
+        let data = self.read_mem(start, end)?;
+        fs::write(&path, &data)?;
+        Ok(format!("Saved ${:04x}-${:04x} to {}\n", start, end, path))
+    }
+
     // -- System
 
     fn cmd_quit(&mut self) -> io::Result<String> {
@@ -627,8 +944,25 @@ impl Connection {
         }
     }
 
+    fn format_current_instr(&mut self) -> io::Result<String> {
+        let regs = self.read_regs()?;
+        let mem = self.read_bank(MemoryBank::Cpu, regs.pc, regs.pc.wrapping_add(10))?;
+        let dis = Disassembler::new(mem.clone(), regs.pc);
+        let (instr, instr_len) = dis.disassemble(regs.pc);
+        Ok(self.format_instr(&regs, &instr, &mem[0..instr_len]))
+    }
+
     fn format_instr(&self, regs: &RegData, instr: &Instruction, instr_bytes: &[u8]) -> String {
         let mut buffer = String::new();
+        let source_map = self.command_parser.get_source_map();
+        if let Some((file, line, _)) = source_map.find_line(regs.pc) {
+            let name = Path::new(file)
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or(file);
+            let text = source_map.get_text(file, line).unwrap_or("");
+            buffer.push_str(format!("{}:{}: {}\n", name, line, text.trim()).as_str());
+        }
         let mut instr_bytes2 = String::new();
         for byte in instr_bytes {
             instr_bytes2.push_str(format!("{:02x} ", byte).as_str());
@@ -638,7 +972,7 @@ impl Connection {
                 "${:04x}: {:12} {:16} A:{:02x} X:{:02x} Y:{:02x} SP:{:02x} {}{}{}{}{}{}{}\n",
                 regs.pc,
                 instr_bytes2,
-                format!("{}", instr),
+                disassembler::format_with_labels(instr, regs.pc, self.command_parser.get_symbols()),
                 regs.a,
                 regs.x,
                 regs.y,
@@ -683,12 +1017,50 @@ impl Connection {
         buffer
     }
 
+    fn source_line(&self, address: u16) -> Option<(String, usize)> {
+        self.command_parser
+            .get_source_map()
+            .find_line(address)
+            .map(|(file, line, _)| (file.to_string(), line))
+    }
+
+    fn step_instr(&mut self, over: bool) -> io::Result<u16> {
+        let regs = self.read_regs()?;
+        let mem = self.read_bank(MemoryBank::Cpu, regs.pc, regs.pc.wrapping_add(1))?;
+        if over && mem[0] == OPCODE_JSR {
+            let target = regs.pc.wrapping_add(3);
+            loop {
+                let bp_hit = self.execute_num_cmd(Command::Step)?;
+                if bp_hit > 0 || self.read_regs()?.pc == target {
+                    return Ok(bp_hit);
+                }
+            }
+        }
+        self.execute_num_cmd(Command::Step)
+    }
+
+    // Steps until the pc leaves the current source line or moves backwards within it, so
+    // loops on a single line stop after each iteration. Without source info this is a
+    // single instruction.
+    fn step_line(&mut self, over: bool) -> io::Result<u16> {
+        let mut pc = self.read_regs()?.pc;
+        let line = self.source_line(pc);
+        loop {
+            let bp_hit = self.step_instr(over)?;
+            let next_pc = self.read_regs()?.pc;
+            if bp_hit > 0 || line.is_none() || next_pc <= pc || self.source_line(next_pc) != line {
+                return Ok(bp_hit);
+            }
+            pc = next_pc;
+        }
+    }
+
     fn format_regs(&self, regs: RegData) -> String {
         let mut buffer = String::new();
-        buffer.push_str("PC   A  X  Y  SP 00 01 NV-BDIZC\n");
+        buffer.push_str("PC   A  X  Y  SP 00 01 NV-BDIZC LIN CYC\n");
         buffer.push_str(
             format!(
-                "{:04x} {:02x} {:02x} {:02x} {:02x} {:02x} {:02x} {}{}1{}{}{}{}{}\n",
+                "{:04x} {:02x} {:02x} {:02x} {:02x} {:02x} {:02x} {}{}1{}{}{}{}{} {:03x} {:03x}\n",
                 regs.pc,
                 regs.a,
                 regs.x,
@@ -730,7 +1102,9 @@ impl Connection {
                     "1"
                 } else {
                     "0"
-                }
+                },
+                regs.raster_line,
+                regs.raster_cycle
             ).as_str(),
         );
         buffer
@@ -739,21 +1113,43 @@ impl Connection {
 
 struct CommandParser {
     radix: u32,
+    source_map: SourceMap,
+    symbols: SymbolTable,
 }
 
 impl CommandParser {
     pub fn new() -> Self {
-        Self { radix: 16 }
+        Self {
+            radix: 16,
+            source_map: SourceMap::new(),
+            symbols: SymbolTable::new(),
+        }
     }
 
     pub fn get_radix(&self) -> u32 {
         self.radix
     }
 
+    pub fn get_source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    pub fn get_source_map_mut(&mut self) -> &mut SourceMap {
+        &mut self.source_map
+    }
+
+    pub fn get_symbols(&self) -> &SymbolTable {
+        &self.symbols
+    }
+
     pub fn set_radix(&mut self, radix: u32) {
         self.radix = radix;
     }
 
+    pub fn set_symbols(&mut self, symbols: SymbolTable) {
+        self.symbols = symbols;
+    }
+
     pub fn parse(&self, input: &String) -> Result<Cmd, String> {
         let mut tokens = input.split_whitespace();
         if let Some(command) = tokens.next() {
@@ -766,21 +1162,46 @@ impl CommandParser {
                 "disable" | "dis" => self.parse_disable(&mut tokens),
                 "ignore" => self.parse_ignore(&mut tokens),
                 "until" | "un" => self.parse_until(&mut tokens),
+                // Catchpoint
+                "catch" => self.parse_catch(&mut tokens),
+                "raster" | "ras" => self.parse_raster(&mut tokens),
+                // Label
+                "add_label" | "al" => self.parse_add_label(&mut tokens),
+                "clear_labels" => self.parse_clear_labels(&mut tokens),
+                "delete_label" | "dl" => self.parse_delete_label(&mut tokens),
+                "load_labels" | "ll" => self.parse_load_labels(&mut tokens),
+                "show_labels" | "sl" => self.parse_show_labels(&mut tokens),
+                // Watchpoint
+                "awatch" | "aw" => self.parse_watch(WatchMode::Access, &mut tokens),
+                "rwatch" | "rw" => self.parse_watch(WatchMode::Read, &mut tokens),
+                "watch" | "w" => self.parse_watch(WatchMode::Write, &mut tokens),
                 // Debugger
+                "back" => self.parse_back(&mut tokens),
                 "goto" | "g" => self.parse_goto(&mut tokens),
+                "history" | "chis" => self.parse_history(&mut tokens),
+                "list" => self.parse_list(&mut tokens),
                 "next" | "n" => self.parse_next(&mut tokens),
                 "registers" | "r" => self.parse_registers(&mut tokens),
                 "return" | "ret" => self.parse_return(&mut tokens),
+                "reverse" => self.parse_reverse(&mut tokens),
+                "reverse-continue" | "rc" => self.parse_reverse_continue(&mut tokens),
                 "step" | "z" => self.parse_step(&mut tokens),
+                "trace" => self.parse_trace(&mut tokens),
                 // Memory
+                "a" => self.parse_assemble(&mut tokens),
+                "bank" => self.parse_bank(&mut tokens),
                 "compare" | "c" => self.parse_compare(&mut tokens),
                 "disass" | "d" => self.parse_disassemble(&mut tokens),
                 "fill" | "f" => self.parse_fill(&mut tokens),
+                "bload" | "bl" => self.parse_load_bin(&mut tokens),
+                "bsave" | "bs" => self.parse_save_bin(&mut tokens),
                 "hunt" | "h" => self.parse_hunt(&mut tokens),
+                "load" | "l" => self.parse_load(&mut tokens),
                 "mem" | "m" => self.parse_memory(&mut tokens),
                 "memchar" | "mc" => self.parse_mem_char(&mut tokens),
                 "move" | "t" => self.parse_move(&mut tokens),
                 "i" => self.parse_petscii(&mut tokens),
+                "save" | "s" => self.parse_save(&mut tokens),
                 // System
                 "reset" => self.parse_reset(&mut tokens),
                 "screen" | "sc" => self.parse_screen(&mut tokens),
@@ -800,7 +1221,7 @@ impl CommandParser {
     // -- Breakpoint
 
     fn parse_break(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
-        let address = self.parse_num_maybe(tokens.next())?;
+        let address = self.parse_address_maybe(tokens.next())?;
         self.ensure_eos(tokens)?;
         match address {
             Some(address) => Ok(Cmd::BpSet(address)),
@@ -811,10 +1232,9 @@ impl CommandParser {
     fn parse_condition(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
         let index = self.parse_num(tokens.next())?;
         self.ensure_keyword("if", tokens)?;
-        let expr = tokens.next();
-        self.ensure_eos(tokens)?;
-        if let Some(expr) = expr {
-            Ok(Cmd::BpCondition(index, expr.to_string()))
+        let expr = tokens.collect::<Vec<&str>>().join(" ");
+        if !expr.is_empty() {
+            Ok(Cmd::BpCondition(index, expr))
         } else {
             Err(format!("Missing expression"))
         }
@@ -846,7 +1266,7 @@ impl CommandParser {
     }
 
     fn parse_until(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
-        let address = self.parse_num_maybe(tokens.next())?;
+        let address = self.parse_address_maybe(tokens.next())?;
         self.ensure_eos(tokens)?;
         match address {
             Some(address) => Ok(Cmd::BpUntil(address)),
@@ -854,14 +1274,146 @@ impl CommandParser {
         }
     }
 
+    // -- Catchpoint
+
+    fn parse_catch(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let kind = match tokens.next() {
+            Some(token) => match token.to_lowercase().as_str() {
+                "irq" => Interrupt::Irq,
+                "nmi" => Interrupt::Nmi,
+                _ => return Err(format!("Invalid interrupt {}", token)),
+            },
+            None => return Ok(Cmd::CpList),
+        };
+        let mut token = tokens.next();
+        let source = match token {
+            Some(value) if value.to_lowercase() != "if" => {
+                token = tokens.next();
+                Some(InterruptSource::from(value)?)
+            }
+            _ => None,
+        };
+        let condition = self.parse_if(token, tokens)?;
+        Ok(Cmd::CpSet(Trigger::Interrupt(kind, source), condition))
+    }
+
+    fn parse_raster(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let line = match tokens.next() {
+            Some(token) => self.parse_num(Some(token))?,
+            None => return Ok(Cmd::CpList),
+        };
+        let mut token = tokens.next();
+        let cycle = match token {
+            Some(value) if value.to_lowercase() != "if" => {
+                token = tokens.next();
+                Some(self.parse_num(Some(value))?)
+            }
+            _ => None,
+        };
+        let condition = self.parse_if(token, tokens)?;
+        Ok(Cmd::CpSet(Trigger::Raster(line, cycle), condition))
+    }
+
+    // -- Label
+
+    fn parse_add_label(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let address = self.parse_num(tokens.next())?;
+        let name = match tokens.next() {
+            Some(name) => name.strip_prefix('.').unwrap_or(name).to_string(),
+            None => return Err("Missing label".to_string()),
+        };
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::LabelAdd(address, name))
+    }
+
+    fn parse_clear_labels(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::LabelClear)
+    }
+
+    fn parse_delete_label(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let name = match tokens.next() {
+            Some(name) => name.strip_prefix('.').unwrap_or(name).to_string(),
+            None => return Err("Missing label".to_string()),
+        };
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::LabelDelete(name))
+    }
+
+    fn parse_load_labels(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let path = tokens.collect::<Vec<&str>>().join(" ");
+        let path = path.trim_matches('"');
+        if !path.is_empty() {
+            Ok(Cmd::LabelLoad(path.to_string()))
+        } else {
+            Err("Missing file name".to_string())
+        }
+    }
+
+    fn parse_show_labels(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::LabelList)
+    }
+
+    // -- Watchpoint
+
+    fn parse_watch(
+        &self,
+        mode: WatchMode,
+        tokens: &mut dyn Iterator<Item = &str>,
+    ) -> Result<Cmd, String> {
+        let mut token = tokens.next();
+        let vic = mode != WatchMode::Write && token.map_or(false, |t| t.to_lowercase() == "vic");
+        if vic {
+            token = tokens.next();
+        } else if token.is_none() {
+            return Ok(Cmd::WpList);
+        }
+        let start = self.parse_address(token)?;
+        let mut token = tokens.next();
+        let end = match token {
+            Some(value) if value.to_lowercase() != "if" => {
+                token = tokens.next();
+                self.parse_address(Some(value))?
+            }
+            _ => start,
+        };
+        let condition = self.parse_if(token, tokens)?;
+        if end < start {
+            return Err(format!("Invalid range ${:04x}-${:04x}", start, end));
+        }
+        Ok(Cmd::WpSet(start, end, mode, vic, condition))
+    }
+
     // -- Debugger
 
+    fn parse_back(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let count = self.parse_num_maybe(tokens.next())?;
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::Back(count.unwrap_or(1)))
+    }
+
     fn parse_goto(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
-        let address = self.parse_num_maybe(tokens.next())?;
+        let address = self.parse_address_maybe(tokens.next())?;
         self.ensure_eos(tokens)?;
         Ok(Cmd::Goto(address))
     }
 
+    fn parse_history(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let count = self.parse_num_maybe(tokens.next())?;
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::History(count.unwrap_or(0x20)))
+    }
+
+    fn parse_list(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let location = match tokens.next() {
+            Some(token) => Some(self.parse_location(token)?),
+            None => None,
+        };
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::List(location))
+    }
+
     fn parse_next(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
         let count = self.parse_num_maybe(tokens.next())?;
         self.ensure_eos(tokens)?;
@@ -921,14 +1473,69 @@ impl CommandParser {
         Ok(Cmd::Return)
     }
 
+    fn parse_reverse(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let command = match tokens.next() {
+            Some(token) if token.to_lowercase() == "on" => {
+                let budget = self.parse_num_maybe(tokens.next())?;
+                Cmd::Reverse(Some(budget.map_or(REVERSE_BUDGET, |budget| budget as usize)))
+            }
+            Some(token) if token.to_lowercase() == "off" => Cmd::Reverse(None),
+            Some(token) => return Err(format!("Unexpected token {}", token)),
+            None => return Err("Missing on or off".to_string()),
+        };
+        self.ensure_eos(tokens)?;
+        Ok(command)
+    }
+
+    fn parse_reverse_continue(
+        &self,
+        tokens: &mut dyn Iterator<Item = &str>,
+    ) -> Result<Cmd, String> {
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::ReverseContinue)
+    }
+
     fn parse_step(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
         let count = self.parse_num_maybe(tokens.next())?;
         self.ensure_eos(tokens)?;
         Ok(Cmd::Step(count.unwrap_or(1)))
     }
 
+    fn parse_trace(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let command = match tokens.next() {
+            Some(token) if token.to_lowercase() == "on" => {
+                let size = self.parse_num_maybe(tokens.next())?;
+                Cmd::Trace(Some(size.map_or(TRACE_SIZE, |size| size as usize)))
+            }
+            Some(token) if token.to_lowercase() == "off" => Cmd::Trace(None),
+            Some(token) => return Err(format!("Unexpected token {}", token)),
+            None => return Err("Missing on or off".to_string()),
+        };
+        self.ensure_eos(tokens)?;
+        Ok(command)
+    }
+
     // -- Memory
 
+    fn parse_assemble(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let address = self.parse_address(tokens.next())?;
+        let source = tokens.collect::<Vec<&str>>().join(" ");
+        if !source.is_empty() {
+            Ok(Cmd::Assemble(address, Some(source)))
+        } else {
+            Ok(Cmd::Assemble(address, None))
+        }
+    }
+
+    fn parse_bank(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let bank = match tokens.next() {
+            Some(name) => Some(MemoryBank::from(name.to_lowercase().as_str())?),
+            None => None,
+        };
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::Bank(bank))
+    }
+
     fn parse_compare(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
         let start = self.parse_num(tokens.next())?;
         let end = self.parse_num(tokens.next())?;
@@ -938,8 +1545,8 @@ impl CommandParser {
     }
 
     fn parse_disassemble(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
-        let start = self.parse_num_maybe(tokens.next())?;
-        let end = self.parse_num_maybe(tokens.next())?;
+        let start = self.parse_address_maybe(tokens.next())?;
+        let end = self.parse_address_maybe(tokens.next())?;
         self.ensure_eos(tokens)?;
         Ok(Cmd::Disassemble(start, end))
     }
@@ -984,9 +1591,23 @@ impl CommandParser {
         }
     }
 
+    fn parse_load(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let path = self.parse_file_name(tokens)?;
+        let address = self.parse_address_maybe(tokens.next())?;
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::Load(path, address))
+    }
+
+    fn parse_load_bin(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let path = self.parse_file_name(tokens)?;
+        let address = self.parse_address(tokens.next())?;
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::LoadBin(path, address))
+    }
+
     fn parse_memory(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
-        let start = self.parse_num_maybe(tokens.next())?;
-        let end = self.parse_num_maybe(tokens.next())?;
+        let start = self.parse_address_maybe(tokens.next())?;
+        let end = self.parse_address_maybe(tokens.next())?;
         self.ensure_eos(tokens)?;
         Ok(Cmd::Memory(start, end))
     }
@@ -1012,6 +1633,22 @@ impl CommandParser {
         Ok(Cmd::Petscii(start, end))
     }
 
+    fn parse_save(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let path = self.parse_file_name(tokens)?;
+        let start = self.parse_address(tokens.next())?;
+        let end = self.parse_address(tokens.next())?;
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::Save(path, start, end))
+    }
+
+    fn parse_save_bin(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<Cmd, String> {
+        let path = self.parse_file_name(tokens)?;
+        let start = self.parse_address(tokens.next())?;
+        let end = self.parse_address(tokens.next())?;
+        self.ensure_eos(tokens)?;
+        Ok(Cmd::SaveBin(path, start, end))
+    }
+
     // -- System
 
     fn parse_reset(&self, tokens: &mut Iterator<Item = &str>) -> Result<Cmd, String> {
@@ -1078,10 +1715,92 @@ impl CommandParser {
         }
     }
 
+    fn parse_address(&self, input: Option<&str>) -> Result<u16, String> {
+        match input {
+            Some(value) => {
+                if let Some(name) = value.strip_prefix('.') {
+                    return self.parse_label(name);
+                }
+                if value.contains(':') {
+                    let (file, line) = self.parse_location(value)?;
+                    return self
+                        .source_map
+                        .find_address(&file, line)
+                        .ok_or_else(|| format!("No code at {}", value));
+                }
+                u16::from_str_radix(value, self.radix).or_else(|_| {
+                    self.parse_label(value)
+                        .map_err(|_| format!("Invalid address {}", value))
+                })
+            }
+            None => Err("missing argument".to_string()),
+        }
+    }
+
+    fn parse_address_maybe(&self, input: Option<&str>) -> Result<Option<u16>, String> {
+        match input {
+            Some(_) => self.parse_address(input).map(Some),
+            None => Ok(None),
+        }
+    }
+
     fn parse_byte(&self, value: &str) -> Result<u8, String> {
         u8::from_str_radix(value, self.radix).map_err(|_| format!("Invalid number {}", value))
     }
 
+    // File names may be quoted to include spaces.
+    fn parse_file_name(&self, tokens: &mut dyn Iterator<Item = &str>) -> Result<String, String> {
+        let mut name = match tokens.next() {
+            Some(token) => token.to_string(),
+            None => return Err("Missing file name".to_string()),
+        };
+        if name.starts_with('"') {
+            while name.len() == 1 || !name.ends_with('"') {
+                match tokens.next() {
+                    Some(token) => {
+                        name.push(' ');
+                        name.push_str(token);
+                    }
+                    None => return Err(format!("Invalid file name {}", name)),
+                }
+            }
+            name = name[1..name.len() - 1].to_string();
+        }
+        Ok(name)
+    }
+
+    fn parse_if(
+        &self,
+        token: Option<&str>,
+        tokens: &mut dyn Iterator<Item = &str>,
+    ) -> Result<Option<String>, String> {
+        match token {
+            Some(keyword) if keyword.to_lowercase() == "if" => {
+                let expr = tokens.collect::<Vec<&str>>().join(" ");
+                if expr.is_empty() {
+                    return Err("Missing expression".to_string());
+                }
+                Ok(Some(expr))
+            }
+            Some(token) => Err(format!("Unexpected token {}", token)),
+            None => Ok(None),
+        }
+    }
+
+    fn parse_label(&self, name: &str) -> Result<u16, String> {
+        self.symbols
+            .get(name)
+            .ok_or_else(|| format!("Unknown label {}", name))
+    }
+
+    fn parse_location(&self, value: &str) -> Result<(String, usize), String> {
+        let mut parts = value.rsplitn(2, ':');
+        match (parts.next().map(|line| line.parse::<usize>()), parts.next()) {
+            (Some(Ok(line)), Some(file)) if !file.is_empty() => Ok((file.to_string(), line)),
+            _ => Err(format!("Invalid location {}", value)),
+        }
+    }
+
     fn parse_num(&self, input: Option<&str>) -> Result<u16, String> {
         if let Some(value) = input {
             u16::from_str_radix(value, self.radix).map_err(|_| format!("Invalid number {}", value))
@@ -1112,7 +1831,9 @@ impl CommandHelp {
         if let Some(command) = command {
             match command.trim().to_lowercase().as_str() {
                 // Breakpoint
-                "break" | "bk" => CommandHelp::help_cmd("break [address]", "bk"),
+                "break" | "bk" => {
+                    CommandHelp::help_cmd("break [<address>|<label>|<file>:<line>]", "bk")
+                }
                 "condition" | "cond" => {
                     CommandHelp::help_cmd("condition <index> if <cond_exp>", "cond")
                 }
@@ -1120,24 +1841,64 @@ impl CommandHelp {
                 "delete" | "del" => CommandHelp::help_cmd("delete [<index>]", "del"),
                 "disable" | "dis" => CommandHelp::help_cmd("disable [<index>]", "dis"),
                 "ignore" => CommandHelp::help_cmd("ignore <index> [<count>]", ""),
-                "until" | "un" => CommandHelp::help_cmd("until <address>", "un"),
+                "until" | "un" => {
+                    CommandHelp::help_cmd("until <address>|<label>|<file>:<line>", "un")
+                }
+                // Catchpoint
+                "catch" => CommandHelp::help_cmd(
+                    "catch [irq|nmi [cia1|cia2|vic|exp] [if <cond_exp>]]",
+                    "",
+                ),
+                "raster" | "ras" => {
+                    CommandHelp::help_cmd("raster [<line> [<cycle>] [if <cond_exp>]]", "ras")
+                }
+                // Label
+                "add_label" | "al" => CommandHelp::help_cmd("add_label <address> <label>", "al"),
+                "clear_labels" => CommandHelp::help_cmd("clear_labels", ""),
+                "delete_label" | "dl" => CommandHelp::help_cmd("delete_label <label>", "dl"),
+                "load_labels" | "ll" => CommandHelp::help_cmd("load_labels \"<filename>\"", "ll"),
+                "show_labels" | "sl" => CommandHelp::help_cmd("show_labels", "sl"),
+                // Watchpoint
+                "awatch" | "aw" => CommandHelp::help_cmd(
+                    "awatch [vic] [<address> [<address>] [if <cond_exp>]]",
+                    "aw",
+                ),
+                "rwatch" | "rw" => CommandHelp::help_cmd(
+                    "rwatch [vic] [<address> [<address>] [if <cond_exp>]]",
+                    "rw",
+                ),
+                "watch" | "w" => {
+                    CommandHelp::help_cmd("watch [<address> [<address>] [if <cond_exp>]]", "w")
+                }
                 // Debugger
+                "back" => CommandHelp::help_cmd("back [<count>]", ""),
                 "goto" | "g" => CommandHelp::help_cmd("goto <address>", "g"),
+                "history" | "chis" => CommandHelp::help_cmd("history [<count>]", "chis"),
+                "list" => CommandHelp::help_cmd("list [<file>:<line>]", ""),
                 "next" | "n" => CommandHelp::help_cmd("next [<count>]", "n"),
                 "registers" | "r" => {
                     CommandHelp::help_cmd("registers [<reg> = <num>[, <reg> = <num>]*]", "r")
                 }
                 "return" | "ret" => CommandHelp::help_cmd("return", "ret"),
+                "reverse" => CommandHelp::help_cmd("reverse on [<size_kb>] | reverse off", ""),
+                "reverse-continue" | "rc" => CommandHelp::help_cmd("reverse-continue", "rc"),
                 "step" | "z" => CommandHelp::help_cmd("step [<count>]", "z"),
+                "trace" => CommandHelp::help_cmd("trace on [<size>] | trace off", ""),
                 // Memory
+                "a" => CommandHelp::help_cmd("a <address> [<instruction>]", ""),
+                "bank" => CommandHelp::help_cmd("bank [cpu|ram|rom|io|vic|cart]", ""),
                 "compare" | "c" => CommandHelp::help_cmd("compare", "c"),
                 "disass" | "d" => CommandHelp::help_cmd("disass [<address> [<address>]]", "d"),
                 "fill" | "f" => CommandHelp::help_cmd("fill <address> <address> <data_list>", "f"),
+                "bload" | "bl" => CommandHelp::help_cmd("bload <file> <address>", "bl"),
+                "bsave" | "bs" => CommandHelp::help_cmd("bsave <file> <address> <address>", "bs"),
                 "hunt" | "h" => CommandHelp::help_cmd("hunt <address> <address> <data_list>", "h"),
+                "load" | "l" => CommandHelp::help_cmd("load <file> [<address>]", "l"),
                 "mem" | "m" => CommandHelp::help_cmd("mem [<address> [<address>]]", "m"),
                 "memchar" | "mc" => CommandHelp::help_cmd("memchar [<address>]", "mc"),
                 "move" | "t" => CommandHelp::help_cmd("move <address> <address> <address>", "t"),
                 "petscii" | "i" => CommandHelp::help_cmd("petscii <address> [<address>]", "i"),
+                "save" | "s" => CommandHelp::help_cmd("save <file> <address> <address>", "s"),
                 // System
                 "reset" => CommandHelp::help_cmd("reset [<type>]", ""),
                 "screen" | "sc" => CommandHelp::help_cmd("screen", "sc"),
@@ -1178,22 +1939,50 @@ impl CommandHelp {
         buffer.push_str("ignore\n");
         buffer.push_str("until (un)\n");
         buffer.push_str("\n");
+        buffer.push_str("* Catchpoint *\n");
+        buffer.push_str("catch\n");
+        buffer.push_str("raster (ras)\n");
+        buffer.push_str("\n");
+        buffer.push_str("* Label *\n");
+        buffer.push_str("add_label (al)\n");
+        buffer.push_str("clear_labels\n");
+        buffer.push_str("delete_label (dl)\n");
+        buffer.push_str("load_labels (ll)\n");
+        buffer.push_str("show_labels (sl)\n");
+        buffer.push_str("\n");
+        buffer.push_str("* Watchpoint *\n");
+        buffer.push_str("awatch (aw)\n");
+        buffer.push_str("rwatch (rw)\n");
+        buffer.push_str("watch (w)\n");
+        buffer.push_str("\n");
         buffer.push_str("* Debug *\n");
+        buffer.push_str("back\n");
         buffer.push_str("goto (g)\n");
+        buffer.push_str("history (chis)\n");
+        buffer.push_str("list\n");
         buffer.push_str("next (n)\n");
         buffer.push_str("registers (r)\n");
         buffer.push_str("return (ret)\n");
+        buffer.push_str("reverse\n");
+        buffer.push_str("reverse-continue (rc)\n");
         buffer.push_str("step (z)\n");
+        buffer.push_str("trace\n");
         buffer.push_str("\n");
         buffer.push_str("* Memory *\n");
+        buffer.push_str("a\n");
+        buffer.push_str("bank\n");
+        buffer.push_str("bload (bl)\n");
+        buffer.push_str("bsave (bs)\n");
         buffer.push_str("compare (c)\n");
         buffer.push_str("disass (d)\n");
         buffer.push_str("fill (f)\n");
         buffer.push_str("hunt (h)\n");
+        buffer.push_str("load (l)\n");
         buffer.push_str("mem (m)\n");
         buffer.push_str("memchar (mc)\n");
         buffer.push_str("move (t)\n");
         buffer.push_str("petscii (i)\n");
+        buffer.push_str("save (s)\n");
         buffer.push_str("\n");
         buffer.push_str("* System *\n");
         buffer.push_str("reset\n");
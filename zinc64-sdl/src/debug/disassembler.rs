@@ -2,7 +2,364 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
-use zinc64::cpu::{Instruction, Operand};
+use std::fmt;
+
+use zinc64::system::SymbolTable;
+
+// SPEC: http://www.oxyron.de/html/opcodes02.html
+// Design:
+//   All 256 opcodes including the undocumented ones are described by their mnemonic and
+//   addressing mode in a single table that is shared with the assembler. Instructions are
+//   printed the same way as cpu instructions, and labels replace operand addresses when
+//   available, e.g. sta $d020,x becomes sta EXTCOL,x. Branches are labeled by their target.
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Mode {
+    Implied,
+    Accumulator,
+    Immediate,
+    ZeroPage,
+    ZeroPageX,
+    ZeroPageY,
+    Absolute,
+    AbsoluteX,
+    AbsoluteY,
+    IndirectX,
+    IndirectY,
+    Indirect,
+    Relative,
+}
+
+impl Mode {
+    pub fn len(&self) -> usize {
+        match *self {
+            Mode::Implied | Mode::Accumulator => 1,
+            Mode::Absolute | Mode::AbsoluteX | Mode::AbsoluteY | Mode::Indirect => 3,
+            _ => 2,
+        }
+    }
+}
+
+pub static OPCODES: [(&str, Mode); 256] = [
+    // 0x00
+    ("brk", Mode::Implied),
+    ("ora", Mode::IndirectX),
+    ("kil", Mode::Implied),
+    ("slo", Mode::IndirectX),
+    ("nop", Mode::ZeroPage),
+    ("ora", Mode::ZeroPage),
+    ("asl", Mode::ZeroPage),
+    ("slo", Mode::ZeroPage),
+    ("php", Mode::Implied),
+    ("ora", Mode::Immediate),
+    ("asl", Mode::Accumulator),
+    ("anc", Mode::Immediate),
+    ("nop", Mode::Absolute),
+    ("ora", Mode::Absolute),
+    ("asl", Mode::Absolute),
+    ("slo", Mode::Absolute),
+    // 0x10
+    ("bpl", Mode::Relative),
+    ("ora", Mode::IndirectY),
+    ("kil", Mode::Implied),
+    ("slo", Mode::IndirectY),
+    ("nop", Mode::ZeroPageX),
+    ("ora", Mode::ZeroPageX),
+    ("asl", Mode::ZeroPageX),
+    ("slo", Mode::ZeroPageX),
+    ("clc", Mode::Implied),
+    ("ora", Mode::AbsoluteY),
+    ("nop", Mode::Implied),
+    ("slo", Mode::AbsoluteY),
+    ("nop", Mode::AbsoluteX),
+    ("ora", Mode::AbsoluteX),
+    ("asl", Mode::AbsoluteX),
+    ("slo", Mode::AbsoluteX),
+    // 0x20
+    ("jsr", Mode::Absolute),
+    ("and", Mode::IndirectX),
+    ("kil", Mode::Implied),
+    ("rla", Mode::IndirectX),
+    ("bit", Mode::ZeroPage),
+    ("and", Mode::ZeroPage),
+    ("rol", Mode::ZeroPage),
+    ("rla", Mode::ZeroPage),
+    ("plp", Mode::Implied),
+    ("and", Mode::Immediate),
+    ("rol", Mode::Accumulator),
+    ("anc", Mode::Immediate),
+    ("bit", Mode::Absolute),
+    ("and", Mode::Absolute),
+    ("rol", Mode::Absolute),
+    ("rla", Mode::Absolute),
+    // 0x30
+    ("bmi", Mode::Relative),
+    ("and", Mode::IndirectY),
+    ("kil", Mode::Implied),
+    ("rla", Mode::IndirectY),
+    ("nop", Mode::ZeroPageX),
+    ("and", Mode::ZeroPageX),
+    ("rol", Mode::ZeroPageX),
+    ("rla", Mode::ZeroPageX),
+    ("sec", Mode::Implied),
+    ("and", Mode::AbsoluteY),
+    ("nop", Mode::Implied),
+    ("rla", Mode::AbsoluteY),
+    ("nop", Mode::AbsoluteX),
+    ("and", Mode::AbsoluteX),
+    ("rol", Mode::AbsoluteX),
+    ("rla", Mode::AbsoluteX),
+    // 0x40
+    ("rti", Mode::Implied),
+    ("eor", Mode::IndirectX),
+    ("kil", Mode::Implied),
+    ("sre", Mode::IndirectX),
+    ("nop", Mode::ZeroPage),
+    ("eor", Mode::ZeroPage),
+    ("lsr", Mode::ZeroPage),
+    ("sre", Mode::ZeroPage),
+    ("pha", Mode::Implied),
+    ("eor", Mode::Immediate),
+    ("lsr", Mode::Accumulator),
+    ("alr", Mode::Immediate),
+    ("jmp", Mode::Absolute),
+    ("eor", Mode::Absolute),
+    ("lsr", Mode::Absolute),
+    ("sre", Mode::Absolute),
+    // 0x50
+    ("bvc", Mode::Relative),
+    ("eor", Mode::IndirectY),
+    ("kil", Mode::Implied),
+    ("sre", Mode::IndirectY),
+    ("nop", Mode::ZeroPageX),
+    ("eor", Mode::ZeroPageX),
+    ("lsr", Mode::ZeroPageX),
+    ("sre", Mode::ZeroPageX),
+    ("cli", Mode::Implied),
+    ("eor", Mode::AbsoluteY),
+    ("nop", Mode::Implied),
+    ("sre", Mode::AbsoluteY),
+    ("nop", Mode::AbsoluteX),
+    ("eor", Mode::AbsoluteX),
+    ("lsr", Mode::AbsoluteX),
+    ("sre", Mode::AbsoluteX),
+    // 0x60
+    ("rts", Mode::Implied),
+    ("adc", Mode::IndirectX),
+    ("kil", Mode::Implied),
+    ("rra", Mode::IndirectX),
+    ("nop", Mode::ZeroPage),
+    ("adc", Mode::ZeroPage),
+    ("ror", Mode::ZeroPage),
+    ("rra", Mode::ZeroPage),
+    ("pla", Mode::Implied),
+    ("adc", Mode::Immediate),
+    ("ror", Mode::Accumulator),
+    ("arr", Mode::Immediate),
+    ("jmp", Mode::Indirect),
+    ("adc", Mode::Absolute),
+    ("ror", Mode::Absolute),
+    ("rra", Mode::Absolute),
+    // 0x70
+    ("bvs", Mode::Relative),
+    ("adc", Mode::IndirectY),
+    ("kil", Mode::Implied),
+    ("rra", Mode::IndirectY),
+    ("nop", Mode::ZeroPageX),
+    ("adc", Mode::ZeroPageX),
+    ("ror", Mode::ZeroPageX),
+    ("rra", Mode::ZeroPageX),
+    ("sei", Mode::Implied),
+    ("adc", Mode::AbsoluteY),
+    ("nop", Mode::Implied),
+    ("rra", Mode::AbsoluteY),
+    ("nop", Mode::AbsoluteX),
+    ("adc", Mode::AbsoluteX),
+    ("ror", Mode::AbsoluteX),
+    ("rra", Mode::AbsoluteX),
+    // 0x80
+    ("nop", Mode::Immediate),
+    ("sta", Mode::IndirectX),
+    ("nop", Mode::Immediate),
+    ("sax", Mode::IndirectX),
+    ("sty", Mode::ZeroPage),
+    ("sta", Mode::ZeroPage),
+    ("stx", Mode::ZeroPage),
+    ("sax", Mode::ZeroPage),
+    ("dey", Mode::Implied),
+    ("nop", Mode::Immediate),
+    ("txa", Mode::Implied),
+    ("xaa", Mode::Immediate),
+    ("sty", Mode::Absolute),
+    ("sta", Mode::Absolute),
+    ("stx", Mode::Absolute),
+    ("sax", Mode::Absolute),
+    // 0x90
+    ("bcc", Mode::Relative),
+    ("sta", Mode::IndirectY),
+    ("kil", Mode::Implied),
+    ("ahx", Mode::IndirectY),
+    ("sty", Mode::ZeroPageX),
+    ("sta", Mode::ZeroPageX),
+    ("stx", Mode::ZeroPageY),
+    ("sax", Mode::ZeroPageY),
+    ("tya", Mode::Implied),
+    ("sta", Mode::AbsoluteY),
+    ("txs", Mode::Implied),
+    ("tas", Mode::AbsoluteY),
+    ("shy", Mode::AbsoluteX),
+    ("sta", Mode::AbsoluteX),
+    ("shx", Mode::AbsoluteY),
+    ("ahx", Mode::AbsoluteY),
+    // 0xa0
+    ("ldy", Mode::Immediate),
+    ("lda", Mode::IndirectX),
+    ("ldx", Mode::Immediate),
+    ("lax", Mode::IndirectX),
+    ("ldy", Mode::ZeroPage),
+    ("lda", Mode::ZeroPage),
+    ("ldx", Mode::ZeroPage),
+    ("lax", Mode::ZeroPage),
+    ("tay", Mode::Implied),
+    ("lda", Mode::Immediate),
+    ("tax", Mode::Implied),
+    ("lax", Mode::Immediate),
+    ("ldy", Mode::Absolute),
+    ("lda", Mode::Absolute),
+    ("ldx", Mode::Absolute),
+    ("lax", Mode::Absolute),
+    // 0xb0
+    ("bcs", Mode::Relative),
+    ("lda", Mode::IndirectY),
+    ("kil", Mode::Implied),
+    ("lax", Mode::IndirectY),
+    ("ldy", Mode::ZeroPageX),
+    ("lda", Mode::ZeroPageX),
+    ("ldx", Mode::ZeroPageY),
+    ("lax", Mode::ZeroPageY),
+    ("clv", Mode::Implied),
+    ("lda", Mode::AbsoluteY),
+    ("tsx", Mode::Implied),
+    ("las", Mode::AbsoluteY),
+    ("ldy", Mode::AbsoluteX),
+    ("lda", Mode::AbsoluteX),
+    ("ldx", Mode::AbsoluteY),
+    ("lax", Mode::AbsoluteY),
+    // 0xc0
+    ("cpy", Mode::Immediate),
+    ("cmp", Mode::IndirectX),
+    ("nop", Mode::Immediate),
+    ("dcp", Mode::IndirectX),
+    ("cpy", Mode::ZeroPage),
+    ("cmp", Mode::ZeroPage),
+    ("dec", Mode::ZeroPage),
+    ("dcp", Mode::ZeroPage),
+    ("iny", Mode::Implied),
+    ("cmp", Mode::Immediate),
+    ("dex", Mode::Implied),
+    ("axs", Mode::Immediate),
+    ("cpy", Mode::Absolute),
+    ("cmp", Mode::Absolute),
+    ("dec", Mode::Absolute),
+    ("dcp", Mode::Absolute),
+    // 0xd0
+    ("bne", Mode::Relative),
+    ("cmp", Mode::IndirectY),
+    ("kil", Mode::Implied),
+    ("dcp", Mode::IndirectY),
+    ("nop", Mode::ZeroPageX),
+    ("cmp", Mode::ZeroPageX),
+    ("dec", Mode::ZeroPageX),
+    ("dcp", Mode::ZeroPageX),
+    ("cld", Mode::Implied),
+    ("cmp", Mode::AbsoluteY),
+    ("nop", Mode::Implied),
+    ("dcp", Mode::AbsoluteY),
+    ("nop", Mode::AbsoluteX),
+    ("cmp", Mode::AbsoluteX),
+    ("dec", Mode::AbsoluteX),
+    ("dcp", Mode::AbsoluteX),
+    // 0xe0
+    ("cpx", Mode::Immediate),
+    ("sbc", Mode::IndirectX),
+    ("nop", Mode::Immediate),
+    ("isc", Mode::IndirectX),
+    ("cpx", Mode::ZeroPage),
+    ("sbc", Mode::ZeroPage),
+    ("inc", Mode::ZeroPage),
+    ("isc", Mode::ZeroPage),
+    ("inx", Mode::Implied),
+    ("sbc", Mode::Immediate),
+    ("nop", Mode::Implied),
+    ("sbc", Mode::Immediate),
+    ("cpx", Mode::Absolute),
+    ("sbc", Mode::Absolute),
+    ("inc", Mode::Absolute),
+    ("isc", Mode::Absolute),
+    // 0xf0
+    ("beq", Mode::Relative),
+    ("sbc", Mode::IndirectY),
+    ("kil", Mode::Implied),
+    ("isc", Mode::IndirectY),
+    ("nop", Mode::ZeroPageX),
+    ("sbc", Mode::ZeroPageX),
+    ("inc", Mode::ZeroPageX),
+    ("isc", Mode::ZeroPageX),
+    ("sed", Mode::Implied),
+    ("sbc", Mode::AbsoluteY),
+    ("nop", Mode::Implied),
+    ("isc", Mode::AbsoluteY),
+    ("nop", Mode::AbsoluteX),
+    ("sbc", Mode::AbsoluteX),
+    ("inc", Mode::AbsoluteX),
+    ("isc", Mode::AbsoluteX),
+];
+
+pub struct Instruction {
+    pub opcode: u8,
+    pub operand: u16,
+}
+
+impl Instruction {
+    pub fn get_mnemonic(&self) -> &'static str {
+        OPCODES[self.opcode as usize].0
+    }
+
+    pub fn get_mode(&self) -> Mode {
+        OPCODES[self.opcode as usize].1
+    }
+
+    pub fn get_target(&self, address: u16) -> u16 {
+        let offset = self.operand as u8 as i8;
+        address.wrapping_add(2).wrapping_add(offset as u16)
+    }
+
+    fn format(&self, prefix: &str, value: &str) -> String {
+        let operand = match self.get_mode() {
+            Mode::Implied => return self.get_mnemonic().to_string(),
+            Mode::Accumulator => "acc".to_string(),
+            Mode::Immediate => format!("#{:02x}", self.operand),
+            Mode::ZeroPage | Mode::Absolute | Mode::Relative => format!("{}{}", prefix, value),
+            Mode::ZeroPageX | Mode::AbsoluteX => format!("{}{},x", prefix, value),
+            Mode::ZeroPageY | Mode::AbsoluteY => format!("{}{},y", prefix, value),
+            Mode::IndirectX => format!("{}({},x)", prefix, value),
+            Mode::IndirectY => format!("{}({}),y", prefix, value),
+            Mode::Indirect => format!("{}({})", prefix, value),
+        };
+        format!("{} {}", self.get_mnemonic(), operand)
+    }
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let value = if self.get_mode().len() == 3 {
+            format!("{:04x}", self.operand)
+        } else {
+            format!("{:02x}", self.operand)
+        };
+        write!(f, "{}", self.format("$", &value))
+    }
+}
 
 pub struct Disassembler {
     data: Vec<u8>,
@@ -16,538 +373,13 @@ impl Disassembler {
 
     pub fn disassemble(&self, address: u16) -> (Instruction, usize) {
         let opcode = self.read_byte(address);
-        match opcode {
-            0x00 => (Instruction::BRK, 1),
-            0x01 => (
-                Instruction::ORA(Operand::IndirectX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x05 => (
-                Instruction::ORA(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x06 => (
-                Instruction::ASL(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x08 => (Instruction::PHP, 1),
-            0x09 => (
-                Instruction::ORA(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0x0a => (Instruction::ASL(Operand::Accumulator), 1),
-            0x0d => (
-                Instruction::ORA(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x0e => (
-                Instruction::ASL(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x10 => (
-                Instruction::BPL(Operand::Relative(self.read_byte(address + 1) as i8)),
-                2,
-            ),
-            0x11 => (
-                Instruction::ORA(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0x15 => (
-                Instruction::ORA(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x16 => (
-                Instruction::ASL(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x18 => (Instruction::CLC, 1),
-            0x19 => (
-                Instruction::ORA(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0x1d => (
-                Instruction::ORA(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0x1e => (
-                Instruction::ASL(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0x20 => (
-                Instruction::JSR(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x21 => (
-                Instruction::AND(Operand::IndirectX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x24 => (
-                Instruction::BIT(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x25 => (
-                Instruction::AND(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x26 => (
-                Instruction::ROL(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x28 => (Instruction::PLP, 1),
-            0x29 => (
-                Instruction::AND(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0x2a => (Instruction::ROL(Operand::Accumulator), 1),
-            0x2c => (
-                Instruction::BIT(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x2d => (
-                Instruction::AND(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x2e => (
-                Instruction::ROL(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x30 => (
-                Instruction::BMI(Operand::Relative(self.read_byte(address + 1) as i8)),
-                2,
-            ),
-            0x31 => (
-                Instruction::AND(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0x35 => (
-                Instruction::AND(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x36 => (
-                Instruction::ROL(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x38 => (Instruction::SEC, 1),
-            0x39 => (
-                Instruction::AND(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0x3d => (
-                Instruction::AND(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0x3e => (
-                Instruction::ROL(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0x40 => (Instruction::RTI, 1),
-            0x41 => (
-                Instruction::EOR(Operand::IndirectX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x45 => (
-                Instruction::EOR(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x46 => (
-                Instruction::LSR(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x48 => (Instruction::PHA, 1),
-            0x49 => (
-                Instruction::EOR(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0x4a => (Instruction::LSR(Operand::Accumulator), 1),
-            0x4c => (
-                Instruction::JMP(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x4d => (
-                Instruction::EOR(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x4e => (
-                Instruction::LSR(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x50 => (
-                Instruction::BVC(Operand::Relative(self.read_byte(address + 1) as i8)),
-                2,
-            ),
-            0x51 => (
-                Instruction::EOR(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0x55 => (
-                Instruction::EOR(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x56 => (
-                Instruction::LSR(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x58 => (Instruction::CLI, 1),
-            0x59 => (
-                Instruction::EOR(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0x5d => (
-                Instruction::EOR(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0x5e => (
-                Instruction::LSR(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0x60 => (Instruction::RTS, 1),
-            0x61 => (
-                Instruction::ADC(Operand::IndirectX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x65 => (
-                Instruction::ADC(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x66 => (
-                Instruction::ROR(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x68 => (Instruction::PLA, 1),
-            0x69 => (
-                Instruction::ADC(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0x6a => (Instruction::ROR(Operand::Accumulator), 1),
-            0x6c => (
-                Instruction::JMP(Operand::Indirect(self.read_word(address + 1))),
-                3,
-            ),
-            0x6d => (
-                Instruction::ADC(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x6e => (
-                Instruction::ROR(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x70 => (
-                Instruction::BVS(Operand::Relative(self.read_byte(address + 1) as i8)),
-                2,
-            ),
-            0x71 => (
-                Instruction::ADC(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0x75 => (
-                Instruction::ADC(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x76 => (
-                Instruction::ROR(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x78 => (Instruction::SEI, 1),
-            0x79 => (
-                Instruction::ADC(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0x7d => (
-                Instruction::ADC(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0x7e => (
-                Instruction::ROR(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0x81 => (
-                Instruction::STA(Operand::IndirectX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x84 => (
-                Instruction::STY(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x85 => (
-                Instruction::STA(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x86 => (
-                Instruction::STX(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0x88 => (Instruction::DEY, 1),
-            0x8a => (Instruction::TXA, 1),
-            0x8c => (
-                Instruction::STY(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x8d => (
-                Instruction::STA(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x8e => (
-                Instruction::STX(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0x90 => (
-                Instruction::BCC(Operand::Relative(self.read_byte(address + 1) as i8)),
-                2,
-            ),
-            0x91 => (
-                Instruction::STA(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0x94 => (
-                Instruction::STY(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x95 => (
-                Instruction::STA(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0x96 => (
-                Instruction::STX(Operand::ZeroPageY(self.read_byte(address + 1))),
-                2,
-            ),
-            0x98 => (Instruction::TYA, 1),
-            0x99 => (
-                Instruction::STA(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0x9a => (Instruction::TXS, 1),
-            0x9d => (
-                Instruction::STA(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0xa0 => (
-                Instruction::LDY(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0xa1 => (
-                Instruction::LDA(Operand::IndirectX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xa2 => (
-                Instruction::LDX(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0xa4 => (
-                Instruction::LDY(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xa5 => (
-                Instruction::LDA(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xa6 => (
-                Instruction::LDX(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xa7 => (
-                Instruction::LAX(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xa8 => (Instruction::TAY, 1),
-            0xa9 => (
-                Instruction::LDA(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0xaa => (Instruction::TAX, 1),
-            0xac => (
-                Instruction::LDY(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xad => (
-                Instruction::LDA(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xae => (
-                Instruction::LDX(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xb0 => (
-                Instruction::BCS(Operand::Relative(self.read_byte(address + 1) as i8)),
-                2,
-            ),
-            0xb1 => (
-                Instruction::LDA(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0xb3 => (
-                Instruction::LAX(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0xb4 => (
-                Instruction::LDY(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xb5 => (
-                Instruction::LDA(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xb6 => (
-                Instruction::LDX(Operand::ZeroPageY(self.read_byte(address + 1))),
-                2,
-            ),
-            0xb8 => (Instruction::CLV, 1),
-            0xb9 => (
-                Instruction::LDA(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0xba => (Instruction::TSX, 1),
-            0xbc => (
-                Instruction::LDY(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0xbd => (
-                Instruction::LDA(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0xbe => (
-                Instruction::LDX(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0xc0 => (
-                Instruction::CPY(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0xc1 => (
-                Instruction::CMP(Operand::IndirectX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xc4 => (
-                Instruction::CPY(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xc5 => (
-                Instruction::CMP(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xc6 => (
-                Instruction::DEC(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xc8 => (Instruction::INY, 1),
-            0xc9 => (
-                Instruction::CMP(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0xca => (Instruction::DEX, 1),
-            0xcb => (
-                Instruction::AXS(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0xcc => (
-                Instruction::CPY(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xcd => (
-                Instruction::CMP(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xce => (
-                Instruction::DEC(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xd0 => (
-                Instruction::BNE(Operand::Relative(self.read_byte(address + 1) as i8)),
-                2,
-            ),
-            0xd1 => (
-                Instruction::CMP(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0xd5 => (
-                Instruction::CMP(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xd6 => (
-                Instruction::DEC(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xd8 => (Instruction::CLD, 1),
-            0xd9 => (
-                Instruction::CMP(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0xdd => (
-                Instruction::CMP(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0xde => (
-                Instruction::DEC(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0xe0 => (
-                Instruction::CPX(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0xe1 => (
-                Instruction::SBC(Operand::IndirectX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xe4 => (
-                Instruction::CPX(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xe5 => (
-                Instruction::SBC(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xe6 => (
-                Instruction::INC(Operand::ZeroPage(self.read_byte(address + 1))),
-                2,
-            ),
-            0xe8 => (Instruction::INX, 1),
-            0xe9 => (
-                Instruction::SBC(Operand::Immediate(self.read_byte(address + 1))),
-                2,
-            ),
-            0xea => (Instruction::NOP, 1),
-            0xec => (
-                Instruction::CPX(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xed => (
-                Instruction::SBC(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xee => (
-                Instruction::INC(Operand::Absolute(self.read_word(address + 1))),
-                3,
-            ),
-            0xf0 => (
-                Instruction::BEQ(Operand::Relative(self.read_byte(address + 1) as i8)),
-                2,
-            ),
-            0xf1 => (
-                Instruction::SBC(Operand::IndirectY(self.read_byte(address + 1))),
-                2,
-            ),
-            0xf5 => (
-                Instruction::SBC(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xf6 => (
-                Instruction::INC(Operand::ZeroPageX(self.read_byte(address + 1))),
-                2,
-            ),
-            0xf8 => (Instruction::SED, 1),
-            0xf9 => (
-                Instruction::SBC(Operand::AbsoluteY(self.read_word(address + 1))),
-                3,
-            ),
-            0xfd => (
-                Instruction::SBC(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            0xfe => (
-                Instruction::INC(Operand::AbsoluteX(self.read_word(address + 1))),
-                3,
-            ),
-            _ => panic!("invalid opcode 0x{:x} at 0x{:x}", opcode, address),
-        }
+        let len = OPCODES[opcode as usize].1.len();
+        let operand = match len {
+            3 => self.read_word(address + 1),
+            2 => self.read_byte(address + 1) as u16,
+            _ => 0,
+        };
+        (Instruction { opcode, operand }, len)
     }
 
     pub fn read_byte(&self, address: u16) -> u8 {
@@ -560,3 +392,15 @@ impl Disassembler {
         ((high as u16) << 8) | low as u16
     }
 }
+
+pub fn format_with_labels(instr: &Instruction, address: u16, symbols: &SymbolTable) -> String {
+    let target = match instr.get_mode() {
+        Mode::Implied | Mode::Accumulator | Mode::Immediate => None,
+        Mode::Relative => Some(instr.get_target(address)),
+        _ => Some(instr.operand),
+    };
+    match target.and_then(|target| symbols.get_label(target)) {
+        Some(label) => instr.format("", label),
+        None => format!("{}", instr),
+    }
+}
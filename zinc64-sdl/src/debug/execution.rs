@@ -2,10 +2,12 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
+use std::path::Path;
 use std::sync::mpsc::Sender;
 
 use byteorder::{BigEndian, WriteBytesExt};
-use zinc64::system::C64;
+use zinc64::core::MemoryBank;
+use zinc64::system::{C64, Catchpoint, Journal, Trace, Trigger, WatchMode, Watchpoint};
 
 use super::command;
 use super::{Command, CommandResult, RegOp};
@@ -82,13 +84,32 @@ impl ExecutionEngine {
             Command::BpList => Ok(self.bp_list()),
             Command::BpRemove(index) => self.bp_remove(index),
             Command::BpSet(address, autodelete) => Ok(self.bp_set(address, autodelete)),
+            // Catchpoint
+            Command::CpList => Ok(self.cp_list()),
+            Command::CpSet(trigger, ref expr, radix) => self.cp_set(trigger, expr, radix),
+            // Symbol
+            Command::SymAdd(ref name, address) => Ok(self.sym_add(name, address)),
+            Command::SymClear => Ok(self.sym_clear()),
+            Command::SymLoad(ref path) => self.sym_load(path),
+            Command::SymRead => Ok(self.sym_read()),
+            Command::SymRemove(ref name) => self.sym_remove(name),
+            // Watchpoint
+            Command::WpList => Ok(self.wp_list()),
+            Command::WpSet(start, end, mode, vic, ref expr, radix) => {
+                self.wp_set(start, end, mode, vic, expr, radix)
+            }
             // Debugger
             Command::Continue => Ok(self.continue_()),
             Command::RegRead => Ok(self.reg_read()),
             Command::RegWrite(ref ops) => Ok(self.reg_write(ops)),
+            Command::ReverseContinue => self.reverse_continue(),
+            Command::ReverseSet(budget) => Ok(self.reverse_set(budget)),
             Command::Step => Ok(self.step()),
+            Command::StepBack => self.step_back(),
+            Command::TraceRead(count) => self.trace_read(count),
+            Command::TraceSet(capacity) => Ok(self.trace_set(capacity)),
             // Memory
-            Command::MemRead(start, end) => Ok(self.mem_read(start, end)),
+            Command::MemRead(bank, start, end) => Ok(self.mem_read(bank, start, end)),
             Command::MemWrite(address, ref data) => Ok(self.mem_write(address, data)),
             // System
             Command::SysQuit => Ok(self.sys_quit()),
@@ -138,12 +159,18 @@ impl ExecutionEngine {
     ) -> Result<CommandResult, String> {
         let bpm = self.c64.get_bpm_mut();
         bpm.set_condition(index, expr, Some(radix))?;
-        let bp = bpm.get(index)?;
+        let (kind, condition) = if let Ok(bp) = bpm.get(index) {
+            ("breakpoint", bp.condition.as_ref())
+        } else if let Ok(cp) = bpm.get_catchpoint(index) {
+            ("catchpoint", cp.condition.as_ref())
+        } else {
+            ("watchpoint", bpm.get_watchpoint(index)?.condition.as_ref())
+        };
         let buffer = format!(
-            "Setting condition for breakpoint {} to: {}\n",
-            bp.index,
-            bp.condition
-                .as_ref()
+            "Setting condition for {} {} to: {}\n",
+            kind,
+            index,
+            condition
                 .map(|cond| format!("{}", cond))
                 .unwrap_or("".to_string())
         );
@@ -184,6 +211,12 @@ impl ExecutionEngine {
                 ).as_str(),
             );
         }
+        for cp in bpm.list_catchpoints() {
+            buffer.push_str(format_catchpoint(cp).as_str());
+        }
+        for wp in bpm.list_watchpoints() {
+            buffer.push_str(format_watchpoint(wp).as_str());
+        }
         if buffer.is_empty() {
             buffer.push_str("No breakpoints are set\n");
         }
@@ -203,6 +236,111 @@ impl ExecutionEngine {
         CommandResult::Text(buffer)
     }
 
+    // -- Catchpoint
+
+    fn cp_list(&self) -> CommandResult {
+        let bpm = self.c64.get_bpm();
+        let mut buffer = String::new();
+        for cp in bpm.list_catchpoints() {
+            buffer.push_str(format_catchpoint(cp).as_str());
+        }
+        if buffer.is_empty() {
+            buffer.push_str("No catchpoints are set\n");
+        }
+        CommandResult::Text(buffer)
+    }
+
+    fn cp_set(
+        &mut self,
+        trigger: Trigger,
+        expr: &Option<String>,
+        radix: u32,
+    ) -> Result<CommandResult, String> {
+        let bpm = self.c64.get_bpm_mut();
+        let index = bpm.catch(trigger);
+        if let Some(ref expr) = *expr {
+            if let Err(error) = bpm.set_condition(index, expr, Some(radix)) {
+                bpm.remove(index)?;
+                return Err(error);
+            }
+        }
+        let cp = bpm.get_catchpoint(index)?;
+        Ok(CommandResult::Text(format_catchpoint(cp)))
+    }
+
+    // -- Symbol
+
+    fn sym_add(&mut self, name: &str, address: u16) -> CommandResult {
+        let symbols = self.c64.get_bpm_mut().get_symbols_mut();
+        symbols.insert(name, address);
+        CommandResult::Unit
+    }
+
+    fn sym_clear(&mut self) -> CommandResult {
+        let symbols = self.c64.get_bpm_mut().get_symbols_mut();
+        symbols.clear();
+        CommandResult::Unit
+    }
+
+    fn sym_load(&mut self, path: &str) -> Result<CommandResult, String> {
+        let symbols = self.c64.get_bpm_mut().get_symbols_mut();
+        let count = symbols
+            .load(Path::new(path))
+            .map_err(|err| format!("Failed to load labels from {}, error - {}", path, err))?;
+        Ok(CommandResult::Text(format!(
+            "Loaded {} labels from {}\n",
+            count, path
+        )))
+    }
+
+    fn sym_read(&self) -> CommandResult {
+        let symbols = self.c64.get_bpm().get_symbols();
+        CommandResult::Symbols(symbols.clone())
+    }
+
+    fn sym_remove(&mut self, name: &str) -> Result<CommandResult, String> {
+        let symbols = self.c64.get_bpm_mut().get_symbols_mut();
+        match symbols.remove(name) {
+            Some(_) => Ok(CommandResult::Unit),
+            None => Err(format!("Unknown label {}", name)),
+        }
+    }
+
+    // -- Watchpoint
+
+    fn wp_list(&self) -> CommandResult {
+        let bpm = self.c64.get_bpm();
+        let mut buffer = String::new();
+        for wp in bpm.list_watchpoints() {
+            buffer.push_str(format_watchpoint(wp).as_str());
+        }
+        if buffer.is_empty() {
+            buffer.push_str("No watchpoints are set\n");
+        }
+        CommandResult::Text(buffer)
+    }
+
+    fn wp_set(
+        &mut self,
+        start: u16,
+        end: u16,
+        mode: WatchMode,
+        vic: bool,
+        expr: &Option<String>,
+        radix: u32,
+    ) -> Result<CommandResult, String> {
+        let bpm = self.c64.get_bpm_mut();
+        let index = bpm.watch(start, end, mode, vic);
+        if let Some(ref expr) = *expr {
+            if let Err(error) = bpm.set_condition(index, expr, Some(radix)) {
+                bpm.remove(index)?;
+                return Err(error);
+            }
+        }
+        let wp = bpm.get_watchpoint(index)?;
+        Ok(CommandResult::Text(format_watchpoint(wp)))
+    }
+
     // Debugger
 
     fn continue_(&mut self) -> CommandResult {
@@ -212,6 +350,7 @@ impl ExecutionEngine {
 
     fn reg_read(&mut self) -> CommandResult {
         let cpu = self.c64.get_cpu();
+        let (raster_line, raster_cycle) = self.c64.get_raster_position();
         let regs = command::RegData {
             a: cpu.get_a(),
             x: cpu.get_x(),
@@ -221,6 +360,8 @@ impl ExecutionEngine {
             pc: cpu.get_pc(),
             port_00: cpu.read(0x00),
             port_01: cpu.read(0x01),
+            raster_line,
+            raster_cycle,
         };
         CommandResult::Registers(regs)
     }
@@ -240,20 +381,77 @@ impl ExecutionEngine {
         CommandResult::Unit
     }
 
+    fn reverse_continue(&mut self) -> Result<CommandResult, String> {
+        if self.c64.get_journal().is_none() {
+            return Err("Reverse execution is disabled".to_string());
+        }
+        while self.c64.step_back() {
+            if self.c64.check_breakpoints() {
+                return Ok(CommandResult::Text("Stopped on breakpoint\n".to_string()));
+            }
+        }
+        Ok(CommandResult::Text("Reached start of history\n".to_string()))
+    }
+
+    fn reverse_set(&mut self, budget: Option<usize>) -> CommandResult {
+        match budget {
+            Some(budget) => {
+                if let Some(journal) = self.c64.get_journal_mut() {
+                    journal.set_budget(budget);
+                    return CommandResult::Unit;
+                }
+                self.c64.set_journal(Some(Journal::new(budget)));
+            }
+            None => self.c64.set_journal(None),
+        }
+        CommandResult::Unit
+    }
+
     fn step(&mut self) -> CommandResult {
         self.c64.step();
         let bp_hit = if self.c64.check_breakpoints() { 1 } else { 0 };
         CommandResult::Number(bp_hit)
     }
 
+    fn step_back(&mut self) -> Result<CommandResult, String> {
+        if self.c64.get_journal().is_none() {
+            return Err("Reverse execution is disabled".to_string());
+        }
+        if !self.c64.step_back() {
+            return Err("Reached start of history".to_string());
+        }
+        let bp_hit = if self.c64.check_breakpoints() { 1 } else { 0 };
+        Ok(CommandResult::Number(bp_hit))
+    }
+
+    fn trace_read(&self, count: usize) -> Result<CommandResult, String> {
+        match self.c64.get_trace() {
+            Some(trace) => Ok(CommandResult::Trace(trace.last(count))),
+            None => Err("Trace is disabled".to_string()),
+        }
+    }
+
+    fn trace_set(&mut self, capacity: Option<usize>) -> CommandResult {
+        match capacity {
+            Some(capacity) => {
+                if let Some(trace) = self.c64.get_trace_mut() {
+                    trace.set_capacity(capacity);
+                    return CommandResult::Unit;
+                }
+                self.c64.set_trace(Some(Trace::new(capacity)));
+            }
+            None => self.c64.set_trace(None),
+        }
+        CommandResult::Unit
+    }
+
     // -- Memory
 
-    fn mem_read(&self, start: u16, end: u16) -> CommandResult {
-        let cpu = self.c64.get_cpu();
+    fn mem_read(&self, bank: MemoryBank, start: u16, end: u16) -> CommandResult {
         let mut buffer = Vec::new();
         let mut address = start;
         while address < end {
-            buffer.push(cpu.read(address));
+            buffer.push(self.c64.peek(bank, address));
             address = address.wrapping_add(1);
         }
         CommandResult::Buffer(buffer)
@@ -279,8 +477,8 @@ impl ExecutionEngine {
     fn sys_screen(&self) -> CommandResult {
         let cia2 = self.c64.get_cia_2();
         let vic = self.c64.get_vic();
-        let cia2_port_a = cia2.borrow_mut().read(0x00);
-        let vm = (((vic.borrow_mut().read(0x18) & 0xf0) >> 4) as u16) << 10;
+        let cia2_port_a = cia2.borrow().peek(0x00);
+        let vm = (((vic.borrow().peek(0x18) & 0xf0) >> 4) as u16) << 10;
         let vm_base = ((!cia2_port_a & 0x03) as u16) << 14 | vm;
         CommandResult::Number(vm_base)
     }
@@ -297,3 +495,30 @@ impl ExecutionEngine {
         Ok(CommandResult::Buffer(buffer))
     }
 }
+
+fn format_catchpoint(cp: &Catchpoint) -> String {
+    format!(
+        "Cp {}: {}{}{}\n",
+        cp.index,
+        cp.trigger,
+        cp.condition
+            .as_ref()
+            .map_or(String::new(), |cond| format!(" if {}", cond)),
+        if cp.enabled { "" } else { " disabled" },
+    )
+}
+
+fn format_watchpoint(wp: &Watchpoint) -> String {
+    format!(
+        "Wp {}: ${:04x}-${:04x} {}{}{}{}\n",
+        wp.index,
+        wp.start,
+        wp.end,
+        wp.mode,
+        if wp.vic { " vic" } else { "" },
+        wp.condition
+            .as_ref()
+            .map_or(String::new(), |cond| format!(" if {}", cond)),
+        if wp.enabled { "" } else { " disabled" },
+    )
+}
@@ -2,14 +2,18 @@
 // Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
 // Licensed under the GPLv3. See LICENSE file in the project root for full license text.
 
+mod assembler;
 mod charset;
 pub mod command;
 mod debugger;
 mod disassembler;
 mod execution;
 mod rap_server;
+mod source_map;
+mod trace;
 
 pub use self::command::{Command, CommandResult, RegOp};
 pub use self::debugger::Debugger;
 pub use self::execution::{ExecutionEngine, State};
 pub use self::rap_server::RapServer;
+pub use self::trace::TraceFile;
@@ -13,6 +13,7 @@ use std::u16;
 use std::u8;
 
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
+use zinc64::core::MemoryBank;
 
 use super::command::{Command, CommandResult};
 
@@ -207,7 +208,7 @@ impl Connection {
         info!(target: "rap", "Read 0x{:04x} {}", self.offset, len);
         let start = self.offset;
         let end = self.offset.wrapping_add(len as u16);
-        let command = Command::MemRead(start, end);
+        let command = Command::MemRead(MemoryBank::Cpu, start, end);
         let mut data = match self.execute_emu(command)? {
             CommandResult::Buffer(data) => Ok(data),
             result => Err(self.invalid_response(result)),
@@ -0,0 +1,342 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// SPEC: KickAssembler -debugdump (<Sources> and <Block> sections of C64debugger xml) and
+//   ld65 --dbgfile (file, line, seg and span records).
+// Design:
+//   The source map keeps the address range generated by each source line. Source files are
+//   read when the debug info is loaded, relative to its directory unless the path is
+//   absolute, and files that cannot be read are still mapped without their text. Each source
+//   file remembers the debug info it came from, so loading that debug info again replaces
+//   its ranges instead of adding them twice.
+
+struct SourceFile {
+    origin: PathBuf,
+    path: String,
+    lines: Vec<String>,
+}
+
+#[derive(Clone, Copy)]
+struct SourceRange {
+    start: u16,
+    end: u16,
+    file: usize,
+    line: usize,
+}
+
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    ranges: Vec<SourceRange>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self {
+            files: Vec::new(),
+            ranges: Vec::new(),
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.files.clear();
+        self.ranges.clear();
+    }
+
+    pub fn find_address(&self, file: &str, line: usize) -> Option<u16> {
+        self.ranges
+            .iter()
+            .filter(|range| range.line >= line && self.is_file(range.file, file))
+            .min_by_key(|range| (range.line, range.start))
+            .map(|range| range.start)
+    }
+
+    pub fn find_line(&self, address: u16) -> Option<(&str, usize, u16)> {
+        self.ranges
+            .iter()
+            .filter(|range| range.start <= address && address <= range.end)
+            .min_by_key(|range| range.end.wrapping_sub(range.start))
+            .map(|range| {
+                let path = self.files[range.file].path.as_str();
+                (path, range.line, range.start)
+            })
+    }
+
+    pub fn get_text(&self, file: &str, line: usize) -> Option<&str> {
+        self.files
+            .iter()
+            .enumerate()
+            .find(|&(index, _)| self.is_file(index, file))
+            .and_then(|(_, source)| source.lines.get(line.wrapping_sub(1)))
+            .map(|text| text.as_str())
+    }
+
+    pub fn load(&mut self, path: &Path) -> io::Result<usize> {
+        info!(target: "debugger", "Loading debug info from {}", path.to_str().unwrap());
+        let text = fs::read_to_string(path)?;
+        self.remove(path);
+        let count = self.ranges.len();
+        if text.trim_start().starts_with('<') {
+            self.parse_kickass(&text, path);
+        } else {
+            self.parse_ca65(&text, path);
+        }
+        Ok(self.ranges.len() - count)
+    }
+
+    fn add_file(&mut self, path: &str, origin: &Path) -> usize {
+        let base_dir = origin.parent().unwrap_or_else(|| Path::new(""));
+        let full_path = base_dir.join(path);
+        let lines = fs::read_to_string(&full_path)
+            .or_else(|_| fs::read_to_string(path))
+            .map(|text| text.lines().map(|line| line.to_string()).collect())
+            .unwrap_or_else(|_| Vec::new());
+        self.files.push(SourceFile {
+            origin: origin.to_path_buf(),
+            path: path.to_string(),
+            lines,
+        });
+        self.files.len() - 1
+    }
+
+    fn remove(&mut self, origin: &Path) {
+        let mut indices = Vec::new();
+        let mut files = Vec::new();
+        for file in self.files.drain(..) {
+            if file.origin == origin {
+                indices.push(None);
+            } else {
+                indices.push(Some(files.len()));
+                files.push(file);
+            }
+        }
+        self.files = files;
+        self.ranges = self
+            .ranges
+            .iter()
+            .filter_map(|range| indices[range.file].map(|file| SourceRange { file, ..*range }))
+            .collect();
+    }
+
+    fn is_file(&self, index: usize, file: &str) -> bool {
+        let path = self.files[index].path.as_str();
+        path == file || path.ends_with(format!("/{}", file).as_str())
+    }
+
+    // file id=0,name="main.s",size=446,mtime=0x5c4ac5b4,mod=0
+    // line id=4,file=0,line=12,span=3+4
+    // seg id=0,name="CODE",start=0x000810,size=0x0023,addrsize=absolute,type=ro
+    // span id=3,seg=0,start=16,size=3
+    fn parse_ca65(&mut self, text: &str, origin: &Path) {
+        let mut files = HashMap::new();
+        let mut segments = HashMap::new();
+        let mut spans = HashMap::new();
+        let mut lines = Vec::new();
+        for record in text.lines() {
+            let mut parts = record.splitn(2, char::is_whitespace);
+            let kind = parts.next().unwrap_or("");
+            let fields = parse_fields(parts.next().unwrap_or(""));
+            let id = fields.get("id").and_then(|id| parse_number(id));
+            match (kind, id) {
+                ("file", Some(id)) => {
+                    let name = fields.get("name").map_or("", |name| name.trim_matches('"'));
+                    let index = self.add_file(name, origin);
+                    files.insert(id, index);
+                }
+                ("line", _) => {
+                    let is_macro = fields.get("type").map_or(false, |kind| *kind == "2");
+                    if let (Some(file), Some(line), Some(span), false) = (
+                        fields.get("file").and_then(|file| parse_number(file)),
+                        fields.get("line").and_then(|line| parse_number(line)),
+                        fields.get("span"),
+                        is_macro,
+                    ) {
+                        for span in span.split('+').filter_map(parse_number) {
+                            lines.push((file, line, span));
+                        }
+                    }
+                }
+                ("seg", Some(id)) => {
+                    if let Some(start) = fields.get("start").and_then(|start| parse_number(start)) {
+                        segments.insert(id, start);
+                    }
+                }
+                ("span", Some(id)) => {
+                    if let (Some(seg), Some(start), Some(size)) = (
+                        fields.get("seg").and_then(|seg| parse_number(seg)),
+                        fields.get("start").and_then(|start| parse_number(start)),
+                        fields.get("size").and_then(|size| parse_number(size)),
+                    ) {
+                        spans.insert(id, (seg, start, size));
+                    }
+                }
+                _ => {}
+            }
+        }
+        for (file, line, span) in lines {
+            if let (Some(&file), Some(&(seg, start, size))) = (files.get(&file), spans.get(&span)) {
+                if let Some(&base) = segments.get(&seg).filter(|_| size > 0) {
+                    let start = base + start;
+                    self.ranges.push(SourceRange {
+                        start: start as u16,
+                        end: (start + size - 1) as u16,
+                        file,
+                        line,
+                    });
+                }
+            }
+        }
+    }
+
+    // <Sources values="INDEX,FILE"> 1,/home/user/main.asm </Sources>
+    // <Block name="Default"> $0810,$0812,1,5,1,5,12 </Block>
+    fn parse_kickass(&mut self, text: &str, origin: &Path) {
+        let mut files = HashMap::new();
+        let mut section = "";
+        for line in text.lines() {
+            let line = line.trim();
+            if line.starts_with("<Sources") {
+                section = "sources";
+            } else if line.starts_with("<Block") {
+                section = "block";
+            } else if line.starts_with('<') {
+                section = "";
+            } else {
+                let fields = line.split(',').collect::<Vec<&str>>();
+                match section {
+                    "sources" if fields.len() >= 2 => {
+                        if let Some(id) = parse_number(fields[0]) {
+                            let index = self.add_file(&fields[1..].join(","), origin);
+                            files.insert(id, index);
+                        }
+                    }
+                    "block" if fields.len() >= 4 => {
+                        let start = parse_number(fields[0]);
+                        let end = parse_number(fields[1]);
+                        let file = parse_number(fields[2]).and_then(|id| files.get(&id));
+                        let line = parse_number(fields[3]);
+                        if let (Some(start), Some(end), Some(&file), Some(line)) =
+                            (start, end, file, line)
+                        {
+                            self.ranges.push(SourceRange {
+                                start: start as u16,
+                                end: end as u16,
+                                file,
+                                line,
+                            });
+                        }
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
+
+fn parse_fields(text: &str) -> HashMap<&str, &str> {
+    text.split(',')
+        .filter_map(|field| {
+            let mut parts = field.splitn(2, '=');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => Some((key.trim(), value.trim())),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn parse_number(value: &str) -> Option<usize> {
+    let value = value.trim();
+    if let Some(digits) = value.strip_prefix('$') {
+        usize::from_str_radix(digits, 16).ok()
+    } else if let Some(digits) = value.strip_prefix("0x") {
+        usize::from_str_radix(digits, 16).ok()
+    } else {
+        value.parse::<usize>().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    static CA65_DBG: &'static str = "\
+file id=0,name=\"main.s\",size=446,mtime=0x5c4ac5b4,mod=0
+seg id=0,name=\"CODE\",start=0x000810,size=0x0023,addrsize=absolute,type=ro
+span id=3,seg=0,start=16,size=3
+span id=4,seg=0,start=19,size=0
+span id=5,seg=0,start=0,size=35
+line id=4,file=0,line=12,span=3
+line id=5,file=0,line=13,span=4
+line id=6,file=0,line=14,type=2,span=5
+";
+
+    static KICKASS_DBG: &'static str = "\
+<C64debugger version=\"1.0\">
+  <Sources values=\"INDEX,FILE\">
+    1,/home/user/main.asm
+  </Sources>
+  <Segment name=\"Default\">
+    <Block name=\"Default\">
+      $0810,$0812,1,5,1,5,12
+      $0813,$0813,1,6,1,6,8
+    </Block>
+  </Segment>
+</C64debugger>
+";
+
+    #[test]
+    fn parse_ca65_lines() {
+        let mut source_map = SourceMap::new();
+        source_map.parse_ca65(CA65_DBG, Path::new("/nonexistent/game.dbg"));
+        assert_eq!(1, source_map.ranges.len());
+        assert_eq!(Some(("main.s", 12, 0x0820)), source_map.find_line(0x0822));
+        assert_eq!(None, source_map.find_line(0x0823));
+        assert_eq!(Some(0x0820), source_map.find_address("main.s", 10));
+        assert_eq!(None, source_map.find_address("main.s", 13));
+        assert_eq!(None, source_map.get_text("main.s", 12));
+    }
+
+    #[test]
+    fn parse_kickass_blocks() {
+        let mut source_map = SourceMap::new();
+        source_map.parse_kickass(KICKASS_DBG, Path::new("/nonexistent/game.dbg"));
+        assert_eq!(2, source_map.ranges.len());
+        assert_eq!(
+            Some(("/home/user/main.asm", 5, 0x0810)),
+            source_map.find_line(0x0811)
+        );
+        assert_eq!(
+            Some(("/home/user/main.asm", 6, 0x0813)),
+            source_map.find_line(0x0813)
+        );
+        assert_eq!(Some(0x0813), source_map.find_address("main.asm", 6));
+        assert_eq!(None, source_map.find_address("other.asm", 6));
+    }
+
+    #[test]
+    fn reload_replaces_ranges() {
+        let path = env::temp_dir().join("zinc64-source-map-reload.dbg");
+        fs::write(&path, KICKASS_DBG).unwrap();
+        let mut source_map = SourceMap::new();
+        assert_eq!(2, source_map.load(&path).unwrap());
+        source_map.parse_ca65(CA65_DBG, Path::new("/nonexistent/game.dbg"));
+        assert_eq!(2, source_map.load(&path).unwrap());
+        assert_eq!(2, source_map.files.len());
+        assert_eq!(3, source_map.ranges.len());
+        assert_eq!(Some(("main.s", 12, 0x0820)), source_map.find_line(0x0820));
+        assert_eq!(
+            Some(("/home/user/main.asm", 5, 0x0810)),
+            source_map.find_line(0x0810)
+        );
+        source_map.clear();
+        assert_eq!(None, source_map.find_line(0x0810));
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,70 @@
+// This file is part of zinc64.
+// Copyright (c) 2016-2018 Sebastian Jastrzebski. All rights reserved.
+// Licensed under the GPLv3. See LICENSE file in the project root for full license text.
+
+use std::fs::File;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use zinc64::system::{TraceEntry, TraceOutput};
+
+use super::disassembler::Disassembler;
+
+// SPEC: Lines follow the layout of VICE traces up to the cycle count so both can be diffed.
+
+pub struct TraceFile {
+    writer: BufWriter<File>,
+}
+
+impl TraceFile {
+    pub fn new(path: &Path) -> io::Result<TraceFile> {
+        info!(target: "debugger", "Writing trace to {}", path.to_str().unwrap());
+        let file = File::create(path)?;
+        Ok(TraceFile {
+            writer: BufWriter::new(file),
+        })
+    }
+}
+
+impl TraceOutput for TraceFile {
+    fn write(&mut self, entry: &TraceEntry) {
+        if let Err(err) = self.writer.write_all(format_entry(entry).as_bytes()) {
+            error!(target: "debugger", "Failed to write trace: {}", err);
+        }
+    }
+}
+
+pub fn format_entry(entry: &TraceEntry) -> String {
+    let dis = Disassembler::new(entry.opcode.to_vec(), entry.pc);
+    let (instr, instr_len) = dis.disassemble(entry.pc);
+    let mut instr_bytes = String::new();
+    for byte in entry.opcode[0..instr_len].iter() {
+        instr_bytes.push_str(format!("{:02X} ", byte).as_str());
+    }
+    let flags = "NV-BDIZC"
+        .chars()
+        .enumerate()
+        .map(|(i, flag)| {
+            if entry.p & (0x80 >> i) != 0 {
+                flag
+            } else {
+                '.'
+            }
+        })
+        .collect::<String>();
+    format!(
+        ".C:{:04x}  {:9} {:14} - A:{:02X} X:{:02X} Y:{:02X} SP:{:02x} {} {:10} LIN:{:03x} CYC:{:02x}\n",
+        entry.pc,
+        instr_bytes,
+        format!("{}", instr).to_uppercase(),
+        entry.a,
+        entry.x,
+        entry.y,
+        entry.sp,
+        flags,
+        entry.cycles,
+        entry.raster_line,
+        entry.raster_cycle,
+    )
+}
@@ -78,8 +78,9 @@ impl App {
                 .unwrap_or(SocketAddr::from(([127, 0, 0, 1], 9999)));
             info!(target: "app", "Starting debugger at {}", address);
             let command_tx_clone = command_tx.clone();
+            let debug_info = options.debug_info.clone();
             thread::spawn(move || {
-                let debugger = Debugger::new(command_tx_clone);
+                let debugger = Debugger::new(command_tx_clone, debug_info);
                 debugger.start(address).expect("Failed to start debugger");
             });
         }
@@ -143,9 +144,17 @@ impl App {
             self.handle_events(&mut events);
             self.handle_commands(false);
         }
+        self.execution_engine.get_c64_mut().detach_cartridge();
+        self.execution_engine.get_c64_mut().detach_midi();
+        self.execution_engine.get_c64_mut().detach_ram_expansion();
+        self.execution_engine.get_c64_mut().detach_tape();
         Ok(())
     }
 
+    fn freeze_cartridge(&mut self) {
+        self.execution_engine.get_c64_mut().freeze_cartridge();
+    }
+
     #[allow(dead_code)]
     fn handle_cpu_jam(&mut self) -> bool {
         match self.options.jam_action {
@@ -189,6 +198,11 @@ impl App {
         }
     }
 
+    fn reset_datassette_counter(&mut self) {
+        let datassette = self.execution_engine.get_c64().get_datasette();
+        datassette.borrow_mut().reset_counter();
+    }
+
     fn sync_frame(&mut self) {
         let refresh_rate = self
             .execution_engine
@@ -219,6 +233,33 @@ impl App {
         }
     }
 
+    fn toggle_datassette_fast_forward(&mut self) {
+        let datassette = self.execution_engine.get_c64().get_datasette();
+        if !datassette.borrow().is_winding() {
+            datassette.borrow_mut().fast_forward();
+        } else {
+            datassette.borrow_mut().stop();
+        }
+    }
+
+    fn toggle_datassette_record(&mut self) {
+        let datassette = self.execution_engine.get_c64().get_datasette();
+        if !datassette.borrow().is_playing() {
+            datassette.borrow_mut().record();
+        } else {
+            datassette.borrow_mut().stop();
+        }
+    }
+
+    fn toggle_datassette_rewind(&mut self) {
+        let datassette = self.execution_engine.get_c64().get_datasette();
+        if !datassette.borrow().is_winding() {
+            datassette.borrow_mut().rewind();
+        } else {
+            datassette.borrow_mut().stop();
+        }
+    }
+
     fn toggle_mute(&mut self) {
         self.audio_device.lock().toggle_mute();
     }
@@ -338,6 +379,15 @@ impl App {
                 {
                     self.reset();
                 }
+                Event::KeyDown {
+                    keycode: Some(Keycode::Z),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.contains(keyboard::LALTMOD) =>
+                {
+                    self.freeze_cartridge();
+                }
                 Event::KeyDown {
                     keycode: Some(Keycode::F1),
                     keymod,
@@ -347,6 +397,42 @@ impl App {
                 {
                     self.toggle_datassette_play();
                 }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F2),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.contains(keyboard::LCTRLMOD) =>
+                {
+                    self.toggle_datassette_record();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F3),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.contains(keyboard::LCTRLMOD) =>
+                {
+                    self.toggle_datassette_rewind();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F4),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.contains(keyboard::LCTRLMOD) =>
+                {
+                    self.toggle_datassette_fast_forward();
+                }
+                Event::KeyDown {
+                    keycode: Some(Keycode::F5),
+                    keymod,
+                    repeat: false,
+                    ..
+                } if keymod.contains(keyboard::LCTRLMOD) =>
+                {
+                    self.reset_datassette_counter();
+                }
                 Event::KeyDown {
                     keycode: Some(Keycode::Return),
                     keymod,